@@ -0,0 +1,69 @@
+//! Times the tree-walking interpreter against the bytecode VM on a few
+//! microbenchmarks. Run with `cargo bench`; pass a name to run only the
+//! benchmarks containing it.
+
+use rujs::interpreter::{Engine, Interpreter};
+use rujs::lexical::lexer::Lexer;
+use rujs::parser::ast::ASTNode;
+use std::env;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+const BENCHMARKS: &[(&str, &str)] = &[
+    (
+        "fibonacci",
+        "function fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+         fib(20);",
+    ),
+    (
+        "loops",
+        "let sum = 0;
+         for (let i = 0; i < 200000; i++) { if (i % 3 === 0) continue; sum = sum + i * 2; }
+         sum;",
+    ),
+    (
+        "property access",
+        "function point(x, y) { let p = {}; p.x = x; p.y = y; return p; }
+         let total = 0;
+         for (let i = 0; i < 50000; i++) { let p = point(i, i + 1); total = total + p.x * p.y - p['x']; }
+         total;",
+    ),
+];
+
+fn main() {
+    // `cargo bench` passes `--bench`; anything else filters by name.
+    let filter = env::args().skip(1).find(|argument| !argument.starts_with("--"));
+    println!("{:<18} {:>14} {:>14} {:>8}", "benchmark", "tree-walker", "bytecode", "speedup");
+    for &(name, source) in BENCHMARKS {
+        if filter.as_deref().is_some_and(|filter| !name.contains(filter)) {
+            continue;
+        }
+        let (tree_result, tree_time) = measure(Engine::TreeWalker, source);
+        let (bytecode_result, bytecode_time) = measure(Engine::Bytecode, source);
+        assert_eq!(tree_result, bytecode_result, "{}: the engines disagree", name);
+        println!(
+            "{:<18} {:>14?} {:>14?} {:>7.2}x",
+            name,
+            tree_time,
+            bytecode_time,
+            tree_time.as_secs_f64() / bytecode_time.as_secs_f64()
+        );
+    }
+}
+
+/// The script's completion value and its fastest run time, parsing
+/// included, on a fresh interpreter each run.
+fn measure(engine: Engine, source: &str) -> (String, Duration) {
+    let mut result = String::new();
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let mut interpreter = Interpreter::with_printer(|_| {});
+        interpreter.set_engine(engine);
+        let value = interpreter.run(ASTNode::parse_program(&Lexer::new(source).tokenize()));
+        best = best.min(start.elapsed());
+        result = value.map(|value| value.to_string()).unwrap_or_else(|error| format!("Uncaught {}", error));
+    }
+    (result, best)
+}
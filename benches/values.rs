@@ -0,0 +1,83 @@
+//! Compares the NaN-boxed `Value` the VM keeps on its stack and in object
+//! slots with the `JsValue` enum: their sizes, the memory objects take, and
+//! the time to copy and add large arrays of numbers in each. Run with
+//! `cargo bench --bench values`.
+
+use rujs::interpreter::{Engine, Interpreter};
+use rujs::lexical::lexer::Lexer;
+use rujs::parser::ast::ASTNode;
+use rujs::parser::operators::BinaryOp;
+use rujs::runtime::boxed::{self, Value};
+use rujs::runtime::gc;
+use rujs::runtime::property::{PropertyDescriptor, PropertySlot};
+use rujs::runtime::value::JsValue;
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+const ELEMENTS: usize = 1_000_000;
+const OBJECTS: usize = 10_000;
+
+fn main() {
+    println!("{:<20} {:>12} {:>12}", "size", "enum", "boxed");
+    println!("{:<20} {:>11}B {:>11}B", "value", size_of::<JsValue>(), size_of::<Value>());
+    println!("{:<20} {:>11}B {:>11}B", "property", size_of::<PropertyDescriptor>(), size_of::<PropertySlot>());
+
+    // Bytes per object with four data properties, and what the same slots
+    // would take as descriptors.
+    let before = gc::stats().live_bytes;
+    let mut interpreter = Interpreter::with_printer(|_| {});
+    interpreter.set_engine(Engine::Bytecode);
+    let source = format!(
+        "let objects = {{}};
+         for (let i = 0; i < {}; i++) {{ let o = {{}}; o.a = i; o.b = i + 1; o.c = 'c'; o.d = o; objects[i] = o; }}
+         0;",
+        OBJECTS
+    );
+    interpreter.run(ASTNode::parse_program(&Lexer::new(&source).tokenize())).expect("the script runs");
+    let per_object = gc::stats().live_bytes.saturating_sub(before) / OBJECTS;
+    let descriptors = per_object + 4 * (size_of::<PropertyDescriptor>() - size_of::<PropertySlot>());
+    println!("{:<20} {:>11}B {:>11}B", "object, 4 props", descriptors, per_object);
+    drop(interpreter);
+
+    let enums: Vec<JsValue> = (0..ELEMENTS).map(|index| JsValue::Number(index as f64)).collect();
+    let boxed: Vec<Value> = (0..ELEMENTS).map(|index| Value::int(index as i32)).collect();
+    println!();
+    println!("{:<20} {:>12} {:>12}", "1M numbers", "enum", "boxed");
+    let copy = (measure(|| enums.clone().len()), measure(|| boxed.clone().len()));
+    println!("{:<20} {:>12?} {:>12?}", "copy", copy.0, copy.1);
+    let add = (
+        measure(|| {
+            let sums: Vec<JsValue> = enums
+                .iter()
+                .zip(enums.iter().rev())
+                .map(|pair| match pair {
+                    (JsValue::Number(a), JsValue::Number(b)) => JsValue::Number(a + b),
+                    _ => JsValue::Undefined,
+                })
+                .collect();
+            sums.len()
+        }),
+        measure(|| {
+            let sums: Vec<Value> = boxed
+                .iter()
+                .zip(boxed.iter().rev())
+                .map(|(a, b)| boxed::arithmetic(BinaryOp::Add, a, b).unwrap_or_default())
+                .collect();
+            sums.len()
+        }),
+    );
+    println!("{:<20} {:>12?} {:>12?}", "add pairs", add.0, add.1);
+}
+
+/// The fastest of a few runs of `work`.
+fn measure(work: impl Fn() -> usize) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        black_box(work());
+        best = best.min(start.elapsed());
+    }
+    best
+}
@@ -0,0 +1,655 @@
+use crate::bytecode::opcode::{Instruction, Opcode, Operand};
+use crate::bytecode::{Constant, FunctionTemplate, GlobalDeclarations, Handler, TemplateKind};
+use crate::interpreter::inline_cache::InlineCaches;
+use crate::lexical::atom::Atom;
+use crate::runtime::bigint::BigInt;
+use crate::runtime::string::JsString;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{fmt, fs, io};
+
+/// Identifies a rujs bytecode file.
+const MAGIC: &[u8; 4] = b"RJBC";
+
+/// Bumped whenever the encoding or the instruction set changes, so files
+/// written by other versions are recompiled rather than misread.
+pub const FORMAT_VERSION: u32 = 5;
+
+/// Bytes before the template tree: the magic, version, source hash and
+/// checksum.
+const HEADER_SIZE: usize = 24;
+
+/// Error raised when bytes are not a cache entry for the given source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheError {
+    pub message: String,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, CacheError> {
+    Err(CacheError { message: message.into() })
+}
+
+/// 64-bit FNV-1a of the source's UTF-8 bytes. Unlike the standard library's
+/// hashers it is fixed, so hashes stay valid across builds.
+pub fn source_hash(source: &str) -> u64 {
+    fnv1a(source.as_bytes())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Encodes a compiled script as the magic, the format version, the hash of
+/// the source it was compiled from, a checksum of the rest, then the
+/// template tree. Integers are little-endian; strings and lists are
+/// prefixed with a `u32` length. Names are UTF-8, and string constants
+/// UTF-16 code units, which may be lone surrogates.
+pub fn serialize(template: &FunctionTemplate, source: &str) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.bytes.extend_from_slice(MAGIC);
+    writer.u32(FORMAT_VERSION);
+    writer.u64(source_hash(source));
+    writer.u64(0);
+    writer.template(template);
+    let checksum = fnv1a(&writer.bytes[HEADER_SIZE..]);
+    writer.bytes[HEADER_SIZE - 8..HEADER_SIZE].copy_from_slice(&checksum.to_le_bytes());
+    writer.bytes
+}
+
+/// Decodes what [`serialize`] wrote for `source`. Fails if the bytes come
+/// from another format version or another source text, have changed since
+/// they were written, or do not decode to templates whose code the VM can
+/// run.
+pub fn deserialize(bytes: &[u8], source: &str) -> Result<Rc<FunctionTemplate>, CacheError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return error("Not a rujs bytecode file");
+    }
+    let version = reader.u32()?;
+    if version != FORMAT_VERSION {
+        return error(format!("Bytecode format version {} is not {}", version, FORMAT_VERSION));
+    }
+    if reader.u64()? != source_hash(source) {
+        return error("Bytecode was compiled from a different source");
+    }
+    if reader.u64()? != fnv1a(&bytes[HEADER_SIZE..]) {
+        return error("Bytecode checksum mismatch");
+    }
+    let template = reader.template(0)?;
+    if reader.position != bytes.len() {
+        return error("Trailing bytes after the script");
+    }
+    if template.kind != TemplateKind::Script {
+        return error("Expected a script template");
+    }
+    Ok(template)
+}
+
+/// A cache entry on disk: the compiled form of one script file, stored
+/// beside it.
+pub struct BytecodeCache {
+    path: PathBuf,
+}
+
+impl BytecodeCache {
+    /// The entry for the script at `script`, named after it with `.rjbc`
+    /// appended.
+    pub fn for_script(script: &Path) -> Self {
+        let mut path = script.as_os_str().to_owned();
+        path.push(".rjbc");
+        BytecodeCache { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The cached script for `source`, if the entry exists and is valid
+    /// for it. A corrupt entry is `None` too, so that the caller recompiles.
+    pub fn load(&self, source: &str) -> Option<Rc<FunctionTemplate>> {
+        let bytes = fs::read(&self.path).ok()?;
+        deserialize(&bytes, source).ok()
+    }
+
+    pub fn store(&self, template: &FunctionTemplate, source: &str) -> io::Result<()> {
+        fs::write(&self.path, serialize(template, source))
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn length(&mut self, length: usize) {
+        self.u32(u32::try_from(length).expect("lengths fit in u32"));
+    }
+
+    fn string(&mut self, text: &str) {
+        self.length(text.len());
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+
+    fn code_units(&mut self, text: &JsString) {
+        self.length(text.len());
+        text.code_units().for_each(|unit| self.u16(unit));
+    }
+
+    fn strings<S: AsRef<str>>(&mut self, strings: &[S]) {
+        self.length(strings.len());
+        strings.iter().for_each(|text| self.string(text.as_ref()));
+    }
+
+    fn template(&mut self, template: &FunctionTemplate) {
+        self.u8(match template.kind {
+            TemplateKind::Script => 0,
+            TemplateKind::Function => 1,
+        });
+        self.string(&template.name);
+        self.u16(template.parameter_count);
+        self.strings(&template.registers);
+        self.length(template.scopes.len());
+        template.scopes.iter().for_each(|names| self.strings(names));
+        self.u16(template.max_stack);
+        self.u8(template.strict as u8);
+        self.length(template.code.len());
+        self.bytes.extend_from_slice(&template.code);
+
+        self.length(template.constants.len());
+        for constant in &template.constants {
+            match constant {
+                Constant::Number(number) => {
+                    self.u8(0);
+                    self.u64(number.to_bits());
+                }
+                Constant::String(text) => {
+                    self.u8(1);
+                    self.code_units(text);
+                }
+                Constant::BigInt(value) => {
+                    self.u8(2);
+                    self.string(&value.to_string());
+                }
+                Constant::Name(name) => {
+                    self.u8(3);
+                    self.string(name);
+                }
+            }
+        }
+
+        self.length(template.functions.len());
+        template.functions.iter().for_each(|function| self.template(function));
+
+        self.length(template.handlers.len());
+        for handler in &template.handlers {
+            self.u32(handler.start);
+            self.u32(handler.end);
+            self.u32(handler.target);
+            self.u16(handler.stack_depth);
+            self.u16(handler.scope_depth);
+        }
+
+        self.length(template.positions.len());
+        for &(offset, position) in &template.positions {
+            self.u32(offset);
+            self.u32(position);
+        }
+
+        let globals = &template.globals;
+        self.strings(&globals.vars);
+        self.length(globals.functions.len());
+        for (name, index) in &globals.functions {
+            self.string(name);
+            self.u16(*index);
+        }
+        self.length(globals.lexicals.len());
+        for (name, constant) in &globals.lexicals {
+            self.string(name);
+            self.u8(*constant as u8);
+        }
+        self.string(&template.source);
+    }
+}
+
+/// Templates nest as deeply as functions in the source; deeper files are
+/// rejected instead of overflowing the stack.
+const MAX_NESTING: usize = 1_000;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], CacheError> {
+        match self.bytes.get(self.position..self.position.saturating_add(count)) {
+            Some(bytes) => {
+                self.position += count;
+                Ok(bytes)
+            }
+            None => error("Unexpected end of bytecode"),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, CacheError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, CacheError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => error(format!("Invalid boolean {}", other)),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, CacheError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, CacheError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, CacheError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A list length, checked against the bytes left so that corrupt
+    /// lengths cannot make us allocate wildly.
+    fn length(&mut self) -> Result<usize, CacheError> {
+        let length = self.u32()? as usize;
+        if length > self.bytes.len() - self.position {
+            return error("Unexpected end of bytecode");
+        }
+        Ok(length)
+    }
+
+    fn string(&mut self) -> Result<Rc<str>, CacheError> {
+        let length = self.length()?;
+        match std::str::from_utf8(self.take(length)?) {
+            Ok(text) => Ok(text.into()),
+            Err(_) => error("Invalid UTF-8 in a string"),
+        }
+    }
+
+    fn code_units(&mut self) -> Result<JsString, CacheError> {
+        let length = self.length()?;
+        let bytes = self.take(length.saturating_mul(2))?;
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        Ok(JsString::from_code_units(&units))
+    }
+
+    fn strings(&mut self) -> Result<Vec<Rc<str>>, CacheError> {
+        (0..self.length()?).map(|_| self.string()).collect()
+    }
+
+    fn atom(&mut self) -> Result<Atom, CacheError> {
+        self.string().map(|text| Atom::new(&text))
+    }
+
+    fn template(&mut self, depth: usize) -> Result<Rc<FunctionTemplate>, CacheError> {
+        if depth > MAX_NESTING {
+            return error("Functions nested too deeply");
+        }
+        let kind = match self.u8()? {
+            0 => TemplateKind::Script,
+            1 => TemplateKind::Function,
+            other => return error(format!("Invalid template kind {}", other)),
+        };
+        let name = self.string()?;
+        let parameter_count = self.u16()?;
+        let registers = self.strings()?;
+        let scopes = (0..self.length()?).map(|_| self.strings().map(Rc::from)).collect::<Result<_, _>>()?;
+        let max_stack = self.u16()?;
+        let strict = self.bool()?;
+        let length = self.length()?;
+        let code = self.take(length)?.to_vec();
+
+        let mut constants = Vec::new();
+        for _ in 0..self.length()? {
+            constants.push(match self.u8()? {
+                0 => Constant::Number(f64::from_bits(self.u64()?)),
+                1 => Constant::String(self.code_units()?),
+                2 => match BigInt::from_js_string(&self.string()?) {
+                    Some(value) => Constant::BigInt(Rc::new(value)),
+                    None => return error("Invalid BigInt constant"),
+                },
+                3 => Constant::Name(self.atom()?),
+                other => return error(format!("Invalid constant tag {}", other)),
+            });
+        }
+
+        let functions = (0..self.length()?).map(|_| self.template(depth + 1)).collect::<Result<_, _>>()?;
+
+        let mut handlers = Vec::new();
+        for _ in 0..self.length()? {
+            handlers.push(Handler {
+                start: self.u32()?,
+                end: self.u32()?,
+                target: self.u32()?,
+                stack_depth: self.u16()?,
+                scope_depth: self.u16()?,
+            });
+        }
+
+        let mut positions = Vec::new();
+        for _ in 0..self.length()? {
+            positions.push((self.u32()?, self.u32()?));
+        }
+
+        let mut globals = GlobalDeclarations {
+            vars: (0..self.length()?).map(|_| self.atom()).collect::<Result<_, _>>()?,
+            ..GlobalDeclarations::default()
+        };
+        for _ in 0..self.length()? {
+            globals.functions.push((self.atom()?, self.u16()?));
+        }
+        for _ in 0..self.length()? {
+            globals.lexicals.push((self.atom()?, self.bool()?));
+        }
+        let source = self.string()?;
+
+        let template = FunctionTemplate {
+            kind,
+            name,
+            parameter_count,
+            registers,
+            scopes,
+            max_stack,
+            strict,
+            code,
+            constants,
+            functions,
+            handlers,
+            positions,
+            globals,
+            source,
+            caches: InlineCaches::default(),
+        };
+        validate(&template)?;
+        Ok(Rc::new(template))
+    }
+}
+
+/// Checks what the VM takes on trust: that the code decodes to whole
+/// instructions ending in one that leaves the function, that jumps and
+/// handlers land on instructions, that operands index existing registers,
+/// constants, scope layouts and templates, that the operand stack holds
+/// what each instruction pops and stays within `max_stack`, and that a
+/// script declares the globals its code initializes. Scoped variable
+/// operands are not checked; the version, source hash and checksum are
+/// what keep the VM from running code it did not compile.
+fn validate(template: &FunctionTemplate) -> Result<(), CacheError> {
+    if template.parameter_count as usize > template.registers.len() {
+        return error("More parameters than registers");
+    }
+    let instructions: Vec<_> = template.instructions().collect();
+    let end = instructions.last().map_or(0, |instruction| instruction.next_offset());
+    if end != template.code.len() {
+        return error(format!("Invalid instruction at offset {}", end));
+    }
+    if !matches!(instructions.last().map(|instruction| instruction.opcode), Some(Opcode::Return | Opcode::Throw | Opcode::Jump)) {
+        return error("Code runs past its end");
+    }
+    let is_start = |offset: usize| instructions.binary_search_by_key(&offset, |instruction| instruction.offset).is_ok();
+
+    for instruction in &instructions {
+        if let Some(target) = instruction.jump_target() {
+            if !is_start(target) {
+                return error(format!("Jump to {} at offset {} is not to an instruction", target, instruction.offset));
+            }
+        }
+        let [first, second] = instruction.operands;
+        let limit = match instruction.opcode {
+            Opcode::GetLocal | Opcode::SetLocal | Opcode::InitLocal | Opcode::ClearLocal => template.registers.len(),
+            Opcode::PushScope => template.scopes.len(),
+            Opcode::Closure => template.functions.len(),
+            Opcode::Constant => template.constants.len(),
+            Opcode::Call => {
+                if !matches!(template.constants.get(second as usize), Some(Constant::String(_))) {
+                    return error(format!("Constant {} is not a string", second));
+                }
+                continue;
+            }
+            // Only a script's own code initializes its `let` and `const`
+            // globals, which it must declare.
+            Opcode::InitGlobal => {
+                let name = name_constant(template, first)?;
+                if template.kind != TemplateKind::Script || !template.globals.lexicals.iter().any(|(lexical, _)| *lexical == name) {
+                    return error(format!("Initializes undeclared global {}", name));
+                }
+                continue;
+            }
+            // The remaining index operands are the names of globals and
+            // properties.
+            opcode if opcode.operands().first() == Some(&Operand::Index) => {
+                name_constant(template, first)?;
+                continue;
+            }
+            _ => continue,
+        };
+        if first as usize >= limit {
+            return error(format!("Operand {} of {} at offset {} is out of range", first, instruction.opcode.name(), instruction.offset));
+        }
+    }
+
+    for handler in &template.handlers {
+        if handler.start > handler.end || !is_start(handler.target as usize) {
+            return error("Invalid exception handler");
+        }
+    }
+    let heights = stack_heights(template, &instructions)?;
+    // Unwinding cuts the stack down to the handler's depth, so the
+    // instructions it protects must not have popped below it.
+    for handler in &template.handlers {
+        for (instruction, height) in instructions.iter().zip(&heights) {
+            let last_byte = instruction.next_offset() as u32 - 1;
+            let protected = handler.start <= last_byte && last_byte < handler.end;
+            if protected && height.is_some_and(|height| height - instruction.stack_inputs() < handler.stack_depth as i32) {
+                return error(format!("Stack below its handler's depth at offset {}", instruction.offset));
+            }
+        }
+    }
+
+    for (name, index) in &template.globals.functions {
+        match template.functions.get(*index as usize) {
+            Some(function) if function.kind == TemplateKind::Function => {}
+            _ => return error("Global function index out of range"),
+        }
+        if template.globals.lexicals.iter().any(|(lexical, _)| lexical == name) {
+            return error(format!("Global {} is both a function and a lexical declaration", name));
+        }
+    }
+    if template.functions.iter().any(|function| function.kind != TemplateKind::Function) {
+        return error("Expected a function template");
+    }
+    Ok(())
+}
+
+/// The operand stack height before each instruction, following control
+/// from the entry and from each handler. Unreachable instructions have
+/// none. Fails if an instruction would pop values its frame did not push,
+/// push past `max_stack`, or be reached at two different heights.
+fn stack_heights(template: &FunctionTemplate, instructions: &[Instruction]) -> Result<Vec<Option<i32>>, CacheError> {
+    let mut heights = vec![None; instructions.len()];
+    let mut pending = vec![(0, 0)];
+    pending.extend(template.handlers.iter().map(|handler| (handler.target as usize, handler.stack_depth as i32 + 1)));
+    while let Some((offset, height)) = pending.pop() {
+        let index = instructions
+            .binary_search_by_key(&offset, |instruction| instruction.offset)
+            .expect("jumps and handlers were checked to land on instructions");
+        match heights[index] {
+            Some(known) if known == height => continue,
+            Some(_) => return error(format!("Stack height differs between paths to offset {}", offset)),
+            None => heights[index] = Some(height),
+        }
+        let instruction = &instructions[index];
+        let after = height + instruction.stack_effect();
+        if height < instruction.stack_inputs() {
+            return error(format!("Stack underflow at offset {}", offset));
+        }
+        if height.max(after) > template.max_stack as i32 {
+            return error(format!("Stack overflow at offset {}", offset));
+        }
+        if let Some(target) = instruction.jump_target() {
+            let kept = match instruction.opcode {
+                Opcode::JumpIfFalseOrPop | Opcode::JumpIfTrueOrPop | Opcode::JumpIfNotNullishOrPop => height,
+                _ => after,
+            };
+            pending.push((target, kept));
+        }
+        if !matches!(instruction.opcode, Opcode::Return | Opcode::Throw | Opcode::Jump) {
+            pending.push((instruction.next_offset(), after));
+        }
+    }
+    Ok(heights)
+}
+
+fn name_constant(template: &FunctionTemplate, index: i32) -> Result<Atom, CacheError> {
+    match template.constants.get(index as usize) {
+        Some(Constant::Name(name)) => Ok(*name),
+        _ => error(format!("Constant {} is not a name", index)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compiler::Compiler;
+    use crate::interpreter::Interpreter;
+    use crate::lexical::lexer::Lexer;
+    use crate::parser::ast::ASTNode;
+
+    const SOURCE: &str = "'use strict';
+        let big = 12345678901234567890n;
+        function outer(a) { function inner() { return a + 0.5; } try { return inner(); } finally { big = big + 1n; } }
+        outer(1) + ' ' + big + ' ' + typeof outer;";
+
+    fn compile(code: &str) -> Rc<FunctionTemplate> {
+        Compiler::compile(&ASTNode::parse_program(&Lexer::new(code).tokenize())).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let template = compile(SOURCE);
+        let bytes = serialize(&template, SOURCE);
+        assert_eq!(&bytes[..4], b"RJBC");
+        let loaded = deserialize(&bytes, SOURCE).unwrap();
+        assert_eq!(loaded, template);
+
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.run_compiled(loaded).unwrap();
+        assert_eq!(result.to_string(), "1.5 12345678901234567891 function");
+    }
+
+    #[test]
+    fn test_rejects_stale_and_corrupt_entries() {
+        let bytes = serialize(&compile(SOURCE), SOURCE);
+        let message = |bytes: &[u8], source: &str| deserialize(bytes, source).unwrap_err().message;
+
+        assert_eq!(message(&bytes, "1;"), "Bytecode was compiled from a different source");
+        let mut other_version = bytes.clone();
+        other_version[4] = 99;
+        assert_eq!(message(&other_version, SOURCE), format!("Bytecode format version 99 is not {}", FORMAT_VERSION));
+        assert_eq!(message(b"#!/bin/sh", SOURCE), "Not a rujs bytecode file");
+        assert_eq!(message(&bytes[..bytes.len() - 1], SOURCE), "Bytecode checksum mismatch");
+
+        // The checksum catches every truncation and single-byte corruption.
+        for length in 0..bytes.len() {
+            assert!(deserialize(&bytes[..length], SOURCE).is_err());
+        }
+        for index in 16..bytes.len() {
+            let mut corrupt = bytes.clone();
+            corrupt[index] ^= 0x5a;
+            assert!(deserialize(&corrupt, SOURCE).is_err());
+        }
+    }
+
+    /// Rewrites the checksum to match corrupted bytes, as if they had been
+    /// written that way.
+    fn reseal(bytes: &mut [u8]) {
+        let checksum = fnv1a(&bytes[HEADER_SIZE..]);
+        bytes[HEADER_SIZE - 8..HEADER_SIZE].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn test_validation_rejects_unsound_code() {
+        let source = "let x = 1; function f(a) { return a; } f(x);";
+        let template = compile(source);
+        let message = |template: &FunctionTemplate| deserialize(&serialize(template, source), source).unwrap_err().message;
+
+        let call = template.instructions().find(|instruction| instruction.opcode == Opcode::Call).unwrap();
+        let mut underflow = (*template).clone();
+        underflow.code[call.offset + 1] = 5;
+        assert_eq!(message(&underflow), format!("Stack underflow at offset {}", call.offset));
+
+        let mut shallow = (*template).clone();
+        shallow.max_stack -= 1;
+        assert!(message(&shallow).starts_with("Stack overflow at offset"));
+
+        let mut undeclared = (*template).clone();
+        undeclared.globals.lexicals.clear();
+        assert_eq!(message(&undeclared), "Initializes undeclared global x");
+    }
+
+    #[test]
+    fn test_corrupt_entries_never_panic_the_vm() {
+        // No jumps, so that no corruption makes a loop that never ends.
+        let source = "let a = 1; const b = 'b'; var o = { p: a };
+            function f(x, y) { o.q = x + y; return typeof o.q; }
+            f(a, b) + f(o.p, 2) + o['p'];";
+        let bytes = serialize(&compile(source), source);
+        for index in HEADER_SIZE..bytes.len() {
+            for mask in [0x01, 0x5a, 0xff] {
+                let mut corrupt = bytes.clone();
+                corrupt[index] ^= mask;
+                reseal(&mut corrupt);
+                if let Ok(template) = deserialize(&corrupt, source) {
+                    let _ = Interpreter::with_printer(|_| {}).run_compiled(template);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cache_file() {
+        let script = std::env::temp_dir().join(format!("rujs-cache-test-{}.js", std::process::id()));
+        let cache = BytecodeCache::for_script(&script);
+        assert_eq!(cache.path().extension().unwrap(), "rjbc");
+        assert!(cache.load(SOURCE).is_none());
+
+        let template = compile(SOURCE);
+        cache.store(&template, SOURCE).unwrap();
+        assert_eq!(cache.load(SOURCE), Some(template));
+        assert!(cache.load("1;").is_none());
+        fs::remove_file(cache.path()).unwrap();
+    }
+}
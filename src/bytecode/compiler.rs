@@ -0,0 +1,1494 @@
+use crate::bytecode::opcode::{Instruction, Opcode};
+use crate::bytecode::{Constant, FunctionTemplate, GlobalDeclarations, Handler, TemplateKind};
+use crate::interpreter::inline_cache::InlineCaches;
+use crate::lexical::atom::Atom;
+use crate::parser::arena::{ArenaLiteral, AstArena, NodeId, NodeKind, NodeList};
+use crate::parser::ast::{ASTNode, VariableKind};
+use crate::parser::numeric::number_to_string;
+use crate::parser::operators::{AssignOp, LogicalOp, UnaryOp, UpdateOp};
+use crate::runtime::bigint::BigInt;
+use crate::runtime::error::JsError;
+use crate::runtime::string::JsString;
+use crate::semantic::early_errors;
+use crate::semantic::scope::{BindingId, BindingKind, ScopeId, ScopeKind, ScopeTree};
+use crate::semantic::strict::StrictMode;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Where a binding lives at runtime.
+#[derive(Debug, Clone, Copy)]
+enum Location {
+    Register(u16),
+    /// A slot of a scope pushed with `PushScope`; `scope` is the scope's
+    /// position in the compiler's scope chain.
+    Scoped { scope: usize, slot: u16 },
+    /// Looked up by name in the global environment.
+    Global,
+}
+
+/// A statement that `break` or `continue` can leave, or code protected by
+/// a `catch` or `finally`.
+enum Control {
+    Jumps {
+        labels: Vec<Atom>,
+        is_loop: bool,
+        breaks: Vec<usize>,
+        continues: Vec<usize>,
+        /// Scope chain length inside the statement.
+        chain: usize,
+    },
+    Protected(Region),
+}
+
+/// Code covered by one exception handler. A `break`, `continue` or
+/// `return` out of it ends the current segment, so that a `finally` copied
+/// in front of the jump does not count as covered.
+struct Region {
+    segments: Vec<(u32, u32)>,
+    start: u32,
+    stack_depth: i32,
+    chain: usize,
+    finalizer: Option<NodeId>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Number(u64),
+    String(Box<[u16]>),
+    Name(Atom),
+    BigInt(Rc<str>),
+}
+
+/// The template being built and the state of its code generation.
+struct FunctionState {
+    template: FunctionTemplate,
+    constant_indices: HashMap<ConstantKey, u16>,
+    /// Nested templates by declaration, so a `finally` compiled more than
+    /// once still creates each function from a single template.
+    function_indices: HashMap<NodeId, u16>,
+    /// Scope chain length on entry; scopes before it belong to enclosing
+    /// functions.
+    chain_base: usize,
+    depth: i32,
+    control: Vec<Control>,
+    /// Register holding a script's completion value.
+    completion: Option<u16>,
+    free_temporaries: Vec<u16>,
+}
+
+impl FunctionState {
+    fn new(kind: TemplateKind, name: &str, source: String, strict: bool, chain_base: usize) -> Self {
+        FunctionState {
+            template: FunctionTemplate {
+                kind,
+                name: name.into(),
+                parameter_count: 0,
+                registers: Vec::new(),
+                scopes: Vec::new(),
+                max_stack: 0,
+                strict,
+                code: Vec::new(),
+                constants: Vec::new(),
+                functions: Vec::new(),
+                handlers: Vec::new(),
+                positions: Vec::new(),
+                globals: GlobalDeclarations::default(),
+                source: source.into(),
+                caches: InlineCaches::default(),
+            },
+            constant_indices: HashMap::new(),
+            function_indices: HashMap::new(),
+            chain_base,
+            depth: 0,
+            control: Vec::new(),
+            completion: None,
+            free_temporaries: Vec::new(),
+        }
+    }
+}
+
+/// Lowers a program into [`FunctionTemplate`]s: one for the script and one
+/// for each function declaration, nested in the template of the code
+/// around it.
+///
+/// Scope analysis decides where each variable lives. Top-level
+/// declarations and undeclared names are looked up in the global
+/// environment by name; other variables get a register of their function,
+/// unless a nested function refers to them, in which case they get a slot
+/// in a scope that closures keep alive.
+pub struct Compiler<'a> {
+    arena: &'a AstArena,
+    scopes: &'a ScopeTree,
+    strict: &'a StrictMode,
+    captured: Vec<bool>,
+    locations: HashMap<BindingId, Location>,
+    /// Scopes pushed at the current point of the code, outermost first,
+    /// including those of enclosing functions.
+    chain: Vec<ScopeId>,
+    function: FunctionState,
+}
+
+impl<'a> Compiler<'a> {
+    /// Compiles a script, after reporting its first early error, if any, as
+    /// a SyntaxError.
+    pub fn compile(program: &ASTNode) -> Result<Rc<FunctionTemplate>, JsError> {
+        let arena = AstArena::from_ast(program);
+        let scopes = ScopeTree::analyze(&arena);
+        let strict = StrictMode::analyze(&arena, false);
+        if let Some(error) = early_errors::check(&arena, &scopes, &strict).into_iter().next() {
+            return Err(JsError::syntax_error(error.message));
+        }
+        Compiler::new(&arena, &scopes, &strict).compile_program().map(Rc::new)
+    }
+
+    fn new(arena: &'a AstArena, scopes: &'a ScopeTree, strict: &'a StrictMode) -> Self {
+        let mut compiler = Compiler {
+            arena,
+            scopes,
+            strict,
+            captured: Vec::new(),
+            locations: HashMap::new(),
+            chain: Vec::new(),
+            function: FunctionState::new(TemplateKind::Script, "", String::new(), false, 0),
+        };
+        compiler.captured = scopes.bindings().map(|binding| compiler.is_captured(binding)).collect();
+        compiler
+    }
+
+    /// Whether code in a function other than the one declaring `binding`
+    /// refers to it.
+    fn is_captured(&self, binding: BindingId) -> bool {
+        let binding = self.scopes.binding(binding);
+        let home = self.function_scope(binding.scope);
+        binding
+            .references
+            .iter()
+            .any(|&reference| self.function_scope(self.scopes.enclosing_scope(self.arena, reference)) != home)
+    }
+
+    fn function_scope(&self, scope: ScopeId) -> ScopeId {
+        self.scopes
+            .ancestors(scope)
+            .find(|&scope| matches!(self.scopes.scope(scope).kind, ScopeKind::Function | ScopeKind::Global))
+            .unwrap()
+    }
+
+    fn compile_program(mut self) -> Result<FunctionTemplate, JsError> {
+        let root = self.arena.root();
+        let NodeKind::Program { body } = *self.arena.kind(root) else {
+            panic!("Expected a program root");
+        };
+        self.function.template.strict = self.strict.is_strict(root);
+        self.mark_position(root);
+
+        let mut bindings: Vec<BindingId> = self.scopes.scope(self.scopes.root()).bindings().collect();
+        bindings.sort();
+        // Every global needs its location before any function using it is
+        // compiled.
+        for &binding in &bindings {
+            self.locations.insert(binding, Location::Global);
+        }
+        for binding in bindings {
+            let declared = self.scopes.binding(binding);
+            let name = declared.name;
+            match declared.kind {
+                BindingKind::Var => self.function.template.globals.vars.push(name),
+                kind if kind.is_lexical() => {
+                    let constant = kind == BindingKind::Const;
+                    self.function.template.globals.lexicals.push((name, constant));
+                }
+                _ => {}
+            }
+            if let Some(declaration) = self.function_declaration(binding) {
+                let index = self.function_template(declaration)?;
+                self.function.template.globals.functions.push((name, index));
+            }
+        }
+
+        let completion = self.register("%completion")?;
+        self.function.completion = Some(completion);
+        self.emit(Opcode::Undefined, &[]);
+        self.emit(Opcode::InitLocal, &[completion as i32]);
+        for &statement in self.arena.list(body) {
+            self.statement(statement)?;
+        }
+        self.emit(Opcode::GetLocal, &[completion as i32]);
+        self.emit(Opcode::Return, &[]);
+        Ok(self.finish())
+    }
+
+    fn finish(&mut self) -> FunctionTemplate {
+        let empty = FunctionState::new(TemplateKind::Script, "", String::new(), false, 0);
+        std::mem::replace(&mut self.function, empty).template
+    }
+
+    /// Compiles a nested function declaration, once, and returns the index
+    /// of its template among the current function's.
+    fn function_template(&mut self, declaration: NodeId) -> Result<u16, JsError> {
+        if let Some(&index) = self.function.function_indices.get(&declaration) {
+            return Ok(index);
+        }
+        let NodeKind::FunctionDeclaration { name, parameters, body } = *self.arena.kind(declaration) else {
+            panic!("Expected a function declaration");
+        };
+
+        let state = FunctionState::new(
+            TemplateKind::Function,
+            self.arena.identifier_name(name).unwrap(),
+            self.arena.to_ast(declaration).to_source(),
+            self.strict.is_strict(declaration),
+            self.chain.len(),
+        );
+        let outer = std::mem::replace(&mut self.function, state);
+        let result = self.function_body(declaration, self.arena.list(parameters), body);
+        let inner = std::mem::replace(&mut self.function, outer);
+        result?;
+
+        let index = index(self.function.template.functions.len(), "functions")?;
+        self.function.template.functions.push(Rc::new(inner.template));
+        self.function.function_indices.insert(declaration, index);
+        Ok(index)
+    }
+
+    fn function_body(&mut self, declaration: NodeId, parameters: &[NodeId], body: NodeId) -> Result<(), JsError> {
+        self.mark_position(declaration);
+        for &parameter in parameters {
+            self.register(self.arena.identifier_name(parameter).unwrap())?;
+        }
+        self.function.template.parameter_count = index(parameters.len(), "parameters")?;
+
+        let scope = self.scopes.scope_of(declaration).expect("functions have a scope");
+        let pushed = self.enter_scope(scope, parameters)?;
+        let NodeKind::Block { body: statements } = *self.arena.kind(body) else {
+            panic!("Function body must be a block");
+        };
+        for &statement in self.arena.list(statements) {
+            self.statement(statement)?;
+        }
+        self.emit(Opcode::Undefined, &[]);
+        self.emit(Opcode::Return, &[]);
+        // Returning discards the function's scopes along with its frame.
+        if pushed {
+            self.chain.pop();
+        }
+        Ok(())
+    }
+
+    /// The function declaration that initializes `binding`, if any: the
+    /// last one, when a name is declared more than once.
+    fn function_declaration(&self, binding: BindingId) -> Option<NodeId> {
+        self.scopes.binding(binding).declarations.iter().rev().find_map(|&identifier| {
+            let parent = self.arena.parent(identifier)?;
+            matches!(self.arena.kind(parent), NodeKind::FunctionDeclaration { name, .. } if *name == identifier)
+                .then_some(parent)
+        })
+    }
+
+    /// Assigns a location to every binding of `scope`, pushing a scope for
+    /// the captured ones, and runs the scope's declaration instantiation:
+    /// `var`s start as `undefined`, `let` and `const` in their temporal dead
+    /// zone, and functions are created. Returns whether a scope was pushed.
+    fn enter_scope(&mut self, scope: ScopeId, parameters: &[NodeId]) -> Result<bool, JsError> {
+        let kind_of_scope = self.scopes.scope(scope).kind;
+        let mut bindings: Vec<BindingId> = self.scopes.scope(scope).bindings().collect();
+        bindings.sort();
+
+        let slots: Vec<Rc<str>> = bindings
+            .iter()
+            .filter(|binding| self.captured[binding.index()])
+            .map(|&binding| self.scopes.binding(binding).name.as_str().into())
+            .collect();
+        let pushed = !slots.is_empty();
+        if pushed {
+            let layout = index(self.function.template.scopes.len(), "scopes")?;
+            self.function.template.scopes.push(slots.into());
+            self.emit(Opcode::PushScope, &[layout as i32]);
+            self.chain.push(scope);
+        }
+
+        let mut next_slot = 0;
+        for &binding in &bindings {
+            let location = if self.captured[binding.index()] {
+                next_slot += 1;
+                Location::Scoped {
+                    scope: self.chain.len() - 1,
+                    slot: next_slot - 1,
+                }
+            } else if let Some(position) = self.parameter_position(binding, parameters) {
+                Location::Register(position as u16)
+            } else {
+                Location::Register(self.register(self.scopes.binding(binding).name.as_str())?)
+            };
+            self.locations.insert(binding, location);
+        }
+
+        let mut functions = Vec::new();
+        for &binding in &bindings {
+            match (self.scopes.binding(binding).kind, self.locations[&binding]) {
+                (BindingKind::Parameter, Location::Scoped { .. }) => {
+                    let position = self.parameter_position(binding, parameters).unwrap();
+                    self.emit(Opcode::GetLocal, &[position as i32]);
+                    self.initialize(binding)?;
+                }
+                (BindingKind::Var, _) => {
+                    self.emit(Opcode::Undefined, &[]);
+                    self.initialize(binding)?;
+                }
+                // Registers start uninitialized when a function is called,
+                // but a block can run more than once per call.
+                (kind, Location::Register(register)) if kind.is_lexical() && kind_of_scope != ScopeKind::Function => {
+                    self.emit(Opcode::ClearLocal, &[register as i32]);
+                }
+                _ => {}
+            }
+            if let Some(declaration) = self.function_declaration(binding) {
+                functions.push((binding, declaration));
+            }
+        }
+        for (binding, declaration) in functions {
+            let template = self.function_template(declaration)?;
+            self.emit(Opcode::Closure, &[template as i32]);
+            self.initialize(binding)?;
+        }
+        Ok(pushed)
+    }
+
+    fn leave_scope(&mut self, pushed: bool) {
+        if pushed {
+            self.emit(Opcode::PopScope, &[]);
+            self.chain.pop();
+        }
+    }
+
+    /// The register a parameter binding's argument arrives in: that of its
+    /// last declaration, since a repeated name takes the later argument.
+    fn parameter_position(&self, binding: BindingId, parameters: &[NodeId]) -> Option<usize> {
+        parameters
+            .iter()
+            .rposition(|&parameter| self.scopes.binding_of(parameter) == Some(binding))
+    }
+
+    fn statement(&mut self, statement: NodeId) -> Result<(), JsError> {
+        self.mark_position(statement);
+        match *self.arena.kind(statement) {
+            NodeKind::VariableDeclaration { kind, declarations } => {
+                for &declarator in self.arena.list(declarations) {
+                    self.declarator(kind, declarator)?;
+                }
+            }
+            // Created when their scope was entered.
+            NodeKind::FunctionDeclaration { .. } => {}
+            NodeKind::IfStatement {
+                condition,
+                body,
+                else_body,
+            } => {
+                self.clear_completion();
+                self.expression(condition)?;
+                let skip_body = self.emit_jump(Opcode::JumpIfFalse);
+                self.statement(body)?;
+                match else_body {
+                    Some(else_body) => {
+                        let skip_else = self.emit_jump(Opcode::Jump);
+                        self.patch_jump(skip_body);
+                        self.statement(else_body)?;
+                        self.patch_jump(skip_else);
+                    }
+                    None => self.patch_jump(skip_body),
+                }
+            }
+            NodeKind::Block { body } => {
+                let scope = self.scopes.scope_of(statement).expect("blocks have a scope");
+                let pushed = self.enter_scope(scope, &[])?;
+                for &statement in self.arena.list(body) {
+                    self.statement(statement)?;
+                }
+                self.leave_scope(pushed);
+            }
+            NodeKind::ExpressionStatement { expression } => self.expression_statement(expression)?,
+            NodeKind::ReturnStatement { argument } => self.return_statement(argument)?,
+            NodeKind::BreakStatement { label } => self.jump_statement(label, false)?,
+            NodeKind::ContinueStatement { label } => self.jump_statement(label, true)?,
+            NodeKind::ThrowStatement { argument } => {
+                self.expression(argument)?;
+                self.emit(Opcode::Throw, &[]);
+            }
+            NodeKind::TryStatement {
+                block,
+                handler,
+                finalizer,
+            } => self.try_statement(block, handler, finalizer)?,
+            NodeKind::LabeledStatement { .. } => self.labelled(statement, Vec::new())?,
+            NodeKind::WhileStatement { .. } | NodeKind::ForStatement { .. } => self.iteration(statement, Vec::new())?,
+            _ => self.expression_statement(statement)?,
+        }
+        Ok(())
+    }
+
+    fn expression_statement(&mut self, expression: NodeId) -> Result<(), JsError> {
+        self.expression(expression)?;
+        match self.function.completion {
+            Some(completion) => self.emit(Opcode::InitLocal, &[completion as i32]),
+            None => self.emit(Opcode::Pop, &[]),
+        };
+        Ok(())
+    }
+
+    /// Statements whose completion value is `undefined` unless their body
+    /// produces one reset the script's completion value on entry.
+    fn clear_completion(&mut self) {
+        if let Some(completion) = self.function.completion {
+            self.emit(Opcode::Undefined, &[]);
+            self.emit(Opcode::InitLocal, &[completion as i32]);
+        }
+    }
+
+    fn declarator(&mut self, kind: VariableKind, declarator: NodeId) -> Result<(), JsError> {
+        let NodeKind::VariableDeclarator { identifier, value } = *self.arena.kind(declarator) else {
+            panic!("Expected a variable declarator");
+        };
+        match (kind, value) {
+            // Hoisted already; `var x;` leaves an existing value alone.
+            (VariableKind::Var, None) => {}
+            (VariableKind::Var, Some(value)) => {
+                self.expression(value)?;
+                self.assign(identifier)?;
+                self.emit(Opcode::Pop, &[]);
+            }
+            (VariableKind::Let | VariableKind::Const, value) => {
+                match value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        self.emit(Opcode::Undefined, &[]);
+                    }
+                }
+                let binding = self.scopes.binding_of(identifier).expect("declarations are resolved");
+                self.initialize(binding)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn return_statement(&mut self, argument: Option<NodeId>) -> Result<(), JsError> {
+        match argument {
+            Some(argument) => self.expression(argument)?,
+            None => {
+                self.emit(Opcode::Undefined, &[]);
+            }
+        }
+        let has_finalizer = self.function.control.iter().any(|control| {
+            matches!(
+                control,
+                Control::Protected(Region {
+                    finalizer: Some(_),
+                    ..
+                })
+            )
+        });
+        if !has_finalizer {
+            self.emit(Opcode::Return, &[]);
+            return Ok(());
+        }
+
+        // Every enclosing `finally` runs before the function returns.
+        let value = self.temporary();
+        self.emit(Opcode::InitLocal, &[value as i32]);
+        let closed = self.unwind(0, None)?;
+        self.emit(Opcode::GetLocal, &[value as i32]);
+        self.emit(Opcode::Return, &[]);
+        self.resume(closed);
+        self.release(value);
+        Ok(())
+    }
+
+    fn jump_statement(&mut self, label: Option<NodeId>, is_continue: bool) -> Result<(), JsError> {
+        let label = label.map(|label| self.identifier_atom(label));
+        let target = self
+            .function
+            .control
+            .iter()
+            .rposition(|control| match (control, label) {
+                (Control::Jumps { labels, .. }, Some(label)) => labels.contains(&label),
+                (Control::Jumps { is_loop, .. }, None) => *is_loop,
+                (Control::Protected(_), _) => false,
+            })
+            .expect("early errors rule out jumps without a target");
+        let Control::Jumps { chain, .. } = self.function.control[target] else {
+            unreachable!()
+        };
+
+        let closed = self.unwind(target + 1, Some(chain))?;
+        let jump = self.emit_jump(Opcode::Jump);
+        let Control::Jumps { breaks, continues, .. } = &mut self.function.control[target] else {
+            unreachable!()
+        };
+        if is_continue {
+            continues.push(jump);
+        } else {
+            breaks.push(jump);
+        }
+        self.resume(closed);
+        Ok(())
+    }
+
+    /// Prepares to jump out of every control entry from `target` on: ends
+    /// the current segment of each protected region, runs the `finally`
+    /// blocks in between, innermost first, and pops scopes down to `chain`
+    /// if given. Returns the regions to [`Compiler::resume`] after the jump.
+    fn unwind(&mut self, target: usize, chain: Option<usize>) -> Result<Vec<usize>, JsError> {
+        let mut closed = Vec::new();
+        let mut runtime_chain = self.chain.len();
+        for position in (target..self.function.control.len()).rev() {
+            let offset = self.offset();
+            let Control::Protected(region) = &mut self.function.control[position] else {
+                continue;
+            };
+            region.segments.push((region.start, offset));
+            closed.push(position);
+            if let Some(finalizer) = region.finalizer {
+                let region_chain = region.chain;
+                self.pop_scopes(&mut runtime_chain, region_chain);
+                self.inline_finalizer(position, region_chain, finalizer)?;
+            }
+        }
+        if let Some(chain) = chain {
+            self.pop_scopes(&mut runtime_chain, chain);
+        }
+        Ok(closed)
+    }
+
+    fn resume(&mut self, closed: Vec<usize>) {
+        let offset = self.offset();
+        for position in closed {
+            if let Control::Protected(region) = &mut self.function.control[position] {
+                region.start = offset;
+            }
+        }
+    }
+
+    fn pop_scopes(&mut self, runtime_chain: &mut usize, target: usize) {
+        while *runtime_chain > target {
+            self.emit(Opcode::PopScope, &[]);
+            *runtime_chain -= 1;
+        }
+    }
+
+    /// Copies a `finally` block in front of a jump out of its `try`, as it
+    /// would be compiled right after the `try`.
+    fn inline_finalizer(&mut self, position: usize, chain: usize, finalizer: NodeId) -> Result<(), JsError> {
+        let control = self.function.control.split_off(position);
+        let scopes = self.chain.split_off(chain);
+        let result = self.finalizer(finalizer);
+        self.chain.extend(scopes);
+        self.function.control.extend(control);
+        result
+    }
+
+    /// A `finally` block that completes normally leaves the completion value
+    /// of the statement before it alone.
+    fn finalizer(&mut self, finalizer: NodeId) -> Result<(), JsError> {
+        let Some(completion) = self.function.completion else {
+            return self.statement(finalizer);
+        };
+        let saved = self.temporary();
+        self.emit(Opcode::GetLocal, &[completion as i32]);
+        self.emit(Opcode::InitLocal, &[saved as i32]);
+        self.statement(finalizer)?;
+        self.emit(Opcode::GetLocal, &[saved as i32]);
+        self.emit(Opcode::InitLocal, &[completion as i32]);
+        self.release(saved);
+        Ok(())
+    }
+
+    fn try_statement(&mut self, block: NodeId, handler: Option<NodeId>, finalizer: Option<NodeId>) -> Result<(), JsError> {
+        self.clear_completion();
+        if finalizer.is_some() {
+            self.push_region(finalizer);
+        }
+        if handler.is_some() {
+            self.push_region(None);
+        }
+        self.statement(block)?;
+
+        if let Some(handler) = handler {
+            let region = self.pop_region();
+            let done = self.emit_jump(Opcode::Jump);
+            self.add_handlers(&region)?;
+            self.function.depth = region.stack_depth + 1;
+            self.catch_clause(handler)?;
+            self.patch_jump(done);
+        }
+
+        if let Some(finalizer) = finalizer {
+            let region = self.pop_region();
+            self.finalizer(finalizer)?;
+            let done = self.emit_jump(Opcode::Jump);
+
+            // A throw from the `try` or `catch` runs the `finally` and is
+            // then rethrown.
+            self.add_handlers(&region)?;
+            self.function.depth = region.stack_depth + 1;
+            let exception = self.temporary();
+            self.emit(Opcode::InitLocal, &[exception as i32]);
+            self.statement(finalizer)?;
+            self.emit(Opcode::GetLocal, &[exception as i32]);
+            self.emit(Opcode::Throw, &[]);
+            self.release(exception);
+            self.patch_jump(done);
+        }
+        Ok(())
+    }
+
+    /// Code for a `catch` clause, entered with the exception on the stack.
+    fn catch_clause(&mut self, handler: NodeId) -> Result<(), JsError> {
+        let NodeKind::CatchClause { parameter, body } = *self.arena.kind(handler) else {
+            panic!("Expected a catch clause");
+        };
+        self.mark_position(handler);
+        self.clear_completion();
+        let scope = self.scopes.scope_of(handler).expect("catch clauses have a scope");
+        let pushed = self.enter_scope(scope, &[])?;
+        match parameter {
+            Some(parameter) => {
+                let binding = self.scopes.binding_of(parameter).expect("declarations are resolved");
+                self.initialize(binding)?;
+            }
+            None => {
+                self.emit(Opcode::Pop, &[]);
+            }
+        }
+        self.statement(body)?;
+        self.leave_scope(pushed);
+        Ok(())
+    }
+
+    fn push_region(&mut self, finalizer: Option<NodeId>) {
+        let region = Region {
+            segments: Vec::new(),
+            start: self.offset(),
+            stack_depth: self.function.depth,
+            chain: self.chain.len(),
+            finalizer,
+        };
+        self.function.control.push(Control::Protected(region));
+    }
+
+    fn pop_region(&mut self) -> Region {
+        let Some(Control::Protected(mut region)) = self.function.control.pop() else {
+            panic!("Expected a protected region");
+        };
+        region.segments.push((region.start, self.offset()));
+        region
+    }
+
+    /// Adds handler table entries sending exceptions in `region` to the
+    /// current offset.
+    fn add_handlers(&mut self, region: &Region) -> Result<(), JsError> {
+        let target = self.offset();
+        for &(start, end) in &region.segments {
+            if start < end {
+                self.function.template.handlers.push(Handler {
+                    start,
+                    end,
+                    target,
+                    stack_depth: index(region.stack_depth as usize, "stack slots")?,
+                    scope_depth: index(region.chain - self.function.chain_base, "scopes")?,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// LabelledEvaluation: collects the labels of a chain like `a: b: ...`
+    /// and compiles the statement they label.
+    fn labelled(&mut self, statement: NodeId, mut labels: Vec<Atom>) -> Result<(), JsError> {
+        match *self.arena.kind(statement) {
+            NodeKind::LabeledStatement { label, body } => {
+                labels.push(self.identifier_atom(label));
+                self.mark_position(body);
+                self.labelled(body, labels)
+            }
+            NodeKind::WhileStatement { .. } | NodeKind::ForStatement { .. } => self.iteration(statement, labels),
+            _ => {
+                self.push_jumps(labels, false);
+                self.statement(statement)?;
+                let target = self.offset();
+                self.pop_jumps(target);
+                Ok(())
+            }
+        }
+    }
+
+    fn iteration(&mut self, statement: NodeId, labels: Vec<Atom>) -> Result<(), JsError> {
+        self.clear_completion();
+        match *self.arena.kind(statement) {
+            NodeKind::WhileStatement { condition, body } => {
+                let top = self.offset();
+                self.push_jumps(labels, true);
+                self.expression(condition)?;
+                let exit = self.emit_jump(Opcode::JumpIfFalse);
+                self.statement(body)?;
+                self.emit_loop(top);
+                self.patch_jump(exit);
+                self.pop_jumps(top);
+            }
+            NodeKind::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                // `for (let ...)` bindings live in a scope around the loop;
+                // with `let`, each iteration gets its own copy of it.
+                let pushed = match self.scopes.scope_of(statement) {
+                    Some(scope) => self.enter_scope(scope, &[])?,
+                    None => false,
+                };
+                let per_iteration = pushed
+                    && init.is_some_and(|init| {
+                        matches!(
+                            self.arena.kind(init),
+                            NodeKind::VariableDeclaration {
+                                kind: VariableKind::Let,
+                                ..
+                            }
+                        )
+                    });
+                match init {
+                    Some(init) if matches!(self.arena.kind(init), NodeKind::VariableDeclaration { .. }) => {
+                        self.statement(init)?;
+                    }
+                    Some(init) => {
+                        self.expression(init)?;
+                        self.emit(Opcode::Pop, &[]);
+                    }
+                    None => {}
+                }
+                if per_iteration {
+                    self.emit(Opcode::CopyScope, &[]);
+                }
+
+                let top = self.offset();
+                self.push_jumps(labels, true);
+                let exit = match condition {
+                    Some(condition) => {
+                        self.expression(condition)?;
+                        Some(self.emit_jump(Opcode::JumpIfFalse))
+                    }
+                    None => None,
+                };
+                self.statement(body)?;
+                let next = self.offset();
+                if per_iteration {
+                    self.emit(Opcode::CopyScope, &[]);
+                }
+                if let Some(update) = update {
+                    self.expression(update)?;
+                    self.emit(Opcode::Pop, &[]);
+                }
+                self.emit_loop(top);
+                if let Some(exit) = exit {
+                    self.patch_jump(exit);
+                }
+                self.pop_jumps(next);
+                self.leave_scope(pushed);
+            }
+            _ => panic!("Expected a loop"),
+        }
+        Ok(())
+    }
+
+    fn push_jumps(&mut self, labels: Vec<Atom>, is_loop: bool) {
+        self.function.control.push(Control::Jumps {
+            labels,
+            is_loop,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            chain: self.chain.len(),
+        });
+    }
+
+    /// Points the statement's `continue`s at `next` and its `break`s at the
+    /// current offset.
+    fn pop_jumps(&mut self, next: u32) {
+        let Some(Control::Jumps { breaks, continues, .. }) = self.function.control.pop() else {
+            panic!("Expected a jump target");
+        };
+        for jump in continues {
+            self.patch_jump_to(jump, next as usize);
+        }
+        for jump in breaks {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn expression(&mut self, expression: NodeId) -> Result<(), JsError> {
+        match *self.arena.kind(expression) {
+            NodeKind::Literal(literal) => self.literal(literal)?,
+            NodeKind::Identifier(_) => self.load(expression)?,
+            NodeKind::ThisExpression => {
+                self.emit(Opcode::This, &[]);
+            }
+            NodeKind::ObjectExpression { properties } => {
+                self.emit(Opcode::NewObject, &[]);
+                for &property in self.arena.list(properties) {
+                    let NodeKind::Property { key, value, computed, .. } = *self.arena.kind(property) else {
+                        panic!("Expected a property");
+                    };
+                    match self.property_key(key, computed)? {
+                        Some(name) => {
+                            self.expression(value)?;
+                            self.emit(Opcode::DefineNamed, &[name as i32]);
+                        }
+                        None => {
+                            self.expression(value)?;
+                            self.emit(Opcode::DefineProperty, &[]);
+                        }
+                    }
+                }
+            }
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                self.expression(object)?;
+                let key = self.property_key(property, computed)?;
+                self.mark_position(expression);
+                self.get_property(key);
+            }
+            NodeKind::CallExpression { callee, arguments } => self.call(expression, callee, arguments)?,
+            NodeKind::AssignmentExpression { left, operator, right } => self.assignment(left, operator, right)?,
+            NodeKind::BinaryExpression { left, operator, right } => {
+                self.expression(left)?;
+                self.expression(right)?;
+                self.mark_position(expression);
+                self.emit(Opcode::from_binary_op(operator), &[]);
+            }
+            NodeKind::LogicalExpression { left, operator, right } => {
+                self.expression(left)?;
+                let short_circuit = self.emit_jump(short_circuit_jump(operator));
+                self.expression(right)?;
+                self.patch_jump(short_circuit);
+            }
+            NodeKind::UnaryExpression { operator, argument } => self.unary(operator, argument)?,
+            NodeKind::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            } => self.update(operator, prefix, argument)?,
+            ref other => panic!("Unexpected expression: {:?}", other),
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: ArenaLiteral) -> Result<(), JsError> {
+        let constant = match literal {
+            ArenaLiteral::Number(number) => Constant::Number(number),
+            ArenaLiteral::String(string) => Constant::String(JsString::from_code_units(self.arena.string(string))),
+            ArenaLiteral::BigInt(digits) => {
+                let value = BigInt::parse_literal(digits.as_str())
+                    .ok_or_else(|| JsError::syntax_error("Invalid BigInt literal"))?;
+                Constant::BigInt(Rc::new(value))
+            }
+            ArenaLiteral::Boolean(true) => {
+                self.emit(Opcode::True, &[]);
+                return Ok(());
+            }
+            ArenaLiteral::Boolean(false) => {
+                self.emit(Opcode::False, &[]);
+                return Ok(());
+            }
+            ArenaLiteral::Null => {
+                self.emit(Opcode::Null, &[]);
+                return Ok(());
+            }
+            ArenaLiteral::RegExp { .. } => {
+                return Err(JsError::syntax_error("Regular expression literals are not supported"));
+            }
+        };
+        let constant = self.constant(constant)?;
+        self.emit(Opcode::Constant, &[constant as i32]);
+        Ok(())
+    }
+
+    fn call(&mut self, call: NodeId, callee: NodeId, arguments: NodeList) -> Result<(), JsError> {
+        match *self.arena.kind(callee) {
+            // A method call: the object is the `this` value.
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                self.expression(object)?;
+                self.emit(Opcode::Dup, &[]);
+                let key = self.property_key(property, computed)?;
+                self.mark_position(callee);
+                self.get_property(key);
+                self.emit(Opcode::Swap, &[]);
+            }
+            _ => {
+                self.expression(callee)?;
+                self.emit(Opcode::Undefined, &[]);
+            }
+        }
+        let arguments = self.arena.list(arguments);
+        for &argument in arguments {
+            self.expression(argument)?;
+        }
+        let count = index(arguments.len(), "arguments")?;
+        let text = self.constant(Constant::String(self.arena.to_ast(callee).to_source().into()))?;
+        self.mark_position(call);
+        self.emit(Opcode::Call, &[count as i32, text as i32]);
+        Ok(())
+    }
+
+    fn assignment(&mut self, left: NodeId, operator: AssignOp, right: NodeId) -> Result<(), JsError> {
+        match *self.arena.kind(left) {
+            NodeKind::Identifier(_) => {
+                if operator == AssignOp::Assign {
+                    self.expression(right)?;
+                } else if let Some(logical) = operator.logical_op() {
+                    self.load(left)?;
+                    let short_circuit = self.emit_jump(short_circuit_jump(logical));
+                    self.expression(right)?;
+                    self.assign(left)?;
+                    self.patch_jump(short_circuit);
+                    return Ok(());
+                } else {
+                    self.load(left)?;
+                    self.expression(right)?;
+                    self.emit(Opcode::from_binary_op(operator.binary_op().unwrap()), &[]);
+                }
+                self.assign(left)
+            }
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                self.expression(object)?;
+                let key = self.property_key(property, computed)?;
+                self.mark_position(left);
+                if operator == AssignOp::Assign {
+                    self.expression(right)?;
+                } else if let Some(logical) = operator.logical_op() {
+                    self.duplicate_reference(key);
+                    self.get_property(key);
+                    let short_circuit = self.emit_jump(short_circuit_jump(logical));
+                    let short_circuit_depth = self.function.depth + 1;
+                    self.expression(right)?;
+                    self.set_property(key);
+                    let done = self.emit_jump(Opcode::Jump);
+
+                    // Drop the reference from under the current value.
+                    self.patch_jump(short_circuit);
+                    self.function.depth = short_circuit_depth;
+                    let reference_size = if key.is_some() { 1 } else { 2 };
+                    for _ in 0..reference_size {
+                        self.emit(Opcode::Swap, &[]);
+                        self.emit(Opcode::Pop, &[]);
+                    }
+                    self.patch_jump(done);
+                    return Ok(());
+                } else {
+                    self.duplicate_reference(key);
+                    self.get_property(key);
+                    self.expression(right)?;
+                    self.emit(Opcode::from_binary_op(operator.binary_op().unwrap()), &[]);
+                }
+                self.set_property(key);
+                Ok(())
+            }
+            ref other => panic!("Invalid assignment target: {:?}", other),
+        }
+    }
+
+    fn unary(&mut self, operator: UnaryOp, argument: NodeId) -> Result<(), JsError> {
+        match (operator, *self.arena.kind(argument)) {
+            (UnaryOp::TypeOf, NodeKind::Identifier(name)) => match self.location(argument) {
+                Location::Global => {
+                    let name = self.name(name)?;
+                    self.emit(Opcode::TypeOfGlobal, &[name as i32]);
+                }
+                _ => {
+                    self.load(argument)?;
+                    self.emit(Opcode::TypeOf, &[]);
+                }
+            },
+            (
+                UnaryOp::Delete,
+                NodeKind::MemberExpression {
+                    object,
+                    property,
+                    computed,
+                },
+            ) => {
+                self.expression(object)?;
+                if let Some(name) = self.property_key(property, computed)? {
+                    self.emit(Opcode::Constant, &[name as i32]);
+                }
+                self.emit(Opcode::DeleteProperty, &[]);
+            }
+            // Declarative bindings cannot be deleted.
+            (UnaryOp::Delete, NodeKind::Identifier(name)) => match self.location(argument) {
+                Location::Global => {
+                    let name = self.name(name)?;
+                    self.emit(Opcode::DeleteGlobal, &[name as i32]);
+                }
+                _ => {
+                    self.emit(Opcode::False, &[]);
+                }
+            },
+            (UnaryOp::Delete | UnaryOp::Void, _) => {
+                self.expression(argument)?;
+                self.emit(Opcode::Pop, &[]);
+                let result = if operator == UnaryOp::Delete { Opcode::True } else { Opcode::Undefined };
+                self.emit(result, &[]);
+            }
+            (operator, _) => {
+                self.expression(argument)?;
+                let opcode = match operator {
+                    UnaryOp::Minus => Opcode::Negate,
+                    UnaryOp::Plus => Opcode::ToNumber,
+                    UnaryOp::Not => Opcode::Not,
+                    UnaryOp::BitNot => Opcode::BitNot,
+                    UnaryOp::TypeOf => Opcode::TypeOf,
+                    UnaryOp::Void | UnaryOp::Delete => unreachable!(),
+                };
+                self.emit(opcode, &[]);
+            }
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, operator: UpdateOp, prefix: bool, argument: NodeId) -> Result<(), JsError> {
+        let step = match operator {
+            UpdateOp::Increment => Opcode::Increment,
+            UpdateOp::Decrement => Opcode::Decrement,
+        };
+        match *self.arena.kind(argument) {
+            NodeKind::Identifier(_) => {
+                self.load(argument)?;
+                if !prefix {
+                    self.emit(Opcode::ToNumeric, &[]);
+                    self.emit(Opcode::Dup, &[]);
+                }
+                self.emit(step, &[]);
+                self.assign(argument)?;
+                if !prefix {
+                    self.emit(Opcode::Pop, &[]);
+                }
+            }
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                self.expression(object)?;
+                let key = self.property_key(property, computed)?;
+                self.duplicate_reference(key);
+                self.mark_position(argument);
+                self.get_property(key);
+                if prefix {
+                    self.emit(step, &[]);
+                    self.set_property(key);
+                } else {
+                    self.emit(Opcode::ToNumeric, &[]);
+                    self.emit(Opcode::Dup, &[]);
+                    let old = self.temporary();
+                    self.emit(Opcode::InitLocal, &[old as i32]);
+                    self.emit(step, &[]);
+                    self.set_property(key);
+                    self.emit(Opcode::Pop, &[]);
+                    self.emit(Opcode::GetLocal, &[old as i32]);
+                    self.release(old);
+                }
+            }
+            ref other => panic!("Invalid update target: {:?}", other),
+        }
+        Ok(())
+    }
+
+    /// Pushes the key of a computed member, converted to a property key as
+    /// a reference's is, or returns the constant naming a plain one. A
+    /// string key with lone surrogates is pushed too, as no atom names it.
+    fn property_key(&mut self, property: NodeId, computed: bool) -> Result<Option<u16>, JsError> {
+        let name = match *self.arena.kind(property) {
+            _ if computed => None,
+            NodeKind::Identifier(name) => Some(name),
+            NodeKind::Literal(ArenaLiteral::String(string)) => self.arena.string_name(string),
+            NodeKind::Literal(ArenaLiteral::Number(number)) => Some(Atom::new(&number_to_string(number))),
+            ref other => panic!("Invalid property key: {:?}", other),
+        };
+        if let Some(name) = name {
+            return self.name(name).map(Some);
+        }
+        self.expression(property)?;
+        self.emit(Opcode::ToPropertyKey, &[]);
+        Ok(None)
+    }
+
+    fn get_property(&mut self, key: Option<u16>) {
+        match key {
+            Some(name) => self.emit(Opcode::GetNamed, &[name as i32]),
+            None => self.emit(Opcode::GetProperty, &[]),
+        };
+    }
+
+    fn set_property(&mut self, key: Option<u16>) {
+        match key {
+            Some(name) => self.emit(Opcode::SetNamed, &[name as i32]),
+            None => self.emit(Opcode::SetProperty, &[]),
+        };
+    }
+
+    /// Duplicates the object, and the key if computed, of a member reference
+    /// that is read before it is written.
+    fn duplicate_reference(&mut self, key: Option<u16>) {
+        let opcode = if key.is_some() { Opcode::Dup } else { Opcode::Dup2 };
+        self.emit(opcode, &[]);
+    }
+
+    fn location(&self, identifier: NodeId) -> Location {
+        match self.scopes.binding_of(identifier) {
+            Some(binding) => *self
+                .locations
+                .get(&binding)
+                .expect("a binding is only used inside its scope"),
+            None => Location::Global,
+        }
+    }
+
+    fn load(&mut self, identifier: NodeId) -> Result<(), JsError> {
+        match self.location(identifier) {
+            Location::Register(register) => {
+                self.emit(Opcode::GetLocal, &[register as i32]);
+            }
+            Location::Scoped { scope, slot } => {
+                let depth = self.scope_depth(scope)?;
+                self.emit(Opcode::GetScoped, &[depth as i32, slot as i32]);
+            }
+            Location::Global => {
+                let name = self.name(self.identifier_atom(identifier))?;
+                self.emit(Opcode::GetGlobal, &[name as i32]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the value on top of the stack in the variable `identifier`
+    /// refers to, leaving it on the stack.
+    fn assign(&mut self, identifier: NodeId) -> Result<(), JsError> {
+        let location = self.location(identifier);
+        if let (Some(binding), Location::Register(_) | Location::Scoped { .. }) = (self.scopes.binding_of(identifier), location) {
+            if self.scopes.binding(binding).kind == BindingKind::Const {
+                // Reading first reports the temporal dead zone ahead of the
+                // assignment itself.
+                self.load(identifier)?;
+                self.emit(Opcode::Pop, &[]);
+                self.emit(Opcode::ThrowConstAssignment, &[]);
+                return Ok(());
+            }
+        }
+        match location {
+            Location::Register(register) => {
+                self.emit(Opcode::SetLocal, &[register as i32]);
+            }
+            Location::Scoped { scope, slot } => {
+                let depth = self.scope_depth(scope)?;
+                self.emit(Opcode::SetScoped, &[depth as i32, slot as i32]);
+            }
+            Location::Global => {
+                let name = self.name(self.identifier_atom(identifier))?;
+                self.emit(Opcode::SetGlobal, &[name as i32]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pops the value on top of the stack into `binding`, taking it out of
+    /// its temporal dead zone.
+    fn initialize(&mut self, binding: BindingId) -> Result<(), JsError> {
+        match self.locations[&binding] {
+            Location::Register(register) => {
+                self.emit(Opcode::InitLocal, &[register as i32]);
+            }
+            Location::Scoped { scope, slot } => {
+                let depth = self.scope_depth(scope)?;
+                self.emit(Opcode::InitScoped, &[depth as i32, slot as i32]);
+            }
+            Location::Global => {
+                let name = self.name(self.scopes.binding(binding).name)?;
+                self.emit(Opcode::InitGlobal, &[name as i32]);
+            }
+        }
+        Ok(())
+    }
+
+    /// How many scopes out from the current one the scope at `position` in
+    /// the chain is.
+    fn scope_depth(&self, position: usize) -> Result<u8, JsError> {
+        u8::try_from(self.chain.len() - 1 - position)
+            .map_err(|_| JsError::range_error("Too many nested scopes with captured variables"))
+    }
+
+    fn identifier_atom(&self, identifier: NodeId) -> Atom {
+        match *self.arena.kind(identifier) {
+            NodeKind::Identifier(name) => name,
+            ref other => panic!("Expected an identifier, found {:?}", other),
+        }
+    }
+
+    fn name(&mut self, name: Atom) -> Result<u16, JsError> {
+        self.constant(Constant::Name(name))
+    }
+
+    /// Index of `constant` in the pool, adding it if it is not there yet.
+    fn constant(&mut self, constant: Constant) -> Result<u16, JsError> {
+        let key = match &constant {
+            Constant::Number(number) => ConstantKey::Number(number.to_bits()),
+            Constant::String(text) => ConstantKey::String(text.code_units().collect()),
+            Constant::Name(name) => ConstantKey::Name(*name),
+            Constant::BigInt(value) => ConstantKey::BigInt(value.to_string_radix(10).into()),
+        };
+        if let Some(&index) = self.function.constant_indices.get(&key) {
+            return Ok(index);
+        }
+        let position = index(self.function.template.constants.len(), "constants")?;
+        self.function.template.constants.push(constant);
+        self.function.constant_indices.insert(key, position);
+        Ok(position)
+    }
+
+    fn register(&mut self, name: &str) -> Result<u16, JsError> {
+        let register = index(self.function.template.registers.len(), "registers")?;
+        self.function.template.registers.push(name.into());
+        Ok(register)
+    }
+
+    /// A register for an intermediate value, reused once released.
+    fn temporary(&mut self) -> u16 {
+        match self.function.free_temporaries.pop() {
+            Some(register) => register,
+            None => {
+                let name = format!("%{}", self.function.template.registers.len());
+                self.register(&name).expect("too many registers")
+            }
+        }
+    }
+
+    fn release(&mut self, register: u16) {
+        self.function.free_temporaries.push(register);
+    }
+
+    fn offset(&self) -> u32 {
+        self.function.template.code.len() as u32
+    }
+
+    /// Records that the code from here on comes from `node`.
+    fn mark_position(&mut self, node: NodeId) {
+        let offset = self.offset();
+        let position = self.arena.span(node).start as u32;
+        let positions = &mut self.function.template.positions;
+        match positions.last_mut() {
+            Some(last) if last.0 == offset => *last = (offset, position),
+            Some(last) if last.1 == position => {}
+            _ => positions.push((offset, position)),
+        }
+    }
+
+    fn emit(&mut self, opcode: Opcode, operands: &[i32]) -> usize {
+        let offset = self.function.template.code.len();
+        let code = &mut self.function.template.code;
+        code.push(opcode as u8);
+        for (operand, &value) in opcode.operands().iter().zip(operands) {
+            match operand.size() {
+                1 => code.push(value as u8),
+                2 => code.extend_from_slice(&(value as u16).to_le_bytes()),
+                _ => code.extend_from_slice(&value.to_le_bytes()),
+            }
+        }
+
+        let mut padded = [0; 2];
+        padded[..operands.len()].copy_from_slice(operands);
+        let instruction = Instruction {
+            offset,
+            opcode,
+            operands: padded,
+        };
+        self.function.depth += instruction.stack_effect();
+        let depth = self.function.depth.max(0) as u16;
+        self.function.template.max_stack = self.function.template.max_stack.max(depth);
+        offset
+    }
+
+    /// Emits a forward jump to be pointed somewhere with [`Compiler::patch_jump`].
+    fn emit_jump(&mut self, opcode: Opcode) -> usize {
+        self.emit(opcode, &[0])
+    }
+
+    fn emit_loop(&mut self, target: u32) {
+        let jump = self.emit_jump(Opcode::Jump);
+        self.patch_jump_to(jump, target as usize);
+    }
+
+    fn patch_jump(&mut self, jump: usize) {
+        let target = self.function.template.code.len();
+        self.patch_jump_to(jump, target);
+    }
+
+    fn patch_jump_to(&mut self, jump: usize, target: usize) {
+        let relative = (target as i64 - (jump + Opcode::Jump.size()) as i64) as i32;
+        self.function.template.code[jump + 1..jump + 5].copy_from_slice(&relative.to_le_bytes());
+    }
+}
+
+/// The jump that skips the right operand of a logical operator, keeping
+/// the left one as the result.
+fn short_circuit_jump(operator: LogicalOp) -> Opcode {
+    match operator {
+        LogicalOp::And => Opcode::JumpIfFalseOrPop,
+        LogicalOp::Or => Opcode::JumpIfTrueOrPop,
+        LogicalOp::NullishCoalescing => Opcode::JumpIfNotNullishOrPop,
+    }
+}
+
+fn index(value: usize, what: &str) -> Result<u16, JsError> {
+    u16::try_from(value).map_err(|_| JsError::range_error(format!("Too many {} in one function", what)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn compile(code: &str) -> Rc<FunctionTemplate> {
+        Compiler::compile(&ASTNode::parse_program(&Lexer::new(code).tokenize())).unwrap()
+    }
+
+    fn opcodes(template: &FunctionTemplate) -> Vec<Opcode> {
+        template.instructions().map(|instruction| instruction.opcode).collect()
+    }
+
+    /// Every instruction decodes, every jump lands on an instruction and the
+    /// stack never underflows, in `template` and its nested templates.
+    fn check_well_formed(template: &FunctionTemplate) {
+        let instructions: Vec<_> = template.instructions().collect();
+        let last = instructions.last().unwrap();
+        assert_eq!(last.next_offset(), template.code.len());
+        let starts: Vec<usize> = instructions.iter().map(|instruction| instruction.offset).collect();
+        for instruction in &instructions {
+            if let Some(target) = instruction.jump_target() {
+                assert!(starts.contains(&target), "jump to {} in {:?}", target, template.name);
+            }
+        }
+        for handler in &template.handlers {
+            assert!(handler.start < handler.end);
+            assert!(starts.contains(&(handler.target as usize)));
+        }
+        template.functions.iter().for_each(|function| check_well_formed(function));
+    }
+
+    #[test]
+    fn test_locals_registers_and_scopes() {
+        let script = compile(
+            "function outer(a, b) { let local = a; let shared = b; function inner() { return shared; } return inner; }",
+        );
+        check_well_formed(&script);
+        assert_eq!(script.globals.functions, vec![("outer".into(), 0)]);
+
+        let outer = &script.functions[0];
+        assert_eq!(outer.parameter_count, 2);
+        assert!(outer.registers.iter().any(|name| &**name == "local"));
+        assert!(!outer.registers.iter().any(|name| &**name == "shared"));
+        assert_eq!(outer.scopes, vec![Rc::from(vec![Rc::from("shared")])]);
+        assert_eq!(opcodes(outer)[0], Opcode::PushScope);
+
+        let inner = &outer.functions[0];
+        let get = inner.instructions().find(|instruction| instruction.opcode == Opcode::GetScoped).unwrap();
+        assert_eq!(get.operands, [0, 0]);
+        assert_eq!(&*inner.source, "function inner() {\n  return shared;\n}");
+    }
+
+    #[test]
+    fn test_globals_by_name() {
+        let script = compile("var x = 1; let y = x; const z = 2; x = y + z + 1; undeclared;");
+        check_well_formed(&script);
+        assert_eq!(script.globals.vars, vec![Atom::new("x")]);
+        assert_eq!(script.globals.lexicals, vec![(Atom::new("y"), false), (Atom::new("z"), true)]);
+
+        let ops = opcodes(&script);
+        for opcode in [Opcode::SetGlobal, Opcode::InitGlobal, Opcode::GetGlobal] {
+            assert!(ops.contains(&opcode), "{} missing", opcode);
+        }
+        assert_eq!(script.registers, vec![Rc::from("%completion")]);
+        // Names and the repeated literal `1` share constant pool entries.
+        let ones = script.constants.iter().filter(|constant| **constant == Constant::Number(1.0)).count();
+        assert_eq!(ones, 1);
+        assert!(script.constants.contains(&Constant::Name("undeclared".into())));
+    }
+
+    #[test]
+    fn test_loops_and_per_iteration_scopes() {
+        let script = compile(
+            "let fs = {}; outer: for (let i = 0; i < 3; i++) { fs[i] = function_of(i); while (true) { if (i) continue outer; break; } }\n\
+             function function_of(i) { return i; }",
+        );
+        check_well_formed(&script);
+        assert!(!opcodes(&script).contains(&Opcode::CopyScope));
+
+        let script = compile("for (let i = 0; i < 3; i++) { function f() { return i; } }");
+        check_well_formed(&script);
+        let ops = opcodes(&script);
+        assert_eq!(ops.iter().filter(|&&opcode| opcode == Opcode::CopyScope).count(), 2);
+        // Only the loop's scope holds a captured variable.
+        assert_eq!(ops.iter().filter(|&&opcode| opcode == Opcode::PushScope).count(), 1);
+        assert_eq!(ops.iter().filter(|&&opcode| opcode == Opcode::PopScope).count(), 1);
+    }
+
+    #[test]
+    fn test_exception_handlers() {
+        let script = compile("try { a(); } catch (e) { b(e); } finally { c(); }");
+        check_well_formed(&script);
+        // The catch covers the block; the finally covers block and catch.
+        assert_eq!(script.handlers.len(), 2);
+        assert!(script.handlers[0].end <= script.handlers[1].end);
+        assert!(script.handlers[0].target < script.handlers[1].target);
+
+        // A `break` out of the `try` runs a copy of the finally block, which
+        // is left out of the protected range.
+        let script = compile("while (x) { try { if (y) break; a(); } finally { c(); } }");
+        check_well_formed(&script);
+        let calls_to_c = script
+            .instructions()
+            .filter(|instruction| instruction.opcode == Opcode::Call)
+            .filter(|instruction| script.constants[instruction.operands[1] as usize] == Constant::String("c".into()))
+            .count();
+        assert_eq!(calls_to_c, 3);
+        assert_eq!(script.handlers.len(), 2);
+        assert!(script.handlers.iter().all(|handler| handler.target == script.handlers[0].target));
+    }
+
+    #[test]
+    fn test_source_positions_and_stack_size() {
+        let script = compile("a;\nb.c(1, 2, 3);");
+        check_well_formed(&script);
+        assert_eq!(script.source_position(0), Some(0));
+        let call = script.instructions().find(|instruction| instruction.opcode == Opcode::Call).unwrap();
+        assert_eq!(call.operands[0], 3);
+        assert_eq!(script.source_position(call.offset), Some(3));
+        // `b.c` as the function, `b` as `this`, and three arguments.
+        assert_eq!(script.max_stack, 5);
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let program = ASTNode::parse_program(&Lexer::new("let a; let a;").tokenize());
+        let error = Compiler::compile(&program).unwrap_err();
+        assert_eq!(error.to_string(), "SyntaxError: Identifier 'a' has already been declared");
+
+        let program = ASTNode::parse_program(&Lexer::new("/a/g;").tokenize());
+        assert!(Compiler::compile(&program).is_err());
+    }
+}
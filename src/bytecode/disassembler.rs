@@ -0,0 +1,254 @@
+use crate::bytecode::opcode::{Instruction, Opcode, Operand};
+use crate::bytecode::{Constant, FunctionTemplate, TemplateKind};
+use crate::lexical::span::SourceFile;
+use crate::parser::numeric::number_to_string;
+
+/// Prints compiled templates as text: a header with each template's
+/// registers, constant pool, scope layouts and exception handlers, then its
+/// instructions by offset, interleaved with the source lines they came
+/// from, then its nested templates.
+pub struct Disassembler<'a> {
+    source: Option<&'a SourceFile>,
+    output: String,
+}
+
+impl<'a> Disassembler<'a> {
+    /// Disassembles `template` and the templates nested in it. With the
+    /// source the script was compiled from, instructions are grouped under
+    /// their source lines; without it, under character offsets.
+    pub fn disassemble(template: &FunctionTemplate, source: Option<&'a SourceFile>) -> String {
+        let mut disassembler = Disassembler {
+            source,
+            output: String::new(),
+        };
+        disassembler.template(template, "");
+        disassembler.output
+    }
+
+    fn line(&mut self, text: &str) {
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Prints `template`, whose path of function indices from the script is
+    /// `path`, then its nested templates.
+    fn template(&mut self, template: &FunctionTemplate, path: &str) {
+        let title = match template.kind {
+            TemplateKind::Script => "script".to_string(),
+            TemplateKind::Function => format!("function {} [{}]", display_name(template), path),
+        };
+        let strict = if template.strict { ", strict" } else { "" };
+        self.line(&format!("== {}{} ==", title, strict));
+        self.line(&format!(
+            "parameters {}, registers {}, max stack {}",
+            template.parameter_count,
+            template.registers.len(),
+            template.max_stack
+        ));
+        self.header(template);
+
+        self.line("code:");
+        let mut last_line = None;
+        for instruction in template.instructions() {
+            if let Some(position) = template.source_position(instruction.offset) {
+                let line = self.source.map_or(position as usize, |source| source.location(position as usize).line);
+                if last_line != Some(line) {
+                    self.source_line(position as usize);
+                    last_line = Some(line);
+                }
+            }
+            self.instruction(template, &instruction);
+        }
+
+        for (index, function) in template.functions.iter().enumerate() {
+            self.line("");
+            let path = if path.is_empty() { format!("f{}", index) } else { format!("{}.f{}", path, index) };
+            self.template(function, &path);
+        }
+    }
+
+    fn header(&mut self, template: &FunctionTemplate) {
+        if !template.registers.is_empty() {
+            let registers: Vec<String> = template
+                .registers
+                .iter()
+                .enumerate()
+                .map(|(index, name)| format!("r{}={}", index, name))
+                .collect();
+            self.line(&format!("registers: {}", registers.join(" ")));
+        }
+        if !template.constants.is_empty() {
+            self.line("constants:");
+            for (index, constant) in template.constants.iter().enumerate() {
+                self.line(&format!("  #{:<4} {}", index, display_constant(constant)));
+            }
+        }
+        if !template.scopes.is_empty() {
+            self.line("scopes:");
+            for (index, names) in template.scopes.iter().enumerate() {
+                self.line(&format!("  s{:<4} [{}]", index, names.join(", ")));
+            }
+        }
+        if !template.handlers.is_empty() {
+            self.line("handlers:");
+            for handler in &template.handlers {
+                self.line(&format!(
+                    "  {:04}..{:04} -> {:04}  stack {}, scopes {}",
+                    handler.start, handler.end, handler.target, handler.stack_depth, handler.scope_depth
+                ));
+            }
+        }
+
+        let globals = &template.globals;
+        let mut declarations: Vec<String> = globals.vars.iter().map(|name| format!("var {}", name)).collect();
+        declarations.extend(
+            globals
+                .lexicals
+                .iter()
+                .map(|(name, constant)| format!("{} {}", if *constant { "const" } else { "let" }, name)),
+        );
+        declarations.extend(globals.functions.iter().map(|(name, index)| format!("function {} = f{}", name, index)));
+        if !declarations.is_empty() {
+            self.line(&format!("globals: {}", declarations.join(", ")));
+        }
+    }
+
+    /// A comment naming the source position the following code came from:
+    /// the first position on a line, and the line's text.
+    fn source_line(&mut self, offset: usize) {
+        let text = match self.source {
+            Some(source) => {
+                let location = source.location(offset);
+                format!("{}  {}", location, source.line_text(location.line).trim())
+            }
+            None => format!("@{}", offset),
+        };
+        self.line(&format!("        ; {}", text));
+    }
+
+    fn instruction(&mut self, template: &FunctionTemplate, instruction: &Instruction) {
+        let operands: Vec<String> = match instruction.jump_target() {
+            Some(target) => vec![format!("{:04}", target)],
+            None => instruction
+                .opcode
+                .operands()
+                .iter()
+                .zip(instruction.operands)
+                .map(|(operand, value)| match operand {
+                    Operand::Jump => format!("{:+}", value),
+                    _ => value.to_string(),
+                })
+                .collect(),
+        };
+        let text = format!("  {:04}  {:<20} {}", instruction.offset, instruction.opcode.name(), operands.join(", "));
+        match annotation(template, instruction) {
+            Some(note) => self.line(&format!("{:<44} ; {}", text.trim_end(), note)),
+            None => self.line(text.trim_end()),
+        }
+    }
+}
+
+/// What an instruction's operands refer to, where that is not obvious from
+/// the numbers.
+fn annotation(template: &FunctionTemplate, instruction: &Instruction) -> Option<String> {
+    let [first, second] = instruction.operands;
+    let constant = |index: i32| template.constants.get(index as usize).map(display_constant);
+    match instruction.opcode {
+        Opcode::Constant => constant(first),
+        Opcode::GetLocal | Opcode::SetLocal | Opcode::InitLocal | Opcode::ClearLocal => {
+            template.registers.get(first as usize).map(|name| name.to_string())
+        }
+        Opcode::PushScope => template.scopes.get(first as usize).map(|names| format!("[{}]", names.join(", "))),
+        Opcode::GetGlobal
+        | Opcode::SetGlobal
+        | Opcode::InitGlobal
+        | Opcode::TypeOfGlobal
+        | Opcode::DeleteGlobal
+        | Opcode::GetNamed
+        | Opcode::SetNamed
+        | Opcode::DefineNamed => constant(first),
+        Opcode::Call => {
+            let arguments = if first == 1 { "argument" } else { "arguments" };
+            Some(format!("{} {}, {}", first, arguments, constant(second)?))
+        }
+        Opcode::Closure => template
+            .functions
+            .get(first as usize)
+            .map(|function| format!("f{} {}", first, display_name(function))),
+        _ => None,
+    }
+}
+
+fn display_constant(constant: &Constant) -> String {
+    match constant {
+        Constant::Number(number) => number_to_string(*number),
+        Constant::String(text) => format!("{:?}", text),
+        Constant::Name(name) => format!("{:?}", name),
+        Constant::BigInt(value) => format!("{}n", value),
+    }
+}
+
+fn display_name(template: &FunctionTemplate) -> &str {
+    if template.name.is_empty() {
+        "<anonymous>"
+    } else {
+        &template.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compiler::Compiler;
+    use crate::lexical::lexer::Lexer;
+    use crate::parser::ast::ASTNode;
+
+    fn disassemble(code: &str) -> String {
+        let template = Compiler::compile(&ASTNode::parse_program(&Lexer::new(code).tokenize())).unwrap();
+        Disassembler::disassemble(&template, Some(&SourceFile::new("test.js", code)))
+    }
+
+    #[test]
+    fn test_script() {
+        let expected = "\
+== script ==
+parameters 0, registers 1, max stack 2
+registers: r0=%completion
+constants:
+  #0    1
+  #1    \"x\"
+globals: let x
+code:
+        ; 1:1  let x = 1;
+  0000  Undefined
+  0001  InitLocal            0               ; %completion
+  0004  Constant             0               ; 1
+  0007  InitGlobal           1               ; \"x\"
+        ; 2:1  x + 1;
+  0010  GetGlobal            1               ; \"x\"
+  0013  Constant             0               ; 1
+  0016  Add
+  0017  InitLocal            0               ; %completion
+  0020  GetLocal             0               ; %completion
+  0023  Return
+";
+        assert_eq!(disassemble("let x = 1;\nx + 1;"), expected);
+    }
+
+    #[test]
+    fn test_nested_functions_and_handlers() {
+        let output = disassemble(
+            "function outer(a) {\n  function inner() { return a; }\n  try { return inner(); } catch (e) { throw e; }\n}",
+        );
+        assert!(output.contains("globals: function outer = f0\n"), "{}", output);
+        assert!(output.contains("\n== function outer [f0] ==\nparameters 1,"), "{}", output);
+        assert!(output.contains("\n== function inner [f0.f0] ==\n"), "{}", output);
+        assert!(output.contains("scopes:\n  s0    [a]\n"), "{}", output);
+        assert!(output.contains("handlers:\n  00"), "{}", output);
+        assert!(output.contains("; 3:9  try { return inner(); } catch (e) { throw e; }\n  0016"), "{}", output);
+        assert_eq!(output.matches("; 3:").count(), 1, "{}", output);
+        assert!(output.contains("Closure              0               ; f0 inner\n"), "{}", output);
+        assert!(output.contains("; 0 arguments, \"inner\"\n"), "{}", output);
+        assert!(output.contains("  0000  GetScoped            0, 0\n"), "{}", output);
+    }
+}
@@ -0,0 +1,99 @@
+pub mod cache;
+pub mod compiler;
+pub mod disassembler;
+pub mod opcode;
+
+use crate::bytecode::opcode::Instructions;
+use crate::interpreter::inline_cache::InlineCaches;
+use crate::lexical::atom::Atom;
+use crate::runtime::bigint::BigInt;
+use crate::runtime::string::JsString;
+use std::rc::Rc;
+
+/// An entry of a function's constant pool.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Number(f64),
+    String(JsString),
+    /// The names of globals and properties.
+    Name(Atom),
+    BigInt(Rc<BigInt>),
+}
+
+/// A protected range of code and where control goes when an exception is
+/// thrown inside it. The VM drops the scopes and operand stack values the
+/// range pushed, then pushes the exception and jumps to `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handler {
+    pub start: u32,
+    pub end: u32,
+    pub target: u32,
+    /// Operand stack height at the start of the range.
+    pub stack_depth: u16,
+    /// Scopes pushed by the function at the start of the range.
+    pub scope_depth: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    Script,
+    Function,
+}
+
+/// Top-level declarations of a script, which live in the global
+/// environment rather than in registers, for GlobalDeclarationInstantiation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlobalDeclarations {
+    pub vars: Vec<Atom>,
+    /// Each function's name and index among the script's nested templates.
+    pub functions: Vec<(Atom, u16)>,
+    /// `let` and `const` names, with whether they are constant.
+    pub lexicals: Vec<(Atom, bool)>,
+}
+
+/// Compiled code of a script or function: what every closure created from
+/// it shares.
+///
+/// Arguments arrive in the first `parameter_count` registers; the others
+/// start in their temporal dead zone. Variables that nested functions
+/// capture live in scopes instead, pushed with `PushScope` and addressed by
+/// depth and slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionTemplate {
+    pub kind: TemplateKind,
+    pub name: Rc<str>,
+    pub parameter_count: u16,
+    /// The variable each register holds, for error messages; temporaries
+    /// have names starting with `%`.
+    pub registers: Vec<Rc<str>>,
+    /// Slot names of each scope the code pushes.
+    pub scopes: Vec<Rc<[Rc<str>]>>,
+    /// Operand stack space the code needs.
+    pub max_stack: u16,
+    pub strict: bool,
+    pub code: Vec<u8>,
+    pub constants: Vec<Constant>,
+    pub functions: Vec<Rc<FunctionTemplate>>,
+    /// Innermost first, so the first range containing the throwing
+    /// instruction handles it.
+    pub handlers: Vec<Handler>,
+    /// `(code offset, source offset)` pairs in code order: the instructions
+    /// from each offset on were compiled from source at that position.
+    pub positions: Vec<(u32, u32)>,
+    pub globals: GlobalDeclarations,
+    /// Source text, returned when the function is converted to a string.
+    pub source: Rc<str>,
+    pub caches: InlineCaches,
+}
+
+impl FunctionTemplate {
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(&self.code)
+    }
+
+    /// Source offset of the code at `offset`.
+    pub fn source_position(&self, offset: usize) -> Option<u32> {
+        let index = self.positions.partition_point(|&(start, _)| start as usize <= offset);
+        index.checked_sub(1).map(|index| self.positions[index].1)
+    }
+}
@@ -0,0 +1,404 @@
+use crate::parser::operators::BinaryOp;
+use std::fmt;
+
+/// How an operand is encoded after its opcode byte. Multi-byte operands are
+/// little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// A `u8`, such as how many scopes out a scoped variable lives.
+    Byte,
+    /// A `u16` index into a register file, scope, constant pool or table.
+    Index,
+    /// An `i32` jump offset, relative to the end of the instruction.
+    Jump,
+}
+
+impl Operand {
+    pub fn size(self) -> usize {
+        match self {
+            Operand::Byte => 1,
+            Operand::Index => 2,
+            Operand::Jump => 4,
+        }
+    }
+}
+
+macro_rules! opcodes {
+    ($($(#[doc = $doc:literal])* $name:ident [$($operand:ident),*] $effect:literal,)*) => {
+        /// One instruction of the stack machine. Operands follow the opcode
+        /// byte as described by [`Opcode::operands`]; values are pushed on and
+        /// popped off the frame's operand stack.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(u8)]
+        pub enum Opcode {
+            $($(#[doc = $doc])* $name,)*
+        }
+
+        const OPCODES: &[Opcode] = &[$(Opcode::$name,)*];
+
+        impl Opcode {
+            pub fn from_byte(byte: u8) -> Option<Opcode> {
+                OPCODES.get(byte as usize).copied()
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Opcode::$name => stringify!($name),)*
+                }
+            }
+
+            pub fn operands(self) -> &'static [Operand] {
+                match self {
+                    $(Opcode::$name => &[$(Operand::$operand),*],)*
+                }
+            }
+
+            /// Net change in operand stack height when the instruction falls
+            /// through. `Call`'s depends on its argument count, see
+            /// [`Instruction::stack_effect`].
+            fn fixed_stack_effect(self) -> i32 {
+                match self {
+                    $(Opcode::$name => $effect,)*
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    Undefined [] 1,
+    Null [] 1,
+    True [] 1,
+    False [] 1,
+    /// Pushes the frame's `this` value.
+    This [] 1,
+    /// Pushes an entry of the constant pool.
+    Constant [Index] 1,
+    Pop [] -1,
+    Dup [] 1,
+    /// Duplicates the top two values, keeping their order.
+    Dup2 [] 2,
+    Swap [] 0,
+
+    /// Pushes a register; throws if it is in its temporal dead zone.
+    GetLocal [Index] 1,
+    /// Stores the top of the stack in an initialized register, leaving it
+    /// on the stack.
+    SetLocal [Index] 0,
+    /// Pops a value into a register, initializing it.
+    InitLocal [Index] -1,
+    /// Puts a register back into its temporal dead zone.
+    ClearLocal [Index] 0,
+
+    /// Enters a new scope, laid out by an entry of the template's scope
+    /// table, for variables that closures capture.
+    PushScope [Index] 0,
+    PopScope [] 0,
+    /// Replaces the current scope with a copy, so that closures made in one
+    /// loop iteration keep that iteration's `let` bindings.
+    CopyScope [] 0,
+    /// Pushes slot `Index` of the scope `Byte` levels out from the current one.
+    GetScoped [Byte, Index] 1,
+    SetScoped [Byte, Index] 0,
+    InitScoped [Byte, Index] -1,
+
+    /// Reads the global binding named by a string constant.
+    GetGlobal [Index] 1,
+    /// Assigns to a global binding, leaving the value on the stack. Sloppy
+    /// code creates a global object property if there is no binding.
+    SetGlobal [Index] 0,
+    /// Pops a value into a global `let` or `const` binding, initializing it.
+    InitGlobal [Index] -1,
+    /// `typeof name` for a global: "undefined" rather than a ReferenceError
+    /// when nothing binds the name.
+    TypeOfGlobal [Index] 1,
+    DeleteGlobal [Index] 1,
+    /// Throws the TypeError for assigning to a `const` binding.
+    ThrowConstAssignment [] 0,
+
+    /// Replaces an object and a key with the property's value.
+    GetProperty [] -1,
+    /// Replaces an object with its property named by a string constant.
+    GetNamed [Index] 0,
+    /// Pops an object, key and value, stores the property and pushes the value.
+    SetProperty [] -2,
+    SetNamed [Index] -1,
+    DeleteProperty [] -1,
+    ToPropertyKey [] 0,
+
+    NewObject [] 1,
+    /// Pops a key and value and defines a data property on the object below
+    /// them, as object literals do.
+    DefineProperty [] -2,
+    DefineNamed [Index] -1,
+
+    Add [] -1,
+    Sub [] -1,
+    Mul [] -1,
+    Div [] -1,
+    Mod [] -1,
+    Exp [] -1,
+    Eq [] -1,
+    NotEq [] -1,
+    StrictEq [] -1,
+    StrictNotEq [] -1,
+    Lt [] -1,
+    LtEq [] -1,
+    Gt [] -1,
+    GtEq [] -1,
+    BitAnd [] -1,
+    BitOr [] -1,
+    BitXor [] -1,
+    Shl [] -1,
+    Shr [] -1,
+    UShr [] -1,
+    In [] -1,
+    InstanceOf [] -1,
+    Negate [] 0,
+    /// Unary `+`: ToNumber.
+    ToNumber [] 0,
+    Not [] 0,
+    BitNot [] 0,
+    TypeOf [] 0,
+    ToNumeric [] 0,
+    /// ToNumeric, then adds one.
+    Increment [] 0,
+    Decrement [] 0,
+
+    Jump [Jump] 0,
+    /// Pops the top of the stack and jumps if it is falsy.
+    JumpIfFalse [Jump] -1,
+    JumpIfTrue [Jump] -1,
+    /// Jumps, keeping the top of the stack, if it is falsy; pops it otherwise.
+    JumpIfFalseOrPop [Jump] -1,
+    JumpIfTrueOrPop [Jump] -1,
+    JumpIfNotNullishOrPop [Jump] -1,
+
+    /// Calls with `Index` arguments, popped from above the function and the
+    /// `this` value. The second operand is a string constant naming the
+    /// callee for the "is not a function" TypeError.
+    Call [Index, Index] -1,
+    /// Pushes a new function object for a nested function template, closing
+    /// over the current scope.
+    Closure [Index] 1,
+    Return [] -1,
+    Throw [] -1,
+}
+
+impl Opcode {
+    pub fn from_binary_op(operator: BinaryOp) -> Opcode {
+        match operator {
+            BinaryOp::Add => Opcode::Add,
+            BinaryOp::Sub => Opcode::Sub,
+            BinaryOp::Mul => Opcode::Mul,
+            BinaryOp::Div => Opcode::Div,
+            BinaryOp::Mod => Opcode::Mod,
+            BinaryOp::Exp => Opcode::Exp,
+            BinaryOp::Eq => Opcode::Eq,
+            BinaryOp::NotEq => Opcode::NotEq,
+            BinaryOp::StrictEq => Opcode::StrictEq,
+            BinaryOp::StrictNotEq => Opcode::StrictNotEq,
+            BinaryOp::Lt => Opcode::Lt,
+            BinaryOp::LtEq => Opcode::LtEq,
+            BinaryOp::Gt => Opcode::Gt,
+            BinaryOp::GtEq => Opcode::GtEq,
+            BinaryOp::BitAnd => Opcode::BitAnd,
+            BinaryOp::BitOr => Opcode::BitOr,
+            BinaryOp::BitXor => Opcode::BitXor,
+            BinaryOp::Shl => Opcode::Shl,
+            BinaryOp::Shr => Opcode::Shr,
+            BinaryOp::UShr => Opcode::UShr,
+            BinaryOp::In => Opcode::In,
+            BinaryOp::InstanceOf => Opcode::InstanceOf,
+        }
+    }
+
+    /// The operator a binary opcode applies, if it is one.
+    pub fn binary_op(self) -> Option<BinaryOp> {
+        Some(match self {
+            Opcode::Add => BinaryOp::Add,
+            Opcode::Sub => BinaryOp::Sub,
+            Opcode::Mul => BinaryOp::Mul,
+            Opcode::Div => BinaryOp::Div,
+            Opcode::Mod => BinaryOp::Mod,
+            Opcode::Exp => BinaryOp::Exp,
+            Opcode::Eq => BinaryOp::Eq,
+            Opcode::NotEq => BinaryOp::NotEq,
+            Opcode::StrictEq => BinaryOp::StrictEq,
+            Opcode::StrictNotEq => BinaryOp::StrictNotEq,
+            Opcode::Lt => BinaryOp::Lt,
+            Opcode::LtEq => BinaryOp::LtEq,
+            Opcode::Gt => BinaryOp::Gt,
+            Opcode::GtEq => BinaryOp::GtEq,
+            Opcode::BitAnd => BinaryOp::BitAnd,
+            Opcode::BitOr => BinaryOp::BitOr,
+            Opcode::BitXor => BinaryOp::BitXor,
+            Opcode::Shl => BinaryOp::Shl,
+            Opcode::Shr => BinaryOp::Shr,
+            Opcode::UShr => BinaryOp::UShr,
+            Opcode::In => BinaryOp::In,
+            Opcode::InstanceOf => BinaryOp::InstanceOf,
+            _ => return None,
+        })
+    }
+
+    pub fn is_jump(self) -> bool {
+        self.operands() == [Operand::Jump]
+    }
+
+    /// Size of the whole instruction, opcode byte included.
+    pub fn size(self) -> usize {
+        1 + self.operands().iter().map(|operand| operand.size()).sum::<usize>()
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A decoded instruction. Operands are widened to `i32`; unused ones are 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub opcode: Opcode,
+    pub operands: [i32; 2],
+}
+
+impl Instruction {
+    /// Decodes the instruction at `offset`, or returns `None` if the bytes
+    /// there are not a whole instruction.
+    pub fn decode(code: &[u8], offset: usize) -> Option<Instruction> {
+        let opcode = Opcode::from_byte(*code.get(offset)?)?;
+        let mut operands = [0; 2];
+        let mut position = offset + 1;
+        for (slot, operand) in operands.iter_mut().zip(opcode.operands()) {
+            let bytes = code.get(position..position + operand.size())?;
+            *slot = match operand {
+                Operand::Byte => bytes[0] as i32,
+                Operand::Index => u16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+                Operand::Jump => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            };
+            position += operand.size();
+        }
+        Some(Instruction {
+            offset,
+            opcode,
+            operands,
+        })
+    }
+
+    pub fn next_offset(&self) -> usize {
+        self.offset + self.opcode.size()
+    }
+
+    /// Where a jump instruction goes.
+    pub fn jump_target(&self) -> Option<usize> {
+        self.opcode
+            .is_jump()
+            .then(|| (self.next_offset() as i64 + self.operands[0] as i64) as usize)
+    }
+
+    pub fn stack_effect(&self) -> i32 {
+        match self.opcode {
+            Opcode::Call => -1 - self.operands[0],
+            opcode => opcode.fixed_stack_effect(),
+        }
+    }
+
+    /// How many values the instruction reads from the top of the operand
+    /// stack, which must be there before it runs.
+    pub fn stack_inputs(&self) -> i32 {
+        match self.opcode {
+            Opcode::Call => 2 + self.operands[0],
+            Opcode::SetProperty | Opcode::DefineProperty => 3,
+            Opcode::Dup2
+            | Opcode::Swap
+            | Opcode::GetProperty
+            | Opcode::SetNamed
+            | Opcode::DeleteProperty
+            | Opcode::DefineNamed => 2,
+            opcode if opcode.binary_op().is_some() => 2,
+            Opcode::Pop
+            | Opcode::Dup
+            | Opcode::SetLocal
+            | Opcode::InitLocal
+            | Opcode::SetScoped
+            | Opcode::InitScoped
+            | Opcode::SetGlobal
+            | Opcode::InitGlobal
+            | Opcode::GetNamed
+            | Opcode::ToPropertyKey
+            | Opcode::Negate
+            | Opcode::ToNumber
+            | Opcode::Not
+            | Opcode::BitNot
+            | Opcode::TypeOf
+            | Opcode::ToNumeric
+            | Opcode::Increment
+            | Opcode::Decrement
+            | Opcode::JumpIfFalse
+            | Opcode::JumpIfTrue
+            | Opcode::JumpIfFalseOrPop
+            | Opcode::JumpIfTrueOrPop
+            | Opcode::JumpIfNotNullishOrPop
+            | Opcode::Return
+            | Opcode::Throw => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Iterates over the instructions of a code buffer, stopping at the first
+/// malformed one.
+pub struct Instructions<'a> {
+    code: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(code: &'a [u8]) -> Self {
+        Instructions { code, offset: 0 }
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Instruction;
+
+    fn next(&mut self) -> Option<Instruction> {
+        let instruction = Instruction::decode(self.code, self.offset)?;
+        self.offset = instruction.next_offset();
+        Some(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_bytes_round_trip() {
+        for &opcode in OPCODES {
+            assert_eq!(Opcode::from_byte(opcode as u8), Some(opcode));
+        }
+        assert_eq!(Opcode::from_byte(OPCODES.len() as u8), None);
+        assert_eq!(Opcode::from_binary_op(BinaryOp::UShr).binary_op(), Some(BinaryOp::UShr));
+        assert_eq!(Opcode::Not.binary_op(), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut code = vec![Opcode::GetScoped as u8, 2, 0x34, 0x12, Opcode::Jump as u8];
+        code.extend_from_slice(&(-9i32).to_le_bytes());
+        code.push(Opcode::Call as u8);
+
+        let instructions: Vec<_> = Instructions::new(&code).collect();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].operands, [2, 0x1234]);
+        assert_eq!(instructions[1].offset, 4);
+        assert_eq!(instructions[1].jump_target(), Some(0));
+        // The trailing `Call` is missing its operands.
+        assert_eq!(Instruction::decode(&code, 9), None);
+    }
+}
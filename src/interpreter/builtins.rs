@@ -0,0 +1,769 @@
+use crate::interpreter::Interpreter;
+use crate::parser::codegen::{quote_code_units, quote_string};
+use crate::parser::numeric::number_to_radix_string;
+use crate::runtime::conversions::{to_number, to_object, to_property_key, to_string};
+use crate::runtime::error::{error_to_string, ErrorKind, JsError};
+use crate::runtime::object::{FunctionKind, JsObject, NativeFn, NativeFunction, ObjectKind, ObjectRef};
+use crate::runtime::property::{PropertyDescriptor, PropertyKey};
+use crate::runtime::realm::Realm;
+use crate::runtime::symbol::Symbol;
+use crate::runtime::value::JsValue;
+use crate::runtime::weak::{FinalizationCells, WeakTable};
+
+/// Gives the global object the properties every script can use, and the
+/// intrinsic prototypes their methods.
+pub fn install_globals(global: &ObjectRef, realm: &Realm) {
+    install_prototypes(realm);
+
+    for (name, value) in [
+        ("undefined", JsValue::Undefined),
+        ("NaN", JsValue::Number(f64::NAN)),
+        ("Infinity", JsValue::Number(f64::INFINITY)),
+    ] {
+        global.define_own_property(name.into(), PropertyDescriptor::data(value, false, false, false));
+    }
+
+    let console = realm.ordinary_object();
+    console.create_data_property("log", native_function(realm, "log", console_log));
+    for (name, value) in [
+        ("globalThis", global.clone()),
+        ("console", console),
+        ("Object", object_constructor(realm)),
+        ("Error", error_constructor(realm, ErrorKind::Error, error_call)),
+        ("TypeError", error_constructor(realm, ErrorKind::TypeError, type_error_call)),
+        ("ReferenceError", error_constructor(realm, ErrorKind::ReferenceError, reference_error_call)),
+        ("RangeError", error_constructor(realm, ErrorKind::RangeError, range_error_call)),
+        ("SyntaxError", error_constructor(realm, ErrorKind::SyntaxError, syntax_error_call)),
+        ("WeakMap", constructor(realm, "WeakMap", &realm.weak_map_prototype, weak_map_call)),
+        ("WeakSet", constructor(realm, "WeakSet", &realm.weak_set_prototype, weak_set_call)),
+        ("WeakRef", constructor(realm, "WeakRef", &realm.weak_ref_prototype, weak_ref_call)),
+        (
+            "FinalizationRegistry",
+            constructor(realm, "FinalizationRegistry", &realm.finalization_registry_prototype, finalization_registry_call),
+        ),
+    ] {
+        global.define_own_property(name.into(), PropertyDescriptor::data(value.into(), true, false, true));
+    }
+}
+
+pub fn native_function(realm: &Realm, name: &'static str, call: NativeFn) -> JsValue {
+    ObjectRef::function(&realm.function_prototype, FunctionKind::Native(NativeFunction { name, call })).into()
+}
+
+/// Defines a built-in method: writable and configurable, but not
+/// enumerable.
+fn define_method(realm: &Realm, object: &ObjectRef, name: &'static str, call: NativeFn) {
+    let function = native_function(realm, name, call);
+    object.define_own_property(name.into(), PropertyDescriptor::data(function, true, false, true));
+}
+
+fn install_prototypes(realm: &Realm) {
+    let prototype = &realm.object_prototype;
+    define_method(realm, prototype, "hasOwnProperty", object_has_own_property);
+    define_method(realm, prototype, "isPrototypeOf", object_is_prototype_of);
+    define_method(realm, prototype, "propertyIsEnumerable", object_property_is_enumerable);
+    define_method(realm, prototype, "toString", object_to_string);
+    define_method(realm, prototype, "valueOf", object_value_of);
+
+    define_method(realm, &realm.function_prototype, "call", function_call);
+    define_method(realm, &realm.function_prototype, "toString", function_to_string);
+
+    for (prototype, to_string, value_of) in [
+        (&realm.boolean_prototype, boolean_to_string as NativeFn, boolean_value_of as NativeFn),
+        (&realm.number_prototype, number_to_string, number_value_of),
+        (&realm.string_prototype, string_value_of, string_value_of),
+        (&realm.bigint_prototype, bigint_to_string, bigint_value_of),
+        (&realm.symbol_prototype, symbol_to_string, symbol_value_of),
+    ] {
+        define_method(realm, prototype, "toString", to_string);
+        define_method(realm, prototype, "valueOf", value_of);
+    }
+
+    define_method(realm, &realm.error_prototype, "toString", error_to_string_method);
+
+    define_method(realm, &realm.weak_map_prototype, "delete", weak_map_delete);
+    define_method(realm, &realm.weak_map_prototype, "get", weak_map_get);
+    define_method(realm, &realm.weak_map_prototype, "has", weak_map_has);
+    define_method(realm, &realm.weak_map_prototype, "set", weak_map_set);
+    define_method(realm, &realm.weak_set_prototype, "add", weak_set_add);
+    define_method(realm, &realm.weak_set_prototype, "delete", weak_set_delete);
+    define_method(realm, &realm.weak_set_prototype, "has", weak_set_has);
+    define_method(realm, &realm.weak_ref_prototype, "deref", weak_ref_deref);
+    let registry_prototype = &realm.finalization_registry_prototype;
+    define_method(realm, registry_prototype, "register", finalization_registry_register);
+    define_method(realm, registry_prototype, "unregister", finalization_registry_unregister);
+
+    let symbol_prototype = &realm.symbol_prototype;
+    let description = native_function(realm, "get description", symbol_description);
+    let accessor = PropertyDescriptor::accessor(description, JsValue::Undefined, false, true);
+    symbol_prototype.define_own_property("description".into(), accessor);
+    let to_primitive = native_function(realm, "[Symbol.toPrimitive]", symbol_value_of);
+    let descriptor = PropertyDescriptor::data(to_primitive, false, false, true);
+    symbol_prototype.define_own_property(Symbol::to_primitive().into(), descriptor);
+}
+
+/// `Object`: called as a function, it converts its argument to an object.
+/// It also holds the reflection functions for working with descriptors
+/// and prototypes.
+fn object_constructor(realm: &Realm) -> ObjectRef {
+    let JsValue::Object(object) = native_function(realm, "Object", object_call) else {
+        unreachable!()
+    };
+    let prototype = realm.object_prototype.clone();
+    object.define_own_property("prototype".into(), PropertyDescriptor::data(prototype.into(), false, false, false));
+    let constructor = PropertyDescriptor::data(object.clone().into(), true, false, true);
+    realm.object_prototype.define_own_property("constructor".into(), constructor);
+
+    define_method(realm, &object, "create", object_create);
+    define_method(realm, &object, "defineProperty", object_define_property);
+    define_method(realm, &object, "getOwnPropertyDescriptor", object_get_own_property_descriptor);
+    define_method(realm, &object, "getPrototypeOf", object_get_prototype_of);
+    define_method(realm, &object, "isExtensible", object_is_extensible);
+    define_method(realm, &object, "preventExtensions", object_prevent_extensions);
+    define_method(realm, &object, "setPrototypeOf", object_set_prototype_of);
+    object
+}
+
+/// A constructor function linked both ways with its `prototype`.
+fn constructor(realm: &Realm, name: &'static str, prototype: &ObjectRef, call: NativeFn) -> ObjectRef {
+    let JsValue::Object(constructor) = native_function(realm, name, call) else {
+        unreachable!()
+    };
+    let descriptor = PropertyDescriptor::data(prototype.clone().into(), false, false, false);
+    constructor.define_own_property("prototype".into(), descriptor);
+    let descriptor = PropertyDescriptor::data(constructor.clone().into(), true, false, true);
+    prototype.define_own_property("constructor".into(), descriptor);
+    constructor
+}
+
+/// `Error` and the native error constructors. Their prototypes name the
+/// kind of error and give an empty default message.
+fn error_constructor(realm: &Realm, kind: ErrorKind, call: NativeFn) -> ObjectRef {
+    let prototype = realm.native_error_prototype(kind);
+    let constructor = constructor(realm, kind.as_str(), prototype, call);
+    for (key, value) in [
+        ("name", kind.as_str().into()),
+        ("message", "".into()),
+    ] {
+        prototype.define_own_property(key.into(), PropertyDescriptor::data(value, true, false, true));
+    }
+    constructor
+}
+
+/// Calling an error constructor, with or without `new`, makes an error
+/// object; a `message` other than `undefined` is converted to a string.
+fn construct_error(interpreter: &mut Interpreter, kind: ErrorKind, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let message = match argument(args, 0) {
+        JsValue::Undefined => None,
+        message => Some(to_string(interpreter, &message)?.to_std_string()),
+    };
+    Ok(interpreter.realm().error_object(kind, message.as_deref()).into())
+}
+
+fn error_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    construct_error(interpreter, ErrorKind::Error, args)
+}
+
+fn type_error_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    construct_error(interpreter, ErrorKind::TypeError, args)
+}
+
+fn reference_error_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    construct_error(interpreter, ErrorKind::ReferenceError, args)
+}
+
+fn range_error_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    construct_error(interpreter, ErrorKind::RangeError, args)
+}
+
+fn syntax_error_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    construct_error(interpreter, ErrorKind::SyntaxError, args)
+}
+
+/// `Error.prototype.toString`: `name: message`, leaving out an empty part.
+fn error_to_string_method(interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    let Some(object) = this.as_object() else {
+        return Err(JsError::type_error(format!(
+            "Error.prototype.toString requires that 'this' be an Object: {}",
+            inspect(this)
+        )));
+    };
+    let mut part = |key: &str, default: &str| -> Result<String, JsError> {
+        match object.get(interpreter, &key.into(), this)? {
+            JsValue::Undefined => Ok(default.to_string()),
+            value => Ok(to_string(interpreter, &value)?.to_std_string()),
+        }
+    };
+    let name = part("name", "Error")?;
+    let message = part("message", "")?;
+    Ok(match (name.is_empty(), message.is_empty()) {
+        (_, true) => name,
+        (true, false) => message,
+        (false, false) => format!("{}: {}", name, message),
+    }
+    .into())
+}
+
+// The weak collections and references. Like the error constructors, their
+// constructors make a new object whether or not they are called with `new`.
+// `WeakMap` and `WeakSet` take no initial entries, as there is no iteration
+// protocol to read them with.
+
+fn weak_map_call(interpreter: &mut Interpreter, _this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    let prototype = interpreter.realm().weak_map_prototype.clone();
+    Ok(ObjectRef::new(JsObject::new(Some(prototype), ObjectKind::WeakMap(WeakTable::default()))).into())
+}
+
+fn weak_set_call(interpreter: &mut Interpreter, _this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    let prototype = interpreter.realm().weak_set_prototype.clone();
+    Ok(ObjectRef::new(JsObject::new(Some(prototype), ObjectKind::WeakSet(WeakTable::default()))).into())
+}
+
+fn weak_ref_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let JsValue::Object(target) = argument(args, 0) else {
+        return Err(JsError::type_error("WeakRef: target must be an object"));
+    };
+    let prototype = interpreter.realm().weak_ref_prototype.clone();
+    let weak_ref = ObjectRef::new(JsObject::new(Some(prototype), ObjectKind::WeakRef(target.downgrade())));
+    interpreter.keep_alive(target);
+    Ok(weak_ref.into())
+}
+
+fn finalization_registry_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let callback = argument(args, 0);
+    let Some(callback) = callback.as_object().filter(|callback| callback.is_callable()) else {
+        return Err(JsError::type_error("FinalizationRegistry: cleanup must be callable"));
+    };
+    let prototype = interpreter.realm().finalization_registry_prototype.clone();
+    let kind = ObjectKind::FinalizationRegistry(FinalizationCells::new(callback.clone()));
+    Ok(ObjectRef::new(JsObject::new(Some(prototype), kind)).into())
+}
+
+fn incompatible_receiver(method: &str, this: &JsValue) -> JsError {
+    JsError::type_error(format!("Method {} called on incompatible receiver {}", method, inspect(this)))
+}
+
+/// Runs `f` on the table of `this`, which must be a `WeakMap` for methods
+/// of `WeakMap.prototype` and a `WeakSet` for those of `WeakSet.prototype`.
+fn weak_table<R>(this: &JsValue, method: &str, f: impl FnOnce(&mut WeakTable) -> R) -> Result<R, JsError> {
+    if let Some(object) = this.as_object() {
+        match (&mut object.borrow_mut().kind, method.starts_with("WeakMap")) {
+            (ObjectKind::WeakMap(table), true) | (ObjectKind::WeakSet(table), false) => return Ok(f(table)),
+            _ => {}
+        }
+    }
+    Err(incompatible_receiver(method, this))
+}
+
+fn weak_map_get(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let key = argument(args, 0);
+    weak_table(this, "WeakMap.prototype.get", |table| {
+        key.as_object().and_then(|key| table.get(key)).unwrap_or(JsValue::Undefined)
+    })
+}
+
+fn weak_map_set(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let key = argument(args, 0);
+    weak_table(this, "WeakMap.prototype.set", |table| match key.as_object() {
+        Some(key) => {
+            table.set(key, argument(args, 1));
+            Ok(this.clone())
+        }
+        None => Err(JsError::type_error(format!("Invalid value used as weak map key: {}", inspect(&key)))),
+    })?
+}
+
+fn weak_map_has(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let key = argument(args, 0);
+    weak_table(this, "WeakMap.prototype.has", |table| key.as_object().is_some_and(|key| table.has(key)).into())
+}
+
+fn weak_map_delete(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let key = argument(args, 0);
+    weak_table(this, "WeakMap.prototype.delete", |table| key.as_object().is_some_and(|key| table.delete(key)).into())
+}
+
+fn weak_set_add(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let value = argument(args, 0);
+    weak_table(this, "WeakSet.prototype.add", |table| match value.as_object() {
+        Some(value) => {
+            table.set(value, JsValue::Undefined);
+            Ok(this.clone())
+        }
+        None => Err(JsError::type_error(format!("Invalid value used in weak set: {}", inspect(&value)))),
+    })?
+}
+
+fn weak_set_has(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let value = argument(args, 0);
+    weak_table(this, "WeakSet.prototype.has", |table| value.as_object().is_some_and(|value| table.has(value)).into())
+}
+
+fn weak_set_delete(_interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let value = argument(args, 0);
+    weak_table(this, "WeakSet.prototype.delete", |table| {
+        value.as_object().is_some_and(|value| table.delete(value)).into()
+    })
+}
+
+/// `WeakRef.prototype.deref`: the target, or `undefined` once it has been
+/// collected. A target made or dereferenced in a script stays alive until
+/// that script finishes.
+fn weak_ref_deref(interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    let target = this.as_object().and_then(|object| match &object.borrow().kind {
+        ObjectKind::WeakRef(target) => Some(target.upgrade()),
+        _ => None,
+    });
+    let Some(target) = target else {
+        return Err(incompatible_receiver("WeakRef.prototype.deref", this));
+    };
+    Ok(match target {
+        Some(target) => {
+            interpreter.keep_alive(target.clone());
+            target.into()
+        }
+        None => JsValue::Undefined,
+    })
+}
+
+fn finalization_cells<R>(this: &JsValue, method: &str, f: impl FnOnce(&mut FinalizationCells) -> R) -> Result<R, JsError> {
+    if let Some(object) = this.as_object() {
+        if let ObjectKind::FinalizationRegistry(cells) = &mut object.borrow_mut().kind {
+            return Ok(f(cells));
+        }
+    }
+    Err(incompatible_receiver(method, this))
+}
+
+/// `FinalizationRegistry.prototype.register(target, heldValue,
+/// unregisterToken)`: once `target` is collected, the registry's callback is
+/// called with `heldValue`, unless `unregisterToken` is unregistered first.
+fn finalization_registry_register(
+    _interpreter: &mut Interpreter,
+    this: &JsValue,
+    args: &[JsValue],
+) -> Result<JsValue, JsError> {
+    const METHOD: &str = "FinalizationRegistry.prototype.register";
+    finalization_cells(this, METHOD, |_| ())?;
+    let (target, held_value, token) = (argument(args, 0), argument(args, 1), argument(args, 2));
+    let Some(target_object) = target.as_object() else {
+        return Err(JsError::type_error(format!("{}: invalid target", METHOD)));
+    };
+    if held_value.as_object().is_some_and(|held| ObjectRef::ptr_eq(held, target_object)) {
+        return Err(JsError::type_error(format!("{}: target and holdings must not be same", METHOD)));
+    }
+    let token = match &token {
+        JsValue::Undefined => None,
+        JsValue::Object(token) => Some(token),
+        _ => return Err(JsError::type_error(format!("{}: invalid unregister token", METHOD))),
+    };
+    finalization_cells(this, METHOD, |cells| cells.register(target_object, held_value.clone(), token))?;
+    Ok(JsValue::Undefined)
+}
+
+fn finalization_registry_unregister(
+    _interpreter: &mut Interpreter,
+    this: &JsValue,
+    args: &[JsValue],
+) -> Result<JsValue, JsError> {
+    const METHOD: &str = "FinalizationRegistry.prototype.unregister";
+    finalization_cells(this, METHOD, |_| ())?;
+    let Some(token) = args.first().and_then(JsValue::as_object) else {
+        return Err(JsError::type_error(format!("{}: invalid unregister token", METHOD)));
+    };
+    finalization_cells(this, METHOD, |cells| cells.unregister(token).into())
+}
+
+fn argument(args: &[JsValue], index: usize) -> JsValue {
+    args.get(index).cloned().unwrap_or(JsValue::Undefined)
+}
+
+/// A prototype argument, which must be an object or `null`.
+fn prototype_argument(value: &JsValue) -> Result<Option<ObjectRef>, JsError> {
+    match value {
+        JsValue::Object(object) => Ok(Some(object.clone())),
+        JsValue::Null => Ok(None),
+        other => Err(JsError::type_error(format!(
+            "Object prototype may only be an Object or null: {}",
+            other
+        ))),
+    }
+}
+
+fn object_call(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    match argument(args, 0) {
+        value if value.is_nullish() => Ok(interpreter.realm().ordinary_object().into()),
+        value => Ok(to_object(interpreter, &value)?.into()),
+    }
+}
+
+fn object_create(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let prototype = prototype_argument(&argument(args, 0))?;
+    let object = ObjectRef::ordinary(prototype);
+    if let Some(properties) = args.get(1).filter(|value| !matches!(value, JsValue::Undefined)) {
+        define_properties(interpreter, &object, properties)?;
+    }
+    Ok(object.into())
+}
+
+/// ObjectDefineProperties: every enumerable own property of `properties`
+/// describes a property to define. All descriptors are read before any is
+/// defined.
+fn define_properties(interpreter: &mut Interpreter, object: &ObjectRef, properties: &JsValue) -> Result<(), JsError> {
+    let properties = to_object(interpreter, properties)?;
+    let receiver = JsValue::from(properties.clone());
+    let mut descriptors = Vec::new();
+    for key in properties.own_property_keys() {
+        if properties.get_own_property(&key).is_some_and(|property| property.is_enumerable()) {
+            let attributes = properties.get(interpreter, &key, &receiver)?;
+            descriptors.push((key, PropertyDescriptor::from_object(interpreter, &attributes)?));
+        }
+    }
+    for (key, descriptor) in descriptors {
+        define_property_or_throw(object, key, descriptor)?;
+    }
+    Ok(())
+}
+
+fn define_property_or_throw(object: &ObjectRef, key: PropertyKey, descriptor: PropertyDescriptor) -> Result<(), JsError> {
+    if object.define_own_property(key.clone(), descriptor) {
+        Ok(())
+    } else if object.get_own_property(&key).is_none() {
+        Err(JsError::type_error(format!("Cannot define property {}, object is not extensible", key)))
+    } else {
+        Err(JsError::type_error(format!("Cannot redefine property: {}", key)))
+    }
+}
+
+fn object_define_property(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let target = argument(args, 0);
+    let Some(object) = target.as_object() else {
+        return Err(JsError::type_error("Object.defineProperty called on non-object"));
+    };
+    let key = to_property_key(interpreter, &argument(args, 1))?;
+    let descriptor = PropertyDescriptor::from_object(interpreter, &argument(args, 2))?;
+    define_property_or_throw(object, key, descriptor)?;
+    Ok(target)
+}
+
+fn object_get_own_property_descriptor(
+    interpreter: &mut Interpreter,
+    _this: &JsValue,
+    args: &[JsValue],
+) -> Result<JsValue, JsError> {
+    let object = to_object(interpreter, &argument(args, 0))?;
+    let key = to_property_key(interpreter, &argument(args, 1))?;
+    Ok(match object.get_own_property(&key) {
+        Some(descriptor) => descriptor.to_object(&interpreter.realm().object_prototype).into(),
+        None => JsValue::Undefined,
+    })
+}
+
+fn object_get_prototype_of(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let object = to_object(interpreter, &argument(args, 0))?;
+    Ok(object.get_prototype_of().map_or(JsValue::Null, JsValue::from))
+}
+
+fn object_set_prototype_of(_interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let target = argument(args, 0);
+    if target.is_nullish() {
+        return Err(JsError::type_error("Object.setPrototypeOf called on null or undefined"));
+    }
+    let prototype = prototype_argument(&argument(args, 1))?;
+    if let JsValue::Object(object) = &target {
+        if !object.set_prototype_of(prototype) {
+            let reason = if object.is_extensible() { "Cyclic __proto__ value" } else { "Object is not extensible" };
+            return Err(JsError::type_error(reason));
+        }
+    }
+    Ok(target)
+}
+
+fn object_is_extensible(_interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    Ok(args.first().and_then(JsValue::as_object).is_some_and(ObjectRef::is_extensible).into())
+}
+
+fn object_prevent_extensions(_interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let target = argument(args, 0);
+    if let JsValue::Object(object) = &target {
+        object.prevent_extensions();
+    }
+    Ok(target)
+}
+
+fn object_has_own_property(interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let key = to_property_key(interpreter, &argument(args, 0))?;
+    let object = to_object(interpreter, this)?;
+    Ok(object.get_own_property(&key).is_some().into())
+}
+
+fn object_is_prototype_of(interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let JsValue::Object(value) = argument(args, 0) else {
+        return Ok(false.into());
+    };
+    let object = to_object(interpreter, this)?;
+    let mut ancestor = value.get_prototype_of();
+    while let Some(prototype) = ancestor {
+        if ObjectRef::ptr_eq(&prototype, &object) {
+            return Ok(true.into());
+        }
+        ancestor = prototype.get_prototype_of();
+    }
+    Ok(false.into())
+}
+
+fn object_property_is_enumerable(
+    interpreter: &mut Interpreter,
+    this: &JsValue,
+    args: &[JsValue],
+) -> Result<JsValue, JsError> {
+    let key = to_property_key(interpreter, &argument(args, 0))?;
+    let object = to_object(interpreter, this)?;
+    Ok(object.get_own_property(&key).is_some_and(|property| property.is_enumerable()).into())
+}
+
+fn object_to_string(interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    let tag = match this {
+        JsValue::Undefined => "Undefined",
+        JsValue::Null => "Null",
+        _ => match &to_object(interpreter, this)?.borrow().kind {
+            ObjectKind::Function(_) => "Function",
+            ObjectKind::Primitive(JsValue::Boolean(_)) => "Boolean",
+            ObjectKind::Primitive(JsValue::Number(_)) => "Number",
+            ObjectKind::Primitive(JsValue::String(_)) => "String",
+            // What the `@@toStringTag` of their prototypes would give.
+            ObjectKind::Primitive(JsValue::Symbol(_)) => "Symbol",
+            ObjectKind::Primitive(JsValue::BigInt(_)) => "BigInt",
+            ObjectKind::Error => "Error",
+            ObjectKind::WeakMap(_) => "WeakMap",
+            ObjectKind::WeakSet(_) => "WeakSet",
+            ObjectKind::WeakRef(_) => "WeakRef",
+            ObjectKind::FinalizationRegistry(_) => "FinalizationRegistry",
+            _ => "Object",
+        },
+    };
+    Ok(format!("[object {}]", tag).into())
+}
+
+fn object_value_of(interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    Ok(to_object(interpreter, this)?.into())
+}
+
+fn function_call(interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    if !this.is_callable() {
+        return Err(JsError::type_error("Function.prototype.call called on a non-function"));
+    }
+    let arguments = args.get(1..).unwrap_or_default();
+    interpreter.call(this, argument(args, 0), arguments)
+}
+
+fn function_to_string(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    match this.as_object().filter(|object| object.is_callable()) {
+        Some(function) => Ok(function.builtin_to_string().into()),
+        None => Err(JsError::type_error(
+            "Function.prototype.toString requires that 'this' be a Function",
+        )),
+    }
+}
+
+/// thisBooleanValue and its siblings: the primitive `this` is, or wraps, if
+/// it is of the type `class` names.
+fn this_primitive(this: &JsValue, class: &str, method: &str) -> Result<JsValue, JsError> {
+    let primitive = match this {
+        JsValue::Object(object) => object.primitive_value(),
+        primitive => Some(primitive.clone()),
+    };
+    match primitive {
+        Some(primitive) if primitive.type_of().eq_ignore_ascii_case(class) => Ok(primitive),
+        _ => Err(JsError::type_error(format!(
+            "{}.prototype.{} requires that 'this' be a {}",
+            class, method, class
+        ))),
+    }
+}
+
+/// The radix argument of `toString`, 10 if absent.
+fn radix_argument(interpreter: &mut Interpreter, args: &[JsValue]) -> Result<u32, JsError> {
+    let radix = match argument(args, 0) {
+        JsValue::Undefined => return Ok(10),
+        value => to_number(interpreter, &value)?.trunc(),
+    };
+    if !(2.0..=36.0).contains(&radix) {
+        return Err(JsError::range_error("toString() radix must be between 2 and 36"));
+    }
+    Ok(radix as u32)
+}
+
+fn boolean_to_string(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    Ok(this_primitive(this, "Boolean", "toString")?.to_string().into())
+}
+
+fn boolean_value_of(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    this_primitive(this, "Boolean", "valueOf")
+}
+
+fn number_to_string(interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let JsValue::Number(value) = this_primitive(this, "Number", "toString")? else { unreachable!() };
+    match radix_argument(interpreter, args)? {
+        10 => Ok(JsValue::from(value).to_string().into()),
+        radix => Ok(number_to_radix_string(value, radix).into()),
+    }
+}
+
+fn number_value_of(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    this_primitive(this, "Number", "valueOf")
+}
+
+fn string_value_of(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    this_primitive(this, "String", "valueOf")
+}
+
+fn bigint_to_string(interpreter: &mut Interpreter, this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let JsValue::BigInt(value) = this_primitive(this, "BigInt", "toString")? else { unreachable!() };
+    Ok(value.to_string_radix(radix_argument(interpreter, args)?).into())
+}
+
+fn bigint_value_of(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    this_primitive(this, "BigInt", "valueOf")
+}
+
+fn symbol_to_string(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    Ok(this_primitive(this, "Symbol", "toString")?.to_string().into())
+}
+
+/// `Symbol.prototype.valueOf`, and `@@toPrimitive`, which does the same.
+fn symbol_value_of(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    this_primitive(this, "Symbol", "valueOf")
+}
+
+fn symbol_description(_interpreter: &mut Interpreter, this: &JsValue, _args: &[JsValue]) -> Result<JsValue, JsError> {
+    let JsValue::Symbol(symbol) = this_primitive(this, "Symbol", "description")? else { unreachable!() };
+    Ok(symbol.description().map_or(JsValue::Undefined, JsValue::from))
+}
+
+fn console_log(interpreter: &mut Interpreter, _this: &JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+    let line: Vec<String> = args
+        .iter()
+        .map(|arg| match arg {
+            JsValue::String(text) => text.to_string(),
+            other => inspect(other),
+        })
+        .collect();
+    interpreter.print(&line.join(" "));
+    Ok(JsValue::Undefined)
+}
+
+/// A readable rendering of a value in the style of Node's `util.inspect`:
+/// strings are quoted and objects list their properties.
+pub fn inspect(value: &JsValue) -> String {
+    let mut seen = Vec::new();
+    inspect_value(value, &mut seen)
+}
+
+fn inspect_value(value: &JsValue, seen: &mut Vec<ObjectRef>) -> String {
+    let object = match value {
+        JsValue::String(text) => return quote_code_units(&text.code_units().collect::<Vec<_>>()),
+        JsValue::BigInt(value) => return format!("{}n", value),
+        JsValue::Object(object) => object,
+        primitive => return primitive.to_string(),
+    };
+
+    if object.is_callable() {
+        let name = object.function_name();
+        return if name.is_empty() {
+            "[Function (anonymous)]".to_string()
+        } else {
+            format!("[Function: {}]", name)
+        };
+    }
+    if let Some(primitive) = object.primitive_value() {
+        let kind = match primitive {
+            JsValue::Boolean(_) => "Boolean",
+            JsValue::Number(_) => "Number",
+            JsValue::String(_) => "String",
+            JsValue::Symbol(_) => "Symbol",
+            _ => "BigInt",
+        };
+        return format!("[{}: {}]", kind, inspect_value(&primitive, seen));
+    }
+    if seen.iter().any(|outer| ObjectRef::ptr_eq(outer, object)) {
+        return "[Circular]".to_string();
+    }
+
+    seen.push(object.clone());
+    let mut entries = Vec::new();
+    for key in object.own_property_keys() {
+        let Some(property) = object.get_own_property(&key).filter(PropertyDescriptor::is_enumerable) else {
+            continue;
+        };
+        let value = match (&property.get, &property.set) {
+            (Some(JsValue::Undefined), Some(JsValue::Undefined)) => "undefined".to_string(),
+            (Some(JsValue::Undefined), Some(_)) => "[Setter]".to_string(),
+            (Some(_), Some(JsValue::Undefined)) => "[Getter]".to_string(),
+            (Some(_), Some(_)) => "[Getter/Setter]".to_string(),
+            _ => inspect_value(property.value.as_ref().unwrap(), seen),
+        };
+        entries.push(format!("{}: {}", inspect_key(&key), value));
+    }
+    seen.pop();
+
+    // Without a stack to show, Node brackets errors like this.
+    let is_error = matches!(object.borrow().kind, ObjectKind::Error);
+    match (is_error, entries.is_empty()) {
+        (true, true) => format!("[{}]", error_to_string(object)),
+        (true, false) => format!("[{}] {{ {} }}", error_to_string(object), entries.join(", ")),
+        (false, true) => "{}".to_string(),
+        (false, false) => format!("{{ {} }}", entries.join(", ")),
+    }
+}
+
+fn inspect_key(key: &PropertyKey) -> String {
+    let key = match key {
+        PropertyKey::String(key) => key.to_string(),
+        PropertyKey::Text(key) => String::from_utf16_lossy(key.code_units()),
+        PropertyKey::Symbol(symbol) => return format!("[{}]", symbol.descriptive_string()),
+    };
+    let mut chars = key.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        key
+    } else {
+        quote_string(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::bigint::BigInt;
+
+    #[test]
+    fn test_inspect() {
+        let realm = Realm::new();
+        let object = ObjectRef::default();
+        object.create_data_property("a", JsValue::from(1.0));
+        object.create_data_property("b c", JsValue::from("x"));
+        object.create_data_property("self", object.clone().into());
+        object.create_data_property("f", native_function(&realm, "log", console_log));
+        object.define_own_property("hidden".into(), PropertyDescriptor::data(JsValue::Null, true, false, true));
+
+        assert_eq!(
+            inspect(&object.clone().into()),
+            "{ a: 1, \"b c\": \"x\", self: [Circular], f: [Function: log] }"
+        );
+        assert_eq!(inspect(&JsValue::Null), "null");
+        assert_eq!(inspect(&ObjectRef::default().into()), "{}");
+        assert_eq!(inspect(&BigInt::from_i64(-3).into()), "-3n");
+        assert_eq!(inspect(&ObjectRef::wrapper(&realm.string_prototype, "s".into()).into()), "[String: \"s\"]");
+
+        let tagged = ObjectRef::default();
+        tagged.create_data_property(Symbol::new(Some("tag")), JsValue::from(true));
+        tagged.create_data_property("2", JsValue::from(false));
+        let getter = native_function(&realm, "get", console_log);
+        tagged.define_own_property("g".into(), PropertyDescriptor::accessor(getter, JsValue::Undefined, true, true));
+        assert_eq!(inspect(&tagged.into()), "{ \"2\": false, g: [Getter], [Symbol(tag)]: true }");
+    }
+}
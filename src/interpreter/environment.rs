@@ -0,0 +1,452 @@
+use crate::lexical::atom::Atom;
+use crate::runtime::conversions::Context;
+use crate::runtime::error::JsError;
+use crate::runtime::gc::{Gc, Trace, Tracer};
+use crate::runtime::object::ObjectRef;
+use crate::runtime::property::{PropertyDescriptor, PropertyKey};
+use crate::runtime::value::JsValue;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+pub type EnvRef = Gc<RefCell<Environment>>;
+
+/// A binding of a declarative record. `let`, `const` and class bindings
+/// start out without a value: reading or writing them before their
+/// declaration runs is a ReferenceError, the temporal dead zone.
+#[derive(Debug, Clone)]
+struct Binding {
+    value: Option<JsValue>,
+    mutable: bool,
+}
+
+/// What an environment binds names in.
+#[derive(Debug)]
+enum Record {
+    /// Bindings held by the environment itself, as for blocks and function
+    /// calls.
+    Declarative(HashMap<Atom, Binding>),
+    /// Every property of an object is a binding, as for `with`.
+    Object(ObjectRef),
+    /// The global scope: `var` and function declarations of scripts become
+    /// properties of the global object, while other declarations live in a
+    /// declarative part that shadows them.
+    Global {
+        object: ObjectRef,
+        declarative: HashMap<Atom, Binding>,
+        var_names: HashSet<Atom>,
+    },
+}
+
+/// An Environment Record and a link to the enclosing one. Closures keep
+/// the environment they were created in alive.
+#[derive(Debug)]
+pub struct Environment {
+    record: Record,
+    outer: Option<EnvRef>,
+}
+
+impl Trace for RefCell<Environment> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(environment) = self.try_borrow() else {
+            return tracer.incomplete();
+        };
+        match &environment.record {
+            Record::Declarative(bindings) => bindings.values().for_each(|binding| binding.value.trace(tracer)),
+            Record::Object(object) => object.trace(tracer),
+            Record::Global { object, declarative, .. } => {
+                object.trace(tracer);
+                declarative.values().for_each(|binding| binding.value.trace(tracer));
+            }
+        }
+        environment.outer.trace(tracer);
+    }
+
+    fn unlink(&self) {
+        if let Ok(mut environment) = self.try_borrow_mut() {
+            environment.record = Record::Declarative(HashMap::new());
+            environment.outer = None;
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        let Ok(environment) = self.try_borrow() else {
+            return 0;
+        };
+        let bindings = match &environment.record {
+            Record::Declarative(bindings) | Record::Global { declarative: bindings, .. } => bindings.capacity(),
+            Record::Object(_) => 0,
+        };
+        bindings * std::mem::size_of::<(Atom, Binding)>()
+    }
+}
+
+impl Environment {
+    pub fn declarative(outer: Option<EnvRef>) -> EnvRef {
+        Self::with_record(Record::Declarative(HashMap::new()), outer)
+    }
+
+    pub fn object(object: ObjectRef, outer: Option<EnvRef>) -> EnvRef {
+        Self::with_record(Record::Object(object), outer)
+    }
+
+    pub fn global(object: ObjectRef) -> EnvRef {
+        let record = Record::Global {
+            object,
+            declarative: HashMap::new(),
+            var_names: HashSet::new(),
+        };
+        Self::with_record(record, None)
+    }
+
+    fn with_record(record: Record, outer: Option<EnvRef>) -> EnvRef {
+        Gc::new(RefCell::new(Environment { record, outer }))
+    }
+
+    pub fn outer(&self) -> Option<EnvRef> {
+        self.outer.clone()
+    }
+
+    /// The object whose properties are bindings of an object or global
+    /// record.
+    pub fn binding_object(&self) -> Option<ObjectRef> {
+        match &self.record {
+            Record::Declarative(_) => None,
+            Record::Object(object) | Record::Global { object, .. } => Some(object.clone()),
+        }
+    }
+
+    /// HasBinding.
+    pub fn has_binding(&self, name: Atom) -> bool {
+        match &self.record {
+            Record::Declarative(bindings) => bindings.contains_key(&name),
+            Record::Object(object) => object.has_property(&name.into()),
+            Record::Global { object, declarative, .. } => {
+                declarative.contains_key(&name) || object.has_property(&name.into())
+            }
+        }
+    }
+
+    /// CreateMutableBinding: an uninitialized binding, in the declarative
+    /// part of a global record.
+    pub fn create_mutable_binding(&mut self, name: Atom) {
+        self.declarative_bindings().insert(name, Binding { value: None, mutable: true });
+    }
+
+    /// CreateImmutableBinding, for `const`.
+    pub fn create_immutable_binding(&mut self, name: Atom) {
+        self.declarative_bindings().insert(name, Binding { value: None, mutable: false });
+    }
+
+    /// InitializeBinding: gives a binding made by one of the `create`
+    /// methods its first value, ending its temporal dead zone.
+    pub fn initialize_binding(&mut self, name: Atom, value: JsValue) {
+        let binding = self.declarative_bindings().get_mut(&name).expect("binding to initialize");
+        binding.value = Some(value);
+    }
+
+    /// Creates and initializes a mutable binding, as for parameters.
+    pub fn declare(&mut self, name: Atom, value: JsValue) {
+        self.declarative_bindings().insert(name, Binding { value: Some(value), mutable: true });
+    }
+
+    fn declarative_bindings(&mut self) -> &mut HashMap<Atom, Binding> {
+        match &mut self.record {
+            Record::Declarative(bindings) | Record::Global { declarative: bindings, .. } => bindings,
+            Record::Object(_) => panic!("Object environment records have no declarative bindings"),
+        }
+    }
+
+    /// A declarative binding of this environment itself.
+    fn own_binding(&self, name: Atom) -> Option<&Binding> {
+        match &self.record {
+            Record::Declarative(bindings) | Record::Global { declarative: bindings, .. } => bindings.get(&name),
+            Record::Object(_) => None,
+        }
+    }
+
+    /// ResolveBinding: the nearest environment that binds `name`, or `None`
+    /// if it is unresolvable.
+    pub fn resolve(env: &EnvRef, name: Atom) -> Option<EnvRef> {
+        let mut current = env.clone();
+        loop {
+            if current.borrow().has_binding(name) {
+                return Some(current);
+            }
+            let outer = current.borrow().outer.clone()?;
+            current = outer;
+        }
+    }
+
+    /// GetBindingValue. Properties of a binding object may have vanished
+    /// since `name` was resolved, which strict code reports.
+    pub fn get_binding_value(
+        env: &EnvRef,
+        ctx: &mut dyn Context,
+        name: Atom,
+        strict: bool,
+    ) -> Result<JsValue, JsError> {
+        let object = {
+            let env = env.borrow();
+            if let Some(binding) = env.own_binding(name) {
+                return binding.value.clone().ok_or_else(|| uninitialized(&name));
+            }
+            env.binding_object().expect("a resolved binding")
+        };
+        let key = PropertyKey::from(name);
+        if !object.has_property(&key) {
+            return if strict { Err(not_defined(&name)) } else { Ok(JsValue::Undefined) };
+        }
+        object.get(ctx, &key, &object.clone().into())
+    }
+
+    /// SetMutableBinding. Constants and bindings in their temporal dead zone
+    /// throw; so do read-only properties of a binding object in strict code.
+    pub fn set_mutable_binding(
+        env: &EnvRef,
+        ctx: &mut dyn Context,
+        name: Atom,
+        value: JsValue,
+        strict: bool,
+    ) -> Result<(), JsError> {
+        let object = {
+            let mut env = env.borrow_mut();
+            let own = match &mut env.record {
+                Record::Declarative(bindings) | Record::Global { declarative: bindings, .. } => bindings.get_mut(&name),
+                Record::Object(_) => None,
+            };
+            if let Some(binding) = own {
+                if binding.value.is_none() {
+                    return Err(uninitialized(&name));
+                }
+                if !binding.mutable {
+                    return Err(JsError::type_error("Assignment to constant variable."));
+                }
+                binding.value = Some(value);
+                return Ok(());
+            }
+            env.binding_object().expect("a resolved binding")
+        };
+        let key = PropertyKey::from(name);
+        if strict && !object.has_property(&key) {
+            return Err(not_defined(&name));
+        }
+        if !object.set(ctx, key, value, &object.clone().into())? && strict {
+            return Err(JsError::type_error(format!(
+                "Cannot assign to read only property '{}' of object",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    /// DeleteBinding: only configurable properties of a binding object,
+    /// such as implicitly created globals, can be deleted.
+    pub fn delete_binding(env: &EnvRef, name: Atom) -> bool {
+        let mut env = env.borrow_mut();
+        if env.own_binding(name).is_some() {
+            return false;
+        }
+        let object = env.binding_object().expect("a resolved binding");
+        let key = PropertyKey::from(name);
+        if object.get_own_property(&key).is_none() {
+            return true;
+        }
+        let deleted = object.delete(&key);
+        if let (true, Record::Global { var_names, .. }) = (deleted, &mut env.record) {
+            var_names.remove(&name);
+        }
+        deleted
+    }
+
+    /// HasVarDeclaration: whether a script declared `name` with `var` or as
+    /// a function.
+    pub fn has_var_declaration(&self, name: Atom) -> bool {
+        matches!(&self.record, Record::Global { var_names, .. } if var_names.contains(&name))
+    }
+
+    /// HasLexicalDeclaration: whether a script declared `name` with `let`,
+    /// `const` or `class`.
+    pub fn has_lexical_declaration(&self, name: Atom) -> bool {
+        matches!(&self.record, Record::Global { declarative, .. } if declarative.contains_key(&name))
+    }
+
+    /// HasRestrictedGlobalProperty: whether `name` is a non-configurable
+    /// property of the global object, such as `undefined`, which lexical
+    /// declarations may not shadow.
+    pub fn has_restricted_global_property(&self, name: Atom) -> bool {
+        let object = self.binding_object().expect("a global environment");
+        object.get_own_property(&name.into()).is_some_and(|property| !property.is_configurable())
+    }
+
+    /// CanDeclareGlobalVar.
+    pub fn can_declare_global_var(&self, name: Atom) -> bool {
+        let object = self.binding_object().expect("a global environment");
+        object.get_own_property(&name.into()).is_some() || object.is_extensible()
+    }
+
+    /// CanDeclareGlobalFunction: a function may replace a configurable
+    /// global, or a writable and enumerable one.
+    pub fn can_declare_global_function(&self, name: Atom) -> bool {
+        let object = self.binding_object().expect("a global environment");
+        match object.get_own_property(&name.into()) {
+            None => object.is_extensible(),
+            Some(property) => {
+                property.is_configurable() || (property.is_data() && property.is_writable() && property.is_enumerable())
+            }
+        }
+    }
+
+    /// CreateGlobalVarBinding: a global object property, `undefined` unless
+    /// it already exists.
+    pub fn create_global_var_binding(&mut self, name: Atom) {
+        let Record::Global { object, var_names, .. } = &mut self.record else {
+            panic!("Expected a global environment");
+        };
+        let key = PropertyKey::from(name);
+        if object.get_own_property(&key).is_none() && object.is_extensible() {
+            object.define_own_property(key, PropertyDescriptor::data(JsValue::Undefined, true, true, false));
+        }
+        var_names.insert(name);
+    }
+
+    /// CreateGlobalFunctionBinding: a non-deletable global object property
+    /// holding `function`.
+    pub fn create_global_function_binding(&mut self, name: Atom, function: JsValue) {
+        let Record::Global { object, var_names, .. } = &mut self.record else {
+            panic!("Expected a global environment");
+        };
+        let key = PropertyKey::from(name);
+        let descriptor = match object.get_own_property(&key) {
+            Some(existing) if !existing.is_configurable() => PropertyDescriptor {
+                value: Some(function),
+                ..Default::default()
+            },
+            _ => PropertyDescriptor::data(function, true, true, false),
+        };
+        object.define_own_property(key, descriptor);
+        var_names.insert(name);
+    }
+
+    /// CreatePerIterationEnvironment: a copy of a `for (let ...)` loop's
+    /// environment, so that closures created in each iteration see that
+    /// iteration's bindings.
+    pub fn copy_for_iteration(env: &EnvRef) -> EnvRef {
+        let env = env.borrow();
+        let Record::Declarative(bindings) = &env.record else {
+            panic!("Expected a declarative environment");
+        };
+        Self::with_record(Record::Declarative(bindings.clone()), env.outer.clone())
+    }
+}
+
+pub fn uninitialized(name: &str) -> JsError {
+    JsError::reference_error(format!("Cannot access '{}' before initialization", name))
+}
+
+fn not_defined(name: &str) -> JsError {
+    JsError::reference_error(format!("{} is not defined", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn get(env: &EnvRef, name: &str) -> Result<JsValue, JsError> {
+        let name = Atom::new(name);
+        let binding = Environment::resolve(env, name).ok_or_else(|| not_defined(&name))?;
+        Environment::get_binding_value(&binding, &mut Interpreter::new(), name, true)
+    }
+
+    fn set(env: &EnvRef, name: &str, value: JsValue) -> Result<(), JsError> {
+        let name = Atom::new(name);
+        let binding = Environment::resolve(env, name).ok_or_else(|| not_defined(&name))?;
+        Environment::set_mutable_binding(&binding, &mut Interpreter::new(), name, value, true)
+    }
+
+    #[test]
+    fn test_lookup_and_shadowing() {
+        let outer = Environment::declarative(None);
+        outer.borrow_mut().declare("x".into(), JsValue::from(1.0));
+        let inner = Environment::declarative(Some(outer.clone()));
+        assert!(get(&inner, "x").unwrap().strict_equals(&1.0.into()));
+
+        inner.borrow_mut().declare("x".into(), JsValue::from(2.0));
+        assert!(get(&inner, "x").unwrap().strict_equals(&2.0.into()));
+        assert!(get(&outer, "x").unwrap().strict_equals(&1.0.into()));
+        assert!(Environment::resolve(&inner, "y".into()).is_none());
+    }
+
+    #[test]
+    fn test_assign() {
+        let outer = Environment::declarative(None);
+        outer.borrow_mut().declare("x".into(), JsValue::from(1.0));
+        outer.borrow_mut().create_immutable_binding("c".into());
+        outer.borrow_mut().initialize_binding("c".into(), JsValue::from(1.0));
+        let inner = Environment::declarative(Some(outer.clone()));
+
+        set(&inner, "x", JsValue::from(5.0)).unwrap();
+        assert!(get(&outer, "x").unwrap().strict_equals(&5.0.into()));
+        assert_eq!(
+            set(&inner, "c", JsValue::Null).unwrap_err().to_string(),
+            "TypeError: Assignment to constant variable."
+        );
+    }
+
+    #[test]
+    fn test_temporal_dead_zone() {
+        let env = Environment::declarative(None);
+        env.borrow_mut().create_mutable_binding("x".into());
+        let error = "ReferenceError: Cannot access 'x' before initialization";
+        assert_eq!(get(&env, "x").unwrap_err().to_string(), error);
+        assert_eq!(set(&env, "x", JsValue::Null).unwrap_err().to_string(), error);
+
+        env.borrow_mut().initialize_binding("x".into(), JsValue::from(1.0));
+        assert!(get(&env, "x").unwrap().strict_equals(&1.0.into()));
+    }
+
+    #[test]
+    fn test_object_records() {
+        let object = ObjectRef::default();
+        object.create_data_property("a", JsValue::from(1.0));
+        let env = Environment::object(object.clone(), None);
+        assert!(get(&env, "a").unwrap().strict_equals(&1.0.into()));
+        set(&env, "a", JsValue::from(2.0)).unwrap();
+        assert!(object.get_own_property(&"a".into()).unwrap().value.unwrap().strict_equals(&2.0.into()));
+
+        assert!(Environment::delete_binding(&env, "a".into()));
+        assert!(!env.borrow().has_binding("a".into()));
+    }
+
+    #[test]
+    fn test_global_records() {
+        let object = ObjectRef::default();
+        object.define_own_property("fixed".into(), PropertyDescriptor::data(JsValue::Null, false, false, false));
+        let global = Environment::global(object.clone());
+        let mut env = global.borrow_mut();
+
+        env.create_global_var_binding("v".into());
+        assert!(env.has_var_declaration("v".into()));
+        assert!(!object.get_own_property(&"v".into()).unwrap().is_configurable());
+        env.create_mutable_binding("l".into());
+        assert!(env.has_lexical_declaration("l".into()) && env.has_binding("l".into()));
+        assert!(object.get_own_property(&"l".into()).is_none());
+
+        assert!(env.has_restricted_global_property("fixed".into()));
+        assert!(!env.can_declare_global_function("fixed".into()));
+        assert!(env.can_declare_global_function("v".into()));
+        object.prevent_extensions();
+        assert!(!env.can_declare_global_var("w".into()));
+        assert!(env.can_declare_global_var("v".into()));
+    }
+
+    #[test]
+    fn test_copy_for_iteration() {
+        let env = Environment::declarative(None);
+        env.borrow_mut().declare("i".into(), JsValue::from(0.0));
+        let copy = Environment::copy_for_iteration(&env);
+        set(&copy, "i", JsValue::from(1.0)).unwrap();
+        assert!(get(&env, "i").unwrap().strict_equals(&0.0.into()));
+        assert!(get(&copy, "i").unwrap().strict_equals(&1.0.into()));
+    }
+}
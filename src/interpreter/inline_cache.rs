@@ -0,0 +1,425 @@
+use crate::bytecode::opcode::{Instructions, Opcode};
+use crate::interpreter::vm::BytecodeClosure;
+use crate::interpreter::Closure;
+use crate::runtime::gc::WeakGc;
+use crate::runtime::object::{FunctionKind, NativeFunction, ObjectKind, ObjectRef, WeakObjectRef};
+use crate::runtime::property::{PropertyDescriptor, PropertyKey, PropertySlot};
+use crate::runtime::shape::{Shape, MAX_SHAPED_PROPERTIES};
+use crate::runtime::value::JsValue;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+/// Hit and miss counts of this thread's inline caches; see [`stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub get_hits: usize,
+    pub get_misses: usize,
+    pub set_hits: usize,
+    pub set_misses: usize,
+    pub call_hits: usize,
+    pub call_misses: usize,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter<'_>, what: &str, hits: usize, misses: usize| {
+            let total = hits + misses;
+            let rate = if total == 0 { 0.0 } else { hits as f64 * 100.0 / total as f64 };
+            write!(f, "ic: {} of {} {} hit ({:.1}%)", hits, total, what, rate)
+        };
+        line(f, "property gets", self.get_hits, self.get_misses)?;
+        writeln!(f)?;
+        line(f, "property sets", self.set_hits, self.set_misses)?;
+        writeln!(f)?;
+        line(f, "calls", self.call_hits, self.call_misses)
+    }
+}
+
+thread_local! {
+    static STATS: Cell<CacheStats> = Cell::new(CacheStats::default());
+}
+
+fn count(update: impl FnOnce(&mut CacheStats)) {
+    STATS.with(|stats| {
+        let mut counts = stats.get();
+        update(&mut counts);
+        stats.set(counts);
+    });
+}
+
+/// Counts of every inline cache lookup on this thread so far.
+pub fn stats() -> CacheStats {
+    STATS.with(Cell::get)
+}
+
+pub fn reset_stats() {
+    STATS.with(|stats| stats.set(CacheStats::default()));
+}
+
+/// What one instruction saw last time it ran. Caches are monomorphic: a
+/// miss replaces the entry.
+#[derive(Clone, Default)]
+enum InlineCache {
+    #[default]
+    Empty,
+    /// Objects with `shape` hold the property as data in `slot`.
+    Own { shape: Rc<Shape>, slot: usize },
+    /// Objects with `shape` lack the property, and inherit it as data from
+    /// `prototype`, which keeps it in `slot` of `prototype_shape`.
+    Prototype {
+        shape: Rc<Shape>,
+        prototype: WeakObjectRef,
+        prototype_shape: Rc<Shape>,
+        slot: usize,
+    },
+    /// Objects with shape `from` lack the property, as does their prototype
+    /// chain, and adding it moves them to shape `to`.
+    Transition { from: Rc<Shape>, to: Rc<Shape> },
+    /// The function called last and what it runs. The handles are weak so
+    /// that a cache never keeps a function alive.
+    Call { callee: WeakObjectRef, target: CallTarget },
+}
+
+#[derive(Clone)]
+enum CallTarget {
+    Script(WeakGc<Closure>),
+    Bytecode(WeakGc<BytecodeClosure>),
+    Native(NativeFunction),
+}
+
+impl CallTarget {
+    fn new(kind: &FunctionKind) -> Self {
+        match kind {
+            FunctionKind::Script(closure) => CallTarget::Script(closure.downgrade()),
+            FunctionKind::Bytecode(closure) => CallTarget::Bytecode(closure.downgrade()),
+            FunctionKind::Native(native) => CallTarget::Native(native.clone()),
+        }
+    }
+
+    fn upgrade(&self) -> Option<FunctionKind> {
+        match self {
+            CallTarget::Script(closure) => closure.upgrade().map(FunctionKind::Script),
+            CallTarget::Bytecode(closure) => closure.upgrade().map(FunctionKind::Bytecode),
+            CallTarget::Native(native) => Some(FunctionKind::Native(native.clone())),
+        }
+    }
+}
+
+/// The inline caches of a template's `GetNamed`, `SetNamed` and `Call`
+/// instructions, made the first time the code runs and addressed by
+/// instruction offset.
+///
+/// Caches are run-time state rather than part of the compiled code: they
+/// are never serialized, clone empty and compare equal.
+#[derive(Default)]
+pub struct InlineCaches {
+    /// Offsets of the cached instructions, in code order.
+    offsets: OnceCell<Vec<u32>>,
+    entries: RefCell<Vec<InlineCache>>,
+}
+
+impl InlineCaches {
+    fn index(&self, code: &[u8], offset: usize) -> usize {
+        let offsets = self.offsets.get_or_init(|| {
+            let offsets: Vec<u32> = Instructions::new(code)
+                .filter(|instruction| matches!(instruction.opcode, Opcode::GetNamed | Opcode::SetNamed | Opcode::Call))
+                .map(|instruction| instruction.offset as u32)
+                .collect();
+            *self.entries.borrow_mut() = vec![InlineCache::Empty; offsets.len()];
+            offsets
+        });
+        offsets.binary_search(&(offset as u32)).expect("a cached instruction")
+    }
+
+    /// The value of the data property `key` of `base`, if the `GetNamed` at
+    /// `offset` has seen an object of its shape before. A miss caches what
+    /// the lookup finds, for the caller's generic `[[Get]]` to then repeat.
+    pub fn get(&self, code: &[u8], offset: usize, base: &JsValue, key: &PropertyKey) -> Option<JsValue> {
+        let index = self.index(code, offset);
+        let Some(object) = cacheable(base) else {
+            count(|stats| stats.get_misses += 1);
+            return None;
+        };
+        let value = match &self.entries.borrow()[index] {
+            InlineCache::Own { shape, slot } => own_slot(&object, shape, *slot),
+            InlineCache::Prototype { shape, prototype, prototype_shape, slot } => {
+                let object = object.borrow();
+                let inherited = object.properties().shape().is_some_and(|own| Rc::ptr_eq(own, shape));
+                match object.prototype() {
+                    Some(parent) if inherited && prototype.points_to(parent) => own_slot(parent, prototype_shape, *slot),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match value {
+            Some(value) => {
+                count(|stats| stats.get_hits += 1);
+                Some(value)
+            }
+            None => {
+                count(|stats| stats.get_misses += 1);
+                self.entries.borrow_mut()[index] = get_entry(&object, key);
+                None
+            }
+        }
+    }
+
+    /// Stores `value` as the property `key` of `base`, returning whether it
+    /// did: into a writable own data property, or as a new property when
+    /// nothing on the prototype chain has the key. Anything else, such as
+    /// calling a setter, is left to the caller's generic `[[Set]]`.
+    pub fn set(&self, code: &[u8], offset: usize, base: &JsValue, key: &PropertyKey, value: &JsValue) -> bool {
+        let index = self.index(code, offset);
+        let Some(object) = cacheable(base) else {
+            count(|stats| stats.set_misses += 1);
+            return false;
+        };
+        if store(&self.entries.borrow()[index], &object, key, value) {
+            count(|stats| stats.set_hits += 1);
+            return true;
+        }
+        count(|stats| stats.set_misses += 1);
+        let entry = set_entry(&object, key);
+        let stored = store(&entry, &object, key, value);
+        self.entries.borrow_mut()[index] = entry;
+        stored
+    }
+
+    /// What `callee` runs, remembered by the `Call` at `offset` between
+    /// calls of the same function.
+    pub fn function_kind(&self, code: &[u8], offset: usize, callee: &ObjectRef) -> Option<FunctionKind> {
+        let index = self.index(code, offset);
+        if let InlineCache::Call { callee: cached, target } = &self.entries.borrow()[index] {
+            if cached.points_to(callee) {
+                if let Some(kind) = target.upgrade() {
+                    count(|stats| stats.call_hits += 1);
+                    return Some(kind);
+                }
+            }
+        }
+        count(|stats| stats.call_misses += 1);
+        let kind = callee.function_kind()?;
+        self.entries.borrow_mut()[index] = InlineCache::Call {
+            callee: callee.downgrade(),
+            target: CallTarget::new(&kind),
+        };
+        Some(kind)
+    }
+}
+
+impl Clone for InlineCaches {
+    fn clone(&self) -> Self {
+        InlineCaches::default()
+    }
+}
+
+impl PartialEq for InlineCaches {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for InlineCaches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineCaches").finish_non_exhaustive()
+    }
+}
+
+/// The object a named access on `base` looks in first, unless it is a
+/// primitive or a String object, whose index and `length` properties are
+/// not in its shape.
+fn cacheable(base: &JsValue) -> Option<ObjectRef> {
+    let object = base.as_object()?;
+    if matches!(object.borrow().kind, ObjectKind::Primitive(JsValue::String(_))) {
+        return None;
+    }
+    Some(object.clone())
+}
+
+/// The data property in `slot` of `object`, if `object` still has `shape`.
+fn own_slot(object: &ObjectRef, shape: &Rc<Shape>, slot: usize) -> Option<JsValue> {
+    let object = object.borrow();
+    let properties = object.properties();
+    if !properties.shape().is_some_and(|current| Rc::ptr_eq(current, shape)) {
+        return None;
+    }
+    properties.at(slot).value()
+}
+
+/// Stores `value` through a `SetNamed` cache entry, if it applies to
+/// `object`.
+fn store(entry: &InlineCache, object: &ObjectRef, key: &PropertyKey, value: &JsValue) -> bool {
+    match entry {
+        InlineCache::Own { shape, slot } => {
+            let mut object = object.borrow_mut();
+            let properties = object.properties_mut();
+            if !properties.shape().is_some_and(|current| Rc::ptr_eq(current, shape)) {
+                return false;
+            }
+            let slot = properties.at_mut(*slot);
+            if !slot.is_writable() {
+                return false;
+            }
+            slot.set_value(value.clone().into());
+            true
+        }
+        InlineCache::Transition { from, to } => {
+            if !object.is_extensible() {
+                return false;
+            }
+            let mut object = object.borrow_mut();
+            if !object.properties().shape().is_some_and(|current| Rc::ptr_eq(current, from)) {
+                return false;
+            }
+            if !chain_lacks(object.prototype().cloned(), key) {
+                return false;
+            }
+            let slot = PropertySlot::new(PropertyDescriptor::data(value.clone(), true, true, true));
+            object.properties_mut().transition(to.clone(), slot);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The entry that stores `key` on `object`, if it has a shape.
+fn set_entry(object: &ObjectRef, key: &PropertyKey) -> InlineCache {
+    let object = object.borrow();
+    let Some(shape) = object.properties().shape() else {
+        return InlineCache::Empty;
+    };
+    match shape.slot(key) {
+        Some(slot) => InlineCache::Own { shape: shape.clone(), slot },
+        None if shape.len() < MAX_SHAPED_PROPERTIES => InlineCache::Transition {
+            from: shape.clone(),
+            to: shape.with_key(key.clone()),
+        },
+        None => InlineCache::Empty,
+    }
+}
+
+/// Whether neither `prototype` nor its ancestors have `key`, so that a
+/// `[[Set]]` would create it on the receiver.
+fn chain_lacks(mut prototype: Option<ObjectRef>, key: &PropertyKey) -> bool {
+    while let Some(object) = prototype {
+        let object = object.borrow();
+        if matches!(object.kind, ObjectKind::Primitive(JsValue::String(_))) || object.properties().position(key).is_some() {
+            return false;
+        }
+        prototype = object.prototype().cloned();
+    }
+    true
+}
+
+/// The entry that finds `key` on `object` or its prototype, if both have
+/// shapes and the property is data.
+fn get_entry(object: &ObjectRef, key: &PropertyKey) -> InlineCache {
+    let object = object.borrow();
+    let Some(shape) = object.properties().shape() else {
+        return InlineCache::Empty;
+    };
+    if let Some(slot) = shape.slot(key) {
+        return match object.properties().at(slot).is_data() {
+            true => InlineCache::Own { shape: shape.clone(), slot },
+            false => InlineCache::Empty,
+        };
+    }
+    let Some(prototype) = object.prototype() else {
+        return InlineCache::Empty;
+    };
+    let parent = prototype.borrow();
+    match parent.properties().shape().and_then(|prototype_shape| Some((prototype_shape, prototype_shape.slot(key)?))) {
+        Some((prototype_shape, slot)) if parent.properties().at(slot).is_data() => InlineCache::Prototype {
+            shape: shape.clone(),
+            prototype: prototype.downgrade(),
+            prototype_shape: prototype_shape.clone(),
+            slot,
+        },
+        _ => InlineCache::Empty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Engine, Interpreter};
+
+    fn run(code: &str) -> String {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(Engine::Bytecode);
+        interpreter.eval(code).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_hits_in_a_loop() {
+        reset_stats();
+        let code = "let point = { x: 1, y: 2 }; let total = 0; let i = 0;
+            function sum(o) { return o.x + o.y; }
+            while (i < 100) { point.x = i; total = total + sum(point); i = i + 1; }
+            total;";
+        assert_eq!(run(code), "5150");
+        let stats = stats();
+        assert_eq!((stats.get_hits, stats.get_misses), (198, 2));
+        assert_eq!((stats.set_hits, stats.set_misses), (99, 1));
+        assert_eq!((stats.call_hits, stats.call_misses), (99, 1));
+        assert_eq!(
+            stats.to_string(),
+            "ic: 198 of 200 property gets hit (99.0%)\nic: 99 of 100 property sets hit (99.0%)\nic: 99 of 100 calls hit (99.0%)"
+        );
+    }
+
+    #[test]
+    fn test_invalidation() {
+        let code = "function read(o) { return o.v; }
+            function write(o, value) { o.v = value; return o.v; }
+            function getter() { return 'getter'; }
+            let proto = { v: 'p' }; let a = Object.create(proto);
+            let log = read(a) + read(a);
+            proto.v = 'q'; log += read(a);
+            a.v = 'own'; log += read(a);
+            Object.setPrototypeOf(a, { v: 'other' }); delete a.v; log += read(a);
+            Object.defineProperty(proto, 'v', { get: getter }); log += read(Object.create(proto)) + ' ';
+
+            let b = { v: 1 };
+            log += write(b, 2) + ' ' + write(b, 3);
+            Object.defineProperty(b, 'v', { writable: false }); log += ' ' + write(b, 4);
+            Object.defineProperty(b, 'v', { get: getter }); log += read(b) + ' ';
+
+            let c = { v: 1 }; let i = 0;
+            while (i < 20) { c['k' + i] = i; delete c['k' + i]; c.w = i; delete c.v; c.v = i; i = i + 1; }
+            log += write(c, 'dictionary') + read(c) + ' ' + read('str');";
+        assert_eq!(run(code), "ppqownothergetter 2 3 3getter dictionarydictionary undefined");
+    }
+
+    #[test]
+    fn test_adding_properties() {
+        reset_stats();
+        let code = "function add(o) { o.fresh = 'added'; return o.fresh; }
+            let setterCalls = 0;
+            function setter(value) { setterCalls = setterCalls + 1; }
+            let log = add({}) + add({}) + ' ';
+            let guarded = {}; Object.defineProperty(guarded, 'fresh', { set: setter });
+            log += add(Object.create(guarded)) + ' ' + setterCalls + ' ';
+            let inherited = Object.create({ fresh: 'old' }); log += add(inherited) + ' ';
+            let closed = {}; Object.preventExtensions(closed); log += add(closed) + ' ';
+            log += add({}) + ' ' + Object.getOwnPropertyDescriptor(closed, 'fresh');";
+        assert_eq!(run(code), "addedadded undefined 1 added undefined added undefined");
+        let stats = stats();
+        assert_eq!((stats.set_hits, stats.set_misses), (2, 4));
+    }
+
+    #[test]
+    fn test_calls_follow_the_callee() {
+        reset_stats();
+        let code = "function one() { return 1; } function two() { return 2; }
+            function call(f) { return f(); }
+            call(one) + call(one) + call(two) + call(one) + ' ' + call(Object.prototype.toString);";
+        assert_eq!(run(code), "5 [object Undefined]");
+        // Each call site has its own cache: the five calls of `call` all
+        // miss, and `f()` hits only when it sees `one` twice in a row.
+        let stats = stats();
+        assert_eq!((stats.call_hits, stats.call_misses), (1, 9));
+    }
+}
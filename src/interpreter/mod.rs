@@ -0,0 +1,1422 @@
+pub mod builtins;
+pub mod environment;
+pub mod inline_cache;
+pub mod vm;
+
+use crate::bytecode::compiler::Compiler;
+use crate::interpreter::builtins::{inspect, install_globals};
+use crate::interpreter::environment::{EnvRef, Environment};
+use crate::lexical::atom::Atom;
+use crate::lexical::lexer::Lexer;
+use crate::parser::arena::AstArena;
+use crate::parser::ast::{ASTNode, Literal, VariableKind};
+use crate::parser::numeric::number_to_string;
+use crate::parser::operators::{AssignOp, LogicalOp, UnaryOp, UpdateOp};
+use crate::runtime::bigint::BigInt;
+use crate::runtime::conversions::{to_object, to_property_key, Context};
+use crate::runtime::error::JsError;
+use crate::runtime::gc::{Gc, Trace, Tracer};
+use crate::runtime::object::{FunctionKind, ObjectRef};
+use crate::runtime::operations;
+use crate::runtime::property::{PropertyDescriptor, PropertyKey};
+use crate::runtime::realm::Realm;
+use crate::runtime::string::JsString;
+use crate::runtime::value::JsValue;
+use crate::runtime::weak;
+use crate::semantic::early_errors;
+use crate::semantic::scope::ScopeTree;
+use crate::semantic::strict::{body_has_use_strict, StrictMode};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Nested calls allowed by default before a RangeError. Each call takes a
+/// few kilobytes of native stack in debug builds, so this fits the 2 MiB
+/// stack of a spawned thread.
+const DEFAULT_MAX_CALL_DEPTH: usize = 250;
+
+/// The parts of a function declaration needed to call it, shared by every
+/// closure created from the same declaration.
+#[derive(Debug)]
+pub struct FunctionCode {
+    pub name: Atom,
+    pub parameters: Vec<Atom>,
+    pub body: Vec<ASTNode>,
+    pub strict: bool,
+    /// Source text, returned when the function is converted to a string.
+    pub source: String,
+}
+
+impl FunctionCode {
+    fn new(declaration: &ASTNode, outer_strict: bool) -> Self {
+        let ASTNode::FunctionDeclaration { name, parameters, body, .. } = declaration else {
+            panic!("Expected a function declaration, found {:?}", declaration);
+        };
+        let ASTNode::Block { body, .. } = body.as_ref() else {
+            panic!("Expected a function body, found {:?}", body);
+        };
+        FunctionCode {
+            name: identifier_name(name),
+            parameters: parameters.iter().map(identifier_name).collect(),
+            body: body.clone(),
+            strict: outer_strict || body_has_use_strict(body),
+            source: declaration.to_source(),
+        }
+    }
+}
+
+/// A function value: its code and the environment it was created in.
+#[derive(Debug)]
+pub struct Closure {
+    pub code: Rc<FunctionCode>,
+    pub env: EnvRef,
+}
+
+impl Trace for Closure {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.edge(&self.env);
+    }
+}
+
+/// How a statement finished: a completion record other than a throw, which
+/// is the `Err` of the `Result` around it. `Normal`, `Break` and `Continue`
+/// carry the statement's value if it produced one; `Break` and `Continue`
+/// also carry the label they target, if any.
+#[derive(Debug)]
+enum Completion {
+    Normal(Option<JsValue>),
+    Return(JsValue),
+    Break(Option<String>, Option<JsValue>),
+    Continue(Option<String>, Option<JsValue>),
+}
+
+impl Completion {
+    /// UpdateEmpty: gives a completion without a value the value `value`.
+    fn update_empty(self, value: Option<JsValue>) -> Completion {
+        match self {
+            Completion::Normal(None) => Completion::Normal(value),
+            Completion::Break(label, None) => Completion::Break(label, value),
+            Completion::Continue(label, None) => Completion::Continue(label, value),
+            completion => completion,
+        }
+    }
+
+    fn value(&self) -> Option<&JsValue> {
+        match self {
+            Completion::Normal(value) | Completion::Break(_, value) | Completion::Continue(_, value) => value.as_ref(),
+            Completion::Return(value) => Some(value),
+        }
+    }
+}
+
+/// Where an assignment target lives: a name and the environment that
+/// binds it, `None` if no environment does, or a property of a value.
+enum Reference {
+    Binding(Atom, Option<EnvRef>),
+    Property(JsValue, PropertyKey),
+}
+
+/// How an [`Interpreter`] runs the programs it is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    /// Evaluate the [`ASTNode`] tree directly.
+    #[default]
+    TreeWalker,
+    /// Compile to bytecode and run it on the virtual machine in [`vm`].
+    Bytecode,
+}
+
+/// Runs programs by walking their [`ASTNode`] tree, or by compiling them to
+/// bytecode first; see [`Engine`]. Every script run by the same interpreter
+/// shares one global environment and global object.
+pub struct Interpreter {
+    global: EnvRef,
+    realm: Realm,
+    strict: bool,
+    /// The `this` of the running function, or the global object.
+    this: JsValue,
+    engine: Engine,
+    depth: usize,
+    max_call_depth: usize,
+    /// Programs stay alive for as long as the interpreter so that the
+    /// addresses of their function declarations, the keys of `functions`,
+    /// are never reused.
+    programs: Vec<Rc<ASTNode>>,
+    functions: HashMap<*const ASTNode, Rc<FunctionCode>>,
+    /// Targets of `WeakRef`s made or dereferenced by the running script,
+    /// which stay alive until it finishes.
+    kept_alive: Vec<ObjectRef>,
+    printer: Box<dyn FnMut(&str)>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// An interpreter whose `console.log` writes to standard output.
+    pub fn new() -> Self {
+        Self::with_printer(|line| println!("{}", line))
+    }
+
+    /// An interpreter that hands every line of `console.log` output to
+    /// `printer`.
+    pub fn with_printer(printer: impl FnMut(&str) + 'static) -> Self {
+        let realm = Realm::new();
+        let global_object = realm.ordinary_object();
+        install_globals(&global_object, &realm);
+        let global = Environment::global(global_object.clone());
+        Interpreter {
+            global,
+            realm,
+            strict: false,
+            this: global_object.into(),
+            engine: Engine::default(),
+            depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            programs: Vec::new(),
+            functions: HashMap::new(),
+            kept_alive: Vec::new(),
+            printer: Box::new(printer),
+        }
+    }
+
+    pub fn global(&self) -> &EnvRef {
+        &self.global
+    }
+
+    pub fn realm(&self) -> &Realm {
+        &self.realm
+    }
+
+    /// The object whose properties are the global `var`s and functions.
+    pub fn global_object(&self) -> ObjectRef {
+        self.global.borrow().binding_object().expect("a global environment")
+    }
+
+    /// Sets how deeply calls may nest before throwing a RangeError. Raise it
+    /// only when running on a thread with a correspondingly larger stack.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    pub fn print(&mut self, line: &str) {
+        (self.printer)(line);
+    }
+
+    /// Parses and runs a script, returning its completion value: the value
+    /// of the last statement that produced one, as `eval` would.
+    ///
+    /// Panics on syntax errors, like the parser.
+    pub fn eval(&mut self, source: &str) -> Result<JsValue, JsError> {
+        self.run(ASTNode::parse_program(&Lexer::new(source).tokenize()))
+    }
+
+    /// Runs a parsed program in the global environment, after reporting its
+    /// first early error, if any, as a SyntaxError. An exception the script
+    /// does not catch comes back as [`JsError::Thrown`], holding the thrown
+    /// value; errors the engine raised are error objects by then.
+    ///
+    /// Once the script completes, the cleanup jobs of finalization
+    /// registries whose targets were collected meanwhile run too.
+    pub fn run(&mut self, program: ASTNode) -> Result<JsValue, JsError> {
+        let result = self
+            .run_program(program)
+            .map_err(|error| JsError::Thrown(self.realm.thrown_value(error)));
+        self.finish_script(result)
+    }
+
+    /// Lets go of the objects the script kept alive, then runs the cleanup
+    /// jobs queued meanwhile.
+    fn finish_script(&mut self, result: Result<JsValue, JsError>) -> Result<JsValue, JsError> {
+        self.kept_alive.clear();
+        let value = result?;
+        self.run_cleanup_jobs()?;
+        Ok(value)
+    }
+
+    /// Keeps `object` alive until the running script finishes, so that a
+    /// `WeakRef` to it does not empty in the middle of the script.
+    pub fn keep_alive(&mut self, object: ObjectRef) {
+        self.kept_alive.push(object);
+    }
+
+    /// Calls the cleanup callbacks of the finalization registries whose
+    /// targets have been collected, stopping at the first that throws.
+    /// Collections only queue these jobs, so that no script code runs in
+    /// the middle of another script.
+    pub fn run_cleanup_jobs(&mut self) -> Result<(), JsError> {
+        while let Some(job) = weak::next_cleanup_job() {
+            self.call(&job.callback.into(), JsValue::Undefined, &[job.held_value])
+                .map_err(|error| JsError::Thrown(self.realm.thrown_value(error)))?;
+        }
+        Ok(())
+    }
+
+    fn run_program(&mut self, program: ASTNode) -> Result<JsValue, JsError> {
+        if self.engine == Engine::Bytecode {
+            let template = Compiler::compile(&program)?;
+            return self.run_template(template);
+        }
+
+        let arena = AstArena::from_ast(&program);
+        let scopes = ScopeTree::analyze(&arena);
+        let strict = StrictMode::analyze(&arena, false);
+        if let Some(error) = early_errors::check(&arena, &scopes, &strict).into_iter().next() {
+            return Err(JsError::syntax_error(error.message));
+        }
+
+        let program = Rc::new(program);
+        self.programs.push(program.clone());
+        let ASTNode::Program { body, .. } = program.as_ref() else {
+            panic!("Expected a program, found {:?}", program);
+        };
+
+        let global = self.global.clone();
+        let outer_strict = std::mem::replace(&mut self.strict, body_has_use_strict(body));
+        let result = self
+            .instantiate_global_declarations(body)
+            .and_then(|()| self.execute_statements(body, &global));
+        self.strict = outer_strict;
+
+        // Early errors rule out `return`, `break` and `continue` here.
+        Ok(result?.value().cloned().unwrap_or(JsValue::Undefined))
+    }
+
+    /// Calls `function` with the given `this` value and arguments.
+    pub fn call(&mut self, function: &JsValue, this: JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+        let Some(kind) = function.as_object().and_then(ObjectRef::function_kind) else {
+            return Err(JsError::type_error(format!("{} is not a function", inspect(function))));
+        };
+        if self.depth >= self.max_call_depth {
+            return Err(JsError::range_error("Maximum call stack size exceeded"));
+        }
+
+        self.depth += 1;
+        let result = match kind {
+            FunctionKind::Native(native) => (native.call)(self, &this, args),
+            FunctionKind::Script(closure) => self.call_closure(&closure, this, args),
+            FunctionKind::Bytecode(closure) => self.call_bytecode(&closure, this, args),
+        };
+        self.depth -= 1;
+        result
+    }
+
+    fn call_closure(&mut self, closure: &Closure, this: JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+        let code = &closure.code;
+        let this = self.bind_this(code.strict, this)?;
+        let env = Environment::declarative(Some(closure.env.clone()));
+        for (index, parameter) in code.parameters.iter().enumerate() {
+            let value = args.get(index).cloned().unwrap_or(JsValue::Undefined);
+            env.borrow_mut().declare(*parameter, value);
+        }
+
+        let outer_strict = std::mem::replace(&mut self.strict, code.strict);
+        let outer_this = std::mem::replace(&mut self.this, this);
+        self.instantiate_function_declarations(&code.body, &env);
+        let result = self.execute_statements(&code.body, &env);
+        self.strict = outer_strict;
+        self.this = outer_this;
+
+        Ok(match result? {
+            Completion::Return(value) => value,
+            _ => JsValue::Undefined,
+        })
+    }
+
+    /// OrdinaryCallBindThis: the `this` a function called with `this` sees.
+    /// Strict functions see it as it is; sloppy ones see the global object
+    /// in place of `undefined` or `null`, and primitives as objects.
+    pub(super) fn bind_this(&self, strict: bool, this: JsValue) -> Result<JsValue, JsError> {
+        match this {
+            JsValue::Undefined | JsValue::Null if !strict => Ok(self.global_object().into()),
+            JsValue::Object(_) => Ok(this),
+            primitive if !strict => to_object(self, &primitive).map(JsValue::from),
+            value => Ok(value),
+        }
+    }
+
+    /// GlobalDeclarationInstantiation for a script's top-level declarations.
+    fn instantiate_global_declarations(&mut self, body: &[ASTNode]) -> Result<(), JsError> {
+        let global = self.global.clone();
+        let lexical_names: Vec<(Atom, bool)> = lexical_declarations(body)
+            .into_iter()
+            .map(|(name, kind)| (name, kind == VariableKind::Const))
+            .collect();
+        let mut var_names = Vec::new();
+        for statement in body {
+            collect_var_names(statement, &mut var_names);
+        }
+        let functions = function_declarations(body)
+            .into_iter()
+            .map(|(name, declaration)| (name, self.instantiate_function(declaration, &global)))
+            .collect();
+        self.declare_globals(&lexical_names, &var_names, functions)
+    }
+
+    /// Checks a script's top-level declarations against those of earlier
+    /// scripts and the global object, then creates them. `var`s and
+    /// functions become global object properties; `let` and `const`, given
+    /// with whether they are constant, start in their temporal dead zone.
+    fn declare_globals(
+        &mut self,
+        lexical_names: &[(Atom, bool)],
+        var_names: &[Atom],
+        functions: Vec<(Atom, JsValue)>,
+    ) -> Result<(), JsError> {
+        let global = self.global.clone();
+        let env = global.borrow();
+        for &(name, _) in lexical_names {
+            if env.has_var_declaration(name) || env.has_lexical_declaration(name) || env.has_restricted_global_property(name) {
+                return Err(already_declared(&name));
+            }
+        }
+        for &name in var_names.iter().chain(functions.iter().map(|(name, _)| name)) {
+            if env.has_lexical_declaration(name) {
+                return Err(already_declared(&name));
+            }
+        }
+        for &(name, _) in &functions {
+            if !env.can_declare_global_function(name) {
+                return Err(JsError::type_error(format!("Cannot redefine global function '{}'", name)));
+            }
+        }
+        for &name in var_names {
+            if !env.can_declare_global_var(name) {
+                return Err(JsError::type_error(format!("Cannot define global variable '{}'", name)));
+            }
+        }
+        drop(env);
+
+        let mut env = global.borrow_mut();
+        for (name, function) in functions {
+            env.create_global_function_binding(name, function);
+        }
+        for &name in var_names {
+            env.create_global_var_binding(name);
+        }
+        for &(name, constant) in lexical_names {
+            create_lexical_binding(&mut env, name, constant);
+        }
+        Ok(())
+    }
+
+    /// FunctionDeclarationInstantiation, after the parameters are bound:
+    /// `var`s not named by a parameter start as `undefined`, functions are
+    /// created and `let` and `const` start in their temporal dead zone.
+    fn instantiate_function_declarations(&mut self, body: &[ASTNode], env: &EnvRef) {
+        let mut var_names = Vec::new();
+        for statement in body {
+            collect_var_names(statement, &mut var_names);
+        }
+        for name in var_names {
+            if !env.borrow().has_binding(name) {
+                env.borrow_mut().declare(name, JsValue::Undefined);
+            }
+        }
+        self.instantiate_block_declarations(body, env);
+    }
+
+    /// BlockDeclarationInstantiation: the `let`, `const` and function
+    /// declarations directly in `statements`.
+    fn instantiate_block_declarations(&mut self, statements: &[ASTNode], env: &EnvRef) {
+        for (name, kind) in lexical_declarations(statements) {
+            create_lexical_binding(&mut env.borrow_mut(), name, kind == VariableKind::Const);
+        }
+        for (name, declaration) in function_declarations(statements) {
+            let function = self.instantiate_function(declaration, env);
+            env.borrow_mut().declare(name, function);
+        }
+    }
+
+    fn instantiate_function(&mut self, declaration: &ASTNode, env: &EnvRef) -> JsValue {
+        let strict = self.strict;
+        let code = self
+            .functions
+            .entry(declaration as *const ASTNode)
+            .or_insert_with(|| Rc::new(FunctionCode::new(declaration, strict)))
+            .clone();
+        let closure = Closure { code, env: env.clone() };
+        self.function_object(FunctionKind::Script(Gc::new(closure)))
+    }
+
+    /// A function object for a function declaration. Every one can be a
+    /// constructor, so it gets a `prototype` object pointing back at it.
+    fn function_object(&self, kind: FunctionKind) -> JsValue {
+        let function = ObjectRef::function(&self.realm.function_prototype, kind);
+        let prototype = self.realm.ordinary_object();
+        let constructor = PropertyDescriptor::data(function.clone().into(), true, false, true);
+        prototype.define_own_property(Atom::CONSTRUCTOR.into(), constructor);
+        let descriptor = PropertyDescriptor::data(prototype.into(), true, false, false);
+        function.define_own_property(Atom::PROTOTYPE.into(), descriptor);
+        function.into()
+    }
+
+    fn execute_statements(&mut self, statements: &[ASTNode], env: &EnvRef) -> Result<Completion, JsError> {
+        let mut value = None;
+        for statement in statements {
+            match self.execute(statement, env)? {
+                Completion::Normal(Some(result)) => value = Some(result),
+                Completion::Normal(None) => {}
+                abrupt => return Ok(abrupt.update_empty(value)),
+            }
+        }
+        Ok(Completion::Normal(value))
+    }
+
+    fn execute(&mut self, statement: &ASTNode, env: &EnvRef) -> Result<Completion, JsError> {
+        match statement {
+            ASTNode::VariableDeclaration { kind, declarations, .. } => {
+                for declarator in declarations {
+                    self.execute_declarator(*kind, declarator, env)?;
+                }
+                Ok(Completion::Normal(None))
+            }
+            ASTNode::FunctionDeclaration { .. } => Ok(Completion::Normal(None)),
+            ASTNode::IfStatement {
+                condition,
+                body,
+                else_body,
+                ..
+            } => self.execute_if(condition, body, else_body.as_deref(), env),
+            ASTNode::Block { body, .. } => {
+                let block_env = Environment::declarative(Some(env.clone()));
+                self.instantiate_block_declarations(body, &block_env);
+                self.execute_statements(body, &block_env)
+            }
+            ASTNode::ExpressionStatement { expression, .. } => {
+                Ok(Completion::Normal(Some(self.evaluate(expression, env)?)))
+            }
+            ASTNode::ReturnStatement { argument, .. } => {
+                let value = match argument {
+                    Some(argument) => self.evaluate(argument, env)?,
+                    None => JsValue::Undefined,
+                };
+                Ok(Completion::Return(value))
+            }
+            ASTNode::BreakStatement { label, .. } => {
+                Ok(Completion::Break(label.as_deref().map(|label| identifier_name(label).to_string()), None))
+            }
+            ASTNode::ContinueStatement { label, .. } => {
+                Ok(Completion::Continue(label.as_deref().map(|label| identifier_name(label).to_string()), None))
+            }
+            ASTNode::ThrowStatement { argument, .. } => Err(JsError::Thrown(self.evaluate(argument, env)?)),
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => self.execute_try(block, handler.as_deref(), finalizer.as_deref(), env),
+            ASTNode::LabeledStatement { .. } => self.execute_labelled(statement, env, &mut Vec::new()),
+            ASTNode::WhileStatement { .. } | ASTNode::ForStatement { .. } => self.execute_iteration(statement, env, &[]),
+            expression => Ok(Completion::Normal(Some(self.evaluate(expression, env)?))),
+        }
+    }
+
+    fn execute_if(
+        &mut self,
+        condition: &ASTNode,
+        body: &ASTNode,
+        else_body: Option<&ASTNode>,
+        env: &EnvRef,
+    ) -> Result<Completion, JsError> {
+        let completion = if self.evaluate(condition, env)?.to_boolean() {
+            self.execute(body, env)?
+        } else if let Some(else_body) = else_body {
+            self.execute(else_body, env)?
+        } else {
+            Completion::Normal(None)
+        };
+        Ok(completion.update_empty(Some(JsValue::Undefined)))
+    }
+
+    /// LabelledEvaluation: `labels` collects the labels of a chain like
+    /// `a: b: while (x) ...`, so that `continue a` and `continue b` both
+    /// continue the loop. A `break` to one of them ends the statement
+    /// normally.
+    fn execute_labelled(&mut self, statement: &ASTNode, env: &EnvRef, labels: &mut Vec<String>) -> Result<Completion, JsError> {
+        let completion = match statement {
+            ASTNode::LabeledStatement { label, body, .. } => {
+                labels.push(identifier_name(label).to_string());
+                self.execute_labelled(body, env, labels)?
+            }
+            ASTNode::WhileStatement { .. } | ASTNode::ForStatement { .. } => self.execute_iteration(statement, env, labels)?,
+            other => self.execute(other, env)?,
+        };
+        Ok(match completion {
+            Completion::Break(Some(target), value) if labels.contains(&target) => {
+                Completion::Normal(Some(value.unwrap_or(JsValue::Undefined)))
+            }
+            completion => completion,
+        })
+    }
+
+    /// Runs a `while` or `for` loop whose label set is `labels`. An
+    /// unlabelled `break` ends it normally.
+    fn execute_iteration(&mut self, statement: &ASTNode, env: &EnvRef, labels: &[String]) -> Result<Completion, JsError> {
+        let completion = match statement {
+            ASTNode::WhileStatement { condition, body, .. } => {
+                self.execute_loop(Some(condition), None, body, env.clone(), false, labels)?
+            }
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => self.execute_for(init.as_deref(), condition.as_deref(), update.as_deref(), body, env, labels)?,
+            other => panic!("Expected a loop, found {:?}", other),
+        };
+        Ok(match completion {
+            Completion::Break(None, value) => Completion::Normal(Some(value.unwrap_or(JsValue::Undefined))),
+            completion => completion,
+        })
+    }
+
+    /// `try` with a `catch`, a `finally` or both. A throw from the block
+    /// runs the `catch`; the `finally` then runs however the rest ended,
+    /// and its own completion wins only if it is abrupt.
+    fn execute_try(
+        &mut self,
+        block: &ASTNode,
+        handler: Option<&ASTNode>,
+        finalizer: Option<&ASTNode>,
+        env: &EnvRef,
+    ) -> Result<Completion, JsError> {
+        let mut result = self.execute(block, env);
+        if let (Err(error), Some(handler)) = (&result, handler) {
+            let thrown = self.realm.thrown_value(error.clone());
+            result = self.execute_catch(handler, thrown, env);
+        }
+        if let Some(finalizer) = finalizer {
+            match self.execute(finalizer, env)? {
+                Completion::Normal(_) => {}
+                abrupt => result = Ok(abrupt),
+            }
+        }
+        Ok(result?.update_empty(Some(JsValue::Undefined)))
+    }
+
+    /// CatchClauseEvaluation: the parameter, if any, is bound to `thrown`
+    /// in a scope of its own around the body.
+    fn execute_catch(&mut self, handler: &ASTNode, thrown: JsValue, env: &EnvRef) -> Result<Completion, JsError> {
+        let ASTNode::CatchClause { parameter, body, .. } = handler else {
+            panic!("Expected a catch clause, found {:?}", handler);
+        };
+        match parameter {
+            Some(parameter) => {
+                let catch_env = Environment::declarative(Some(env.clone()));
+                catch_env.borrow_mut().declare(identifier_name(parameter), thrown);
+                self.execute(body, &catch_env)
+            }
+            None => self.execute(body, env),
+        }
+    }
+
+    fn execute_for(
+        &mut self,
+        init: Option<&ASTNode>,
+        condition: Option<&ASTNode>,
+        update: Option<&ASTNode>,
+        body: &ASTNode,
+        env: &EnvRef,
+        labels: &[String],
+    ) -> Result<Completion, JsError> {
+        let (loop_env, per_iteration) = match init {
+            Some(declaration @ ASTNode::VariableDeclaration { kind, .. }) if *kind != VariableKind::Var => {
+                let loop_env = Environment::declarative(Some(env.clone()));
+                self.instantiate_block_declarations(std::slice::from_ref(declaration), &loop_env);
+                (loop_env, *kind == VariableKind::Let)
+            }
+            _ => (env.clone(), false),
+        };
+        match init {
+            Some(declaration @ ASTNode::VariableDeclaration { .. }) => {
+                self.execute(declaration, &loop_env)?;
+            }
+            Some(expression) => {
+                self.evaluate(expression, &loop_env)?;
+            }
+            None => {}
+        }
+        self.execute_loop(condition, update, body, loop_env, per_iteration, labels)
+    }
+
+    /// Runs `body` while `condition` holds, evaluating `update` after each
+    /// iteration. The loop's value is that of the last iteration to produce
+    /// one. With `per_iteration`, every iteration gets its own copy of the
+    /// `let` bindings of `env`, starting from the values the previous one
+    /// left. A `continue` targeting one of `labels` continues this loop;
+    /// any other abrupt completion leaves it.
+    fn execute_loop(
+        &mut self,
+        condition: Option<&ASTNode>,
+        update: Option<&ASTNode>,
+        body: &ASTNode,
+        mut env: EnvRef,
+        per_iteration: bool,
+        labels: &[String],
+    ) -> Result<Completion, JsError> {
+        let mut value = JsValue::Undefined;
+        if per_iteration {
+            env = Environment::copy_for_iteration(&env);
+        }
+        loop {
+            if let Some(condition) = condition {
+                if !self.evaluate(condition, &env)?.to_boolean() {
+                    break;
+                }
+            }
+            let completion = self.execute(body, &env)?;
+            if let Some(result) = completion.value() {
+                value = result.clone();
+            }
+            match completion {
+                Completion::Normal(_) | Completion::Continue(None, _) => {}
+                Completion::Continue(Some(target), _) if labels.contains(&target) => {}
+                abrupt => return Ok(abrupt.update_empty(Some(value))),
+            }
+            if per_iteration {
+                env = Environment::copy_for_iteration(&env);
+            }
+            if let Some(update) = update {
+                self.evaluate(update, &env)?;
+            }
+        }
+        Ok(Completion::Normal(Some(value)))
+    }
+
+    fn execute_declarator(&mut self, kind: VariableKind, declarator: &ASTNode, env: &EnvRef) -> Result<(), JsError> {
+        let ASTNode::VariableDeclarator { identifier, value, .. } = declarator else {
+            panic!("Expected a variable declarator, found {:?}", declarator);
+        };
+        let name = identifier_name(identifier);
+        let value = match value {
+            Some(value) => Some(self.evaluate(value, env)?),
+            None => None,
+        };
+
+        match kind {
+            // Hoisted already; `var x;` leaves an existing value alone.
+            VariableKind::Var => {
+                if let Some(value) = value {
+                    let reference = Reference::Binding(name, Environment::resolve(env, name));
+                    self.put_value(&reference, value)?;
+                }
+            }
+            // Created uninitialized on entering the scope.
+            VariableKind::Let | VariableKind::Const => {
+                env.borrow_mut().initialize_binding(name, value.unwrap_or(JsValue::Undefined));
+            }
+        }
+        Ok(())
+    }
+
+    // Each kind of expression is evaluated by its own method to keep this
+    // function's stack frame, paid for at every level of recursion, small.
+    fn evaluate(&mut self, expression: &ASTNode, env: &EnvRef) -> Result<JsValue, JsError> {
+        match expression {
+            ASTNode::Literal { value, .. } => evaluate_literal(value),
+            ASTNode::ThisExpression { .. } => Ok(self.this.clone()),
+            ASTNode::Identifier { .. } => {
+                let reference = self.reference(expression, env)?;
+                self.get_value(&reference)
+            }
+            ASTNode::ObjectExpression { properties, .. } => self.evaluate_object(properties, env),
+            ASTNode::MemberExpression { .. } => {
+                let reference = self.reference(expression, env)?;
+                self.get_value(&reference)
+            }
+            ASTNode::CallExpression { callee, arguments, .. } => self.evaluate_call(callee, arguments, env),
+            ASTNode::AssignmentExpression { left, operator, right, .. } => {
+                self.evaluate_assignment(left, *operator, right, env)
+            }
+            ASTNode::BinaryExpression { left, operator, right, .. } => {
+                let left = self.evaluate(left, env)?;
+                let right = self.evaluate(right, env)?;
+                operations::binary(self, *operator, &left, &right)
+            }
+            ASTNode::LogicalExpression { left, operator, right, .. } => {
+                let left = self.evaluate(left, env)?;
+                if short_circuits(*operator, &left) {
+                    return Ok(left);
+                }
+                self.evaluate(right, env)
+            }
+            ASTNode::UnaryExpression { operator, argument, .. } => self.evaluate_unary(*operator, argument, env),
+            ASTNode::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+                ..
+            } => self.evaluate_update(*operator, *prefix, argument, env),
+            other => panic!("Unexpected expression: {:?}", other),
+        }
+    }
+
+    fn evaluate_object(&mut self, properties: &[ASTNode], env: &EnvRef) -> Result<JsValue, JsError> {
+        let object = self.realm.ordinary_object();
+        for property in properties {
+            let ASTNode::Property { key, value, computed, .. } = property else {
+                panic!("Expected a property, found {:?}", property);
+            };
+            let key = self.property_key(key, *computed, env)?;
+            let value = self.evaluate(value, env)?;
+            object.create_data_property(key, value);
+        }
+        Ok(object.into())
+    }
+
+    fn evaluate_call(&mut self, callee: &ASTNode, arguments: &[ASTNode], env: &EnvRef) -> Result<JsValue, JsError> {
+        let (function, this) = match callee {
+            ASTNode::MemberExpression { .. } => {
+                let reference = self.reference(callee, env)?;
+                let function = self.get_value(&reference)?;
+                let Reference::Property(base, _) = reference else { unreachable!() };
+                (function, base)
+            }
+            _ => (self.evaluate(callee, env)?, JsValue::Undefined),
+        };
+
+        let mut args = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            args.push(self.evaluate(argument, env)?);
+        }
+        if !function.is_callable() {
+            return Err(JsError::type_error(format!("{} is not a function", callee.to_source())));
+        }
+        self.call(&function, this, &args)
+    }
+
+    fn evaluate_assignment(
+        &mut self,
+        left: &ASTNode,
+        operator: AssignOp,
+        right: &ASTNode,
+        env: &EnvRef,
+    ) -> Result<JsValue, JsError> {
+        let reference = self.reference(left, env)?;
+        let value = if operator == AssignOp::Assign {
+            self.evaluate(right, env)?
+        } else if let Some(logical) = operator.logical_op() {
+            let current = self.get_value(&reference)?;
+            if short_circuits(logical, &current) {
+                return Ok(current);
+            }
+            self.evaluate(right, env)?
+        } else {
+            let current = self.get_value(&reference)?;
+            let operand = self.evaluate(right, env)?;
+            operations::binary(self, operator.binary_op().unwrap(), &current, &operand)?
+        };
+        self.put_value(&reference, value.clone())?;
+        Ok(value)
+    }
+
+    fn evaluate_unary(&mut self, operator: UnaryOp, argument: &ASTNode, env: &EnvRef) -> Result<JsValue, JsError> {
+        match (operator, argument) {
+            (UnaryOp::TypeOf, ASTNode::Identifier { .. }) => match self.reference(argument, env)? {
+                Reference::Binding(_, None) => Ok("undefined".into()),
+                reference => Ok(self.get_value(&reference)?.type_of().into()),
+            },
+            (UnaryOp::Delete, ASTNode::MemberExpression { .. }) => {
+                let Reference::Property(base, key) = self.reference(argument, env)? else { unreachable!() };
+                self.delete_property(&base, &key)
+            }
+            (UnaryOp::Delete, ASTNode::Identifier { name, .. }) => match Environment::resolve(env, *name) {
+                Some(binding) => Ok(Environment::delete_binding(&binding, *name).into()),
+                None => Ok(true.into()),
+            },
+            _ => {
+                let value = self.evaluate(argument, env)?;
+                operations::unary(self, operator, &value)
+            }
+        }
+    }
+
+    fn delete_property(&mut self, base: &JsValue, key: &PropertyKey) -> Result<JsValue, JsError> {
+        let deleted = to_object(self, base)?.delete(key);
+        if !deleted && self.strict {
+            return Err(JsError::type_error(format!("Cannot delete property '{}' of {}", key, inspect(base))));
+        }
+        Ok(deleted.into())
+    }
+
+    fn evaluate_update(
+        &mut self,
+        operator: UpdateOp,
+        prefix: bool,
+        argument: &ASTNode,
+        env: &EnvRef,
+    ) -> Result<JsValue, JsError> {
+        let reference = self.reference(argument, env)?;
+        let value = self.get_value(&reference)?;
+        let delta = match operator {
+            UpdateOp::Increment => 1,
+            UpdateOp::Decrement => -1,
+        };
+        let (old, new) = operations::increment(self, &value, delta)?;
+        self.put_value(&reference, new.clone())?;
+        Ok(if prefix { new } else { old })
+    }
+
+    /// Evaluates an assignment target, or the callee of a method call, up
+    /// to the point of reading or writing it.
+    fn reference(&mut self, target: &ASTNode, env: &EnvRef) -> Result<Reference, JsError> {
+        match target {
+            ASTNode::Identifier { name, .. } => Ok(Reference::Binding(*name, Environment::resolve(env, *name))),
+            ASTNode::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                let base = self.evaluate(object, env)?;
+                let key = self.property_key(property, *computed, env)?;
+                Ok(Reference::Property(base, key))
+            }
+            other => panic!("Invalid assignment target: {:?}", other),
+        }
+    }
+
+    fn get_value(&mut self, reference: &Reference) -> Result<JsValue, JsError> {
+        match reference {
+            Reference::Binding(name, Some(binding)) => {
+                let strict = self.strict;
+                Environment::get_binding_value(binding, self, *name, strict)
+            }
+            Reference::Binding(name, None) => Err(JsError::reference_error(format!("{} is not defined", name))),
+            Reference::Property(base, key) => match base {
+                JsValue::Undefined | JsValue::Null => Err(JsError::type_error(format!(
+                    "Cannot read properties of {} (reading '{}')",
+                    base, key
+                ))),
+                _ => to_object(self, base)?.get(self, key, base),
+            },
+        }
+    }
+
+    fn put_value(&mut self, reference: &Reference, value: JsValue) -> Result<(), JsError> {
+        match reference {
+            Reference::Binding(name, Some(binding)) => {
+                let strict = self.strict;
+                Environment::set_mutable_binding(binding, self, *name, value, strict)
+            }
+            Reference::Binding(name, None) => {
+                if self.strict {
+                    return Err(JsError::reference_error(format!("{} is not defined", name)));
+                }
+                // Sloppy code creates a property of the global object instead.
+                let global_object = self.global_object();
+                global_object.set(self, name.as_str().into(), value, &global_object.clone().into())?;
+                Ok(())
+            }
+            Reference::Property(base, key) => match base {
+                JsValue::Undefined | JsValue::Null => Err(JsError::type_error(format!(
+                    "Cannot set properties of {} (setting '{}')",
+                    base, key
+                ))),
+                _ => {
+                    let object = to_object(self, base)?;
+                    if object.set(self, key.clone(), value, base)? || !self.strict {
+                        return Ok(());
+                    }
+                    Err(JsError::type_error(match object.find_property(key) {
+                        _ if base.as_object().is_none() => format!(
+                            "Cannot create property '{}' on {} '{}'",
+                            key,
+                            base.type_of(),
+                            base
+                        ),
+                        Some(property) if property.is_accessor() => {
+                            format!("Cannot set property {} of {} which has only a getter", key, inspect(base))
+                        }
+                        Some(_) => format!("Cannot assign to read only property '{}' of object", key),
+                        None => format!("Cannot add property {}, object is not extensible", key),
+                    }))
+                }
+            },
+        }
+    }
+
+    fn property_key(&mut self, key: &ASTNode, computed: bool, env: &EnvRef) -> Result<PropertyKey, JsError> {
+        if computed {
+            let key = self.evaluate(key, env)?;
+            return to_property_key(self, &key);
+        }
+        Ok(match key {
+            ASTNode::Identifier { name, .. } => name.as_str().into(),
+            ASTNode::Literal {
+                value: Literal::String(text),
+                ..
+            } => JsString::from_code_units(text).into(),
+            ASTNode::Literal {
+                value: Literal::Number(number),
+                ..
+            } => number_to_string(*number).into(),
+            other => panic!("Invalid property key: {:?}", other),
+        })
+    }
+}
+
+impl Context for Interpreter {
+    fn call(&mut self, function: &JsValue, this: JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+        Interpreter::call(self, function, this, args)
+    }
+
+    fn realm(&self) -> &Realm {
+        &self.realm
+    }
+}
+
+fn evaluate_literal(literal: &Literal) -> Result<JsValue, JsError> {
+    match literal {
+        Literal::Number(number) => Ok(JsValue::Number(*number)),
+        Literal::String(text) => Ok(JsString::from_code_units(text).into()),
+        Literal::Boolean(boolean) => Ok(JsValue::Boolean(*boolean)),
+        Literal::Null => Ok(JsValue::Null),
+        Literal::BigInt(digits) => BigInt::parse_literal(digits)
+            .map(JsValue::from)
+            .ok_or_else(|| JsError::syntax_error("Invalid BigInt literal")),
+        Literal::RegExp { .. } => Err(JsError::syntax_error("Regular expression literals are not supported")),
+    }
+}
+
+fn identifier_name(node: &ASTNode) -> Atom {
+    match node {
+        ASTNode::Identifier { name, .. } => *name,
+        other => panic!("Expected an identifier, found {:?}", other),
+    }
+}
+
+/// The names `let` and `const` declarations directly in `statements`
+/// declare.
+fn lexical_declarations(statements: &[ASTNode]) -> Vec<(Atom, VariableKind)> {
+    let mut names = Vec::new();
+    for statement in statements {
+        if let ASTNode::VariableDeclaration { kind, declarations, .. } = statement {
+            if *kind == VariableKind::Var {
+                continue;
+            }
+            for declarator in declarations {
+                if let ASTNode::VariableDeclarator { identifier, .. } = declarator {
+                    names.push((identifier_name(identifier), *kind));
+                }
+            }
+        }
+    }
+    names
+}
+
+/// The function declarations directly in `statements`, by name, including
+/// labelled ones. When a name repeats, the last declaration wins.
+fn function_declarations(statements: &[ASTNode]) -> Vec<(Atom, &ASTNode)> {
+    let mut functions: Vec<(Atom, &ASTNode)> = Vec::new();
+    for mut statement in statements {
+        while let ASTNode::LabeledStatement { body, .. } = statement {
+            statement = body;
+        }
+        if let ASTNode::FunctionDeclaration { name, .. } = statement {
+            let name = identifier_name(name);
+            functions.retain(|(other, _)| *other != name);
+            functions.push((name, statement));
+        }
+    }
+    functions
+}
+
+fn create_lexical_binding(env: &mut Environment, name: Atom, constant: bool) {
+    if constant {
+        env.create_immutable_binding(name);
+    } else {
+        env.create_mutable_binding(name);
+    }
+}
+
+fn already_declared(name: &str) -> JsError {
+    JsError::syntax_error(format!("Identifier '{}' has already been declared", name))
+}
+
+/// Names declared with `var` in `statement`, without entering nested
+/// functions.
+fn collect_var_names(statement: &ASTNode, names: &mut Vec<Atom>) {
+    match statement {
+        ASTNode::VariableDeclaration {
+            kind: VariableKind::Var,
+            declarations,
+            ..
+        } => {
+            for declarator in declarations {
+                if let ASTNode::VariableDeclarator { identifier, .. } = declarator {
+                    names.push(identifier_name(identifier));
+                }
+            }
+        }
+        ASTNode::Block { body, .. } => body.iter().for_each(|statement| collect_var_names(statement, names)),
+        ASTNode::IfStatement { body, else_body, .. } => {
+            collect_var_names(body, names);
+            if let Some(else_body) = else_body {
+                collect_var_names(else_body, names);
+            }
+        }
+        ASTNode::WhileStatement { body, .. } | ASTNode::LabeledStatement { body, .. } => collect_var_names(body, names),
+        ASTNode::TryStatement {
+            block,
+            handler,
+            finalizer,
+            ..
+        } => {
+            collect_var_names(block, names);
+            if let Some(handler) = handler {
+                collect_var_names(handler, names);
+            }
+            if let Some(finalizer) = finalizer {
+                collect_var_names(finalizer, names);
+            }
+        }
+        ASTNode::CatchClause { body, .. } => collect_var_names(body, names),
+        ASTNode::ForStatement { init, body, .. } => {
+            if let Some(init) = init {
+                collect_var_names(init, names);
+            }
+            collect_var_names(body, names);
+        }
+        _ => {}
+    }
+}
+
+/// Whether `left` alone decides the result of a logical operator.
+fn short_circuits(operator: LogicalOp, left: &JsValue) -> bool {
+    match operator {
+        LogicalOp::And => !left.to_boolean(),
+        LogicalOp::Or => left.to_boolean(),
+        LogicalOp::NullishCoalescing => !left.is_nullish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::cache;
+    use std::cell::RefCell;
+
+    fn eval(code: &str) -> JsValue {
+        Interpreter::new().eval(code).unwrap()
+    }
+
+    fn eval_to_string(code: &str) -> String {
+        eval(code).to_string()
+    }
+
+    fn eval_error(code: &str) -> String {
+        Interpreter::new().eval(code).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_completion_value() {
+        assert_eq!(eval_to_string("1 + 2;"), "3");
+        assert_eq!(eval_to_string("let x = 1; x;"), "1");
+        assert_eq!(eval_to_string("'a'; var y = 2;"), "a");
+        assert_eq!(eval_to_string("if (false) 1;"), "undefined");
+        assert_eq!(eval_to_string(""), "undefined");
+    }
+
+    #[test]
+    fn test_variables_and_hoisting() {
+        assert_eq!(eval_to_string("var a = x; var x = 5; a;"), "undefined");
+        assert_eq!(eval_to_string("var x = 1; var x; x;"), "1");
+        assert_eq!(eval_to_string("let x = 1; { let x = 2; } x;"), "1");
+        assert_eq!(eval_to_string("f(); function f() { return 'hoisted'; }"), "hoisted");
+        assert_eq!(eval_to_string("x = 3; x;"), "3");
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let code = "
+            var total = 0;
+            for (let i = 0; i < 10; i++) {
+                if (i === 3) continue;
+                if (i === 6) break;
+                total += i;
+            }
+            total;
+        ";
+        assert_eq!(eval_to_string(code), "12");
+        assert_eq!(eval_to_string("var n = 0; while (n < 5) n++; n;"), "5");
+        assert_eq!(eval_to_string("var n = 0; while (true) { n++; if (n > 2) break; } n;"), "3");
+    }
+
+    #[test]
+    fn test_functions_and_closures() {
+        let code = "
+            function counter() {
+                let count = 0;
+                function next() { count += 1; return count; }
+                return next;
+            }
+            let c = counter();
+            c(); c();
+            c();
+        ";
+        assert_eq!(eval_to_string(code), "3");
+        assert_eq!(eval_to_string("function fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } fib(15);"), "610");
+        assert_eq!(eval_to_string("function f(a, b) { return b; } f(1);"), "undefined");
+        assert_eq!(eval_to_string("function f() { return 1; } typeof f;"), "function");
+    }
+
+    #[test]
+    fn test_objects() {
+        let code = "
+            let key = 'b';
+            let o = { a: 1, [key]: 2, 'c': 3 };
+            o.a += o[key];
+            o.d = o.a * o.c;
+            delete o.c;
+            o.d + ' ' + ('c' in o) + ' ' + ('d' in o);
+        ";
+        assert_eq!(eval_to_string(code), "9 false true");
+        assert_eq!(eval_to_string("let o = { f: 1 }; o.f++ + o.f;"), "3");
+        assert_eq!(eval_to_string("'h\u{e9}llo'.length + 'abc'[1];"), "5b");
+    }
+
+    #[test]
+    fn test_strings_are_code_units() {
+        // Indexing splits a surrogate pair into lone surrogates, which keep
+        // their code units and join up again.
+        let code = "let s = '\u{1F600}'; let lead = s[0]; lead.length + ' ' + (lead + s[1] === s) + ' ' + (lead === s[1]);";
+        assert_eq!(eval_to_string(code), "1 true false");
+        let code = "let text = ''; for (let i = 0; i < 20000; i++) { text += 'ab'; } text.length + text[39999];";
+        assert_eq!(eval_to_string(code), "40000b");
+    }
+
+    #[test]
+    fn test_lone_surrogates_in_literals() {
+        let code = r#"let o = { "\uD800": 'high' }; o["\uDC00"] = 'low';
+            ("\uD83D" === "\uFFFD") + ' ' + ("😀"[0] === "\uD83D") + ' ' + ("\uD83D" + "\uDE00" === "😀")
+                + ' ' + o["\uD800"] + ' ' + o['\uDC00'];"#;
+        let expected = "false true true high low";
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            assert_eq!(interpreter.eval(code).unwrap().to_string(), expected);
+        }
+
+        // A cache entry keeps them as well.
+        let template = Compiler::compile(&ASTNode::parse_program(&Lexer::new(code).tokenize())).unwrap();
+        let loaded = cache::deserialize(&cache::serialize(&template, code), code).unwrap();
+        assert_eq!(Interpreter::new().run_compiled(loaded).unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(eval_to_string("typeof undeclared;"), "undefined");
+        assert_eq!(eval_to_string("null ?? 'default';"), "default");
+        assert_eq!(eval_to_string("0 || 'a' && 'b';"), "b");
+        assert_eq!(eval_to_string("let x = 0; x ||= 5; x &&= x + 1; x;"), "6");
+        assert_eq!(eval_to_string("let y = 1; y ??= f(); y;"), "1");
+        assert_eq!(eval_to_string("1 / 0 + ' ' + -'x';"), "Infinity NaN");
+    }
+
+    #[test]
+    fn test_bigints_and_conversions() {
+        assert_eq!(eval_to_string("2n ** 64n;"), "18446744073709551616");
+        assert_eq!(eval_to_string("let n = 9n; n++; -n / 4n;"), "-2");
+        assert_eq!(eval_to_string("typeof 1n + ' ' + (1n == 1) + ' ' + (1n === 1);"), "bigint true false");
+        assert_eq!(eval_to_string("function four() { return 4; } let o = { valueOf: four }; o * 2 + ' ' + o;"), "8 4");
+        assert_eq!(eval_to_string("function p() { return 'p'; } let t = {}; t[{ toString: p }] = 1; t.p;"), "1");
+        assert_eq!(eval_error("1n + 1;"), "TypeError: Cannot mix BigInt and other types, use explicit conversions");
+        assert_eq!(eval_error("1n / 0n;"), "RangeError: Division by zero");
+    }
+
+    #[test]
+    fn test_closures_and_per_iteration_bindings() {
+        assert_eq!(
+            eval_to_string(
+                "function counter() { let n = 0; function next() { n += 1; return n; } return next; }
+                 let a = counter(); let b = counter(); a(); a(); a() + ' ' + b();"
+            ),
+            "3 1"
+        );
+        let loop_closures = "let fs = {};
+             for (LET i = 0; i < 3; i++) { function f() { return i; } fs[i] = f; }
+             '' + fs[0]() + fs[1]() + fs[2]();";
+        assert_eq!(eval_to_string(&loop_closures.replace("LET", "let")), "012");
+        assert_eq!(eval_to_string(&loop_closures.replace("LET", "var")), "333");
+        assert_eq!(
+            eval_to_string("let log = ''; for (let i = 0; i < 3; i++) { i++; log += i; } log;"),
+            "13"
+        );
+        assert_eq!(eval_to_string("let x = 'outer'; { let x = 'inner'; } x;"), "outer");
+    }
+
+    #[test]
+    fn test_temporal_dead_zone() {
+        let error = "ReferenceError: Cannot access 'x' before initialization";
+        assert_eq!(eval_error("x; let x = 1;"), error);
+        assert_eq!(eval_error("let x = x;"), error);
+        assert_eq!(eval_error("typeof x; const x = 1;"), error);
+        assert_eq!(eval_error("function f() { return x; } f(); let x;"), error);
+        assert_eq!(eval_error("{ x = 2; let x; }"), error);
+        assert_eq!(eval_error("for (let x = x; ;) {}"), error);
+        assert_eq!(eval_to_string("function f() { return x; } let x = 5; f();"), "5");
+        assert_eq!(eval_to_string("let y = 1; { typeof y; }"), "number");
+    }
+
+    #[test]
+    fn test_global_environment() {
+        assert_eq!(eval_to_string("var v = 1; function f() {} globalThis.v + typeof globalThis.f;"), "1function");
+        assert_eq!(eval_to_string("let l = 1; typeof globalThis.l;"), "undefined");
+        assert_eq!(eval_to_string("implicit = 1; (delete implicit) + ' ' + typeof implicit;"), "true undefined");
+        assert_eq!(eval_to_string("var kept = 1; delete kept;"), "false");
+        assert_eq!(eval_to_string("globalThis.viaObject = 2; viaObject;"), "2");
+        assert_eq!(eval_error("let NaN;"), "SyntaxError: Identifier 'NaN' has already been declared");
+        assert_eq!(eval_error("'use strict'; NaN = 1;"), "TypeError: Cannot assign to read only property 'NaN' of object");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("let shared = 1; var old = 2;").unwrap();
+        let error = interpreter.eval("var shared;").unwrap_err();
+        assert_eq!(error.to_string(), "SyntaxError: Identifier 'shared' has already been declared");
+        let error = interpreter.eval("const old = 3;").unwrap_err();
+        assert_eq!(error.to_string(), "SyntaxError: Identifier 'old' has already been declared");
+        assert!(interpreter.eval("shared + old;").unwrap().strict_equals(&3.0.into()));
+    }
+
+    #[test]
+    fn test_prototypes_and_descriptors() {
+        assert_eq!(eval_to_string("let p = { a: 1 }; let o = Object.create(p); o.b = 2; o.a + o.b;"), "3");
+        assert_eq!(eval_to_string("let o = {}; o.hasOwnProperty('x') + ' ' + ('toString' in o);"), "false true");
+        assert_eq!(eval_to_string("Object.getPrototypeOf({}) === Object.prototype;"), "true");
+        assert_eq!(eval_to_string("function F() {} Object.create(F.prototype) instanceof F;"), "true");
+        assert_eq!(eval_to_string("(255).toString(16) + ' ' + (-255n).toString(2) + ' ' + true.toString();"), "ff -11111111 true");
+        assert_eq!(eval_to_string("'abc'.length + 'abc'[1] + Object('abc').hasOwnProperty('2');"), "3btrue");
+        assert_eq!(eval_to_string("Object.prototype.toString.call(null) + Object.prototype.toString.call(1);"), "[object Null][object Number]");
+        assert_eq!(
+            eval_to_string(
+                "let o = {}; Object.defineProperty(o, 'x', { value: 1 }); o.x = 2;
+                 let d = Object.getOwnPropertyDescriptor(o, 'x');
+                 o.x + ' ' + d.writable + ' ' + d.enumerable + ' ' + delete o.x;"
+            ),
+            "1 false false false"
+        );
+        assert_eq!(
+            eval_to_string(
+                "let count = 0; function get() { count++; return 'got'; }
+                 let o = Object.defineProperty({}, 'g', { get: get }); o.g + o.g + count;"
+            ),
+            "gotgot2"
+        );
+        assert_eq!(eval_error("'use strict'; let o = Object.preventExtensions({}); o.x = 1;"), "TypeError: Cannot add property x, object is not extensible");
+        assert_eq!(
+            eval_error("'use strict'; let o = Object.defineProperty({}, 'x', { value: 1 }); o.x = 2;"),
+            "TypeError: Cannot assign to read only property 'x' of object"
+        );
+        assert_eq!(eval_error("'use strict'; delete 'abc'.length;"), "TypeError: Cannot delete property 'length' of \"abc\"");
+        assert_eq!(eval_error("Object.defineProperty(Object.prototype, 'constructor', { get: 1 });"), "TypeError: getter must be a function: 1");
+        assert_eq!(eval_error("let p = {}; Object.setPrototypeOf(p, Object.create(p));"), "TypeError: Cyclic __proto__ value");
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        assert_eq!(eval_error("missing;"), "ReferenceError: missing is not defined");
+        assert_eq!(eval_error("let o = {}; o.f();"), "TypeError: o.f is not a function");
+        assert_eq!(eval_error("const c = 1; c = 2;"), "TypeError: Assignment to constant variable.");
+        assert_eq!(eval_error("null.x;"), "TypeError: Cannot read properties of null (reading 'x')");
+        assert_eq!(eval_error("function f() { return f(); } f();"), "RangeError: Maximum call stack size exceeded");
+        assert_eq!(eval_error("let a; let a;"), "SyntaxError: Identifier 'a' has already been declared");
+    }
+
+    #[test]
+    fn test_try_catch_finally() {
+        assert_eq!(eval_to_string("let r; try { throw 1; r = 'no'; } catch (e) { r = e + 1; } r;"), "2");
+        assert_eq!(eval_to_string("try { missing; } catch (e) { e.name + ': ' + e.message; }"), "ReferenceError: missing is not defined");
+        assert_eq!(eval_to_string("try { null.x; } catch (e) { e instanceof TypeError && e instanceof Error; }"), "true");
+        assert_eq!(eval_to_string("function f() { return f(); } try { f(); } catch (e) { e.toString(); }"), "RangeError: Maximum call stack size exceeded");
+        assert_eq!(eval_to_string("try { throw 'x'; } catch { 'caught'; }"), "caught");
+        assert_eq!(eval_to_string("let e = 'outer'; try { throw 'inner'; } catch (e) {} e;"), "outer");
+
+        // `finally` keeps the earlier completion unless it is abrupt itself.
+        assert_eq!(eval_to_string("function f() { try { return 1; } finally { 2; } } f();"), "1");
+        assert_eq!(eval_to_string("function f() { try { return 1; } finally { return 2; } } f();"), "2");
+        assert_eq!(eval_to_string("function f() { try { throw 1; } finally { return 'swallowed'; } } f();"), "swallowed");
+        assert_eq!(eval_to_string("let n = 0; while (true) { try { break; } finally { n++; } } n;"), "1");
+        assert_eq!(eval_to_string("let s = ''; for (let i = 0; i < 3; i++) { try { continue; } finally { s += i; } } s;"), "012");
+        assert_eq!(eval_error("try { throw 1; } catch (e) { throw e + 1; } finally { 3; }"), "2");
+        assert_eq!(eval_error("function f() { try { return 1; } finally { throw Error('late'); } } f();"), "Error: late");
+
+        // Completion values, as `eval` reports them.
+        assert_eq!(eval_to_string("try { 1; } finally { 2; }"), "1");
+        assert_eq!(eval_to_string("try { throw 0; } catch (e) { 3; } finally { 4; }"), "3");
+        assert_eq!(eval_to_string("9; try {} finally {}"), "undefined");
+        assert_eq!(eval_to_string("while (true) { 5; break; }"), "5");
+    }
+
+    #[test]
+    fn test_labels() {
+        let code = "let hits = ''; outer: for (let i = 0; i < 3; i++) { for (let j = 0; j < 3; j++) { \
+                    if (j === 1) continue outer; if (i === 2) break outer; hits += i + '' + j + ' '; } } hits;";
+        assert_eq!(eval_to_string(code), "00 10 ");
+        assert_eq!(eval_to_string("let n = 0; a: b: while (true) { n++; if (n < 3) continue a; break b; } n;"), "3");
+        assert_eq!(eval_to_string("block: { 1; break block; 2; }"), "1");
+        assert_eq!(eval_to_string("let s = ''; l: { try { s += 'a'; break l; } finally { s += 'b'; } s += 'c'; } s;"), "ab");
+        assert_eq!(eval_error("a: { break b; }"), "SyntaxError: Undefined label 'b'");
+        assert_eq!(eval_to_string("l: function f() { return 'labelled'; } f();"), "labelled");
+    }
+
+    #[test]
+    fn test_uncaught_exceptions() {
+        let error = Interpreter::new().eval("throw { code: 42 };").unwrap_err();
+        let JsError::Thrown(JsValue::Object(thrown)) = &error else {
+            panic!("Expected a thrown object, got {:?}", error);
+        };
+        assert!(thrown.find_property(&"code".into()).unwrap().value.unwrap().strict_equals(&42.0.into()));
+
+        // Engine errors reach the caller as error objects too.
+        let error = Interpreter::new().eval("undefined.x;").unwrap_err();
+        let thrown = error.value().and_then(JsValue::as_object).expect("an error object");
+        assert_eq!(inspect(&thrown.clone().into()), "[TypeError: Cannot read properties of undefined (reading 'x')]");
+        assert_eq!(eval_error("throw RangeError();"), "RangeError");
+        assert_eq!(eval_error("throw 'plain';"), "plain");
+        assert_eq!(eval_to_string("Error('m').toString() + '|' + Object.prototype.toString.call(SyntaxError());"), "Error: m|[object Error]");
+    }
+
+    #[test]
+    fn test_strict_mode_forbids_implicit_globals() {
+        assert_eq!(eval_error("'use strict'; x = 1;"), "ReferenceError: x is not defined");
+        assert_eq!(eval_error("function f() { 'use strict'; y = 1; } f();"), "ReferenceError: y is not defined");
+        assert_eq!(eval_error("'use strict'; 'abc'.x = 1;"), "TypeError: Cannot create property 'x' on string 'abc'");
+        assert_eq!(eval_to_string("function f() { z = 1; } f(); z;"), "1");
+    }
+
+    #[test]
+    fn test_this_binding() {
+        let code = "var g = this; this.top = 1;
+            function sloppy() { return this; }
+            function strict() { 'use strict'; return this; }
+            function method() { function inner() { return this; } return this.name + (inner() === g); }
+            let o = { sloppy, strict, name: 'o' };
+            o.method = method;
+            (sloppy() === g) + ' ' + (strict() === undefined) + ' ' + (o.sloppy() === o) + ' '
+                + (o.strict() === o) + ' ' + o.method() + ' ' + top;";
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            assert_eq!(interpreter.eval(code).unwrap().to_string(), "true true true true otrue 1", "{:?}", engine);
+        }
+    }
+
+    #[test]
+    fn test_console_log_and_shared_globals() {
+        let lines = Rc::new(RefCell::new(Vec::new()));
+        let sink = lines.clone();
+        let mut interpreter = Interpreter::with_printer(move |line| sink.borrow_mut().push(line.to_string()));
+
+        interpreter.eval("var greeting = 'hi'; console.log(greeting, 1, { a: 'b' });").unwrap();
+        interpreter.eval("function f() {} console.log(greeting + '!', f, null);").unwrap();
+        assert_eq!(*lines.borrow(), ["hi 1 { a: \"b\" }", "hi! [Function: f] null"]);
+    }
+}
@@ -0,0 +1,648 @@
+use crate::bytecode::opcode::{Instruction, Opcode};
+use crate::bytecode::{Constant, FunctionTemplate, TemplateKind};
+use crate::interpreter::environment::{uninitialized, Environment};
+use crate::interpreter::{Interpreter, Reference};
+use crate::lexical::atom::Atom;
+use crate::parser::operators::UnaryOp;
+use crate::runtime::boxed::{self, Value};
+use crate::runtime::conversions::{to_numeric, to_property_key, Numeric};
+use crate::runtime::error::JsError;
+use crate::runtime::gc::{Gc, Trace, Tracer};
+use crate::runtime::object::FunctionKind;
+use crate::runtime::operations;
+use crate::runtime::property::PropertyKey;
+use crate::runtime::value::JsValue;
+use std::cell::RefCell;
+use std::iter;
+use std::rc::Rc;
+
+/// Bytecode frames one run of the dispatch loop may stack up before a call
+/// throws a RangeError. Frames live on the heap, so this only bounds memory.
+const MAX_FRAMES: usize = 10_000;
+
+/// A function value compiled to bytecode: its template and the scope it was
+/// created in.
+#[derive(Debug)]
+pub struct BytecodeClosure {
+    pub template: Rc<FunctionTemplate>,
+    pub scope: Option<ScopeRef>,
+}
+
+pub type ScopeRef = Gc<RefCell<Scope>>;
+
+/// The captured variables of one scope of a bytecode function. Slots start
+/// in their temporal dead zone, like `let` bindings.
+#[derive(Debug)]
+pub struct Scope {
+    slots: Vec<Value>,
+    names: Rc<[Rc<str>]>,
+    outer: Option<ScopeRef>,
+}
+
+impl Trace for BytecodeClosure {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.scope.trace(tracer);
+    }
+}
+
+impl Trace for RefCell<Scope> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(scope) = self.try_borrow() else {
+            return tracer.incomplete();
+        };
+        scope.slots.trace(tracer);
+        scope.outer.trace(tracer);
+    }
+
+    fn unlink(&self) {
+        if let Ok(mut scope) = self.try_borrow_mut() {
+            scope.slots.clear();
+            scope.outer = None;
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.try_borrow()
+            .map_or(0, |scope| scope.slots.capacity() * std::mem::size_of::<Value>())
+    }
+}
+
+impl Scope {
+    /// The scope `depth` levels out from `scope`.
+    fn ancestor(scope: &ScopeRef, depth: u8) -> ScopeRef {
+        let mut current = scope.clone();
+        for _ in 0..depth {
+            let outer = current.borrow().outer.clone().expect("scope depth within the chain");
+            current = outer;
+        }
+        current
+    }
+}
+
+/// An active call of a bytecode function. Its registers and operand stack
+/// are the parts of the machine's shared vectors from its bases on.
+struct Frame {
+    template: Rc<FunctionTemplate>,
+    pc: usize,
+    registers: usize,
+    stack: usize,
+    scope: Option<ScopeRef>,
+    /// Scopes the frame has pushed on top of the closure's.
+    scope_depth: usize,
+    this: Value,
+}
+
+/// The state of one run of the dispatch loop. Calls between bytecode
+/// functions push frames here instead of recursing in Rust.
+#[derive(Default)]
+struct Machine {
+    frames: Vec<Frame>,
+    /// Registers in their temporal dead zone hold [`Value::HOLE`].
+    registers: Vec<Value>,
+    stack: Vec<Value>,
+}
+
+impl Machine {
+    fn push_frame(&mut self, template: Rc<FunctionTemplate>, scope: Option<ScopeRef>, this: Value, args: Vec<Value>) {
+        let registers = self.registers.len();
+        let parameters = template.parameter_count as usize;
+        self.registers.extend(args.into_iter().chain(iter::repeat_with(Value::default)).take(parameters));
+        self.registers.resize_with(registers + template.registers.len(), || Value::HOLE);
+        self.frames.push(Frame {
+            template,
+            pc: 0,
+            registers,
+            stack: self.stack.len(),
+            scope,
+            scope_depth: 0,
+            this,
+        });
+    }
+
+    fn pop_frame(&mut self) -> Frame {
+        let frame = self.frames.pop().expect("a frame to pop");
+        self.registers.truncate(frame.registers);
+        self.stack.truncate(frame.stack);
+        frame
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("a running frame")
+    }
+
+    fn push(&mut self, value: JsValue) {
+        self.stack.push(value.into());
+    }
+
+    fn pop(&mut self) -> JsValue {
+        self.pop_value().into_js()
+    }
+
+    fn push_value(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop_value(&mut self) -> Value {
+        self.stack.pop().expect("operand stack underflow")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("operand stack underflow")
+    }
+
+    fn scope(&mut self) -> &ScopeRef {
+        self.frame().scope.as_ref().expect("a pushed scope")
+    }
+}
+
+impl Interpreter {
+    /// Runs a compiled script in the global environment, as
+    /// [`Interpreter::run`] does a parsed one.
+    pub fn run_compiled(&mut self, template: Rc<FunctionTemplate>) -> Result<JsValue, JsError> {
+        let result = self
+            .run_template(template)
+            .map_err(|error| JsError::Thrown(self.realm.thrown_value(error)));
+        self.finish_script(result)
+    }
+
+    pub(super) fn run_template(&mut self, template: Rc<FunctionTemplate>) -> Result<JsValue, JsError> {
+        assert_eq!(template.kind, TemplateKind::Script, "expected a script template");
+        let globals = &template.globals;
+        let functions = globals
+            .functions
+            .iter()
+            .map(|(name, index)| (*name, self.bytecode_function(&template.functions[*index as usize], None)))
+            .collect();
+        self.declare_globals(&globals.lexicals, &globals.vars, functions)?;
+        let this = self.global_object().into();
+        self.execute_bytecode(template.clone(), None, this, &[])
+    }
+
+    pub(super) fn call_bytecode(&mut self, closure: &BytecodeClosure, this: JsValue, args: &[JsValue]) -> Result<JsValue, JsError> {
+        let this = self.bind_this(closure.template.strict, this)?;
+        self.execute_bytecode(closure.template.clone(), closure.scope.clone(), this, args)
+    }
+
+    fn bytecode_function(&self, template: &Rc<FunctionTemplate>, scope: Option<ScopeRef>) -> JsValue {
+        let closure = BytecodeClosure {
+            template: template.clone(),
+            scope,
+        };
+        self.function_object(FunctionKind::Bytecode(Gc::new(closure)))
+    }
+
+    /// Runs `template` to completion on a machine of its own. An exception
+    /// no frame handles comes back as the `Err`.
+    fn execute_bytecode(
+        &mut self,
+        template: Rc<FunctionTemplate>,
+        scope: Option<ScopeRef>,
+        this: JsValue,
+        args: &[JsValue],
+    ) -> Result<JsValue, JsError> {
+        let outer_strict = std::mem::replace(&mut self.strict, template.strict);
+        let mut machine = Machine::default();
+        machine.push_frame(template, scope, this.into(), args.iter().cloned().map(Value::from).collect());
+        let result = loop {
+            match self.dispatch(&mut machine) {
+                Ok(value) => break Ok(value),
+                Err(error) => {
+                    if let Err(error) = self.unwind(&mut machine, error) {
+                        break Err(error);
+                    }
+                }
+            }
+        };
+        self.strict = outer_strict;
+        result
+    }
+
+    /// Finds the handler for an exception thrown by the current instruction
+    /// of the innermost frame, popping the frames that have none, and
+    /// resumes there with the thrown value on the stack.
+    fn unwind(&mut self, machine: &mut Machine, error: JsError) -> Result<(), JsError> {
+        while let Some(frame) = machine.frames.last_mut() {
+            // `pc` is already past the throwing instruction, or the call
+            // that an inner frame threw from.
+            let offset = (frame.pc - 1) as u32;
+            let handler = frame
+                .template
+                .handlers
+                .iter()
+                .find(|handler| handler.start <= offset && offset < handler.end)
+                .copied();
+            if let Some(handler) = handler {
+                while frame.scope_depth > handler.scope_depth as usize {
+                    let outer = frame.scope.as_ref().and_then(|scope| scope.borrow().outer.clone());
+                    frame.scope = outer;
+                    frame.scope_depth -= 1;
+                }
+                frame.pc = handler.target as usize;
+                self.strict = frame.template.strict;
+                let stack_depth = frame.stack + handler.stack_depth as usize;
+                machine.stack.truncate(stack_depth);
+                machine.push(self.realm.thrown_value(error));
+                return Ok(());
+            }
+            machine.pop_frame();
+        }
+        Err(error)
+    }
+
+    /// The dispatch loop: runs instructions until the outermost frame
+    /// returns, or until one throws.
+    fn dispatch(&mut self, machine: &mut Machine) -> Result<JsValue, JsError> {
+        loop {
+            let frame = machine.frame();
+            let template = frame.template.clone();
+            let instruction = Instruction::decode(&template.code, frame.pc).expect("a valid instruction");
+            frame.pc = instruction.next_offset();
+            let [first, second] = instruction.operands;
+
+            match instruction.opcode {
+                Opcode::Undefined => machine.push_value(Value::UNDEFINED),
+                Opcode::Null => machine.push_value(Value::NULL),
+                Opcode::True => machine.push_value(Value::TRUE),
+                Opcode::False => machine.push_value(Value::FALSE),
+                Opcode::This => {
+                    let this = machine.frame().this.clone();
+                    machine.push_value(this);
+                }
+                Opcode::Constant => machine.push(constant_value(&template.constants[first as usize])),
+                Opcode::Pop => {
+                    machine.pop_value();
+                }
+                Opcode::Dup => machine.push_value(machine.peek().clone()),
+                Opcode::Dup2 => {
+                    let length = machine.stack.len();
+                    machine.stack.extend_from_within(length - 2..);
+                }
+                Opcode::Swap => {
+                    let length = machine.stack.len();
+                    machine.stack.swap(length - 1, length - 2);
+                }
+
+                Opcode::GetLocal => {
+                    let register = machine.frame().registers + first as usize;
+                    let value = machine.registers[register].clone();
+                    if value.is_hole() {
+                        return Err(uninitialized(&template.registers[first as usize]));
+                    }
+                    machine.push_value(value);
+                }
+                Opcode::SetLocal => {
+                    let register = machine.frame().registers + first as usize;
+                    if machine.registers[register].is_hole() {
+                        return Err(uninitialized(&template.registers[first as usize]));
+                    }
+                    machine.registers[register] = machine.peek().clone();
+                }
+                Opcode::InitLocal => {
+                    let register = machine.frame().registers + first as usize;
+                    machine.registers[register] = machine.pop_value();
+                }
+                Opcode::ClearLocal => {
+                    let register = machine.frame().registers + first as usize;
+                    machine.registers[register] = Value::HOLE;
+                }
+
+                Opcode::PushScope => {
+                    let names = template.scopes[first as usize].clone();
+                    let frame = machine.frame();
+                    let scope = Scope {
+                        slots: iter::repeat_with(|| Value::HOLE).take(names.len()).collect(),
+                        names,
+                        outer: frame.scope.take(),
+                    };
+                    frame.scope = Some(Gc::new(RefCell::new(scope)));
+                    frame.scope_depth += 1;
+                }
+                Opcode::PopScope => {
+                    let frame = machine.frame();
+                    let outer = frame.scope.as_ref().and_then(|scope| scope.borrow().outer.clone());
+                    frame.scope = outer;
+                    frame.scope_depth -= 1;
+                }
+                Opcode::CopyScope => {
+                    let copy = {
+                        let scope = machine.scope().borrow();
+                        Scope {
+                            slots: scope.slots.clone(),
+                            names: scope.names.clone(),
+                            outer: scope.outer.clone(),
+                        }
+                    };
+                    machine.frame().scope = Some(Gc::new(RefCell::new(copy)));
+                }
+                Opcode::GetScoped => {
+                    let scope = Scope::ancestor(machine.scope(), first as u8);
+                    let scope = scope.borrow();
+                    let value = &scope.slots[second as usize];
+                    if value.is_hole() {
+                        return Err(uninitialized(&scope.names[second as usize]));
+                    }
+                    machine.push_value(value.clone());
+                }
+                Opcode::SetScoped => {
+                    let scope = Scope::ancestor(machine.scope(), first as u8);
+                    let mut scope = scope.borrow_mut();
+                    if scope.slots[second as usize].is_hole() {
+                        return Err(uninitialized(&scope.names[second as usize]));
+                    }
+                    scope.slots[second as usize] = machine.peek().clone();
+                }
+                Opcode::InitScoped => {
+                    let value = machine.pop_value();
+                    let scope = Scope::ancestor(machine.scope(), first as u8);
+                    scope.borrow_mut().slots[second as usize] = value;
+                }
+
+                Opcode::GetGlobal => {
+                    let reference = self.global_reference(constant_name(&template, first));
+                    let value = self.get_value(&reference)?;
+                    machine.push(value);
+                }
+                Opcode::SetGlobal => {
+                    let reference = self.global_reference(constant_name(&template, first));
+                    self.put_value(&reference, machine.peek().to_js())?;
+                }
+                Opcode::InitGlobal => {
+                    let value = machine.pop();
+                    self.global.borrow_mut().initialize_binding(constant_name(&template, first), value);
+                }
+                Opcode::TypeOfGlobal => {
+                    let type_of = match self.global_reference(constant_name(&template, first)) {
+                        Reference::Binding(_, None) => "undefined",
+                        reference => self.get_value(&reference)?.type_of(),
+                    };
+                    machine.push(type_of.into());
+                }
+                Opcode::DeleteGlobal => {
+                    let name = constant_name(&template, first);
+                    let deleted = match Environment::resolve(&self.global, name) {
+                        Some(binding) => Environment::delete_binding(&binding, name),
+                        None => true,
+                    };
+                    machine.push(deleted.into());
+                }
+                Opcode::ThrowConstAssignment => return Err(JsError::type_error("Assignment to constant variable.")),
+
+                Opcode::GetProperty => {
+                    let key = machine.pop();
+                    let base = machine.pop();
+                    let key = to_property_key(self, &key)?;
+                    let value = self.get_value(&Reference::Property(base, key))?;
+                    machine.push(value);
+                }
+                Opcode::GetNamed => {
+                    let base = machine.pop();
+                    let key = PropertyKey::from(constant_name(&template, first));
+                    let value = match template.caches.get(&template.code, instruction.offset, &base, &key) {
+                        Some(value) => value,
+                        None => self.get_value(&Reference::Property(base, key))?,
+                    };
+                    machine.push(value);
+                }
+                Opcode::SetProperty => {
+                    let value = machine.pop();
+                    let key = machine.pop();
+                    let base = machine.pop();
+                    let key = to_property_key(self, &key)?;
+                    self.put_value(&Reference::Property(base, key), value.clone())?;
+                    machine.push(value);
+                }
+                Opcode::SetNamed => {
+                    let value = machine.pop();
+                    let base = machine.pop();
+                    let key = PropertyKey::from(constant_name(&template, first));
+                    if !template.caches.set(&template.code, instruction.offset, &base, &key, &value) {
+                        self.put_value(&Reference::Property(base, key), value.clone())?;
+                    }
+                    machine.push(value);
+                }
+                Opcode::DeleteProperty => {
+                    let key = machine.pop();
+                    let base = machine.pop();
+                    let key = to_property_key(self, &key)?;
+                    let deleted = self.delete_property(&base, &key)?;
+                    machine.push(deleted);
+                }
+                Opcode::ToPropertyKey => {
+                    let key = machine.pop();
+                    let key = to_property_key(self, &key)?;
+                    machine.push(key.to_value());
+                }
+
+                Opcode::NewObject => machine.push(self.realm.ordinary_object().into()),
+                Opcode::DefineProperty => {
+                    let value = machine.pop();
+                    let key = machine.pop();
+                    let key = to_property_key(self, &key)?;
+                    define_field(&machine.peek().to_js(), key, value);
+                }
+                Opcode::DefineNamed => {
+                    let value = machine.pop();
+                    define_field(&machine.peek().to_js(), constant_name(&template, first).into(), value);
+                }
+
+                Opcode::Negate | Opcode::ToNumber | Opcode::Not | Opcode::BitNot | Opcode::TypeOf => {
+                    let operator = match instruction.opcode {
+                        Opcode::Negate => UnaryOp::Minus,
+                        Opcode::ToNumber => UnaryOp::Plus,
+                        Opcode::Not => UnaryOp::Not,
+                        Opcode::BitNot => UnaryOp::BitNot,
+                        _ => UnaryOp::TypeOf,
+                    };
+                    let argument = machine.pop();
+                    let result = operations::unary(self, operator, &argument)?;
+                    machine.push(result);
+                }
+                Opcode::ToNumeric => {
+                    let value = machine.pop();
+                    let numeric = match to_numeric(self, &value)? {
+                        Numeric::Number(number) => JsValue::Number(number),
+                        Numeric::BigInt(value) => JsValue::BigInt(value),
+                    };
+                    machine.push(numeric);
+                }
+                Opcode::Increment | Opcode::Decrement => {
+                    let delta = if instruction.opcode == Opcode::Increment { 1 } else { -1 };
+                    let value = machine.pop_value();
+                    match boxed::increment(&value, delta) {
+                        Some(new) => machine.push_value(new),
+                        None => {
+                            let (_, new) = operations::increment(self, &value.into_js(), delta as i64)?;
+                            machine.push(new);
+                        }
+                    }
+                }
+
+                Opcode::Jump => machine.frame().pc = instruction.jump_target().unwrap(),
+                Opcode::JumpIfFalse | Opcode::JumpIfTrue => {
+                    let condition = machine.pop_value().to_boolean();
+                    if condition == (instruction.opcode == Opcode::JumpIfTrue) {
+                        machine.frame().pc = instruction.jump_target().unwrap();
+                    }
+                }
+                Opcode::JumpIfFalseOrPop | Opcode::JumpIfTrueOrPop | Opcode::JumpIfNotNullishOrPop => {
+                    let value = machine.peek();
+                    let jump = match instruction.opcode {
+                        Opcode::JumpIfFalseOrPop => !value.to_boolean(),
+                        Opcode::JumpIfTrueOrPop => value.to_boolean(),
+                        _ => !value.is_nullish(),
+                    };
+                    if jump {
+                        machine.frame().pc = instruction.jump_target().unwrap();
+                    } else {
+                        machine.pop_value();
+                    }
+                }
+
+                Opcode::Call => {
+                    let start = machine.stack.len() - first as usize;
+                    let args = machine.stack.split_off(start);
+                    let this = machine.pop();
+                    let function = machine.pop();
+                    let kind = function
+                        .as_object()
+                        .and_then(|callee| template.caches.function_kind(&template.code, instruction.offset, callee));
+                    match kind {
+                        Some(FunctionKind::Bytecode(closure)) => {
+                            if machine.frames.len() >= MAX_FRAMES {
+                                return Err(JsError::range_error("Maximum call stack size exceeded"));
+                            }
+                            let this = self.bind_this(closure.template.strict, this)?;
+                            self.strict = closure.template.strict;
+                            machine.push_frame(closure.template.clone(), closure.scope.clone(), this.into(), args);
+                        }
+                        Some(_) => {
+                            let args: Vec<_> = args.into_iter().map(Value::into_js).collect();
+                            let result = self.call(&function, this, &args)?;
+                            machine.push(result);
+                        }
+                        None => {
+                            let callee = constant_value(&template.constants[second as usize]);
+                            return Err(JsError::type_error(format!("{} is not a function", callee)));
+                        }
+                    }
+                }
+                Opcode::Closure => {
+                    let scope = machine.frame().scope.clone();
+                    let function = self.bytecode_function(&template.functions[first as usize], scope);
+                    machine.push(function);
+                }
+                Opcode::Return => {
+                    let value = machine.pop_value();
+                    machine.pop_frame();
+                    match machine.frames.last() {
+                        Some(caller) => {
+                            self.strict = caller.template.strict;
+                            machine.push_value(value);
+                        }
+                        None => return Ok(value.into_js()),
+                    }
+                }
+                Opcode::Throw => return Err(JsError::Thrown(machine.pop())),
+
+                opcode => {
+                    let operator = opcode.binary_op().expect("a binary operator");
+                    let right = machine.pop_value();
+                    let left = machine.pop_value();
+                    match boxed::arithmetic(operator, &left, &right) {
+                        Some(result) => machine.push_value(result),
+                        None => {
+                            let result = operations::binary(self, operator, &left.into_js(), &right.into_js())?;
+                            machine.push(result);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn global_reference(&self, name: Atom) -> Reference {
+        let binding = Environment::resolve(&self.global, name);
+        Reference::Binding(name, binding)
+    }
+}
+
+fn constant_value(constant: &Constant) -> JsValue {
+    match constant {
+        Constant::Number(number) => JsValue::Number(*number),
+        Constant::String(text) => JsValue::String(text.clone()),
+        Constant::Name(name) => JsValue::String(name.as_str().into()),
+        Constant::BigInt(value) => JsValue::BigInt(value.clone()),
+    }
+}
+
+fn constant_name(template: &FunctionTemplate, index: i32) -> Atom {
+    match template.constants[index as usize] {
+        Constant::Name(name) => name,
+        ref other => panic!("Expected a name constant, found {:?}", other),
+    }
+}
+
+/// A property of an object literal under construction.
+fn define_field(object: &JsValue, key: PropertyKey, value: JsValue) {
+    object.as_object().expect("an object literal").create_data_property(key, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Engine;
+
+    fn eval(code: &str) -> String {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(Engine::Bytecode);
+        match interpreter.eval(code) {
+            Ok(value) => value.to_string(),
+            Err(error) => format!("Uncaught {}", error),
+        }
+    }
+
+    #[test]
+    fn test_calls_and_closures() {
+        assert_eq!(eval("function fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } fib(15);"), "610");
+        let counter = "function counter() { let count = 0; function next() { count += 1; return count; } return next; }
+            let next = counter(); next(); next();";
+        assert_eq!(eval(counter), "2");
+        let loops = "let fs = {}; for (let i = 0; i < 3; i++) { function f() { return i; } fs[i] = f; }
+            fs[0]() + fs[1]() * 10 + fs[2]() * 100;";
+        assert_eq!(eval(loops), "210");
+        assert_eq!(eval("function f(a, b) { return b; } f(1) === undefined;"), "true");
+    }
+
+    #[test]
+    fn test_exceptions_across_frames() {
+        let code = "function thrower(x) { throw x; }
+            function middle() { try { thrower(1); } finally { log = log + 'finally'; } }
+            var log = '';
+            try { middle(); } catch (e) { log = log + ' caught ' + e; }
+            log;";
+        assert_eq!(eval(code), "finally caught 1");
+        assert_eq!(eval("let x = 0; try { null.x; } catch (e) { x = e.name; } x;"), "TypeError");
+        assert_eq!(eval("let i = 0; while (true) { try { i++; if (i > 2) break; } finally { i += 10; } } i;"), "22");
+        assert_eq!(eval("function f() { try { return 1; } finally { log = 2; } } var log; f() + log;"), "3");
+        assert_eq!(eval("throw 5;"), "Uncaught 5");
+    }
+
+    #[test]
+    fn test_deep_recursion_is_a_range_error() {
+        let code = "function down(n) { return down(n + 1); }
+            let result; try { down(0); } catch (e) { result = e.name + ': ' + e.message; } result;";
+        assert_eq!(eval(code), "RangeError: Maximum call stack size exceeded");
+    }
+
+    #[test]
+    fn test_temporal_dead_zone() {
+        assert_eq!(
+            eval("function f() { g(); let x = 1; function g() { return x; } } f();"),
+            "Uncaught ReferenceError: Cannot access 'x' before initialization"
+        );
+        assert_eq!(
+            eval("function f() { const c = 1; c = 2; } f();"),
+            "Uncaught TypeError: Assignment to constant variable."
+        );
+    }
+}
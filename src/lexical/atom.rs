@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock, RwLock, Weak};
+
+/// An interned string: identifiers from the lexer on, names in the AST and
+/// compiled code, and the property keys that name them. Equal strings intern
+/// to the same atom, so comparing and hashing atoms never looks at their
+/// text.
+///
+/// The table is shared by every thread, since programs can be parsed on one
+/// thread and run on another, and atoms are never freed. Only the compiler
+/// and the engine make atoms; property keys computed at run time are
+/// [`KeyAtom`]s, which share the atoms' ids but are freed once unused.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Atom(u32);
+
+/// A string interned while a program runs, for a property key it computed.
+/// It has an id like an [`Atom`], so keys compare and hash by id, but the
+/// id is freed for reuse once the last handle drops. Its text is UTF-16
+/// code units, which need not be valid, so lone surrogates make keys too.
+///
+/// Interning the same text as an atom later keeps the id for good: the atom
+/// gets the key's id, so properties made with either key find each other.
+#[derive(Clone)]
+pub struct KeyAtom(Arc<KeyEntry>);
+
+struct KeyEntry {
+    id: u32,
+    units: Box<[u16]>,
+}
+
+/// What [`KeyAtom::intern`] finds for some text.
+pub enum Interned {
+    Atom(Atom),
+    Key(KeyAtom),
+}
+
+/// Atoms the engine itself looks for, interned ahead of everything else.
+const WELL_KNOWN: [&str; 6] = ["", "length", "prototype", "constructor", "name", "message"];
+
+impl Atom {
+    pub const EMPTY: Atom = Atom(0);
+    pub const LENGTH: Atom = Atom(1);
+    pub const PROTOTYPE: Atom = Atom(2);
+    pub const CONSTRUCTOR: Atom = Atom(3);
+    pub const NAME: Atom = Atom(4);
+    pub const MESSAGE: Atom = Atom(5);
+
+    /// The atom for `text`, adding it to the table if it is not there yet.
+    /// Text that is a live [`KeyAtom`] keeps the key's id.
+    pub fn new(text: &str) -> Atom {
+        if let Some(atom) = Atom::lookup(text) {
+            return atom;
+        }
+        let mut table = TABLE.write().unwrap();
+        if let Some(&atom) = table.ids.get(text) {
+            return atom;
+        }
+        let text: &'static str = Box::leak(text.into());
+        let units: Box<[u16]> = text.encode_utf16().collect();
+        match table.keys.remove(&units).and_then(|key| key.upgrade()) {
+            Some(key) => {
+                let atom = Atom(key.id);
+                table.names[key.id as usize] = text;
+                table.ids.insert(text, atom);
+                // Held for good, so the id is never freed.
+                table.promoted.push(key);
+                atom
+            }
+            None => table.insert(text),
+        }
+    }
+
+    /// The atom for `text`, if it has been interned.
+    pub fn lookup(text: &str) -> Option<Atom> {
+        TABLE.read().unwrap().ids.get(text).copied()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        TABLE.read().unwrap().names[self.0 as usize]
+    }
+
+    /// The id shared with the [`KeyAtom`] it was promoted from, if any.
+    pub fn id(self) -> u32 {
+        self.0
+    }
+}
+
+impl KeyAtom {
+    /// The atom for `units` if there is one, or else the live key for them,
+    /// made if need be. Never adds an atom.
+    pub fn intern(units: &[u16]) -> Interned {
+        let text = String::from_utf16(units).ok();
+        {
+            let table = TABLE.read().unwrap();
+            if let Some(&atom) = text.as_deref().and_then(|text| table.ids.get(text)) {
+                return Interned::Atom(atom);
+            }
+            if let Some(key) = table.keys.get(units).and_then(Weak::upgrade) {
+                return Interned::Key(KeyAtom(key));
+            }
+        }
+        let mut table = TABLE.write().unwrap();
+        if let Some(&atom) = text.as_deref().and_then(|text| table.ids.get(text)) {
+            return Interned::Atom(atom);
+        }
+        if let Some(key) = table.keys.get(units).and_then(Weak::upgrade) {
+            return Interned::Key(KeyAtom(key));
+        }
+        let id = table.free.pop().unwrap_or_else(|| table.next_id());
+        if id as usize == table.names.len() {
+            table.names.push("");
+        }
+        let key = Arc::new(KeyEntry { id, units: units.into() });
+        table.keys.insert(units.into(), Arc::downgrade(&key));
+        Interned::Key(KeyAtom(key))
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    pub fn code_units(&self) -> &[u16] {
+        &self.0.units
+    }
+}
+
+impl Drop for KeyEntry {
+    /// Frees the id, unless the text has been interned again since this
+    /// entry's last handle dropped.
+    fn drop(&mut self) {
+        let mut table = TABLE.write().unwrap();
+        if table.keys.get(&self.units).is_some_and(|key| key.strong_count() == 0) {
+            table.keys.remove(&self.units);
+        }
+        table.free.push(self.id);
+    }
+}
+
+impl PartialEq for KeyAtom {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+impl Eq for KeyAtom {}
+
+impl Hash for KeyAtom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id.hash(state);
+    }
+}
+
+impl fmt::Debug for KeyAtom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&String::from_utf16_lossy(&self.0.units), f)
+    }
+}
+
+struct Table {
+    ids: HashMap<&'static str, Atom>,
+    /// Indexed by id. Ids of live keys hold an empty placeholder.
+    names: Vec<&'static str>,
+    /// Live keys by text. An entry whose last handle has dropped stays until
+    /// its `Drop` removes it.
+    keys: HashMap<Box<[u16]>, Weak<KeyEntry>>,
+    /// Keys interned as atoms since, kept so their ids stay taken.
+    promoted: Vec<Arc<KeyEntry>>,
+    /// Ids of keys that were freed.
+    free: Vec<u32>,
+}
+
+impl Table {
+    fn insert(&mut self, text: &'static str) -> Atom {
+        let atom = Atom(self.next_id());
+        self.names.push(text);
+        self.ids.insert(text, atom);
+        atom
+    }
+
+    fn next_id(&self) -> u32 {
+        u32::try_from(self.names.len()).expect("atom table exceeds u32::MAX entries")
+    }
+}
+
+static TABLE: LazyLock<RwLock<Table>> = LazyLock::new(|| {
+    let mut table = Table {
+        ids: HashMap::new(),
+        names: Vec::new(),
+        keys: HashMap::new(),
+        promoted: Vec::new(),
+        free: Vec::new(),
+    };
+    for text in WELL_KNOWN {
+        table.insert(text);
+    }
+    RwLock::new(table)
+});
+
+impl Deref for Atom {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Atom {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Atom {
+    fn from(text: &str) -> Self {
+        Atom::new(text)
+    }
+}
+
+impl From<String> for Atom {
+    fn from(text: String) -> Self {
+        Atom::new(&text)
+    }
+}
+
+impl PartialEq<str> for Atom {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Atom {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_interning() {
+        let atom = Atom::new("interned");
+        assert_eq!(Atom::new(&String::from("interned")), atom);
+        assert_ne!(Atom::new("other"), atom);
+        assert_eq!(atom.as_str(), "interned");
+        assert_eq!(format!("{} {:?}", atom, atom), "interned \"interned\"");
+        assert!(Atom::lookup("never interned anywhere").is_none());
+
+        for (index, text) in WELL_KNOWN.iter().enumerate() {
+            assert_eq!(Atom::new(text), Atom(index as u32));
+        }
+        assert_eq!(Atom::new("length"), Atom::LENGTH);
+        assert_eq!(Atom::MESSAGE, "message");
+    }
+
+    #[test]
+    fn test_key_atoms() {
+        let units: Vec<u16> = "key atom text".encode_utf16().collect();
+        let Interned::Key(key) = KeyAtom::intern(&units) else {
+            panic!("Expected a key atom");
+        };
+        let Interned::Key(again) = KeyAtom::intern(&units) else {
+            panic!("Expected a key atom");
+        };
+        assert_eq!(key, again);
+        assert!(Atom::lookup("key atom text").is_none());
+
+        // Dropping the last handle removes the key.
+        drop((key, again));
+        assert!(!TABLE.read().unwrap().keys.contains_key(&*units));
+
+        // An atom made while a key is live takes over its id.
+        let Interned::Key(key) = KeyAtom::intern(&[0xD800]) else {
+            panic!("Expected a key atom");
+        };
+        assert!(!TABLE.read().unwrap().free.contains(&key.id()));
+        let Interned::Key(key) = KeyAtom::intern(&units) else {
+            panic!("Expected a key atom");
+        };
+        let atom = Atom::new("key atom text");
+        assert_eq!(atom.id(), key.id());
+        drop(key);
+        assert!(matches!(KeyAtom::intern(&units), Interned::Atom(found) if found == atom));
+        assert_eq!(atom.as_str(), "key atom text");
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let atoms: Vec<Atom> = (0..4)
+            .map(|_| thread::spawn(|| Atom::new("shared between threads")))
+            .map(|thread| thread.join().unwrap())
+            .collect();
+        assert!(atoms.iter().all(|atom| *atom == atoms[0]));
+        assert_eq!(atoms[0].as_str(), "shared between threads");
+    }
+}
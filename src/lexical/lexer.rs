@@ -1,20 +1,42 @@
+use crate::lexical::atom::Atom;
+use crate::lexical::span::Span;
 use crate::lexical::symbols::{DELIMITERS_TRIE, KEYWORDS_TRIE, OPERATORS_TRIE};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     Keyword(String),
-    Identifier(String),
+    Identifier(Atom),
     Number(String),
-    Literal(String),
+    /// A string literal's value in UTF-16 code units, which keep any lone
+    /// surrogates its escapes spell.
+    Literal(Vec<u16>),
+    RegExp(String, String),
+    /// Text that starts a token but is not one, with the error to report.
+    Invalid(String),
     Operator(String),
     Delimiter(String),
     EOF,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+    /// Set on string literals containing a legacy octal escape (`\01`) or
+    /// `\8`/`\9`, all of which strict mode code rejects.
+    pub legacy_escape: bool,
+}
+
 pub struct Lexer {
     code: Vec<char>,
     position: usize,
+    /// `position` in UTF-16 code units, the unit spans are measured in.
+    offset: usize,
     current_char: Option<char>,
+    regex_allowed: bool,
+    token_start: usize,
+    legacy_escape: bool,
 }
 
 
@@ -23,13 +45,20 @@ impl Lexer {
         let mut lexer = Lexer {
             code: code.chars().collect(),
             position: 0,
+            offset: 0,
             current_char: None,
+            regex_allowed: true,
+            token_start: 0,
+            legacy_escape: false,
         };
         lexer.current_char = lexer.code.get(lexer.position).cloned();
         lexer
     }
 
     fn advance(&mut self) {
+        if let Some(ch) = self.current_char {
+            self.offset += ch.len_utf16();
+        }
         self.position += 1;
         self.current_char = if self.position < self.code.len() {
             Some(self.code[self.position])
@@ -50,17 +79,67 @@ impl Lexer {
     }
 
     pub fn next_token(&mut self) -> Token {
+        self.next_spanned_token().token
+    }
+
+    pub fn next_spanned_token(&mut self) -> SpannedToken {
+        self.legacy_escape = false;
+        let token = self.scan_token();
+        self.regex_allowed = Self::allows_regex_after(&token);
+        let span = if token == Token::EOF {
+            Span::new(self.offset, self.offset)
+        } else {
+            Span::new(self.token_start, self.offset)
+        };
+        SpannedToken {
+            token,
+            span,
+            legacy_escape: self.legacy_escape,
+        }
+    }
+
+    /// Lexes the whole input, including the trailing `Token::EOF`.
+    pub fn tokenize(&mut self) -> Vec<SpannedToken> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next_spanned_token();
+            let done = token.token == Token::EOF;
+            tokens.push(token);
+            if done {
+                return tokens;
+            }
+        }
+    }
+
+    /// Whether a `/` following `token` starts a regular expression literal
+    /// rather than a division operator.
+    fn allows_regex_after(token: &Token) -> bool {
+        match token {
+            Token::Identifier(_) | Token::Number(_) | Token::Literal(_) | Token::RegExp(_, _) | Token::Invalid(_) => false,
+            Token::Delimiter(d) => !matches!(d.as_str(), ")" | "]" | "}"),
+            Token::Keyword(kw) => !matches!(
+                kw.as_str(),
+                "this" | "super" | "true" | "false" | "null" | "undefined"
+            ),
+            Token::Operator(op) => !matches!(op.as_str(), "++" | "--"),
+            Token::EOF => true,
+        }
+    }
+
+    fn scan_token(&mut self) -> Token {
         while let Some(ch) = self.current_char {
             if ch.is_whitespace() {
                 self.skip_whitespace();
                 continue;
             }
 
+            self.token_start = self.offset;
+
             if ch.is_alphabetic() || ch == '_' || ch == '$' {
                 return self.lex_identifier_or_keyword();
             }
 
-            if ch.is_digit(10) {
+            if ch.is_ascii_digit() {
                 return self.lex_number();
             }
 
@@ -68,6 +147,12 @@ impl Lexer {
                 return self.lex_string();
             }
 
+            if ch == '/' && self.regex_allowed {
+                if let Some(token) = self.lex_regexp() {
+                    return token;
+                }
+            }
+
             if let Some(token) = self.lex_operator_or_delimiter() {
                 return token;
             }
@@ -100,16 +185,113 @@ impl Lexer {
     fn lex_number(&mut self) -> Token {
         let mut value = String::new();
 
+        let radix_prefix = self.current_char == Some('0')
+            && matches!(
+                self.code.get(self.position + 1),
+                Some('x' | 'X' | 'o' | 'O' | 'b' | 'B')
+            );
+        if radix_prefix {
+            value.push('0');
+            self.advance();
+            let prefix = self.current_char.unwrap();
+            value.push(prefix);
+            self.advance();
+            let radix = match prefix {
+                'x' | 'X' => 16,
+                'o' | 'O' => 8,
+                _ => 2,
+            };
+            while let Some(ch) = self.current_char {
+                if ch.is_digit(radix) || ch == '_' {
+                    value.push(ch);
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            // Digits of a larger radix, or none at all, as in `0b12` or `0x`.
+            let trailing = self.current_char.is_some_and(|ch| ch.is_ascii_alphanumeric() && ch != 'n');
+            if value.len() == 2 || trailing {
+                while self.current_char.is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '_') {
+                    self.advance();
+                }
+                return Token::Invalid("Invalid or unexpected token".to_string());
+            }
+        } else {
+            while let Some(ch) = self.current_char {
+                if ch.is_ascii_digit() || ch == '.' || ch == '_' {
+                    value.push(ch);
+                    self.advance();
+                } else if (ch == 'e' || ch == 'E') && self.exponent_follows() {
+                    value.push(ch);
+                    self.advance();
+                    if let Some(sign @ ('+' | '-')) = self.current_char {
+                        value.push(sign);
+                        self.advance();
+                    }
+                } else {
+                    break;
+                }
+            }
+        }
+
+        if self.current_char == Some('n') {
+            value.push('n');
+            self.advance();
+        }
+
+        Token::Number(value)
+    }
+
+    fn exponent_follows(&self) -> bool {
+        match self.code.get(self.position + 1) {
+            Some(ch) if ch.is_ascii_digit() => true,
+            Some('+' | '-') => self
+                .code
+                .get(self.position + 2)
+                .is_some_and(|ch| ch.is_ascii_digit()),
+            _ => false,
+        }
+    }
+
+    /// Lexes `/pattern/flags`. Returns `None` without consuming anything when
+    /// no closing slash exists on the same line, so the caller can fall back to
+    /// treating the `/` as an operator.
+    fn lex_regexp(&mut self) -> Option<Token> {
+        let mut end = self.position + 1;
+        let mut in_class = false;
+        loop {
+            match self.code.get(end) {
+                None | Some('\n') => return None,
+                Some('\\') => end += 1,
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('/') if !in_class => break,
+                _ => {}
+            }
+            end += 1;
+        }
+        if end == self.position + 1 {
+            // `//` would be a comment, not an empty pattern.
+            return None;
+        }
+
+        let pattern: String = self.code[self.position + 1..end].iter().collect();
+        while self.position <= end {
+            self.advance();
+        }
+
+        let mut flags = String::new();
         while let Some(ch) = self.current_char {
-            if ch.is_digit(10) || ch == '.' {
-                value.push(ch);
+            if ch.is_alphanumeric() {
+                flags.push(ch);
                 self.advance();
             } else {
                 break;
             }
         }
 
-        Token::Number(value)
+        Some(Token::RegExp(pattern, flags))
     }
 
     fn lex_identifier_or_keyword(&mut self) -> Token {
@@ -127,7 +309,7 @@ impl Lexer {
         if KEYWORDS_TRIE.match_symbol(&value.chars().collect::<Vec<_>>(), 0, true).is_some() {
             Token::Keyword(value)
         } else {
-            Token::Identifier(value)
+            Token::Identifier(Atom::new(&value))
         }
     }
 
@@ -135,7 +317,8 @@ impl Lexer {
         let quote = self.current_char.unwrap();
         self.advance();
 
-        let mut value = String::new();
+        let mut value = Vec::new();
+        let mut error = None;
         while let Some(ch) = self.current_char {
             if ch == quote {
                 self.advance();
@@ -143,23 +326,120 @@ impl Lexer {
             } else if ch == '\\' {
                 self.advance();
                 if let Some(escaped_char) = self.current_char {
-                    value.push(match escaped_char {
-                        'n' => '\n',
-                        't' => '\t',
-                        '\\' => '\\',
-                        '"' => '"',
-                        '\'' => '\'',
-                        _ => escaped_char,
-                    });
                     self.advance();
+                    if let Err(message) = self.lex_escape(escaped_char, &mut value) {
+                        error.get_or_insert(message);
+                    }
                 }
             } else {
-                value.push(ch);
+                value.extend(ch.encode_utf16(&mut [0; 2]).iter());
                 self.advance();
             }
         }
 
-        Token::Literal(value)
+        match error {
+            Some(message) => Token::Invalid(message.to_string()),
+            None => Token::Literal(value),
+        }
+    }
+
+    /// Decodes the escape sequence whose first character after the backslash
+    /// (`escaped_char`) has already been consumed, appending its code units
+    /// to `value`. Line continuations decode to nothing; `\u` escapes of
+    /// lone surrogates decode to those surrogates, and those above U+10FFFF
+    /// are an error.
+    fn lex_escape(&mut self, escaped_char: char, value: &mut Vec<u16>) -> Result<(), &'static str> {
+        let decoded = match escaped_char {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{08}',
+            'f' => '\u{0C}',
+            'v' => '\u{0B}',
+            '0'..='7' => {
+                // `\0` on its own is NUL; otherwise a legacy octal escape of up to three digits.
+                if escaped_char != '0' || self.current_char.is_some_and(|c| c.is_ascii_digit()) {
+                    self.legacy_escape = true;
+                }
+                let mut code = escaped_char.to_digit(8).unwrap();
+                let max_digits = if escaped_char <= '3' { 2 } else { 1 };
+                for _ in 0..max_digits {
+                    match self.current_char.and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            code = code * 8 + digit;
+                            self.advance();
+                        }
+                        None => break,
+                    }
+                }
+                char::from_u32(code).unwrap()
+            }
+            'x' => {
+                let code = self.lex_hex_digits(2);
+                char::from_u32(code).unwrap_or('\u{FFFD}')
+            }
+            'u' => {
+                // `\uD83D\uDE00` spells one character in two escapes, and
+                // concatenating the units rejoins them.
+                let code = self.lex_unicode_escape_value().ok_or("Undefined Unicode code-point")?;
+                match char::from_u32(code) {
+                    Some(decoded) => decoded,
+                    None => {
+                        value.push(code as u16);
+                        return Ok(());
+                    }
+                }
+            }
+            '\r' => {
+                if self.current_char == Some('\n') {
+                    self.advance();
+                }
+                return Ok(());
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => return Ok(()),
+            '8' | '9' => {
+                self.legacy_escape = true;
+                escaped_char
+            }
+            _ => escaped_char,
+        };
+        value.extend(decoded.encode_utf16(&mut [0; 2]).iter());
+        Ok(())
+    }
+
+    /// The code point of a `\u` escape, or `None` for a `\u{...}` one above
+    /// U+10FFFF.
+    fn lex_unicode_escape_value(&mut self) -> Option<u32> {
+        if self.current_char == Some('{') {
+            self.advance();
+            let mut code = Some(0u32);
+            while let Some(digit) = self.current_char.and_then(|c| c.to_digit(16)) {
+                code = code
+                    .and_then(|code| code.checked_mul(16)?.checked_add(digit))
+                    .filter(|&code| code <= 0x10FFFF);
+                self.advance();
+            }
+            if self.current_char == Some('}') {
+                self.advance();
+            }
+            code
+        } else {
+            Some(self.lex_hex_digits(4))
+        }
+    }
+
+    fn lex_hex_digits(&mut self, count: usize) -> u32 {
+        let mut code = 0;
+        for _ in 0..count {
+            match self.current_char.and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    code = code * 16 + digit;
+                    self.advance();
+                }
+                None => break,
+            }
+        }
+        code
     }
 }
 
@@ -196,7 +476,7 @@ mod tests {
         ];
 
         for identifier in identifiers {
-            assert_eq!(lexer.next_token(), Token::Identifier(identifier.to_string()));
+            assert_eq!(lexer.next_token(), Token::Identifier(identifier.into()));
         }
 
         assert_eq!(lexer.next_token(), Token::EOF);
@@ -224,12 +504,53 @@ mod tests {
         let strings = vec!["hello", "world", "multi-line\nstring"];
 
         for string in strings {
-            assert_eq!(lexer.next_token(), Token::Literal(string.to_string()));
+            assert_eq!(lexer.next_token(), Token::Literal(string.encode_utf16().collect()));
         }
 
         assert_eq!(lexer.next_token(), Token::EOF);
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let input = r#""\r\b\f\v\0" '\x41\u0042\u{1F600}\uD83D\uDE00' "\101\7" "a\
+b""#;
+        let mut lexer = Lexer::new(input);
+
+        let strings = vec!["\r\u{08}\u{0C}\u{0B}\0", "AB\u{1F600}\u{1F600}", "A\u{07}", "ab"];
+
+        for string in strings {
+            assert_eq!(lexer.next_token(), Token::Literal(string.encode_utf16().collect()));
+        }
+
+        assert_eq!(lexer.next_token(), Token::EOF);
+    }
+
+    #[test]
+    fn test_lone_surrogate_escapes() {
+        let mut lexer = Lexer::new(r#"'\uD83D' '\uDE00\uD83D' "\u{DC00}x""#);
+        assert_eq!(lexer.next_token(), Token::Literal(vec![0xD83D]));
+        assert_eq!(lexer.next_token(), Token::Literal(vec![0xDE00, 0xD83D]));
+        assert_eq!(lexer.next_token(), Token::Literal(vec![0xDC00, 'x' as u16]));
+    }
+
+    #[test]
+    fn test_code_points_above_the_unicode_range() {
+        let mut lexer = Lexer::new(r#"'\u{10FFFF}' '\u{110000}' "\u{FFFFFFFFFF}" 'after'"#);
+        assert_eq!(lexer.next_token(), Token::Literal(vec![0xDBFF, 0xDFFF]));
+        let invalid = Token::Invalid("Undefined Unicode code-point".to_string());
+        assert_eq!(lexer.next_token(), invalid);
+        assert_eq!(lexer.next_token(), invalid);
+        assert_eq!(lexer.next_token(), Token::Literal("after".encode_utf16().collect()));
+    }
+
+    #[test]
+    fn test_legacy_escapes_are_flagged() {
+        let tokens = Lexer::new(r#"'\0' '\00' '\12' '\8' 'a\n' "\x01""#).tokenize();
+        let flags: Vec<bool> = tokens.iter().map(|t| t.legacy_escape).collect();
+
+        assert_eq!(flags, vec![false, true, true, true, false, false, false]);
+    }
+
     #[test]
     fn test_operators() {
         let input = "= == === + - * / % && || ! < <= > >= +=";
@@ -246,6 +567,105 @@ mod tests {
         assert_eq!(lexer.next_token(), Token::EOF);
     }
 
+    #[test]
+    fn test_extended_operators() {
+        let input = "!== ++ -- & | ^ ~ << >> >>> >>>= **= &&= ||= ?? ??=";
+        let mut lexer = Lexer::new(input);
+
+        let operators = vec![
+            "!==", "++", "--", "&", "|", "^", "~", "<<", ">>", ">>>", ">>>=", "**=", "&&=", "||=",
+            "??", "??=",
+        ];
+
+        for operator in operators {
+            assert_eq!(lexer.next_token(), Token::Operator(operator.to_string()));
+        }
+
+        assert_eq!(lexer.next_token(), Token::EOF);
+    }
+
+    #[test]
+    fn test_number_forms() {
+        let input = "0xFF 0b101 0o17 1e3 2.5E-4 10n 1_000";
+        let mut lexer = Lexer::new(input);
+
+        let numbers = vec!["0xFF", "0b101", "0o17", "1e3", "2.5E-4", "10n", "1_000"];
+
+        for number in numbers {
+            assert_eq!(lexer.next_token(), Token::Number(number.to_string()));
+        }
+
+        assert_eq!(lexer.next_token(), Token::EOF);
+
+        let mut lexer = Lexer::new("0b12 0o8 0x 0b1;");
+        for _ in 0..3 {
+            assert_eq!(lexer.next_token(), Token::Invalid("Invalid or unexpected token".to_string()));
+        }
+        assert_eq!(lexer.next_token(), Token::Number("0b1".to_string()));
+    }
+
+    #[test]
+    fn test_regexp_vs_division() {
+        let input = "x = /ab+c/gi; y = a / b / c;";
+        let mut lexer = Lexer::new(input);
+
+        let expected_tokens = vec![
+            Token::Identifier("x".into()),
+            Token::Operator("=".to_string()),
+            Token::RegExp("ab+c".to_string(), "gi".to_string()),
+            Token::Delimiter(";".to_string()),
+            Token::Identifier("y".into()),
+            Token::Operator("=".to_string()),
+            Token::Identifier("a".into()),
+            Token::Operator("/".to_string()),
+            Token::Identifier("b".into()),
+            Token::Operator("/".to_string()),
+            Token::Identifier("c".into()),
+            Token::Delimiter(";".to_string()),
+            Token::EOF,
+        ];
+
+        for expected in expected_tokens {
+            assert_eq!(lexer.next_token(), expected);
+        }
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let tokens = Lexer::new("let  x = 'ab';").tokenize();
+
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 3),
+                Span::new(5, 6),
+                Span::new(7, 8),
+                Span::new(9, 13),
+                Span::new(13, 14),
+                Span::new(14, 14),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_spans_count_utf16_units() {
+        // U+1F600 is a surrogate pair: two units, one Rust char.
+        let tokens = Lexer::new("'😀' + x;").tokenize();
+
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 4),
+                Span::new(5, 6),
+                Span::new(7, 8),
+                Span::new(8, 9),
+                Span::new(9, 9),
+            ]
+        );
+    }
+
     #[test]
     fn test_delimiters() {
         let input = "( ) { } [ ] , ; .";
@@ -269,18 +689,18 @@ mod tests {
 
         let expected_tokens = vec![
             Token::Keyword("let".to_string()),
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::Operator("=".to_string()),
             Token::Number("42".to_string()),
             Token::Delimiter(";".to_string()),
             Token::Keyword("if".to_string()),
             Token::Delimiter("(".to_string()),
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::Operator(">".to_string()),
             Token::Number("10".to_string()),
             Token::Delimiter(")".to_string()),
             Token::Delimiter("{".to_string()),
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::Operator("+=".to_string()),
             Token::Number("5".to_string()),
             Token::Delimiter(";".to_string()),
@@ -301,17 +721,17 @@ mod tests {
 
         let expected_tokens = vec![
             Token::Keyword("let".to_string()),
-            Token::Identifier("_".to_string()),
+            Token::Identifier("_".into()),
             Token::Operator("=".to_string()),
             Token::Number("0".to_string()),
             Token::Delimiter(";".to_string()),
             Token::Keyword("let".to_string()),
-            Token::Identifier("$".to_string()),
+            Token::Identifier("$".into()),
             Token::Operator("=".to_string()),
             Token::Number("5".to_string()),
             Token::Delimiter(";".to_string()),
             Token::Keyword("let".to_string()),
-            Token::Identifier("camelCase".to_string()),
+            Token::Identifier("camelCase".into()),
             Token::Operator("=".to_string()),
             Token::Keyword("true".to_string()),
             Token::Delimiter(";".to_string()),
@@ -325,7 +745,7 @@ mod tests {
 
     #[test]
     fn test_unexpected_characters() {
-        let input = r#"@ # ^ ~ ` | ? \"#;
+        let input = r#"@ # ` \"#;
         let mut lexer = Lexer::new(input);
 
         let expected_tokens = vec![
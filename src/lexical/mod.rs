@@ -1,2 +1,4 @@
 pub mod symbols;
+pub mod atom;
 pub mod lexer;
+pub mod span;
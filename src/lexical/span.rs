@@ -0,0 +1,201 @@
+use std::fmt;
+
+/// Half-open range of offsets `[start, end)` into the source text, counted in
+/// UTF-16 code units as ECMAScript and ESTree positions are. A character
+/// outside the Basic Multilingual Plane takes two units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// One-based line and zero-based column, matching ESTree `loc` positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column + 1)
+    }
+}
+
+/// Source text plus a line index for turning span offsets into locations.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: String,
+    units: Vec<u16>,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: &str, text: &str) -> Self {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let mut line_starts = vec![0];
+
+        // Line terminators are all single units, so matching on units is safe
+        // even next to surrogate pairs.
+        let mut i = 0;
+        while i < units.len() {
+            match units[i] {
+                0x0D if units.get(i + 1) == Some(&0x0A) => {
+                    i += 1;
+                    line_starts.push(i + 1);
+                }
+                0x0A | 0x0D | 0x2028 | 0x2029 => line_starts.push(i + 1),
+                _ => {}
+            }
+            i += 1;
+        }
+
+        SourceFile {
+            name: name.to_string(),
+            units,
+            line_starts,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf16_lossy(&self.units)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn location(&self, offset: usize) -> Location {
+        let line_index = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        Location {
+            line: line_index + 1,
+            column: offset - self.line_starts[line_index],
+        }
+    }
+
+    /// Text of a one-based line, without its terminator.
+    pub fn line_text(&self, line: usize) -> String {
+        let start = self.line_starts[line - 1];
+        let len = self.units[start..]
+            .iter()
+            .take_while(|&&unit| !matches!(unit, 0x0A | 0x0D | 0x2028 | 0x2029))
+            .count();
+        String::from_utf16_lossy(&self.units[start..start + len])
+    }
+
+    pub fn slice(&self, span: Span) -> String {
+        let end = span.end.min(self.units.len());
+        let start = span.start.min(end);
+        String::from_utf16_lossy(&self.units[start..end])
+    }
+
+    /// `name:line:column` of the start of `span`.
+    pub fn describe(&self, span: Span) -> String {
+        format!("{}:{}", self.name, self.location(span.start))
+    }
+
+    /// Formats `message` with the offending line and a caret underline. The
+    /// caret is placed by characters, not code units, so that it lines up in
+    /// a terminal.
+    pub fn render(&self, span: Span, message: &str) -> String {
+        let start = self.location(span.start);
+        let line_start = self.line_starts[start.line - 1];
+        let line = self.line_text(start.line);
+        let column = self.slice(Span::new(line_start, span.start)).chars().count();
+        let underline = if self.location(span.end).line == start.line {
+            self.slice(span).chars().count().max(1)
+        } else {
+            line.chars().count().saturating_sub(column).max(1)
+        };
+
+        format!(
+            "{}: {}\n{}\n{}{}",
+            self.describe(span),
+            message,
+            line,
+            " ".repeat(column),
+            "^".repeat(underline)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_merge() {
+        assert_eq!(Span::new(4, 6).to(Span::new(0, 2)), Span::new(0, 6));
+    }
+
+    #[test]
+    fn test_locations() {
+        let source = SourceFile::new("test.js", "let x;\nx = 1;\r\ny");
+
+        assert_eq!(source.line_count(), 3);
+        assert_eq!(source.location(0), Location { line: 1, column: 0 });
+        assert_eq!(source.location(4), Location { line: 1, column: 4 });
+        assert_eq!(source.location(7), Location { line: 2, column: 0 });
+        assert_eq!(source.location(15), Location { line: 3, column: 0 });
+        assert_eq!(source.line_text(2), "x = 1;");
+    }
+
+    #[test]
+    fn test_render() {
+        let source = SourceFile::new("test.js", "let a = 1;\nlet a = 2;");
+        let rendered = source.render(Span::new(15, 16), "Identifier 'a' has already been declared");
+
+        assert_eq!(
+            rendered,
+            "test.js:2:5: Identifier 'a' has already been declared\nlet a = 2;\n    ^"
+        );
+    }
+
+    #[test]
+    fn test_offsets_count_utf16_units() {
+        let source = SourceFile::new("test.js", "'😀'; x;\n😀");
+
+        assert_eq!(source.slice(Span::new(0, 4)), "'😀'");
+        assert_eq!(source.location(6), Location { line: 1, column: 6 });
+        assert_eq!(source.line_text(2), "😀");
+        assert_eq!(
+            source.render(Span::new(6, 7), "x is not defined"),
+            "test.js:1:7: x is not defined\n'😀'; x;\n     ^"
+        );
+    }
+}
@@ -7,7 +7,7 @@ pub struct SymbolTrie {
     is_last: bool,
 }
 
-static DELIMITERS: [&str; 9] = ["(", ")", "{", "}", "[", "]", ",", ";", "."];
+static DELIMITERS: [&str; 10] = ["(", ")", "{", "}", "[", "]", ",", ";", ".", ":"];
 
 impl SymbolTrie {
     fn new(symbols: &[&str]) -> Self {
@@ -43,7 +43,7 @@ impl SymbolTrie {
         ch.is_whitespace() || DELIMITERS.contains(&s)
     }
 
-    pub fn match_symbol<'a>(&self, code: &'a [char], start: usize, needs_boundary: bool) -> Option<(String, usize)> {
+    pub fn match_symbol(&self, code: &[char], start: usize, needs_boundary: bool) -> Option<(String, usize)> {
         let mut node = self;
         let mut matched = String::new();
         let mut last_match = None;
@@ -75,7 +75,9 @@ pub static OPERATORS_TRIE: LazyLock<SymbolTrie> = LazyLock::new(|| {
     SymbolTrie::new(&[
         "+", "-", "*", "**", "/", "%", "==", "!=", "<", "<=", ">",
         ">=", "&&", "||", "!", "=", "+=", "-=", "*=", "/=", "%=",
-        "===", "..."
+        "===", "...", "!==", "++", "--", "&", "|", "^", "~", "<<",
+        ">>", ">>>", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "**=",
+        "&&=", "||=", "??", "??=",
     ])
 });
 
@@ -89,7 +91,7 @@ pub static KEYWORDS_TRIE: LazyLock<SymbolTrie> = LazyLock::new(|| {
         "continue", "return", "function", "true", "false", "null", "undefined",
         "new", "this", "delete", "typeof", "in", "instanceof", "void", "catch",
         "try", "finally", "switch", "case", "default", "throw", "class", "extends",
-        "super", "import", "export", "from", "as", "await", "async", "yield",
+        "super", "import", "export", "from", "as", "await", "async", "yield", "with",
     ])
 });
 
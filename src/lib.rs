@@ -0,0 +1,6 @@
+pub mod bytecode;
+pub mod interpreter;
+pub mod lexical;
+pub mod parser;
+pub mod runtime;
+pub mod semantic;
@@ -1,6 +1,140 @@
-mod lexical;
-mod parser;
+use rujs::bytecode::cache::BytecodeCache;
+use rujs::bytecode::compiler::Compiler;
+use rujs::bytecode::disassembler::Disassembler;
+use rujs::interpreter::inline_cache;
+use rujs::interpreter::{Engine, Interpreter};
+use rujs::lexical::lexer::Lexer;
+use rujs::lexical::span::SourceFile;
+use rujs::parser::ast::ASTNode;
+use rujs::runtime::gc;
+use std::path::Path;
+use std::{env, fs, panic, process, thread};
+
+/// Native stack for the thread running scripts, and the call depth it
+/// comfortably allows.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Command-line switches.
+#[derive(Default)]
+struct Options {
+    engine: Engine,
+    dump_bytecode: bool,
+    use_cache: bool,
+    gc_mode: gc::Mode,
+    gc_stress: bool,
+    gc_stats: bool,
+    ic_stats: bool,
+}
 
 fn main() {
-    println!("Hello, world!");
+    let mut options = Options::default();
+    let mut path = None;
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--bytecode" => options.engine = Engine::Bytecode,
+            "--dump-bytecode" => options.dump_bytecode = true,
+            "--cache" => options.use_cache = true,
+            "--gc-generational" => options.gc_mode = gc::Mode::Generational,
+            "--gc-stress" => options.gc_stress = true,
+            "--gc-stats" => options.gc_stats = true,
+            "--ic-stats" => options.ic_stats = true,
+            _ if path.is_none() && !argument.starts_with("--") => path = Some(argument),
+            _ => usage(),
+        }
+    }
+    let Some(path) = path else { usage() };
+    let source = fs::read_to_string(&path).unwrap_or_else(|error| {
+        eprintln!("rujs: cannot read {}: {}", path, error);
+        process::exit(2);
+    });
+
+    if options.dump_bytecode {
+        // Prints the compiled script instead of running it.
+        let compiled = parse(&source)
+            .map_err(|message| format!("SyntaxError: {}", message))
+            .and_then(|program| Compiler::compile(&program).map_err(|error| error.to_string()));
+        match compiled {
+            Ok(template) => print!("{}", Disassembler::disassemble(&template, Some(&SourceFile::new(&path, &source)))),
+            Err(error) => {
+                eprintln!("Uncaught {}", error);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    // Thrown values and compiled code live on this thread, so only error
+    // text leaves it.
+    let runner = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&path, &source, &options));
+    if let Err(error) = runner.unwrap().join().unwrap() {
+        eprintln!("Uncaught {}", error);
+        process::exit(1);
+    }
+}
+
+/// Runs the script at `path`, then prints heap and inline cache statistics
+/// if asked to.
+fn run(path: &str, source: &str, options: &Options) -> Result<(), String> {
+    gc::set_mode(options.gc_mode);
+    gc::set_stress(options.gc_stress);
+    let result = run_script(path, source, options);
+    if options.gc_stats {
+        eprintln!("{}", gc::stats());
+    }
+    if options.ic_stats {
+        eprintln!("{}", inline_cache::stats());
+    }
+    result
+}
+
+/// With `--cache`, runs on the bytecode VM from the script's cache entry
+/// when that is valid, and otherwise compiles the script and rewrites the
+/// entry.
+fn run_script(path: &str, source: &str, options: &Options) -> Result<(), String> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_max_call_depth(MAX_CALL_DEPTH);
+    interpreter.set_engine(options.engine);
+    let syntax_error = |message| format!("SyntaxError: {}", message);
+
+    if !options.use_cache {
+        let program = parse(source).map_err(syntax_error)?;
+        return interpreter.run(program).map(|_| ()).map_err(|error| error.to_string());
+    }
+    let cache = BytecodeCache::for_script(Path::new(path));
+    let template = match cache.load(source) {
+        Some(template) => template,
+        None => {
+            let program = parse(source).map_err(syntax_error)?;
+            let template = Compiler::compile(&program).map_err(|error| error.to_string())?;
+            if let Err(error) = cache.store(&template, source) {
+                eprintln!("rujs: cannot write {}: {}", cache.path().display(), error);
+            }
+            template
+        }
+    };
+    interpreter.run_compiled(template).map(|_| ()).map_err(|error| error.to_string())
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: rujs [--bytecode | --cache | --dump-bytecode] [--gc-generational] [--gc-stress] [--gc-stats] [--ic-stats] <script.js>");
+    process::exit(2);
+}
+
+/// Parses `source`, turning the lexer's and parser's panics into an error
+/// message.
+fn parse(source: &str) -> Result<ASTNode, String> {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(|| ASTNode::parse_program(&Lexer::new(source).tokenize()));
+    panic::set_hook(default_hook);
+
+    result.map_err(|payload| {
+        payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|message| message.to_string()))
+            .unwrap_or_else(|| "Invalid or unexpected token".to_string())
+    })
 }
@@ -0,0 +1,1009 @@
+use crate::lexical::atom::Atom;
+use crate::lexical::lexer::SpannedToken;
+use crate::lexical::span::Span;
+use crate::parser::ast::{ASTNode, Literal, VariableKind};
+use crate::parser::builder::AstBuilder;
+use crate::parser::operators::{AssignOp, BinaryOp, LogicalOp, UnaryOp, UpdateOp};
+use crate::parser::parse::Parser;
+use std::num::NonZeroU32;
+
+/// Index of a node inside an [`AstArena`]. Stored as `index + 1` so that
+/// `Option<NodeId>` stays four bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(NonZeroU32);
+
+impl NodeId {
+    fn from_index(index: usize) -> Self {
+        let raw = u32::try_from(index + 1).expect("AST arena exceeds u32::MAX nodes");
+        NodeId(NonZeroU32::new(raw).unwrap())
+    }
+
+    pub fn index(self) -> usize {
+        self.0.get() as usize - 1
+    }
+}
+
+/// A run of consecutive entries in the arena's shared child-list buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeList {
+    start: u32,
+    len: u32,
+}
+
+impl NodeList {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Index of a string literal's code units in the arena's string table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StringId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaLiteral {
+    Number(f64),
+    String(StringId),
+    Boolean(bool),
+    Null,
+    BigInt(Atom),
+    RegExp { pattern: Atom, flags: Atom },
+}
+
+/// Arena counterpart of [`ASTNode`]: children are [`NodeId`]s, lists are
+/// [`NodeList`]s and names are [`Atom`]s, so every variant is `Copy`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Program {
+        body: NodeList,
+    },
+    VariableDeclaration {
+        kind: VariableKind,
+        declarations: NodeList,
+    },
+    VariableDeclarator {
+        identifier: NodeId,
+        value: Option<NodeId>,
+    },
+    FunctionDeclaration {
+        name: NodeId,
+        parameters: NodeList,
+        body: NodeId,
+    },
+    IfStatement {
+        condition: NodeId,
+        body: NodeId,
+        else_body: Option<NodeId>,
+    },
+    Block {
+        body: NodeList,
+    },
+    ExpressionStatement {
+        expression: NodeId,
+    },
+    ReturnStatement {
+        argument: Option<NodeId>,
+    },
+    BreakStatement {
+        label: Option<NodeId>,
+    },
+    ContinueStatement {
+        label: Option<NodeId>,
+    },
+    ThrowStatement {
+        argument: NodeId,
+    },
+    TryStatement {
+        block: NodeId,
+        handler: Option<NodeId>,
+        finalizer: Option<NodeId>,
+    },
+    CatchClause {
+        parameter: Option<NodeId>,
+        body: NodeId,
+    },
+    LabeledStatement {
+        label: NodeId,
+        body: NodeId,
+    },
+    WhileStatement {
+        condition: NodeId,
+        body: NodeId,
+    },
+    ForStatement {
+        init: Option<NodeId>,
+        condition: Option<NodeId>,
+        update: Option<NodeId>,
+        body: NodeId,
+    },
+    AssignmentExpression {
+        left: NodeId,
+        operator: AssignOp,
+        right: NodeId,
+    },
+    BinaryExpression {
+        left: NodeId,
+        operator: BinaryOp,
+        right: NodeId,
+    },
+    LogicalExpression {
+        left: NodeId,
+        operator: LogicalOp,
+        right: NodeId,
+    },
+    UnaryExpression {
+        operator: UnaryOp,
+        argument: NodeId,
+    },
+    UpdateExpression {
+        operator: UpdateOp,
+        prefix: bool,
+        argument: NodeId,
+    },
+    CallExpression {
+        callee: NodeId,
+        arguments: NodeList,
+    },
+    MemberExpression {
+        object: NodeId,
+        property: NodeId,
+        computed: bool,
+    },
+    ObjectExpression {
+        properties: NodeList,
+    },
+    Property {
+        key: NodeId,
+        value: NodeId,
+        computed: bool,
+        shorthand: bool,
+    },
+    Literal(ArenaLiteral),
+    Identifier(Atom),
+    ThisExpression,
+}
+
+/// Flat, index-based AST storage. Nodes live in parallel vectors indexed by
+/// [`NodeId`], which makes parent links and [`SideTable`]s cheap, and building
+/// or dropping a whole tree costs a handful of vector allocations.
+#[derive(Debug, Default)]
+pub struct AstArena {
+    kinds: Vec<NodeKind>,
+    spans: Vec<Span>,
+    parents: Vec<Option<NodeId>>,
+    lists: Vec<NodeId>,
+    /// Code units of the string literals, which need not be valid UTF-16
+    /// and so cannot be atoms.
+    strings: Vec<Box<[u16]>>,
+    root: Option<NodeId>,
+}
+
+impl AstArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `tokens` straight into a new arena, without building an
+    /// intermediate `ASTNode` tree.
+    pub fn parse(tokens: &[SpannedToken]) -> Self {
+        let mut arena = AstArena::new();
+        // Roughly one node per token is a good upper bound for most scripts.
+        arena.kinds.reserve(tokens.len());
+        arena.spans.reserve(tokens.len());
+        arena.parents.reserve(tokens.len());
+
+        let mut parser = Parser::new(tokens, arena);
+        let root = parser.parse_program();
+        let mut arena = parser.into_builder();
+        arena.root = Some(root);
+        arena
+    }
+
+    /// Copies an existing tree into a new arena.
+    pub fn from_ast(node: &ASTNode) -> Self {
+        let mut arena = AstArena::new();
+        let root = arena.lower(node);
+        arena.root = Some(root);
+        arena
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root.expect("arena has no root node")
+    }
+
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.kinds.len()).map(NodeId::from_index)
+    }
+
+    pub fn kind(&self, id: NodeId) -> &NodeKind {
+        &self.kinds[id.index()]
+    }
+
+    pub fn span(&self, id: NodeId) -> Span {
+        self.spans[id.index()]
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id.index()]
+    }
+
+    /// `id`'s parent, grandparent and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&current| self.parent(current))
+    }
+
+    pub fn list(&self, list: NodeList) -> &[NodeId] {
+        &self.lists[list.start as usize..(list.start + list.len) as usize]
+    }
+
+    pub fn string(&self, id: StringId) -> &[u16] {
+        &self.strings[id.0 as usize]
+    }
+
+    /// The atom for a string literal used as a name, such as a property
+    /// key, or `None` if it has lone surrogates.
+    pub fn string_name(&self, id: StringId) -> Option<Atom> {
+        String::from_utf16(self.string(id)).ok().map(Atom::from)
+    }
+
+    pub fn identifier_name(&self, id: NodeId) -> Option<&str> {
+        match self.kind(id) {
+            NodeKind::Identifier(atom) => Some(atom.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Direct children in source order.
+    pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children = Vec::new();
+        visit_children(self.kind(id), &self.lists, |child| children.push(child));
+        children
+    }
+
+    fn push(&mut self, kind: NodeKind, span: Span) -> NodeId {
+        let id = NodeId::from_index(self.kinds.len());
+        self.kinds.push(kind);
+        self.spans.push(span);
+        self.parents.push(None);
+        // Children are always built before their parent.
+        let parents = &mut self.parents;
+        visit_children(&self.kinds[id.index()], &self.lists, |child| parents[child.index()] = Some(id));
+        id
+    }
+
+    fn push_list(&mut self, nodes: Vec<NodeId>) -> NodeList {
+        let start = self.lists.len() as u32;
+        let len = nodes.len() as u32;
+        self.lists.extend(nodes);
+        NodeList { start, len }
+    }
+
+    fn lower(&mut self, node: &ASTNode) -> NodeId {
+        let span = node.span();
+        let lower_all = |arena: &mut Self, nodes: &[ASTNode]| -> Vec<NodeId> {
+            nodes.iter().map(|node| arena.lower(node)).collect()
+        };
+
+        match node {
+            ASTNode::Program { body, .. } => {
+                let body = lower_all(self, body);
+                self.program(body, span)
+            }
+            ASTNode::VariableDeclaration { kind, declarations, .. } => {
+                let declarations = lower_all(self, declarations);
+                self.variable_declaration(*kind, declarations, span)
+            }
+            ASTNode::VariableDeclarator { identifier, value, .. } => {
+                let identifier = self.lower(identifier);
+                let value = value.as_ref().map(|value| self.lower(value));
+                self.variable_declarator(identifier, value, span)
+            }
+            ASTNode::FunctionDeclaration { name, parameters, body, .. } => {
+                let name = self.lower(name);
+                let parameters = lower_all(self, parameters);
+                let body = self.lower(body);
+                self.function_declaration(name, parameters, body, span)
+            }
+            ASTNode::IfStatement { condition, body, else_body, .. } => {
+                let condition = self.lower(condition);
+                let body = self.lower(body);
+                let else_body = else_body.as_ref().map(|else_body| self.lower(else_body));
+                self.if_statement(condition, body, else_body, span)
+            }
+            ASTNode::Block { body, .. } => {
+                let body = lower_all(self, body);
+                self.block(body, span)
+            }
+            ASTNode::ExpressionStatement { expression, .. } => {
+                let expression = self.lower(expression);
+                self.expression_statement(expression, span)
+            }
+            ASTNode::ReturnStatement { argument, .. } => {
+                let argument = argument.as_ref().map(|argument| self.lower(argument));
+                self.return_statement(argument, span)
+            }
+            ASTNode::BreakStatement { label, .. } => {
+                let label = label.as_ref().map(|label| self.lower(label));
+                self.break_statement(label, span)
+            }
+            ASTNode::ContinueStatement { label, .. } => {
+                let label = label.as_ref().map(|label| self.lower(label));
+                self.continue_statement(label, span)
+            }
+            ASTNode::ThrowStatement { argument, .. } => {
+                let argument = self.lower(argument);
+                self.throw_statement(argument, span)
+            }
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => {
+                let block = self.lower(block);
+                let handler = handler.as_ref().map(|handler| self.lower(handler));
+                let finalizer = finalizer.as_ref().map(|finalizer| self.lower(finalizer));
+                self.try_statement(block, handler, finalizer, span)
+            }
+            ASTNode::CatchClause { parameter, body, .. } => {
+                let parameter = parameter.as_ref().map(|parameter| self.lower(parameter));
+                let body = self.lower(body);
+                self.catch_clause(parameter, body, span)
+            }
+            ASTNode::LabeledStatement { label, body, .. } => {
+                let (label, body) = (self.lower(label), self.lower(body));
+                self.labeled_statement(label, body, span)
+            }
+            ASTNode::WhileStatement { condition, body, .. } => {
+                let (condition, body) = (self.lower(condition), self.lower(body));
+                self.while_statement(condition, body, span)
+            }
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => {
+                let init = init.as_ref().map(|init| self.lower(init));
+                let condition = condition.as_ref().map(|condition| self.lower(condition));
+                let update = update.as_ref().map(|update| self.lower(update));
+                let body = self.lower(body);
+                self.for_statement(init, condition, update, body, span)
+            }
+            ASTNode::AssignmentExpression { left, operator, right, .. } => {
+                let (left, right) = (self.lower(left), self.lower(right));
+                self.assignment_expression(left, *operator, right, span)
+            }
+            ASTNode::BinaryExpression { left, operator, right, .. } => {
+                let (left, right) = (self.lower(left), self.lower(right));
+                self.binary_expression(left, *operator, right, span)
+            }
+            ASTNode::LogicalExpression { left, operator, right, .. } => {
+                let (left, right) = (self.lower(left), self.lower(right));
+                self.logical_expression(left, *operator, right, span)
+            }
+            ASTNode::UnaryExpression { operator, argument, .. } => {
+                let argument = self.lower(argument);
+                self.unary_expression(*operator, argument, span)
+            }
+            ASTNode::UpdateExpression { operator, prefix, argument, .. } => {
+                let argument = self.lower(argument);
+                self.update_expression(*operator, *prefix, argument, span)
+            }
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                let callee = self.lower(callee);
+                let arguments = lower_all(self, arguments);
+                self.call_expression(callee, arguments, span)
+            }
+            ASTNode::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                let (object, property) = (self.lower(object), self.lower(property));
+                self.member_expression(object, property, *computed, span)
+            }
+            ASTNode::ObjectExpression { properties, .. } => {
+                let properties = lower_all(self, properties);
+                self.object_expression(properties, span)
+            }
+            ASTNode::Property {
+                key,
+                value,
+                computed,
+                shorthand,
+                ..
+            } => {
+                let (key, value) = (self.lower(key), self.lower(value));
+                self.property(key, value, *computed, *shorthand, span)
+            }
+            ASTNode::Literal { value, .. } => self.literal(value.clone(), span),
+            ASTNode::Identifier { name, .. } => self.identifier(*name, span),
+            ASTNode::ThisExpression { .. } => self.this_expression(span),
+        }
+    }
+
+    /// Rebuilds the boxed tree rooted at `id`.
+    pub fn to_ast(&self, id: NodeId) -> ASTNode {
+        let span = self.span(id);
+        let all = |list: NodeList| self.list(list).iter().map(|&child| self.to_ast(child)).collect();
+        let boxed = |child: NodeId| Box::new(self.to_ast(child));
+
+        match *self.kind(id) {
+            NodeKind::Program { body } => ASTNode::Program { body: all(body), span },
+            NodeKind::VariableDeclaration { kind, declarations } => ASTNode::VariableDeclaration {
+                kind,
+                declarations: all(declarations),
+                span,
+            },
+            NodeKind::VariableDeclarator { identifier, value } => ASTNode::VariableDeclarator {
+                identifier: boxed(identifier),
+                value: value.map(boxed),
+                span,
+            },
+            NodeKind::FunctionDeclaration { name, parameters, body } => ASTNode::FunctionDeclaration {
+                name: boxed(name),
+                parameters: all(parameters),
+                body: boxed(body),
+                span,
+            },
+            NodeKind::IfStatement {
+                condition,
+                body,
+                else_body,
+            } => ASTNode::IfStatement {
+                condition: boxed(condition),
+                body: boxed(body),
+                else_body: else_body.map(boxed),
+                span,
+            },
+            NodeKind::Block { body } => ASTNode::Block { body: all(body), span },
+            NodeKind::ExpressionStatement { expression } => ASTNode::ExpressionStatement {
+                expression: boxed(expression),
+                span,
+            },
+            NodeKind::ReturnStatement { argument } => ASTNode::ReturnStatement {
+                argument: argument.map(boxed),
+                span,
+            },
+            NodeKind::BreakStatement { label } => ASTNode::BreakStatement {
+                label: label.map(boxed),
+                span,
+            },
+            NodeKind::ContinueStatement { label } => ASTNode::ContinueStatement {
+                label: label.map(boxed),
+                span,
+            },
+            NodeKind::ThrowStatement { argument } => ASTNode::ThrowStatement {
+                argument: boxed(argument),
+                span,
+            },
+            NodeKind::TryStatement {
+                block,
+                handler,
+                finalizer,
+            } => ASTNode::TryStatement {
+                block: boxed(block),
+                handler: handler.map(boxed),
+                finalizer: finalizer.map(boxed),
+                span,
+            },
+            NodeKind::CatchClause { parameter, body } => ASTNode::CatchClause {
+                parameter: parameter.map(boxed),
+                body: boxed(body),
+                span,
+            },
+            NodeKind::LabeledStatement { label, body } => ASTNode::LabeledStatement {
+                label: boxed(label),
+                body: boxed(body),
+                span,
+            },
+            NodeKind::WhileStatement { condition, body } => ASTNode::WhileStatement {
+                condition: boxed(condition),
+                body: boxed(body),
+                span,
+            },
+            NodeKind::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+            } => ASTNode::ForStatement {
+                init: init.map(boxed),
+                condition: condition.map(boxed),
+                update: update.map(boxed),
+                body: boxed(body),
+                span,
+            },
+            NodeKind::AssignmentExpression { left, operator, right } => ASTNode::AssignmentExpression {
+                left: boxed(left),
+                operator,
+                right: boxed(right),
+                span,
+            },
+            NodeKind::BinaryExpression { left, operator, right } => ASTNode::BinaryExpression {
+                left: boxed(left),
+                operator,
+                right: boxed(right),
+                span,
+            },
+            NodeKind::LogicalExpression { left, operator, right } => ASTNode::LogicalExpression {
+                left: boxed(left),
+                operator,
+                right: boxed(right),
+                span,
+            },
+            NodeKind::UnaryExpression { operator, argument } => ASTNode::UnaryExpression {
+                operator,
+                argument: boxed(argument),
+                span,
+            },
+            NodeKind::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            } => ASTNode::UpdateExpression {
+                operator,
+                prefix,
+                argument: boxed(argument),
+                span,
+            },
+            NodeKind::CallExpression { callee, arguments } => ASTNode::CallExpression {
+                callee: boxed(callee),
+                arguments: all(arguments),
+                span,
+            },
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+            } => ASTNode::MemberExpression {
+                object: boxed(object),
+                property: boxed(property),
+                computed,
+                span,
+            },
+            NodeKind::ObjectExpression { properties } => ASTNode::ObjectExpression {
+                properties: all(properties),
+                span,
+            },
+            NodeKind::Property {
+                key,
+                value,
+                computed,
+                shorthand,
+            } => ASTNode::Property {
+                key: boxed(key),
+                value: boxed(value),
+                computed,
+                shorthand,
+                span,
+            },
+            NodeKind::Literal(value) => {
+                let text = |atom: Atom| atom.to_string();
+                let value = match value {
+                    ArenaLiteral::Number(number) => Literal::Number(number),
+                    ArenaLiteral::String(id) => Literal::String(self.string(id).to_vec()),
+                    ArenaLiteral::Boolean(boolean) => Literal::Boolean(boolean),
+                    ArenaLiteral::Null => Literal::Null,
+                    ArenaLiteral::BigInt(atom) => Literal::BigInt(text(atom)),
+                    ArenaLiteral::RegExp { pattern, flags } => Literal::RegExp {
+                        pattern: text(pattern),
+                        flags: text(flags),
+                    },
+                };
+                ASTNode::Literal { value, span }
+            }
+            NodeKind::Identifier(atom) => ASTNode::Identifier {
+                name: atom,
+                span,
+            },
+            NodeKind::ThisExpression => ASTNode::ThisExpression { span },
+        }
+    }
+}
+
+impl AstBuilder for AstArena {
+    type Node = NodeId;
+
+    fn span_of(&self, node: &NodeId) -> Span {
+        self.span(*node)
+    }
+
+    fn program(&mut self, body: Vec<NodeId>, span: Span) -> NodeId {
+        let body = self.push_list(body);
+        self.push(NodeKind::Program { body }, span)
+    }
+    fn variable_declaration(&mut self, kind: VariableKind, declarations: Vec<NodeId>, span: Span) -> NodeId {
+        let declarations = self.push_list(declarations);
+        self.push(NodeKind::VariableDeclaration { kind, declarations }, span)
+    }
+    fn variable_declarator(&mut self, identifier: NodeId, value: Option<NodeId>, span: Span) -> NodeId {
+        self.push(NodeKind::VariableDeclarator { identifier, value }, span)
+    }
+    fn function_declaration(&mut self, name: NodeId, parameters: Vec<NodeId>, body: NodeId, span: Span) -> NodeId {
+        let parameters = self.push_list(parameters);
+        self.push(NodeKind::FunctionDeclaration { name, parameters, body }, span)
+    }
+    fn if_statement(&mut self, condition: NodeId, body: NodeId, else_body: Option<NodeId>, span: Span) -> NodeId {
+        self.push(
+            NodeKind::IfStatement {
+                condition,
+                body,
+                else_body,
+            },
+            span,
+        )
+    }
+    fn block(&mut self, body: Vec<NodeId>, span: Span) -> NodeId {
+        let body = self.push_list(body);
+        self.push(NodeKind::Block { body }, span)
+    }
+    fn expression_statement(&mut self, expression: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::ExpressionStatement { expression }, span)
+    }
+    fn return_statement(&mut self, argument: Option<NodeId>, span: Span) -> NodeId {
+        self.push(NodeKind::ReturnStatement { argument }, span)
+    }
+    fn break_statement(&mut self, label: Option<NodeId>, span: Span) -> NodeId {
+        self.push(NodeKind::BreakStatement { label }, span)
+    }
+    fn continue_statement(&mut self, label: Option<NodeId>, span: Span) -> NodeId {
+        self.push(NodeKind::ContinueStatement { label }, span)
+    }
+    fn throw_statement(&mut self, argument: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::ThrowStatement { argument }, span)
+    }
+    fn try_statement(&mut self, block: NodeId, handler: Option<NodeId>, finalizer: Option<NodeId>, span: Span) -> NodeId {
+        self.push(
+            NodeKind::TryStatement {
+                block,
+                handler,
+                finalizer,
+            },
+            span,
+        )
+    }
+    fn catch_clause(&mut self, parameter: Option<NodeId>, body: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::CatchClause { parameter, body }, span)
+    }
+    fn labeled_statement(&mut self, label: NodeId, body: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::LabeledStatement { label, body }, span)
+    }
+    fn while_statement(&mut self, condition: NodeId, body: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::WhileStatement { condition, body }, span)
+    }
+    fn for_statement(
+        &mut self,
+        init: Option<NodeId>,
+        condition: Option<NodeId>,
+        update: Option<NodeId>,
+        body: NodeId,
+        span: Span,
+    ) -> NodeId {
+        self.push(
+            NodeKind::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+            },
+            span,
+        )
+    }
+    fn assignment_expression(&mut self, left: NodeId, operator: AssignOp, right: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::AssignmentExpression { left, operator, right }, span)
+    }
+    fn binary_expression(&mut self, left: NodeId, operator: BinaryOp, right: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::BinaryExpression { left, operator, right }, span)
+    }
+    fn logical_expression(&mut self, left: NodeId, operator: LogicalOp, right: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::LogicalExpression { left, operator, right }, span)
+    }
+    fn unary_expression(&mut self, operator: UnaryOp, argument: NodeId, span: Span) -> NodeId {
+        self.push(NodeKind::UnaryExpression { operator, argument }, span)
+    }
+    fn update_expression(&mut self, operator: UpdateOp, prefix: bool, argument: NodeId, span: Span) -> NodeId {
+        self.push(
+            NodeKind::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            },
+            span,
+        )
+    }
+    fn call_expression(&mut self, callee: NodeId, arguments: Vec<NodeId>, span: Span) -> NodeId {
+        let arguments = self.push_list(arguments);
+        self.push(NodeKind::CallExpression { callee, arguments }, span)
+    }
+    fn member_expression(&mut self, object: NodeId, property: NodeId, computed: bool, span: Span) -> NodeId {
+        self.push(
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+            },
+            span,
+        )
+    }
+    fn object_expression(&mut self, properties: Vec<NodeId>, span: Span) -> NodeId {
+        let properties = self.push_list(properties);
+        self.push(NodeKind::ObjectExpression { properties }, span)
+    }
+    fn property(&mut self, key: NodeId, value: NodeId, computed: bool, shorthand: bool, span: Span) -> NodeId {
+        self.push(
+            NodeKind::Property {
+                key,
+                value,
+                computed,
+                shorthand,
+            },
+            span,
+        )
+    }
+    fn literal(&mut self, value: Literal, span: Span) -> NodeId {
+        let value = match value {
+            Literal::Number(number) => ArenaLiteral::Number(number),
+            Literal::String(units) => {
+                let id = StringId(u32::try_from(self.strings.len()).expect("AST arena exceeds u32::MAX strings"));
+                self.strings.push(units.into());
+                ArenaLiteral::String(id)
+            }
+            Literal::Boolean(boolean) => ArenaLiteral::Boolean(boolean),
+            Literal::Null => ArenaLiteral::Null,
+            Literal::BigInt(digits) => ArenaLiteral::BigInt(Atom::new(&digits)),
+            Literal::RegExp { pattern, flags } => ArenaLiteral::RegExp {
+                pattern: Atom::new(&pattern),
+                flags: Atom::new(&flags),
+            },
+        };
+        self.push(NodeKind::Literal(value), span)
+    }
+    fn identifier(&mut self, name: Atom, span: Span) -> NodeId {
+        self.push(NodeKind::Identifier(name), span)
+    }
+    fn this_expression(&mut self, span: Span) -> NodeId {
+        self.push(NodeKind::ThisExpression, span)
+    }
+}
+
+/// Per-node data kept outside the arena, indexed by [`NodeId`].
+#[derive(Debug, Clone)]
+pub struct SideTable<T> {
+    values: Vec<Option<T>>,
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        SideTable { values: Vec::new() }
+    }
+}
+
+impl<T> SideTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn for_arena(arena: &AstArena) -> Self {
+        let mut values = Vec::with_capacity(arena.len());
+        values.resize_with(arena.len(), || None);
+        SideTable { values }
+    }
+
+    pub fn insert(&mut self, id: NodeId, value: T) -> Option<T> {
+        if id.index() >= self.values.len() {
+            self.values.resize_with(id.index() + 1, || None);
+        }
+        self.values[id.index()].replace(value)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.values.get(id.index()).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.values.get_mut(id.index()).and_then(Option::as_mut)
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.values.get_mut(id.index()).and_then(Option::take)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_ref().map(|value| (NodeId::from_index(index), value)))
+    }
+}
+
+/// Calls `visit` with each direct child of a node of `kind`, in source order,
+/// `lists` being the arena's list storage.
+fn visit_children(kind: &NodeKind, lists: &[NodeId], mut visit: impl FnMut(NodeId)) {
+    let list = |list: NodeList| &lists[list.start as usize..(list.start + list.len) as usize];
+    let mut all = |children: &[NodeId]| children.iter().copied().for_each(&mut visit);
+    match *kind {
+        NodeKind::Program { body } | NodeKind::Block { body } => all(list(body)),
+        NodeKind::VariableDeclaration { declarations, .. } => all(list(declarations)),
+        NodeKind::VariableDeclarator { identifier, value } => {
+            all(&[identifier]);
+            all(value.as_slice());
+        }
+        NodeKind::FunctionDeclaration { name, parameters, body } => {
+            all(&[name]);
+            all(list(parameters));
+            all(&[body]);
+        }
+        NodeKind::IfStatement {
+            condition,
+            body,
+            else_body,
+        } => {
+            all(&[condition, body]);
+            all(else_body.as_slice());
+        }
+        NodeKind::ExpressionStatement { expression } => all(&[expression]),
+        NodeKind::ReturnStatement { argument } => all(argument.as_slice()),
+        NodeKind::BreakStatement { label } | NodeKind::ContinueStatement { label } => all(label.as_slice()),
+        NodeKind::ThrowStatement { argument } => all(&[argument]),
+        NodeKind::TryStatement {
+            block,
+            handler,
+            finalizer,
+        } => {
+            all(&[block]);
+            all(handler.as_slice());
+            all(finalizer.as_slice());
+        }
+        NodeKind::CatchClause { parameter, body } => {
+            all(parameter.as_slice());
+            all(&[body]);
+        }
+        NodeKind::LabeledStatement { label, body } => all(&[label, body]),
+        NodeKind::WhileStatement { condition, body } => all(&[condition, body]),
+        NodeKind::ForStatement {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            for part in [init, condition, update, Some(body)] {
+                all(part.as_slice());
+            }
+        }
+        NodeKind::AssignmentExpression { left, right, .. }
+        | NodeKind::BinaryExpression { left, right, .. }
+        | NodeKind::LogicalExpression { left, right, .. } => all(&[left, right]),
+        NodeKind::UnaryExpression { argument, .. } | NodeKind::UpdateExpression { argument, .. } => all(&[argument]),
+        NodeKind::CallExpression { callee, arguments } => {
+            all(&[callee]);
+            all(list(arguments));
+        }
+        NodeKind::MemberExpression { object, property, .. } => all(&[object, property]),
+        NodeKind::ObjectExpression { properties } => all(list(properties)),
+        NodeKind::Property { key, value, .. } => all(&[key, value]),
+        NodeKind::Literal(_) | NodeKind::Identifier(_) | NodeKind::ThisExpression => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+    use std::mem::size_of;
+
+    const CODE: &str = "let total = a + b * 2, name = 'a';\ntotal += a;\nname = typeof total === 'number' || !a++;";
+
+    #[test]
+    fn test_parse_matches_tree_parser() {
+        let tokens = Lexer::new(CODE).tokenize();
+        let arena = AstArena::parse(&tokens);
+
+        assert_eq!(arena.to_ast(arena.root()), ASTNode::parse_program(&tokens));
+    }
+
+    #[test]
+    fn test_parse_statements_and_calls() {
+        let code = "function f(o) { for (var i = 0; i < 2; i++) { while (o[i]) break; } return o.g({ i, j: 1 }); }";
+        let tokens = Lexer::new(code).tokenize();
+        let arena = AstArena::parse(&tokens);
+
+        assert_eq!(arena.to_ast(arena.root()), ASTNode::parse_program(&tokens));
+    }
+
+    #[test]
+    fn test_from_ast_round_trip() {
+        let ast = ASTNode::parse_program(&Lexer::new(CODE).tokenize());
+        let arena = AstArena::from_ast(&ast);
+
+        assert_eq!(arena.to_ast(arena.root()), ast);
+    }
+
+    #[test]
+    fn test_identifiers_are_interned() {
+        let arena = AstArena::parse(&Lexer::new(CODE).tokenize());
+
+        let atoms: Vec<Atom> = arena
+            .ids()
+            .filter_map(|id| match arena.kind(id) {
+                NodeKind::Identifier(atom) if *atom == "a" => Some(*atom),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(atoms.len(), 3);
+        assert!(atoms.iter().all(|atom| *atom == Atom::new("a")));
+        // The string literal 'a' keeps its code units, and names the same atom.
+        let literal = arena.ids().find_map(|id| match arena.kind(id) {
+            NodeKind::Literal(ArenaLiteral::String(string)) => Some(*string),
+            _ => None,
+        });
+        assert_eq!(arena.string(literal.unwrap()), [b'a' as u16]);
+        assert_eq!(arena.string_name(literal.unwrap()), Some(atoms[0]));
+    }
+
+    #[test]
+    fn test_parent_links() {
+        let arena = AstArena::parse(&Lexer::new("x = a + b;").tokenize());
+        let b = arena
+            .ids()
+            .find(|&id| arena.identifier_name(id) == Some("b"))
+            .unwrap();
+
+        let kinds: Vec<&str> = arena
+            .ancestors(b)
+            .map(|id| match arena.kind(id) {
+                NodeKind::BinaryExpression { .. } => "BinaryExpression",
+                NodeKind::AssignmentExpression { .. } => "AssignmentExpression",
+                NodeKind::ExpressionStatement { .. } => "ExpressionStatement",
+                NodeKind::Program { .. } => "Program",
+                other => panic!("unexpected ancestor {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec!["BinaryExpression", "AssignmentExpression", "ExpressionStatement", "Program"]
+        );
+        assert_eq!(arena.parent(arena.root()), None);
+    }
+
+    #[test]
+    fn test_side_table() {
+        let arena = AstArena::parse(&Lexer::new("a; b; a;").tokenize());
+        let mut depths = SideTable::for_arena(&arena);
+        for id in arena.ids() {
+            depths.insert(id, arena.ancestors(id).count());
+        }
+
+        assert_eq!(depths.get(arena.root()), Some(&0));
+        let identifier_depths: Vec<usize> = depths
+            .iter()
+            .filter(|(id, _)| arena.identifier_name(*id).is_some())
+            .map(|(_, depth)| *depth)
+            .collect();
+        assert_eq!(identifier_depths, vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn test_nodes_are_compact() {
+        assert_eq!(size_of::<Option<NodeId>>(), 4);
+        assert!(size_of::<NodeKind>() <= 24);
+        assert!(size_of::<NodeKind>() < size_of::<ASTNode>());
+    }
+}
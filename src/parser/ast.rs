@@ -1,225 +1,878 @@
-use crate::lexical::lexer::Token;
+use crate::lexical::atom::Atom;
+use crate::lexical::lexer::SpannedToken;
+use crate::lexical::span::Span;
+use crate::parser::builder::TreeBuilder;
+use crate::parser::numeric;
+use crate::parser::parse::Parser;
+use crate::parser::operators::{AssignOp, BinaryOp, LogicalOp, UnaryOp, UpdateOp};
+use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f64),
+    /// UTF-16 code units, as string values hold, so that lone surrogates
+    /// survive.
+    String(Vec<u16>),
+    Boolean(bool),
+    Null,
+    BigInt(String),
+    RegExp { pattern: String, flags: String },
+}
+
+impl Literal {
+    /// Builds a numeric or BigInt literal from the raw text of a `Token::Number`.
+    /// BigInt literals must be integers without an exponent or a legacy
+    /// octal `0` prefix, so `1.5n`, `1e3n` and `017n` are errors.
+    pub fn from_numeric(raw: &str) -> Result<Self, String> {
+        let digits = raw.replace('_', "");
+        if let Some(bigint) = digits.strip_suffix('n') {
+            let prefixed = matches!(bigint.get(..2), Some("0x" | "0X" | "0o" | "0O" | "0b" | "0B"));
+            let decimal = bigint.chars().all(|c| c.is_ascii_digit()) && (bigint == "0" || !bigint.starts_with('0'));
+            if !prefixed && !decimal {
+                return Err("Invalid BigInt literal".to_string());
+            }
+            return Ok(Literal::BigInt(bigint.to_string()));
+        }
+        numeric::parse_number(&digits).map(Literal::Number)
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Number(value) => f.write_str(&numeric::number_to_string(*value)),
+            Literal::String(value) => write!(f, "{:?}", String::from_utf16_lossy(value)),
+            Literal::Boolean(value) => write!(f, "{}", value),
+            Literal::Null => write!(f, "null"),
+            Literal::BigInt(digits) => write!(f, "{}n", digits),
+            Literal::RegExp { pattern, flags } => write!(f, "/{}/{}", pattern, flags),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VariableKind {
+    Var,
+    Let,
+    Const,
+}
+
+impl VariableKind {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "var" => Some(VariableKind::Var),
+            "let" => Some(VariableKind::Let),
+            "const" => Some(VariableKind::Const),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VariableKind::Var => "var",
+            VariableKind::Let => "let",
+            VariableKind::Const => "const",
+        }
+    }
+}
+
+impl fmt::Display for VariableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ASTNode {
-    Program(Vec<ASTNode>),
+    Program {
+        body: Vec<ASTNode>,
+        span: Span,
+    },
     VariableDeclaration {
-        identifier: String,
-        value: Box<ASTNode>,
+        kind: VariableKind,
+        declarations: Vec<ASTNode>,
+        span: Span,
+    },
+    VariableDeclarator {
+        identifier: Box<ASTNode>,
+        value: Option<Box<ASTNode>>,
+        span: Span,
     },
     FunctionDeclaration {
-        name: String,
-        parameters: Vec<String>,
-        body: Vec<ASTNode>,
+        name: Box<ASTNode>,
+        parameters: Vec<ASTNode>,
+        body: Box<ASTNode>,
+        span: Span,
     },
     IfStatement {
         condition: Box<ASTNode>,
+        body: Box<ASTNode>,
+        else_body: Option<Box<ASTNode>>,
+        span: Span,
+    },
+    Block {
         body: Vec<ASTNode>,
-        else_body: Vec<ASTNode>,
+        span: Span,
+    },
+    ExpressionStatement {
+        expression: Box<ASTNode>,
+        span: Span,
+    },
+    ReturnStatement {
+        argument: Option<Box<ASTNode>>,
+        span: Span,
+    },
+    BreakStatement {
+        label: Option<Box<ASTNode>>,
+        span: Span,
+    },
+    ContinueStatement {
+        label: Option<Box<ASTNode>>,
+        span: Span,
+    },
+    ThrowStatement {
+        argument: Box<ASTNode>,
+        span: Span,
+    },
+    TryStatement {
+        block: Box<ASTNode>,
+        handler: Option<Box<ASTNode>>,
+        finalizer: Option<Box<ASTNode>>,
+        span: Span,
+    },
+    CatchClause {
+        parameter: Option<Box<ASTNode>>,
+        body: Box<ASTNode>,
+        span: Span,
+    },
+    LabeledStatement {
+        label: Box<ASTNode>,
+        body: Box<ASTNode>,
+        span: Span,
+    },
+    WhileStatement {
+        condition: Box<ASTNode>,
+        body: Box<ASTNode>,
+        span: Span,
+    },
+    ForStatement {
+        init: Option<Box<ASTNode>>,
+        condition: Option<Box<ASTNode>>,
+        update: Option<Box<ASTNode>>,
+        body: Box<ASTNode>,
+        span: Span,
     },
-    Block(Vec<ASTNode>),
-    ExpressionStatement(Box<ASTNode>),
     AssignmentExpression {
         left: Box<ASTNode>,
-        operator: String,
+        operator: AssignOp,
         right: Box<ASTNode>,
+        span: Span,
     },
     BinaryExpression {
         left: Box<ASTNode>,
-        operator: String,
+        operator: BinaryOp,
         right: Box<ASTNode>,
+        span: Span,
+    },
+    LogicalExpression {
+        left: Box<ASTNode>,
+        operator: LogicalOp,
+        right: Box<ASTNode>,
+        span: Span,
+    },
+    UnaryExpression {
+        operator: UnaryOp,
+        argument: Box<ASTNode>,
+        span: Span,
+    },
+    UpdateExpression {
+        operator: UpdateOp,
+        prefix: bool,
+        argument: Box<ASTNode>,
+        span: Span,
+    },
+    CallExpression {
+        callee: Box<ASTNode>,
+        arguments: Vec<ASTNode>,
+        span: Span,
+    },
+    /// `object.property`, or `object[property]` when `computed`.
+    MemberExpression {
+        object: Box<ASTNode>,
+        property: Box<ASTNode>,
+        computed: bool,
+        span: Span,
+    },
+    ObjectExpression {
+        properties: Vec<ASTNode>,
+        span: Span,
+    },
+    /// `key: value` inside an object literal. Shorthand `{ a }` has an
+    /// identifier key and an identifier value with the same name.
+    Property {
+        key: Box<ASTNode>,
+        value: Box<ASTNode>,
+        computed: bool,
+        shorthand: bool,
+        span: Span,
+    },
+    Literal {
+        value: Literal,
+        span: Span,
+    },
+    Identifier {
+        name: Atom,
+        span: Span,
+    },
+    ThisExpression {
+        span: Span,
     },
-    Literal(String),
-    Identifier(String),
 }
 
 impl ASTNode {
-    pub fn new_program() -> Self {
-        ASTNode::Program(vec![])
+    pub fn new_program(span: Span) -> Self {
+        ASTNode::Program { body: vec![], span }
     }
     pub fn add_to_program(&mut self, node: ASTNode) {
-        if let ASTNode::Program(nodes) = self {
-            nodes.push(node);
+        if let ASTNode::Program { body, span } = self {
+            *span = span.to(node.span());
+            body.push(node);
         }
     }
-    pub fn new_variable_declaration(identifier: String, value: ASTNode) -> Self {
+    pub fn new_variable_declaration(kind: VariableKind, declarations: Vec<ASTNode>, span: Span) -> Self {
         ASTNode::VariableDeclaration {
-            identifier,
-            value: Box::new(value),
+            kind,
+            declarations,
+            span,
         }
     }
-    pub fn new_function_declaration(name: String, parameters: Vec<String>, body: Vec<ASTNode>) -> Self {
+    pub fn new_variable_declarator(identifier: ASTNode, value: Option<ASTNode>, span: Span) -> Self {
+        ASTNode::VariableDeclarator {
+            identifier: Box::new(identifier),
+            value: value.map(Box::new),
+            span,
+        }
+    }
+    pub fn new_function_declaration(name: ASTNode, parameters: Vec<ASTNode>, body: ASTNode, span: Span) -> Self {
         ASTNode::FunctionDeclaration {
-            name,
+            name: Box::new(name),
             parameters,
-            body,
+            body: Box::new(body),
+            span,
         }
     }
-    pub fn new_if_statement(condition: ASTNode, body: Vec<ASTNode>, else_body: Vec<ASTNode>) -> Self {
+    pub fn new_if_statement(condition: ASTNode, body: ASTNode, else_body: Option<ASTNode>, span: Span) -> Self {
         ASTNode::IfStatement {
             condition: Box::new(condition),
-            body,
-            else_body,
+            body: Box::new(body),
+            else_body: else_body.map(Box::new),
+            span,
+        }
+    }
+    pub fn new_block(nodes: Vec<ASTNode>, span: Span) -> Self {
+        ASTNode::Block { body: nodes, span }
+    }
+    pub fn new_expression_statement(expression: ASTNode, span: Span) -> Self {
+        ASTNode::ExpressionStatement {
+            expression: Box::new(expression),
+            span,
+        }
+    }
+    pub fn new_return_statement(argument: Option<ASTNode>, span: Span) -> Self {
+        ASTNode::ReturnStatement {
+            argument: argument.map(Box::new),
+            span,
+        }
+    }
+    pub fn new_break_statement(label: Option<ASTNode>, span: Span) -> Self {
+        ASTNode::BreakStatement {
+            label: label.map(Box::new),
+            span,
+        }
+    }
+    pub fn new_continue_statement(label: Option<ASTNode>, span: Span) -> Self {
+        ASTNode::ContinueStatement {
+            label: label.map(Box::new),
+            span,
+        }
+    }
+    pub fn new_throw_statement(argument: ASTNode, span: Span) -> Self {
+        ASTNode::ThrowStatement {
+            argument: Box::new(argument),
+            span,
         }
     }
-    pub fn new_block(nodes: Vec<ASTNode>) -> Self {
-        ASTNode::Block(nodes)
+    pub fn new_try_statement(block: ASTNode, handler: Option<ASTNode>, finalizer: Option<ASTNode>, span: Span) -> Self {
+        ASTNode::TryStatement {
+            block: Box::new(block),
+            handler: handler.map(Box::new),
+            finalizer: finalizer.map(Box::new),
+            span,
+        }
     }
-    pub fn new_expression_statement(expression: ASTNode) -> Self {
-        ASTNode::ExpressionStatement(Box::new(expression))
+    pub fn new_catch_clause(parameter: Option<ASTNode>, body: ASTNode, span: Span) -> Self {
+        ASTNode::CatchClause {
+            parameter: parameter.map(Box::new),
+            body: Box::new(body),
+            span,
+        }
+    }
+    pub fn new_labeled_statement(label: ASTNode, body: ASTNode, span: Span) -> Self {
+        ASTNode::LabeledStatement {
+            label: Box::new(label),
+            body: Box::new(body),
+            span,
+        }
     }
-    pub fn new_assignment_expression(left: ASTNode, operator: String, right: ASTNode) -> Self {
+    pub fn new_while_statement(condition: ASTNode, body: ASTNode, span: Span) -> Self {
+        ASTNode::WhileStatement {
+            condition: Box::new(condition),
+            body: Box::new(body),
+            span,
+        }
+    }
+    pub fn new_for_statement(
+        init: Option<ASTNode>,
+        condition: Option<ASTNode>,
+        update: Option<ASTNode>,
+        body: ASTNode,
+        span: Span,
+    ) -> Self {
+        ASTNode::ForStatement {
+            init: init.map(Box::new),
+            condition: condition.map(Box::new),
+            update: update.map(Box::new),
+            body: Box::new(body),
+            span,
+        }
+    }
+    pub fn new_assignment_expression(left: ASTNode, operator: AssignOp, right: ASTNode, span: Span) -> Self {
         ASTNode::AssignmentExpression {
             left: Box::new(left),
             operator,
             right: Box::new(right),
+            span,
         }
     }
-    pub fn new_binary_expression(left: ASTNode, operator: String, right: ASTNode) -> Self {
+    pub fn new_binary_expression(left: ASTNode, operator: BinaryOp, right: ASTNode, span: Span) -> Self {
         ASTNode::BinaryExpression {
             left: Box::new(left),
             operator,
             right: Box::new(right),
+            span,
+        }
+    }
+    pub fn new_logical_expression(left: ASTNode, operator: LogicalOp, right: ASTNode, span: Span) -> Self {
+        ASTNode::LogicalExpression {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+            span,
+        }
+    }
+    pub fn new_unary_expression(operator: UnaryOp, argument: ASTNode, span: Span) -> Self {
+        ASTNode::UnaryExpression {
+            operator,
+            argument: Box::new(argument),
+            span,
         }
     }
-    pub fn new_literal(value: String) -> Self {
-        ASTNode::Literal(value)
+    pub fn new_update_expression(operator: UpdateOp, prefix: bool, argument: ASTNode, span: Span) -> Self {
+        ASTNode::UpdateExpression {
+            operator,
+            prefix,
+            argument: Box::new(argument),
+            span,
+        }
     }
-    pub fn new_identifier(name: String) -> Self {
-        ASTNode::Identifier(name)
+    pub fn new_call_expression(callee: ASTNode, arguments: Vec<ASTNode>, span: Span) -> Self {
+        ASTNode::CallExpression {
+            callee: Box::new(callee),
+            arguments,
+            span,
+        }
+    }
+    pub fn new_member_expression(object: ASTNode, property: ASTNode, computed: bool, span: Span) -> Self {
+        ASTNode::MemberExpression {
+            object: Box::new(object),
+            property: Box::new(property),
+            computed,
+            span,
+        }
+    }
+    pub fn new_object_expression(properties: Vec<ASTNode>, span: Span) -> Self {
+        ASTNode::ObjectExpression { properties, span }
+    }
+    pub fn new_property(key: ASTNode, value: ASTNode, computed: bool, shorthand: bool, span: Span) -> Self {
+        ASTNode::Property {
+            key: Box::new(key),
+            value: Box::new(value),
+            computed,
+            shorthand,
+            span,
+        }
+    }
+    pub fn new_literal(value: Literal, span: Span) -> Self {
+        ASTNode::Literal { value, span }
+    }
+    pub fn new_identifier(name: Atom, span: Span) -> Self {
+        ASTNode::Identifier { name, span }
+    }
+    pub fn new_this_expression(span: Span) -> Self {
+        ASTNode::ThisExpression { span }
     }
 
-    pub fn parse_program(tokens: &[Token]) -> ASTNode {
-        let mut index = 0;
-        let mut nodes = Vec::new();
+    pub fn span(&self) -> Span {
+        match self {
+            ASTNode::Program { span, .. }
+            | ASTNode::VariableDeclaration { span, .. }
+            | ASTNode::VariableDeclarator { span, .. }
+            | ASTNode::FunctionDeclaration { span, .. }
+            | ASTNode::IfStatement { span, .. }
+            | ASTNode::Block { span, .. }
+            | ASTNode::ExpressionStatement { span, .. }
+            | ASTNode::ReturnStatement { span, .. }
+            | ASTNode::BreakStatement { span, .. }
+            | ASTNode::ContinueStatement { span, .. }
+            | ASTNode::ThrowStatement { span, .. }
+            | ASTNode::TryStatement { span, .. }
+            | ASTNode::CatchClause { span, .. }
+            | ASTNode::LabeledStatement { span, .. }
+            | ASTNode::WhileStatement { span, .. }
+            | ASTNode::ForStatement { span, .. }
+            | ASTNode::AssignmentExpression { span, .. }
+            | ASTNode::BinaryExpression { span, .. }
+            | ASTNode::LogicalExpression { span, .. }
+            | ASTNode::UnaryExpression { span, .. }
+            | ASTNode::UpdateExpression { span, .. }
+            | ASTNode::CallExpression { span, .. }
+            | ASTNode::MemberExpression { span, .. }
+            | ASTNode::ObjectExpression { span, .. }
+            | ASTNode::Property { span, .. }
+            | ASTNode::Literal { span, .. }
+            | ASTNode::Identifier { span, .. }
+            | ASTNode::ThisExpression { span } => *span,
+        }
+    }
 
-        while index < tokens.len() {
-            let statement = Self::parse_statement(tokens, &mut index);
-            nodes.push(statement);
+    pub fn set_span(&mut self, new_span: Span) {
+        match self {
+            ASTNode::Program { span, .. }
+            | ASTNode::VariableDeclaration { span, .. }
+            | ASTNode::VariableDeclarator { span, .. }
+            | ASTNode::FunctionDeclaration { span, .. }
+            | ASTNode::IfStatement { span, .. }
+            | ASTNode::Block { span, .. }
+            | ASTNode::ExpressionStatement { span, .. }
+            | ASTNode::ReturnStatement { span, .. }
+            | ASTNode::BreakStatement { span, .. }
+            | ASTNode::ContinueStatement { span, .. }
+            | ASTNode::ThrowStatement { span, .. }
+            | ASTNode::TryStatement { span, .. }
+            | ASTNode::CatchClause { span, .. }
+            | ASTNode::LabeledStatement { span, .. }
+            | ASTNode::WhileStatement { span, .. }
+            | ASTNode::ForStatement { span, .. }
+            | ASTNode::AssignmentExpression { span, .. }
+            | ASTNode::BinaryExpression { span, .. }
+            | ASTNode::LogicalExpression { span, .. }
+            | ASTNode::UnaryExpression { span, .. }
+            | ASTNode::UpdateExpression { span, .. }
+            | ASTNode::CallExpression { span, .. }
+            | ASTNode::MemberExpression { span, .. }
+            | ASTNode::ObjectExpression { span, .. }
+            | ASTNode::Property { span, .. }
+            | ASTNode::Literal { span, .. }
+            | ASTNode::Identifier { span, .. }
+            | ASTNode::ThisExpression { span } => *span = new_span,
+        }
+    }
 
-            if let Some(Token::EOF) = tokens.get(index) {
-                break;
+    /// Copy of the tree with every span reset, for comparing trees by shape.
+    pub fn without_spans(&self) -> ASTNode {
+        fn clear(node: &mut ASTNode) {
+            node.set_span(Span::default());
+            for child in node.children_mut() {
+                clear(child);
             }
         }
 
-        ASTNode::Program(nodes)
+        let mut copy = self.clone();
+        clear(&mut copy);
+        copy
     }
 
-    pub fn parse_statement(tokens: &[Token], index: &mut usize) -> ASTNode {
-        match tokens.get(*index) {
-            Some(Token::Keyword(ref kw)) if kw == "let" => {
-                *index += 1; // Consume 'let'
-                let identifier = match tokens.get(*index) {
-                    Some(Token::Identifier(name)) => name.clone(),
-                    _ => panic!("Expected identifier after 'let'"),
-                };
-                *index += 1;
-
-                match tokens.get(*index) {
-                    Some(Token::Operator(op)) if op == "=" => *index += 1,
-                    _ => panic!("Expected '=' after variable name"),
-                }
-
-                let value = Self::parse_expression(tokens, index);
-
-                if let Some(Token::Delimiter(d)) = tokens.get(*index) {
-                    if d == ";" {
-                        *index += 1;
-                    }
-                }
-
-                ASTNode::VariableDeclaration {
-                    identifier,
-                    value: Box::new(value),
-                }
+    /// Direct children in source order.
+    pub fn children(&self) -> Vec<&ASTNode> {
+        match self {
+            ASTNode::Program { body, .. } | ASTNode::Block { body, .. } => body.iter().collect(),
+            ASTNode::VariableDeclaration { declarations, .. } => declarations.iter().collect(),
+            ASTNode::VariableDeclarator { identifier, value, .. } => {
+                let mut children = vec![identifier.as_ref()];
+                children.extend(value.as_deref());
+                children
+            }
+            ASTNode::FunctionDeclaration { name, parameters, body, .. } => {
+                let mut children = vec![name.as_ref()];
+                children.extend(parameters.iter());
+                children.push(body);
+                children
+            }
+            ASTNode::IfStatement { condition, body, else_body, .. } => {
+                let mut children = vec![condition.as_ref(), body.as_ref()];
+                children.extend(else_body.as_deref());
+                children
+            }
+            ASTNode::ExpressionStatement { expression, .. } => vec![expression],
+            ASTNode::ReturnStatement { argument, .. } => argument.as_deref().into_iter().collect(),
+            ASTNode::BreakStatement { label, .. } | ASTNode::ContinueStatement { label, .. } => label.as_deref().into_iter().collect(),
+            ASTNode::ThrowStatement { argument, .. } => vec![argument],
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => {
+                let mut children = vec![block.as_ref()];
+                children.extend(handler.as_deref());
+                children.extend(finalizer.as_deref());
+                children
+            }
+            ASTNode::CatchClause { parameter, body, .. } => {
+                let mut children: Vec<_> = parameter.as_deref().into_iter().collect();
+                children.push(body.as_ref());
+                children
+            }
+            ASTNode::LabeledStatement { label, body, .. } => vec![label, body],
+            ASTNode::WhileStatement { condition, body, .. } => vec![condition, body],
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => {
+                let mut children: Vec<_> = [init, condition, update]
+                    .into_iter()
+                    .filter_map(|child| child.as_deref())
+                    .collect();
+                children.push(body.as_ref());
+                children
             }
-            _ => panic!("Unsupported statement or token: {:?}", tokens.get(*index)),
+            ASTNode::AssignmentExpression { left, right, .. }
+            | ASTNode::BinaryExpression { left, right, .. }
+            | ASTNode::LogicalExpression { left, right, .. } => vec![left, right],
+            ASTNode::UnaryExpression { argument, .. } | ASTNode::UpdateExpression { argument, .. } => vec![argument],
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                let mut children = vec![callee.as_ref()];
+                children.extend(arguments.iter());
+                children
+            }
+            ASTNode::MemberExpression { object, property, .. } => vec![object, property],
+            ASTNode::ObjectExpression { properties, .. } => properties.iter().collect(),
+            ASTNode::Property { key, value, .. } => vec![key, value],
+            ASTNode::Literal { .. } | ASTNode::Identifier { .. } | ASTNode::ThisExpression { .. } => vec![],
         }
     }
 
-    pub fn parse_expression(tokens: &[Token], index: &mut usize) -> ASTNode {
-        match tokens.get(*index) {
-            Some(Token::Literal(value)) => {
-                *index += 1;
-                ASTNode::Literal(value.clone())
+    /// Mutable counterpart of [`ASTNode::children`].
+    pub fn children_mut(&mut self) -> Vec<&mut ASTNode> {
+        match self {
+            ASTNode::Program { body, .. } | ASTNode::Block { body, .. } => body.iter_mut().collect(),
+            ASTNode::VariableDeclaration { declarations, .. } => declarations.iter_mut().collect(),
+            ASTNode::VariableDeclarator { identifier, value, .. } => {
+                let mut children = vec![identifier.as_mut()];
+                children.extend(value.as_deref_mut());
+                children
+            }
+            ASTNode::FunctionDeclaration { name, parameters, body, .. } => {
+                let mut children = vec![name.as_mut()];
+                children.extend(parameters.iter_mut());
+                children.push(body);
+                children
+            }
+            ASTNode::IfStatement { condition, body, else_body, .. } => {
+                let mut children = vec![condition.as_mut(), body.as_mut()];
+                children.extend(else_body.as_deref_mut());
+                children
+            }
+            ASTNode::ExpressionStatement { expression, .. } => vec![expression],
+            ASTNode::ReturnStatement { argument, .. } => argument.as_deref_mut().into_iter().collect(),
+            ASTNode::BreakStatement { label, .. } | ASTNode::ContinueStatement { label, .. } => label.as_deref_mut().into_iter().collect(),
+            ASTNode::ThrowStatement { argument, .. } => vec![argument],
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => {
+                let mut children = vec![block.as_mut()];
+                children.extend(handler.as_deref_mut());
+                children.extend(finalizer.as_deref_mut());
+                children
             }
-            Some(Token::Identifier(name)) => {
-                *index += 1;
-                ASTNode::Identifier(name.clone())
+            ASTNode::CatchClause { parameter, body, .. } => {
+                let mut children: Vec<_> = parameter.as_deref_mut().into_iter().collect();
+                children.push(body.as_mut());
+                children
             }
-            _ => panic!("Unsupported expression at index {}", index),
+            ASTNode::LabeledStatement { label, body, .. } => vec![label, body],
+            ASTNode::WhileStatement { condition, body, .. } => vec![condition, body],
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => {
+                let mut children: Vec<_> = [init, condition, update]
+                    .into_iter()
+                    .filter_map(|child| child.as_deref_mut())
+                    .collect();
+                children.push(body.as_mut());
+                children
+            }
+            ASTNode::AssignmentExpression { left, right, .. }
+            | ASTNode::BinaryExpression { left, right, .. }
+            | ASTNode::LogicalExpression { left, right, .. } => vec![left, right],
+            ASTNode::UnaryExpression { argument, .. } | ASTNode::UpdateExpression { argument, .. } => vec![argument],
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                let mut children = vec![callee.as_mut()];
+                children.extend(arguments.iter_mut());
+                children
+            }
+            ASTNode::MemberExpression { object, property, .. } => vec![object, property],
+            ASTNode::ObjectExpression { properties, .. } => properties.iter_mut().collect(),
+            ASTNode::Property { key, value, .. } => vec![key, value],
+            ASTNode::Literal { .. } | ASTNode::Identifier { .. } | ASTNode::ThisExpression { .. } => vec![],
         }
     }
 
+    pub fn parse_program(tokens: &[SpannedToken]) -> ASTNode {
+        Parser::new(tokens, TreeBuilder).parse_program()
+    }
+
+    pub fn parse_statement(tokens: &[SpannedToken], index: &mut usize) -> ASTNode {
+        let mut parser = Parser::new(tokens, TreeBuilder);
+        parser.set_position(*index);
+        let statement = parser.parse_statement();
+        *index = parser.position();
+        statement
+    }
+
+    pub fn parse_expression(tokens: &[SpannedToken], index: &mut usize) -> ASTNode {
+        let mut parser = Parser::new(tokens, TreeBuilder);
+        parser.set_position(*index);
+        let expression = parser.parse_expression();
+        *index = parser.position();
+        expression
+    }
+
     pub fn pretty_print(&self, indent: usize) -> String {
         let mut result = String::new();
         let padding = " ".repeat(indent * 2);
 
         match self {
-            ASTNode::Program(nodes) => {
+            ASTNode::Program { body: nodes, .. } => {
                 result.push_str(&format!("{}Program:\n", padding));
                 for node in nodes {
                     result.push_str(&node.pretty_print(indent + 1));
                 }
             }
-            ASTNode::VariableDeclaration { identifier, value } => {
-                result.push_str(&format!(
-                    "{}VariableDeclaration: {} = {}\n",
-                    padding,
-                    identifier,
-                    value.pretty_print(indent + 1)
-                ));
-            }
-            ASTNode::FunctionDeclaration { name, parameters, body } => {
-                result.push_str(&format!(
-                    "{}FunctionDeclaration: {}({:?})\n",
-                    padding, name, parameters
-                ));
-                for node in body {
-                    result.push_str(&node.pretty_print(indent + 1));
+            ASTNode::VariableDeclaration { kind, declarations, .. } => {
+                result.push_str(&format!("{}VariableDeclaration: {}\n", padding, kind));
+                for declaration in declarations {
+                    result.push_str(&declaration.pretty_print(indent + 1));
+                }
+            }
+            ASTNode::VariableDeclarator { identifier, value, .. } => {
+                result.push_str(&format!("{}VariableDeclarator:\n", padding));
+                result.push_str(&identifier.pretty_print(indent + 1));
+                if let Some(value) = value {
+                    result.push_str(&value.pretty_print(indent + 1));
                 }
             }
-            ASTNode::IfStatement { condition, body, else_body } => {
+            ASTNode::FunctionDeclaration { name, parameters, body, .. } => {
+                result.push_str(&format!("{}FunctionDeclaration:\n", padding));
+                result.push_str(&name.pretty_print(indent + 1));
+                result.push_str(&format!("{}Parameters:\n", " ".repeat((indent + 1) * 2)));
+                for parameter in parameters {
+                    result.push_str(&parameter.pretty_print(indent + 2));
+                }
+                result.push_str(&body.pretty_print(indent + 1));
+            }
+            ASTNode::IfStatement { condition, body, else_body, .. } => {
                 result.push_str(&format!("{}IfStatement:\n", padding));
                 result.push_str(&format!("{}Condition:\n", " ".repeat((indent + 1) * 2)));
                 result.push_str(&condition.pretty_print(indent + 2));
                 result.push_str(&format!("{}Then:\n", " ".repeat((indent + 1) * 2)));
-                for node in body {
-                    result.push_str(&node.pretty_print(indent + 2));
-                }
-                if !else_body.is_empty() {
+                result.push_str(&body.pretty_print(indent + 2));
+                if let Some(else_body) = else_body {
                     result.push_str(&format!("{}Else:\n", " ".repeat((indent + 1) * 2)));
-                    for node in else_body {
-                        result.push_str(&node.pretty_print(indent + 2));
-                    }
+                    result.push_str(&else_body.pretty_print(indent + 2));
                 }
             }
-            ASTNode::Block(nodes) => {
+            ASTNode::Block { body: nodes, .. } => {
                 result.push_str(&format!("{}Block:\n", padding));
                 for node in nodes {
                     result.push_str(&node.pretty_print(indent + 1));
                 }
             }
-            ASTNode::ExpressionStatement(expression) => {
+            ASTNode::ExpressionStatement { expression, .. } => {
                 result.push_str(&format!("{}ExpressionStatement:\n", padding));
                 result.push_str(&expression.pretty_print(indent + 1));
             }
-            ASTNode::AssignmentExpression { left, operator, right } => {
+            ASTNode::ReturnStatement { argument, .. } => {
+                result.push_str(&format!("{}ReturnStatement:\n", padding));
+                if let Some(argument) = argument {
+                    result.push_str(&argument.pretty_print(indent + 1));
+                }
+            }
+            ASTNode::BreakStatement { label, .. } => match label {
+                Some(label) => {
+                    result.push_str(&format!("{}BreakStatement:\n", padding));
+                    result.push_str(&label.pretty_print(indent + 1));
+                }
+                None => result.push_str(&format!("{}BreakStatement\n", padding)),
+            },
+            ASTNode::ContinueStatement { label, .. } => match label {
+                Some(label) => {
+                    result.push_str(&format!("{}ContinueStatement:\n", padding));
+                    result.push_str(&label.pretty_print(indent + 1));
+                }
+                None => result.push_str(&format!("{}ContinueStatement\n", padding)),
+            },
+            ASTNode::ThrowStatement { argument, .. } => {
+                result.push_str(&format!("{}ThrowStatement:\n", padding));
+                result.push_str(&argument.pretty_print(indent + 1));
+            }
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => {
+                result.push_str(&format!("{}TryStatement:\n", padding));
+                result.push_str(&block.pretty_print(indent + 1));
+                if let Some(handler) = handler {
+                    result.push_str(&handler.pretty_print(indent + 1));
+                }
+                if let Some(finalizer) = finalizer {
+                    result.push_str(&format!("{}Finally:\n", " ".repeat((indent + 1) * 2)));
+                    result.push_str(&finalizer.pretty_print(indent + 2));
+                }
+            }
+            ASTNode::CatchClause { parameter, body, .. } => {
+                result.push_str(&format!("{}CatchClause:\n", padding));
+                if let Some(parameter) = parameter {
+                    result.push_str(&parameter.pretty_print(indent + 1));
+                }
+                result.push_str(&body.pretty_print(indent + 1));
+            }
+            ASTNode::LabeledStatement { label, body, .. } => {
+                result.push_str(&format!("{}LabeledStatement:\n", padding));
+                result.push_str(&label.pretty_print(indent + 1));
+                result.push_str(&body.pretty_print(indent + 1));
+            }
+            ASTNode::WhileStatement { condition, body, .. } => {
+                result.push_str(&format!("{}WhileStatement:\n", padding));
+                result.push_str(&format!("{}Condition:\n", " ".repeat((indent + 1) * 2)));
+                result.push_str(&condition.pretty_print(indent + 2));
+                result.push_str(&format!("{}Body:\n", " ".repeat((indent + 1) * 2)));
+                result.push_str(&body.pretty_print(indent + 2));
+            }
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => {
+                result.push_str(&format!("{}ForStatement:\n", padding));
+                let sections = [("Init", init), ("Condition", condition), ("Update", update)];
+                for (label, section) in sections {
+                    if let Some(section) = section {
+                        result.push_str(&format!("{}{}:\n", " ".repeat((indent + 1) * 2), label));
+                        result.push_str(&section.pretty_print(indent + 2));
+                    }
+                }
+                result.push_str(&format!("{}Body:\n", " ".repeat((indent + 1) * 2)));
+                result.push_str(&body.pretty_print(indent + 2));
+            }
+            ASTNode::AssignmentExpression { left, operator, right, .. } => {
                 result.push_str(&format!("{}AssignmentExpression: {}\n", padding, operator));
                 result.push_str(&left.pretty_print(indent + 1));
                 result.push_str(&right.pretty_print(indent + 1));
             }
-            ASTNode::BinaryExpression { left, operator, right } => {
+            ASTNode::BinaryExpression { left, operator, right, .. } => {
                 result.push_str(&format!("{}BinaryExpression: {}\n", padding, operator));
                 result.push_str(&left.pretty_print(indent + 1));
                 result.push_str(&right.pretty_print(indent + 1));
             }
-            ASTNode::Literal(value) => {
+            ASTNode::LogicalExpression { left, operator, right, .. } => {
+                result.push_str(&format!("{}LogicalExpression: {}\n", padding, operator));
+                result.push_str(&left.pretty_print(indent + 1));
+                result.push_str(&right.pretty_print(indent + 1));
+            }
+            ASTNode::UnaryExpression { operator, argument, .. } => {
+                result.push_str(&format!("{}UnaryExpression: {}\n", padding, operator));
+                result.push_str(&argument.pretty_print(indent + 1));
+            }
+            ASTNode::UpdateExpression { operator, prefix, argument, .. } => {
+                let fixity = if *prefix { "prefix" } else { "postfix" };
+                result.push_str(&format!("{}UpdateExpression: {} ({})\n", padding, operator, fixity));
+                result.push_str(&argument.pretty_print(indent + 1));
+            }
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                result.push_str(&format!("{}CallExpression:\n", padding));
+                result.push_str(&callee.pretty_print(indent + 1));
+                result.push_str(&format!("{}Arguments:\n", " ".repeat((indent + 1) * 2)));
+                for argument in arguments {
+                    result.push_str(&argument.pretty_print(indent + 2));
+                }
+            }
+            ASTNode::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                let access = if *computed { " (computed)" } else { "" };
+                result.push_str(&format!("{}MemberExpression:{}\n", padding, access));
+                result.push_str(&object.pretty_print(indent + 1));
+                result.push_str(&property.pretty_print(indent + 1));
+            }
+            ASTNode::ObjectExpression { properties, .. } => {
+                result.push_str(&format!("{}ObjectExpression:\n", padding));
+                for property in properties {
+                    result.push_str(&property.pretty_print(indent + 1));
+                }
+            }
+            ASTNode::Property {
+                key,
+                value,
+                computed,
+                shorthand,
+                ..
+            } => {
+                let form = match (computed, shorthand) {
+                    (true, _) => " (computed)",
+                    (_, true) => " (shorthand)",
+                    _ => "",
+                };
+                result.push_str(&format!("{}Property:{}\n", padding, form));
+                result.push_str(&key.pretty_print(indent + 1));
+                result.push_str(&value.pretty_print(indent + 1));
+            }
+            ASTNode::Literal { value, .. } => {
                 result.push_str(&format!("{}Literal: {}\n", padding, value));
             }
-            ASTNode::Identifier(name) => {
+            ASTNode::Identifier { name, .. } => {
                 result.push_str(&format!("{}Identifier: {}\n", padding, name));
             }
+            ASTNode::ThisExpression { .. } => {
+                result.push_str(&format!("{}ThisExpression\n", padding));
+            }
         }
 
         result
@@ -228,28 +881,309 @@ impl ASTNode {
 
 #[cfg(test)]
 mod test {
-    use crate::lexical::lexer::Token;
+    use crate::lexical::lexer::Lexer;
     use super::*;
 
+    fn parse(code: &str) -> ASTNode {
+        ASTNode::parse_program(&Lexer::new(code).tokenize())
+    }
+
+    fn parse_expr(code: &str) -> ASTNode {
+        match parse(code) {
+            ASTNode::Program { mut body, .. } => match body.remove(0) {
+                ASTNode::ExpressionStatement { expression, .. } => *expression,
+                other => panic!("Expected expression statement, got {:?}", other),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Compares trees structurally; `pretty_print` leaves spans out.
+    fn assert_same_tree(actual: &ASTNode, expected: &ASTNode) {
+        assert_eq!(actual.pretty_print(0), expected.pretty_print(0));
+    }
+
+    fn ident(name: &str) -> ASTNode {
+        ASTNode::new_identifier(name.into(), Span::default())
+    }
+
+    fn number(value: f64) -> ASTNode {
+        ASTNode::new_literal(Literal::Number(value), Span::default())
+    }
+
+    fn literal_of(code: &str) -> Literal {
+        match parse_expr(code) {
+            ASTNode::Literal { value, .. } => value,
+            other => panic!("Expected literal, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_program() {
-        let tokens = vec![
-            Token::Keyword("let".to_string()),
-            Token::Identifier("x".to_string()),
-            Token::Operator("=".to_string()),
-            Token::Literal("42".to_string()),
-            Token::Delimiter(";".to_string()),
-        ];
+        let tokens = Lexer::new("let x = 42;").tokenize();
 
         let ast = ASTNode::parse_program(&tokens);
-        let expected_ast = ASTNode::Program(vec![
-            ASTNode::VariableDeclaration {
-                identifier: "x".to_string(),
-                value: Box::new(ASTNode::Literal("42".to_string())),
-            },
-        ]);
+        let expected_ast = ASTNode::Program {
+            body: vec![ASTNode::VariableDeclaration {
+                kind: VariableKind::Let,
+                declarations: vec![ASTNode::VariableDeclarator {
+                    identifier: Box::new(ASTNode::Identifier {
+                        name: "x".into(),
+                        span: Span::new(4, 5),
+                    }),
+                    value: Some(Box::new(ASTNode::Literal {
+                        value: Literal::Number(42.0),
+                        span: Span::new(8, 10),
+                    })),
+                    span: Span::new(4, 10),
+                }],
+                span: Span::new(0, 11),
+            }],
+            span: Span::new(0, 11),
+        };
+
+        assert_eq!(ast, expected_ast);
+        assert_eq!(
+            ast.pretty_print(0),
+            "Program:\n  VariableDeclaration: let\n    VariableDeclarator:\n      Identifier: x\n      Literal: 42\n"
+        );
+    }
+
+    #[test]
+    fn test_multiple_declarators() {
+        let ast = parse("var a = 1, b;");
+        assert_eq!(
+            ast.pretty_print(0),
+            "Program:\n  VariableDeclaration: var\n    VariableDeclarator:\n      Identifier: a\n      Literal: 1\n    VariableDeclarator:\n      Identifier: b\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing initializer in const declaration")]
+    fn test_const_requires_initializer() {
+        parse("const a;");
+    }
+
+    #[test]
+    fn test_typed_literals() {
+        assert_eq!(literal_of("'hi'"), Literal::String("hi".encode_utf16().collect()));
+        assert_eq!(literal_of("0x1F"), Literal::Number(31.0));
+        assert_eq!(literal_of("1e3"), Literal::Number(1000.0));
+        assert_eq!(literal_of("true"), Literal::Boolean(true));
+        assert_eq!(literal_of("null"), Literal::Null);
+        assert_eq!(literal_of("12n"), Literal::BigInt("12".to_string()));
+        assert_eq!(literal_of("0n"), Literal::BigInt("0".to_string()));
+        assert_eq!(literal_of("0x1_Fn"), Literal::BigInt("0x1F".to_string()));
+        assert_eq!(
+            literal_of("/a+/g"),
+            Literal::RegExp {
+                pattern: "a+".to_string(),
+                flags: "g".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_binary_precedence() {
+        let expected = ASTNode::new_binary_expression(
+            ident("a"),
+            BinaryOp::Add,
+            ASTNode::new_binary_expression(ident("b"), BinaryOp::Mul, ident("c"), Span::default()),
+            Span::default(),
+        );
+        assert_same_tree(&parse_expr("a + b * c"), &expected);
+    }
+
+    #[test]
+    fn test_exponent_is_right_associative() {
+        let expected = ASTNode::new_binary_expression(
+            ident("a"),
+            BinaryOp::Exp,
+            ASTNode::new_binary_expression(ident("b"), BinaryOp::Exp, ident("c"), Span::default()),
+            Span::default(),
+        );
+        assert_same_tree(&parse_expr("a ** b ** c"), &expected);
+    }
+
+    #[test]
+    fn test_logical_expression_is_separate() {
+        let expected = ASTNode::new_logical_expression(
+            ident("a"),
+            LogicalOp::Or,
+            ASTNode::new_logical_expression(
+                ident("b"),
+                LogicalOp::And,
+                ASTNode::new_binary_expression(ident("c"), BinaryOp::StrictEq, number(1.0), Span::default()),
+                Span::default(),
+            ),
+            Span::default(),
+        );
+        assert_same_tree(&parse_expr("a || b && c === 1"), &expected);
+    }
+
+    #[test]
+    fn test_assignment_and_update() {
+        let expected = ASTNode::new_assignment_expression(
+            ident("x"),
+            AssignOp::AddAssign,
+            ASTNode::new_update_expression(UpdateOp::Increment, false, ident("y"), Span::default()),
+            Span::default(),
+        );
+        assert_same_tree(&parse_expr("x += y++"), &expected);
+
+        let expected = ASTNode::new_unary_expression(
+            UnaryOp::TypeOf,
+            ASTNode::new_update_expression(UpdateOp::Decrement, true, ident("z"), Span::default()),
+            Span::default(),
+        );
+        assert_same_tree(&parse_expr("typeof --z"), &expected);
+    }
+
+    #[test]
+    fn test_parenthesized_expression() {
+        let expected = ASTNode::new_binary_expression(
+            ASTNode::new_binary_expression(ident("a"), BinaryOp::Sub, ident("b"), Span::default()),
+            BinaryOp::Div,
+            number(2.0),
+            Span::default(),
+        );
+        assert_same_tree(&parse_expr("(a - b) / 2"), &expected);
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let ast = parse_expr("a + b * c");
+        assert_eq!(ast.span(), Span::new(0, 9));
+
+        let ASTNode::BinaryExpression { left, right, .. } = ast else {
+            panic!("Expected binary expression");
+        };
+        assert_eq!(left.span(), Span::new(0, 1));
+        assert_eq!(right.span(), Span::new(4, 9));
+
+        assert_eq!(parse_expr("typeof x").span(), Span::new(0, 8));
+        assert_eq!(parse_expr("x++").span(), Span::new(0, 3));
+        assert_eq!(parse_expr("(a - b) / 2").span(), Span::new(0, 11));
+    }
+
+    #[test]
+    fn test_function_and_if_statements() {
+        let ast = parse("function f(a, b) { if (a) { return_ = b; } else b = a; }");
+        assert_eq!(
+            ast.pretty_print(0),
+            "Program:\n  FunctionDeclaration:\n    Identifier: f\n    Parameters:\n      Identifier: a\n      Identifier: b\n    Block:\n      IfStatement:\n        Condition:\n          Identifier: a\n        Then:\n          Block:\n            ExpressionStatement:\n              AssignmentExpression: =\n                Identifier: return_\n                Identifier: b\n        Else:\n          ExpressionStatement:\n            AssignmentExpression: =\n              Identifier: b\n              Identifier: a\n"
+        );
+        assert_eq!(ast.children()[0].span(), Span::new(0, 56));
+    }
+
+    #[test]
+    fn test_try_and_labelled_statements() {
+        let ast = parse("a: try { throw x; } catch (e) { break a; } finally {}");
+        assert_eq!(
+            ast.pretty_print(0),
+            "Program:\n  LabeledStatement:\n    Identifier: a\n    TryStatement:\n      Block:\n        ThrowStatement:\n          Identifier: x\n      CatchClause:\n        Identifier: e\n        Block:\n          BreakStatement:\n            Identifier: a\n      Finally:\n        Block:\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing catch or finally after try")]
+    fn test_try_needs_catch_or_finally() {
+        parse("try {} x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected ','")]
+    fn test_missing_parameter_comma() {
+        parse("function f(a b) {}");
+    }
+
+    #[test]
+    fn test_sloppy_code_allows_strict_only_errors() {
+        let code = "var public = 017 + 08; x = '\\01'; function f() { 'use\\x20strict'; var static = 010; } \
+                    function g() { 'use strict'; } ('use strict'); var implements = 010;";
+        assert!(matches!(parse(code), ASTNode::Program { .. }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Octal literals are not allowed in strict mode.")]
+    fn test_strict_octal_literal() {
+        parse("'use strict'; x = 017;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Decimals with leading zeros are not allowed in strict mode.")]
+    fn test_strict_leading_zero_decimal() {
+        parse("function f() { 'a'; \"use strict\"; return 08; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid or unexpected token at 8..12")]
+    fn test_invalid_binary_literal() {
+        parse("let x = 0b12;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid BigInt literal at 8..12")]
+    fn test_fractional_bigint_literal() {
+        parse("let x = 1.5n;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid BigInt literal at 8..12")]
+    fn test_exponent_bigint_literal() {
+        parse("let x = 1e3n;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid BigInt literal at 8..12")]
+    fn test_legacy_octal_bigint_literal() {
+        parse("let x = 017n;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Undefined Unicode code-point at 4..20")]
+    fn test_code_point_escape_overflow() {
+        parse("x = '\\u{FFFFFFFFFF}';");
+    }
+
+    #[test]
+    #[should_panic(expected = "Octal escape sequences are not allowed in strict mode. at 0..5")]
+    fn test_octal_escape_before_use_strict() {
+        parse("'\\01'; 'use strict';");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected strict mode reserved word at 9..15")]
+    fn test_use_strict_applies_to_function_name() {
+        parse("function static() { 'use strict'; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected strict mode reserved word")]
+    fn test_strict_reserved_reference() {
+        let tokens = Lexer::new("interface;").tokenize();
+        let mut parser = Parser::new(&tokens, TreeBuilder);
+        parser.set_strict(true);
+        parser.parse_program();
+    }
+
+    #[test]
+    #[should_panic(expected = "Strict mode code may not include a with statement")]
+    fn test_strict_with_statement() {
+        parse("'use strict'; with (a) {}");
+    }
+
+    #[test]
+    fn test_spans_map_to_source_locations() {
+        use crate::lexical::span::{Location, SourceFile};
+
+        let code = "let a = 1;\nb = a;";
+        let source = SourceFile::new("test.js", code);
+        let ASTNode::Program { body, .. } = parse(code) else {
+            unreachable!()
+        };
 
-        assert_eq!(format!("{:?}", ast), format!("{:?}", expected_ast));
-        assert_eq!(ast.pretty_print(0), "bla");
+        assert_eq!(source.location(body[1].span().start), Location { line: 2, column: 0 });
+        assert_eq!(source.slice(body[1].span()), "b = a;");
     }
 }
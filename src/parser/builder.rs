@@ -0,0 +1,177 @@
+use crate::lexical::atom::Atom;
+use crate::lexical::span::Span;
+use crate::parser::ast::{ASTNode, Literal, VariableKind};
+use crate::parser::operators::{AssignOp, BinaryOp, LogicalOp, UnaryOp, UpdateOp};
+
+/// Node constructors the parser calls, one per node kind. Implementations
+/// decide how nodes are stored: [`TreeBuilder`] produces the boxed
+/// [`ASTNode`] tree, `AstArena` appends to flat vectors.
+pub trait AstBuilder {
+    type Node;
+
+    fn span_of(&self, node: &Self::Node) -> Span;
+
+    fn program(&mut self, body: Vec<Self::Node>, span: Span) -> Self::Node;
+    fn variable_declaration(&mut self, kind: VariableKind, declarations: Vec<Self::Node>, span: Span) -> Self::Node;
+    fn variable_declarator(&mut self, identifier: Self::Node, value: Option<Self::Node>, span: Span) -> Self::Node;
+    fn function_declaration(
+        &mut self,
+        name: Self::Node,
+        parameters: Vec<Self::Node>,
+        body: Self::Node,
+        span: Span,
+    ) -> Self::Node;
+    fn if_statement(
+        &mut self,
+        condition: Self::Node,
+        body: Self::Node,
+        else_body: Option<Self::Node>,
+        span: Span,
+    ) -> Self::Node;
+    fn block(&mut self, body: Vec<Self::Node>, span: Span) -> Self::Node;
+    fn expression_statement(&mut self, expression: Self::Node, span: Span) -> Self::Node;
+    fn return_statement(&mut self, argument: Option<Self::Node>, span: Span) -> Self::Node;
+    fn break_statement(&mut self, label: Option<Self::Node>, span: Span) -> Self::Node;
+    fn continue_statement(&mut self, label: Option<Self::Node>, span: Span) -> Self::Node;
+    fn throw_statement(&mut self, argument: Self::Node, span: Span) -> Self::Node;
+    fn try_statement(
+        &mut self,
+        block: Self::Node,
+        handler: Option<Self::Node>,
+        finalizer: Option<Self::Node>,
+        span: Span,
+    ) -> Self::Node;
+    fn catch_clause(&mut self, parameter: Option<Self::Node>, body: Self::Node, span: Span) -> Self::Node;
+    fn labeled_statement(&mut self, label: Self::Node, body: Self::Node, span: Span) -> Self::Node;
+    fn while_statement(&mut self, condition: Self::Node, body: Self::Node, span: Span) -> Self::Node;
+    fn for_statement(
+        &mut self,
+        init: Option<Self::Node>,
+        condition: Option<Self::Node>,
+        update: Option<Self::Node>,
+        body: Self::Node,
+        span: Span,
+    ) -> Self::Node;
+    fn assignment_expression(&mut self, left: Self::Node, operator: AssignOp, right: Self::Node, span: Span) -> Self::Node;
+    fn binary_expression(&mut self, left: Self::Node, operator: BinaryOp, right: Self::Node, span: Span) -> Self::Node;
+    fn logical_expression(&mut self, left: Self::Node, operator: LogicalOp, right: Self::Node, span: Span) -> Self::Node;
+    fn unary_expression(&mut self, operator: UnaryOp, argument: Self::Node, span: Span) -> Self::Node;
+    fn update_expression(&mut self, operator: UpdateOp, prefix: bool, argument: Self::Node, span: Span) -> Self::Node;
+    fn call_expression(&mut self, callee: Self::Node, arguments: Vec<Self::Node>, span: Span) -> Self::Node;
+    fn member_expression(&mut self, object: Self::Node, property: Self::Node, computed: bool, span: Span) -> Self::Node;
+    fn object_expression(&mut self, properties: Vec<Self::Node>, span: Span) -> Self::Node;
+    fn property(
+        &mut self,
+        key: Self::Node,
+        value: Self::Node,
+        computed: bool,
+        shorthand: bool,
+        span: Span,
+    ) -> Self::Node;
+    fn literal(&mut self, value: Literal, span: Span) -> Self::Node;
+    fn identifier(&mut self, name: Atom, span: Span) -> Self::Node;
+    fn this_expression(&mut self, span: Span) -> Self::Node;
+}
+
+/// Builds the owned, boxed [`ASTNode`] tree.
+pub struct TreeBuilder;
+
+impl AstBuilder for TreeBuilder {
+    type Node = ASTNode;
+
+    fn span_of(&self, node: &ASTNode) -> Span {
+        node.span()
+    }
+
+    fn program(&mut self, body: Vec<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::Program { body, span }
+    }
+    fn variable_declaration(&mut self, kind: VariableKind, declarations: Vec<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_variable_declaration(kind, declarations, span)
+    }
+    fn variable_declarator(&mut self, identifier: ASTNode, value: Option<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_variable_declarator(identifier, value, span)
+    }
+    fn function_declaration(&mut self, name: ASTNode, parameters: Vec<ASTNode>, body: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_function_declaration(name, parameters, body, span)
+    }
+    fn if_statement(&mut self, condition: ASTNode, body: ASTNode, else_body: Option<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_if_statement(condition, body, else_body, span)
+    }
+    fn block(&mut self, body: Vec<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_block(body, span)
+    }
+    fn expression_statement(&mut self, expression: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_expression_statement(expression, span)
+    }
+    fn return_statement(&mut self, argument: Option<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_return_statement(argument, span)
+    }
+    fn break_statement(&mut self, label: Option<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_break_statement(label, span)
+    }
+    fn continue_statement(&mut self, label: Option<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_continue_statement(label, span)
+    }
+    fn throw_statement(&mut self, argument: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_throw_statement(argument, span)
+    }
+    fn try_statement(&mut self, block: ASTNode, handler: Option<ASTNode>, finalizer: Option<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_try_statement(block, handler, finalizer, span)
+    }
+    fn catch_clause(&mut self, parameter: Option<ASTNode>, body: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_catch_clause(parameter, body, span)
+    }
+    fn labeled_statement(&mut self, label: ASTNode, body: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_labeled_statement(label, body, span)
+    }
+    fn while_statement(&mut self, condition: ASTNode, body: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_while_statement(condition, body, span)
+    }
+    fn for_statement(
+        &mut self,
+        init: Option<ASTNode>,
+        condition: Option<ASTNode>,
+        update: Option<ASTNode>,
+        body: ASTNode,
+        span: Span,
+    ) -> ASTNode {
+        ASTNode::new_for_statement(init, condition, update, body, span)
+    }
+    fn assignment_expression(&mut self, left: ASTNode, operator: AssignOp, right: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_assignment_expression(left, operator, right, span)
+    }
+    fn binary_expression(&mut self, left: ASTNode, operator: BinaryOp, right: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_binary_expression(left, operator, right, span)
+    }
+    fn logical_expression(&mut self, left: ASTNode, operator: LogicalOp, right: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_logical_expression(left, operator, right, span)
+    }
+    fn unary_expression(&mut self, operator: UnaryOp, argument: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_unary_expression(operator, argument, span)
+    }
+    fn update_expression(&mut self, operator: UpdateOp, prefix: bool, argument: ASTNode, span: Span) -> ASTNode {
+        ASTNode::new_update_expression(operator, prefix, argument, span)
+    }
+    fn call_expression(&mut self, callee: ASTNode, arguments: Vec<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_call_expression(callee, arguments, span)
+    }
+    fn member_expression(&mut self, object: ASTNode, property: ASTNode, computed: bool, span: Span) -> ASTNode {
+        ASTNode::new_member_expression(object, property, computed, span)
+    }
+    fn object_expression(&mut self, properties: Vec<ASTNode>, span: Span) -> ASTNode {
+        ASTNode::new_object_expression(properties, span)
+    }
+    fn property(&mut self, key: ASTNode, value: ASTNode, computed: bool, shorthand: bool, span: Span) -> ASTNode {
+        ASTNode::new_property(key, value, computed, shorthand, span)
+    }
+    fn literal(&mut self, value: Literal, span: Span) -> ASTNode {
+        ASTNode::new_literal(value, span)
+    }
+    fn identifier(&mut self, name: Atom, span: Span) -> ASTNode {
+        ASTNode::new_identifier(name, span)
+    }
+    fn this_expression(&mut self, span: Span) -> ASTNode {
+        ASTNode::new_this_expression(span)
+    }
+}
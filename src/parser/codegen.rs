@@ -0,0 +1,877 @@
+use crate::parser::ast::{ASTNode, Literal};
+use crate::parser::numeric::number_to_string;
+use crate::parser::operators::{LogicalOp, UnaryOp, UpdateOp};
+
+/// Precedence levels used when deciding where parentheses are required.
+/// Binary and logical operators use their own `precedence()` in between.
+const ASSIGNMENT: u8 = 2;
+const UNARY: u8 = 14;
+const POSTFIX: u8 = 15;
+const LEFT_HAND_SIDE: u8 = 16;
+const PRIMARY: u8 = 20;
+
+/// Prints an AST back to JavaScript source, adding only the parentheses the
+/// operator precedence requires.
+pub struct CodeGenerator {
+    output: String,
+    indent: usize,
+}
+
+impl Default for CodeGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CodeGenerator {
+    pub fn new() -> Self {
+        CodeGenerator {
+            output: String::new(),
+            indent: 0,
+        }
+    }
+
+    pub fn generate(node: &ASTNode) -> String {
+        let mut generator = CodeGenerator::new();
+        if Self::is_expression(node) {
+            generator.expression(node, 0);
+        } else {
+            generator.statement(node);
+        }
+        generator.output
+    }
+
+    fn is_expression(node: &ASTNode) -> bool {
+        !matches!(
+            node,
+            ASTNode::Program { .. }
+                | ASTNode::VariableDeclaration { .. }
+                | ASTNode::VariableDeclarator { .. }
+                | ASTNode::FunctionDeclaration { .. }
+                | ASTNode::IfStatement { .. }
+                | ASTNode::Block { .. }
+                | ASTNode::ExpressionStatement { .. }
+                | ASTNode::ReturnStatement { .. }
+                | ASTNode::BreakStatement { .. }
+                | ASTNode::ContinueStatement { .. }
+                | ASTNode::ThrowStatement { .. }
+                | ASTNode::TryStatement { .. }
+                | ASTNode::CatchClause { .. }
+                | ASTNode::LabeledStatement { .. }
+                | ASTNode::WhileStatement { .. }
+                | ASTNode::ForStatement { .. }
+        )
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        self.output.push_str(&"  ".repeat(self.indent));
+    }
+
+    /// Prints `nodes` one per line. In a program or function body, whose
+    /// leading string statements are its directive prologue, a string that
+    /// was not a directive keeps its parentheses, and a `"use strict"` that
+    /// was not written plainly is escaped, so the code stays as strict as
+    /// it was.
+    fn statements(&mut self, nodes: &[ASTNode], mut prologue: bool) {
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            match Self::string_statement(node).filter(|_| prologue) {
+                Some((literal, false)) => {
+                    self.expression(literal, PRIMARY + 1);
+                    self.write(";");
+                    prologue = false;
+                }
+                Some((literal, true)) if Self::is_escaped_use_strict(literal) => self.write("\"use\\x20strict\";"),
+                Some(_) => self.statement(node),
+                None => {
+                    prologue = false;
+                    self.statement(node);
+                }
+            }
+        }
+    }
+
+    /// The string literal an expression statement consists of, and whether
+    /// it was written unparenthesised, as a directive is.
+    fn string_statement(node: &ASTNode) -> Option<(&ASTNode, bool)> {
+        match node {
+            ASTNode::ExpressionStatement { expression, span } => match expression.as_ref() {
+                ASTNode::Literal {
+                    value: Literal::String(_),
+                    span: literal_span,
+                } => Some((expression, literal_span.start == span.start)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether `literal` is the text `use strict` but not the 12 characters
+    /// `'use strict'`, so that it makes no `use strict` directive.
+    fn is_escaped_use_strict(literal: &ASTNode) -> bool {
+        match literal {
+            ASTNode::Literal {
+                value: Literal::String(value),
+                span,
+            } => span.len() != 12 && value.iter().copied().eq("use strict".encode_utf16()),
+            _ => false,
+        }
+    }
+
+    fn block(&mut self, body: &[ASTNode], prologue: bool) {
+        if body.is_empty() {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.indent += 1;
+        self.newline();
+        self.statements(body, prologue);
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    fn statement(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::Program { body, .. } => {
+                self.statements(body, true);
+                if !body.is_empty() {
+                    self.write("\n");
+                }
+            }
+            ASTNode::VariableDeclaration { .. } => {
+                self.variable_declaration(node);
+                self.write(";");
+            }
+            ASTNode::VariableDeclarator { identifier, value, .. } => {
+                self.expression(identifier, PRIMARY);
+                if let Some(value) = value {
+                    self.write(" = ");
+                    self.expression(value, ASSIGNMENT);
+                }
+            }
+            ASTNode::FunctionDeclaration { name, parameters, body, .. } => {
+                self.write("function ");
+                self.expression(name, PRIMARY);
+                self.write("(");
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.expression(parameter, ASSIGNMENT);
+                }
+                self.write(") ");
+                match body.as_ref() {
+                    ASTNode::Block { body, .. } => self.block(body, true),
+                    body => self.statement(body),
+                }
+            }
+            ASTNode::IfStatement { condition, body, else_body, .. } => {
+                self.write("if (");
+                self.expression(condition, 0);
+                self.write(") ");
+                // A nested `if` without `else` would otherwise capture our `else`.
+                let dangling = else_body.is_some()
+                    && matches!(body.as_ref(), ASTNode::IfStatement { else_body: None, .. });
+                if dangling {
+                    self.write("{");
+                    self.indent += 1;
+                    self.newline();
+                    self.statement(body);
+                    self.indent -= 1;
+                    self.newline();
+                    self.write("}");
+                } else {
+                    self.statement(body);
+                }
+                if let Some(else_body) = else_body {
+                    self.write(" else ");
+                    self.statement(else_body);
+                }
+            }
+            ASTNode::Block { body, .. } => self.block(body, false),
+            ASTNode::ExpressionStatement { expression, .. } => {
+                // A leading `{` would be read back as a block.
+                let min_precedence = if Self::starts_with_object(expression) { PRIMARY + 1 } else { 0 };
+                self.expression(expression, min_precedence);
+                self.write(";");
+            }
+            ASTNode::ReturnStatement { argument, .. } => {
+                self.write("return");
+                if let Some(argument) = argument {
+                    self.write(" ");
+                    self.expression(argument, 0);
+                }
+                self.write(";");
+            }
+            ASTNode::BreakStatement { label, .. } | ASTNode::ContinueStatement { label, .. } => {
+                self.write(if matches!(node, ASTNode::BreakStatement { .. }) { "break" } else { "continue" });
+                if let Some(label) = label {
+                    self.write(" ");
+                    self.expression(label, PRIMARY);
+                }
+                self.write(";");
+            }
+            ASTNode::ThrowStatement { argument, .. } => {
+                self.write("throw ");
+                self.expression(argument, 0);
+                self.write(";");
+            }
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => {
+                self.write("try ");
+                self.statement(block);
+                if let Some(handler) = handler {
+                    self.write(" ");
+                    self.statement(handler);
+                }
+                if let Some(finalizer) = finalizer {
+                    self.write(" finally ");
+                    self.statement(finalizer);
+                }
+            }
+            ASTNode::CatchClause { parameter, body, .. } => {
+                self.write("catch ");
+                if let Some(parameter) = parameter {
+                    self.write("(");
+                    self.expression(parameter, PRIMARY);
+                    self.write(") ");
+                }
+                self.statement(body);
+            }
+            ASTNode::LabeledStatement { label, body, .. } => {
+                self.expression(label, PRIMARY);
+                self.write(": ");
+                self.statement(body);
+            }
+            ASTNode::WhileStatement { condition, body, .. } => {
+                self.write("while (");
+                self.expression(condition, 0);
+                self.write(") ");
+                self.statement(body);
+            }
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => {
+                self.write("for (");
+                if let Some(init) = init {
+                    self.expression(init, 0);
+                }
+                self.write(";");
+                if let Some(condition) = condition {
+                    self.write(" ");
+                    self.expression(condition, 0);
+                }
+                self.write(";");
+                if let Some(update) = update {
+                    self.write(" ");
+                    self.expression(update, 0);
+                }
+                self.write(") ");
+                self.statement(body);
+            }
+            _ => {
+                self.expression(node, 0);
+                self.write(";");
+            }
+        }
+    }
+
+    fn variable_declaration(&mut self, node: &ASTNode) {
+        if let ASTNode::VariableDeclaration { kind, declarations, .. } = node {
+            self.write(kind.as_str());
+            self.write(" ");
+            for (i, declaration) in declarations.iter().enumerate() {
+                if i > 0 {
+                    self.write(", ");
+                }
+                self.statement(declaration);
+            }
+        }
+    }
+
+    /// Precedence of `node` as an operand; lower binds looser.
+    fn precedence(node: &ASTNode) -> u8 {
+        match node {
+            ASTNode::AssignmentExpression { .. } => ASSIGNMENT,
+            ASTNode::LogicalExpression { operator, .. } => operator.precedence(),
+            ASTNode::BinaryExpression { operator, .. } => operator.precedence(),
+            ASTNode::UnaryExpression { .. } => UNARY,
+            ASTNode::UpdateExpression { prefix: true, .. } => UNARY,
+            ASTNode::UpdateExpression { prefix: false, .. } => POSTFIX,
+            ASTNode::CallExpression { .. } | ASTNode::MemberExpression { .. } => LEFT_HAND_SIDE,
+            ASTNode::Literal {
+                value: Literal::Number(value),
+                ..
+            } if value.is_sign_negative() && !value.is_nan() => UNARY,
+            _ => PRIMARY,
+        }
+    }
+
+    /// Prints `node`, parenthesised if it binds looser than `min_precedence`.
+    fn expression(&mut self, node: &ASTNode, min_precedence: u8) {
+        let wrap = Self::precedence(node) < min_precedence;
+        if wrap {
+            self.write("(");
+        }
+
+        match node {
+            ASTNode::AssignmentExpression { left, operator, right, .. } => {
+                self.expression(left, LEFT_HAND_SIDE);
+                self.write(&format!(" {} ", operator));
+                self.expression(right, ASSIGNMENT);
+            }
+            ASTNode::BinaryExpression { left, operator, right, .. } => {
+                let precedence = operator.precedence();
+                let (left_min, right_min) = if operator.is_right_associative() {
+                    // `-a ** b` is a syntax error, so unary operands need parentheses.
+                    (precedence.max(UNARY) + 1, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                self.expression(left, left_min);
+                self.write(&format!(" {} ", operator));
+                self.expression(right, right_min);
+            }
+            ASTNode::LogicalExpression { left, operator, right, .. } => {
+                let precedence = operator.precedence();
+                let left_min = Self::logical_operand_min(*operator, left, precedence);
+                let right_min = Self::logical_operand_min(*operator, right, precedence + 1);
+                self.expression(left, left_min);
+                self.write(&format!(" {} ", operator));
+                self.expression(right, right_min);
+            }
+            ASTNode::UnaryExpression { operator, argument, .. } => {
+                self.write(operator.as_str());
+                if operator.is_keyword() || Self::needs_sign_space(*operator, argument) {
+                    self.write(" ");
+                }
+                self.expression(argument, UNARY);
+            }
+            ASTNode::UpdateExpression {
+                operator,
+                prefix: true,
+                argument,
+                ..
+            } => {
+                self.write(operator.as_str());
+                self.expression(argument, LEFT_HAND_SIDE);
+            }
+            ASTNode::UpdateExpression { operator, argument, .. } => {
+                self.expression(argument, LEFT_HAND_SIDE);
+                self.write(operator.as_str());
+            }
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                self.expression(callee, LEFT_HAND_SIDE);
+                self.write("(");
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.expression(argument, ASSIGNMENT);
+                }
+                self.write(")");
+            }
+            ASTNode::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                // `1.x` would lex as the number `1.` followed by `x`.
+                let object_min = match object.as_ref() {
+                    ASTNode::Literal {
+                        value: Literal::Number(_),
+                        ..
+                    } if !computed => PRIMARY + 1,
+                    _ => LEFT_HAND_SIDE,
+                };
+                self.expression(object, object_min);
+                if *computed {
+                    self.write("[");
+                    self.expression(property, 0);
+                    self.write("]");
+                } else {
+                    self.write(".");
+                    self.expression(property, PRIMARY);
+                }
+            }
+            ASTNode::ObjectExpression { properties, .. } => {
+                self.write("{");
+                for (i, property) in properties.iter().enumerate() {
+                    self.write(if i > 0 { ", " } else { " " });
+                    self.expression(property, 0);
+                }
+                self.write(if properties.is_empty() { "}" } else { " }" });
+            }
+            ASTNode::Property {
+                key,
+                value,
+                computed,
+                shorthand,
+                ..
+            } => {
+                if *computed {
+                    self.write("[");
+                    self.expression(key, ASSIGNMENT);
+                    self.write("]");
+                } else {
+                    self.expression(key, PRIMARY);
+                }
+                if !shorthand {
+                    self.write(": ");
+                    self.expression(value, ASSIGNMENT);
+                }
+            }
+            ASTNode::Literal { value, .. } => self.literal(value),
+            ASTNode::Identifier { name, .. } => self.write(name),
+            ASTNode::ThisExpression { .. } => self.write("this"),
+            ASTNode::VariableDeclaration { .. } => self.variable_declaration(node),
+            _ => self.statement(node),
+        }
+
+        if wrap {
+            self.write(")");
+        }
+    }
+
+    /// Whether the printed form of `node` begins with an object literal.
+    fn starts_with_object(node: &ASTNode) -> bool {
+        match node {
+            ASTNode::ObjectExpression { .. } => true,
+            ASTNode::AssignmentExpression { left, .. }
+            | ASTNode::BinaryExpression { left, .. }
+            | ASTNode::LogicalExpression { left, .. } => Self::starts_with_object(left),
+            ASTNode::UpdateExpression {
+                prefix: false, argument, ..
+            } => Self::starts_with_object(argument),
+            ASTNode::CallExpression { callee: object, .. } | ASTNode::MemberExpression { object, .. } => {
+                Self::starts_with_object(object)
+            }
+            _ => false,
+        }
+    }
+
+    /// `??` cannot be mixed with `&&`/`||` without parentheses.
+    fn logical_operand_min(operator: LogicalOp, operand: &ASTNode, min: u8) -> u8 {
+        match operand {
+            ASTNode::LogicalExpression { operator: inner, .. }
+                if (operator == LogicalOp::NullishCoalescing) != (*inner == LogicalOp::NullishCoalescing) =>
+            {
+                PRIMARY
+            }
+            _ => min,
+        }
+    }
+
+    /// Whether `-` / `+` must be separated from an operand that starts with
+    /// the same sign, so `-(-x)` prints as `- -x` rather than `--x`.
+    fn needs_sign_space(operator: UnaryOp, argument: &ASTNode) -> bool {
+        let sign = match operator {
+            UnaryOp::Minus => '-',
+            UnaryOp::Plus => '+',
+            _ => return false,
+        };
+        match argument {
+            ASTNode::UnaryExpression { operator: inner, .. } => inner.as_str().starts_with(sign),
+            ASTNode::UpdateExpression {
+                operator: inner,
+                prefix: true,
+                ..
+            } => match inner {
+                UpdateOp::Increment => sign == '+',
+                UpdateOp::Decrement => sign == '-',
+            },
+            ASTNode::Literal {
+                value: Literal::Number(value),
+                ..
+            } => sign == '-' && value.is_sign_negative(),
+            _ => false,
+        }
+    }
+
+    fn literal(&mut self, value: &Literal) {
+        match value {
+            Literal::Number(number) => {
+                let text = number_to_string(*number);
+                self.write(&text);
+            }
+            Literal::String(text) => {
+                let quoted = quote_code_units(text);
+                self.write(&quoted);
+            }
+            Literal::Boolean(true) => self.write("true"),
+            Literal::Boolean(false) => self.write("false"),
+            Literal::Null => self.write("null"),
+            Literal::BigInt(digits) => {
+                self.write(digits);
+                self.write("n");
+            }
+            Literal::RegExp { pattern, flags } => {
+                self.write("/");
+                self.write(pattern);
+                self.write("/");
+                self.write(flags);
+            }
+        }
+    }
+}
+
+/// Quotes `text` as a JavaScript string literal, picking whichever quote
+/// character needs fewer escapes.
+pub fn quote_string(text: &str) -> String {
+    quote_code_units(&text.encode_utf16().collect::<Vec<_>>())
+}
+
+/// [`quote_string`] for a string of UTF-16 code units, writing lone
+/// surrogates as `\u` escapes.
+pub fn quote_code_units(units: &[u16]) -> String {
+    let chars: Vec<_> = char::decode_utf16(units.iter().copied()).collect();
+    let doubles = chars.iter().filter(|ch| matches!(ch, Ok('"'))).count();
+    let singles = chars.iter().filter(|ch| matches!(ch, Ok('\''))).count();
+    let quote = if doubles > singles { '\'' } else { '"' };
+
+    let mut out = String::new();
+    out.push(quote);
+    let mut chars = chars.into_iter().peekable();
+    while let Some(ch) = chars.next() {
+        let ch = match ch {
+            Ok(ch) => ch,
+            Err(error) => {
+                out.push_str(&format!("\\u{:04X}", error.unpaired_surrogate()));
+                continue;
+            }
+        };
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0B}' => out.push_str("\\v"),
+            '\u{0C}' => out.push_str("\\f"),
+            '\0' => {
+                // `\0` followed by a digit would read as an octal escape.
+                if chars.peek().is_some_and(|next| matches!(next, Ok(next) if next.is_ascii_digit())) {
+                    out.push_str("\\x00");
+                } else {
+                    out.push_str("\\0");
+                }
+            }
+            '\u{2028}' => out.push_str("\\u2028"),
+            '\u{2029}' => out.push_str("\\u2029"),
+            ch if ch == quote => {
+                out.push('\\');
+                out.push(ch);
+            }
+            ch if (ch as u32) < 0x20 || ch as u32 == 0x7F => out.push_str(&format!("\\x{:02X}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+impl ASTNode {
+    /// JavaScript source for this node; see [`CodeGenerator`].
+    pub fn to_source(&self) -> String {
+        CodeGenerator::generate(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+    use crate::lexical::span::Span;
+    use crate::parser::operators::{AssignOp, BinaryOp};
+    use crate::semantic::strict::body_has_use_strict;
+
+    const BINARY_OPERATORS: [BinaryOp; 22] = [
+        BinaryOp::Add,
+        BinaryOp::Sub,
+        BinaryOp::Mul,
+        BinaryOp::Div,
+        BinaryOp::Mod,
+        BinaryOp::Exp,
+        BinaryOp::Eq,
+        BinaryOp::NotEq,
+        BinaryOp::StrictEq,
+        BinaryOp::StrictNotEq,
+        BinaryOp::Lt,
+        BinaryOp::LtEq,
+        BinaryOp::Gt,
+        BinaryOp::GtEq,
+        BinaryOp::BitAnd,
+        BinaryOp::BitOr,
+        BinaryOp::BitXor,
+        BinaryOp::Shl,
+        BinaryOp::Shr,
+        BinaryOp::UShr,
+        BinaryOp::In,
+        BinaryOp::InstanceOf,
+    ];
+
+    fn parse(code: &str) -> ASTNode {
+        ASTNode::parse_program(&Lexer::new(code).tokenize())
+    }
+
+    fn assert_round_trip(ast: &ASTNode) {
+        let printed = ast.to_source();
+        let reparsed = parse(&printed);
+        assert_eq!(reparsed.without_spans(), ast.without_spans(), "printed as:\n{}", printed);
+        assert_eq!(reparsed.to_source(), printed, "printing is not stable");
+    }
+
+    const CORPUS: &[&str] = &[
+        "let x = 42;",
+        "var a = 1, b, c = a;",
+        "const s = 'it\\'s';",
+        "x = 'tab\\tnew\\nline \\u2028 \\x00 \\u{1F600} \"q\"';",
+        "a + b * c - d / e % f;",
+        "(a + b) * c;",
+        "a - (b - c);",
+        "a ** b ** c;",
+        "(a ** b) ** c;",
+        "(-a) ** b;",
+        "a ** -b;",
+        "a || b && c;",
+        "(a || b) && c;",
+        "a ?? (b || c);",
+        "(a && b) ?? c;",
+        "a = b = c;",
+        "x = typeof y === 'undefined';",
+        "x = !a instanceof B;",
+        "x = !(a instanceof B);",
+        "x = - -y;",
+        "x = - --y;",
+        "x = +(+y);",
+        "x = a++ + ++b;",
+        "x = a-- - --b;",
+        "delete x;\nvoid 0;",
+        "x = 1e21 + 1.5e-7 + 0.1 + 0xff + 017 + 10n;",
+        "x = /ab+c/gi;",
+        "x = a < b == c > d;",
+        "x = a << b >>> c >> d;",
+        "x = a & b | c ^ d;",
+        "x = 'a' in o;",
+        "x = (a = 1) + (b += 2);",
+        "x = null ?? undefined;",
+        "x &&= y ||= z ??= w;",
+        "x = true;",
+        "this.x = this;",
+        "function f(a, b) {\n  let c = a + b;\n}",
+        "if (a) {\n  b;\n} else if (c) d; else {\n  e;\n}",
+        "{\n  let x = 1;\n  {}\n}",
+        "function f(n) {\n  while (n) {\n    if (n > 10) break; else continue;\n  }\n  return;\n}",
+        "for (let i = 0, j; i < 10; i++) x += i;",
+        "for (;;) {}",
+        "for (i = 0; ; ) return i;",
+        "console.log(a.b[c](1, d = 2), f()());",
+        "(1).toString();",
+        "({}).x;",
+        "({ a: 1, b, [c + 1]: { 'd': 2, 3: e }, if: f });",
+        "x = { __proto__: null };",
+        "(a + b).c;",
+        "outer: for (;;) {\n  inner: while (x) {\n    if (y) break outer; else continue inner;\n  }\n}",
+        "try {\n  throw a + b;\n} catch (e) {\n  x = e;\n} finally {\n  y();\n}",
+        "try {} catch {}\ntry {} finally {}",
+        "block: {\n  break block;\n}",
+    ];
+
+    #[test]
+    fn test_corpus_round_trips() {
+        for snippet in CORPUS {
+            assert_round_trip(&parse(snippet));
+        }
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        let cases = [
+            ("(a + b) + c;", "a + b + c;\n"),
+            ("a + (b + c);", "a + (b + c);\n"),
+            ("(a * b) + c;", "a * b + c;\n"),
+            ("((a));", "a;\n"),
+            ("(a ?? b) ?? c;", "a ?? b ?? c;\n"),
+            ("a ?? (b && c);", "a ?? (b && c);\n"),
+            ("x = (y = z);", "x = y = z;\n"),
+            ("(x = y) = z;", "(x = y) = z;\n"),
+            ("-(a ** b);", "-(a ** b);\n"),
+            ("-(-a);", "- -a;\n"),
+            ("typeof (a + b);", "typeof (a + b);\n"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse(input).to_source(), expected, "printing {}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_quoting() {
+        assert_eq!(quote_string("plain"), "\"plain\"");
+        assert_eq!(quote_string("say \"hi\""), "'say \"hi\"'");
+        assert_eq!(quote_string("it's \"x\" \"y\""), "'it\\'s \"x\" \"y\"'");
+        assert_eq!(quote_string("\0 \u{0}1"), "\"\\0 \\x001\"");
+        assert_eq!(quote_string("\u{1}"), "\"\\x01\"");
+        assert_eq!(quote_code_units(&[0xD83D, 0x61, 0xD83D, 0xDE00]), "\"\\uD83Da\u{1F600}\"");
+    }
+
+    #[test]
+    fn test_directive_prologues_keep_their_strictness() {
+        let cases = [
+            ("('use strict'); x;", "(\"use strict\");\nx;\n"),
+            ("'use strict'; x;", "\"use strict\";\nx;\n"),
+            ("'a'; ('use strict'); 'use strict';", "\"a\";\n(\"use strict\");\n\"use strict\";\n"),
+            ("'use\\x20strict'; x;", "\"use\\x20strict\";\nx;\n"),
+            (
+                "function f() { 'a'; ('use strict'); }",
+                "function f() {\n  \"a\";\n  (\"use strict\");\n}\n",
+            ),
+        ];
+        for (input, expected) in cases {
+            let program = parse(input);
+            assert_eq!(program.to_source(), expected, "printing {}", input);
+            assert_round_trip(&program);
+            let (ASTNode::Program { body, .. }, ASTNode::Program { body: reparsed, .. }) = (&program, &parse(expected))
+            else {
+                unreachable!()
+            };
+            assert_eq!(body_has_use_strict(reparsed), body_has_use_strict(body), "strictness of {}", input);
+        }
+
+        // The body of a function stays sloppy too.
+        let printed = parse("function f() { ('use strict'); }").to_source();
+        let ASTNode::Program { body, .. } = parse(&printed) else { unreachable!() };
+        let ASTNode::FunctionDeclaration { body, .. } = &body[0] else { unreachable!() };
+        let ASTNode::Block { body, .. } = body.as_ref() else { unreachable!() };
+        assert!(!body_has_use_strict(body));
+    }
+
+    #[test]
+    fn test_statements() {
+        let span = Span::default();
+        let ident = |name: &str| ASTNode::new_identifier(name.into(), span);
+        let call = |name: &str| ASTNode::new_expression_statement(ident(name), span);
+
+        let function = ASTNode::new_function_declaration(
+            ident("f"),
+            vec![ident("a"), ident("b")],
+            ASTNode::new_block(
+                vec![ASTNode::new_if_statement(
+                    ident("a"),
+                    ASTNode::new_if_statement(ident("b"), call("x"), None, span),
+                    Some(ASTNode::new_block(vec![call("y")], span)),
+                    span,
+                )],
+                span,
+            ),
+            span,
+        );
+
+        assert_eq!(
+            function.to_source(),
+            "function f(a, b) {\n  if (a) {\n    if (b) x;\n  } else {\n    y;\n  }\n}"
+        );
+    }
+
+    /// Small deterministic xorshift generator, so failures are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn random_expression(rng: &mut Rng, depth: usize) -> ASTNode {
+        let span = Span::default();
+        let leaf = depth == 0 || rng.below(4) == 0;
+        if leaf {
+            return match rng.below(6) {
+                0 => ASTNode::new_identifier(["a", "b", "c"][rng.below(3)].into(), span),
+                1 => {
+                    let numbers = [0.0, 1.0, 0.5, 1e21, 1.5e-7, 123456789.0, 2.0f64.powi(60)];
+                    ASTNode::new_literal(Literal::Number(numbers[rng.below(numbers.len())]), span)
+                }
+                2 => {
+                    let strings = ["", "a'b", "\"", "\\", "\n\t", "\u{2028}", "\0", "é😀"];
+                    ASTNode::new_literal(Literal::String(strings[rng.below(strings.len())].encode_utf16().collect()), span)
+                }
+                3 => ASTNode::new_literal(Literal::Boolean(rng.below(2) == 0), span),
+                4 => ASTNode::new_literal(Literal::Null, span),
+                _ => ASTNode::new_literal(Literal::BigInt("7".to_string()), span),
+            };
+        }
+
+        let child = |rng: &mut Rng| random_expression(rng, depth - 1);
+        match rng.below(6) {
+            0 | 1 => {
+                let operator = BINARY_OPERATORS[rng.below(BINARY_OPERATORS.len())];
+                ASTNode::new_binary_expression(child(rng), operator, child(rng), span)
+            }
+            2 => {
+                let operators = [LogicalOp::And, LogicalOp::Or, LogicalOp::NullishCoalescing];
+                let operator = operators[rng.below(3)];
+                ASTNode::new_logical_expression(child(rng), operator, child(rng), span)
+            }
+            3 => {
+                let operators = [
+                    UnaryOp::Minus,
+                    UnaryOp::Plus,
+                    UnaryOp::Not,
+                    UnaryOp::BitNot,
+                    UnaryOp::TypeOf,
+                    UnaryOp::Void,
+                    UnaryOp::Delete,
+                ];
+                ASTNode::new_unary_expression(operators[rng.below(operators.len())], child(rng), span)
+            }
+            4 => {
+                let operator = [UpdateOp::Increment, UpdateOp::Decrement][rng.below(2)];
+                ASTNode::new_update_expression(operator, rng.below(2) == 0, child(rng), span)
+            }
+            _ => {
+                let operators = [AssignOp::Assign, AssignOp::AddAssign, AssignOp::ExpAssign, AssignOp::NullishAssign];
+                let operator = operators[rng.below(operators.len())];
+                ASTNode::new_assignment_expression(child(rng), operator, child(rng), span)
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_expressions_round_trip() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        for _ in 0..2000 {
+            let expression = random_expression(&mut rng, 5);
+            let program = ASTNode::Program {
+                body: vec![ASTNode::new_expression_statement(expression, Span::default())],
+                span: Span::default(),
+            };
+            assert_round_trip(&program);
+        }
+    }
+}
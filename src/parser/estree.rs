@@ -0,0 +1,635 @@
+use crate::lexical::span::{Location, SourceFile, Span};
+use crate::parser::ast::{ASTNode, Literal, VariableKind};
+use crate::parser::json::Json;
+use crate::parser::operators::{AssignOp, BinaryOp, LogicalOp, UnaryOp, UpdateOp};
+use std::fmt;
+
+/// Error raised when a JSON document is not a tree rujs can represent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstreeError {
+    pub message: String,
+}
+
+impl fmt::Display for EstreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for EstreeError {}
+
+fn error<T>(message: String) -> Result<T, EstreeError> {
+    Err(EstreeError { message })
+}
+
+fn position(location: Location) -> Json {
+    Json::object(vec![
+        ("line", Json::Number(location.line as f64)),
+        ("column", Json::Number(location.column as f64)),
+    ])
+}
+
+/// Builds `{type, start, end, loc, ...fields}` in the key order acorn uses.
+fn node(kind: &str, span: Span, source: &SourceFile, fields: Vec<(&str, Json)>) -> Json {
+    let mut entries = vec![
+        ("type", Json::String(kind.to_string())),
+        ("start", Json::Number(span.start as f64)),
+        ("end", Json::Number(span.end as f64)),
+        (
+            "loc",
+            Json::object(vec![
+                ("start", position(source.location(span.start))),
+                ("end", position(source.location(span.end))),
+            ]),
+        ),
+    ];
+    entries.extend(fields);
+    Json::object(entries)
+}
+
+fn nodes(items: &[ASTNode], source: &SourceFile) -> Json {
+    Json::Array(items.iter().map(|item| item.to_estree(source)).collect())
+}
+
+fn optional(item: &Option<Box<ASTNode>>, source: &SourceFile) -> Json {
+    item.as_ref().map_or(Json::Null, |item| item.to_estree(source))
+}
+
+fn string(value: &str) -> Json {
+    Json::String(value.to_string())
+}
+
+impl ASTNode {
+    /// Serialises the tree as ESTree JSON with `start`/`end` offsets and
+    /// line/column `loc` information resolved against `source`.
+    pub fn to_estree(&self, source: &SourceFile) -> Json {
+        let span = self.span();
+        match self {
+            ASTNode::Program { body, .. } => node(
+                "Program",
+                span,
+                source,
+                vec![("body", nodes(body, source)), ("sourceType", string("script"))],
+            ),
+            ASTNode::VariableDeclaration { kind, declarations, .. } => node(
+                "VariableDeclaration",
+                span,
+                source,
+                vec![("declarations", nodes(declarations, source)), ("kind", string(kind.as_str()))],
+            ),
+            ASTNode::VariableDeclarator { identifier, value, .. } => node(
+                "VariableDeclarator",
+                span,
+                source,
+                vec![("id", identifier.to_estree(source)), ("init", optional(value, source))],
+            ),
+            ASTNode::FunctionDeclaration { name, parameters, body, .. } => node(
+                "FunctionDeclaration",
+                span,
+                source,
+                vec![
+                    ("id", name.to_estree(source)),
+                    ("expression", Json::Bool(false)),
+                    ("generator", Json::Bool(false)),
+                    ("async", Json::Bool(false)),
+                    ("params", nodes(parameters, source)),
+                    ("body", body.to_estree(source)),
+                ],
+            ),
+            ASTNode::IfStatement { condition, body, else_body, .. } => node(
+                "IfStatement",
+                span,
+                source,
+                vec![
+                    ("test", condition.to_estree(source)),
+                    ("consequent", body.to_estree(source)),
+                    ("alternate", optional(else_body, source)),
+                ],
+            ),
+            ASTNode::Block { body, .. } => node("BlockStatement", span, source, vec![("body", nodes(body, source))]),
+            ASTNode::ExpressionStatement { expression, .. } => node(
+                "ExpressionStatement",
+                span,
+                source,
+                vec![("expression", expression.to_estree(source))],
+            ),
+            ASTNode::ReturnStatement { argument, .. } => {
+                node("ReturnStatement", span, source, vec![("argument", optional(argument, source))])
+            }
+            ASTNode::BreakStatement { label, .. } => node("BreakStatement", span, source, vec![("label", optional(label, source))]),
+            ASTNode::ContinueStatement { label, .. } => {
+                node("ContinueStatement", span, source, vec![("label", optional(label, source))])
+            }
+            ASTNode::ThrowStatement { argument, .. } => {
+                node("ThrowStatement", span, source, vec![("argument", argument.to_estree(source))])
+            }
+            ASTNode::TryStatement {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => node(
+                "TryStatement",
+                span,
+                source,
+                vec![
+                    ("block", block.to_estree(source)),
+                    ("handler", optional(handler, source)),
+                    ("finalizer", optional(finalizer, source)),
+                ],
+            ),
+            ASTNode::CatchClause { parameter, body, .. } => node(
+                "CatchClause",
+                span,
+                source,
+                vec![("param", optional(parameter, source)), ("body", body.to_estree(source))],
+            ),
+            ASTNode::LabeledStatement { label, body, .. } => node(
+                "LabeledStatement",
+                span,
+                source,
+                vec![("label", label.to_estree(source)), ("body", body.to_estree(source))],
+            ),
+            ASTNode::WhileStatement { condition, body, .. } => node(
+                "WhileStatement",
+                span,
+                source,
+                vec![("test", condition.to_estree(source)), ("body", body.to_estree(source))],
+            ),
+            ASTNode::ForStatement {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => node(
+                "ForStatement",
+                span,
+                source,
+                vec![
+                    ("init", optional(init, source)),
+                    ("test", optional(condition, source)),
+                    ("update", optional(update, source)),
+                    ("body", body.to_estree(source)),
+                ],
+            ),
+            ASTNode::AssignmentExpression { left, operator, right, .. } => node(
+                "AssignmentExpression",
+                span,
+                source,
+                vec![
+                    ("operator", string(operator.as_str())),
+                    ("left", left.to_estree(source)),
+                    ("right", right.to_estree(source)),
+                ],
+            ),
+            ASTNode::BinaryExpression { left, operator, right, .. } => node(
+                "BinaryExpression",
+                span,
+                source,
+                vec![
+                    ("left", left.to_estree(source)),
+                    ("operator", string(operator.as_str())),
+                    ("right", right.to_estree(source)),
+                ],
+            ),
+            ASTNode::LogicalExpression { left, operator, right, .. } => node(
+                "LogicalExpression",
+                span,
+                source,
+                vec![
+                    ("left", left.to_estree(source)),
+                    ("operator", string(operator.as_str())),
+                    ("right", right.to_estree(source)),
+                ],
+            ),
+            ASTNode::UnaryExpression { operator, argument, .. } => node(
+                "UnaryExpression",
+                span,
+                source,
+                vec![
+                    ("operator", string(operator.as_str())),
+                    ("prefix", Json::Bool(true)),
+                    ("argument", argument.to_estree(source)),
+                ],
+            ),
+            ASTNode::UpdateExpression { operator, prefix, argument, .. } => node(
+                "UpdateExpression",
+                span,
+                source,
+                vec![
+                    ("operator", string(operator.as_str())),
+                    ("prefix", Json::Bool(*prefix)),
+                    ("argument", argument.to_estree(source)),
+                ],
+            ),
+            ASTNode::CallExpression { callee, arguments, .. } => node(
+                "CallExpression",
+                span,
+                source,
+                vec![
+                    ("callee", callee.to_estree(source)),
+                    ("arguments", nodes(arguments, source)),
+                    ("optional", Json::Bool(false)),
+                ],
+            ),
+            ASTNode::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            } => node(
+                "MemberExpression",
+                span,
+                source,
+                vec![
+                    ("object", object.to_estree(source)),
+                    ("property", property.to_estree(source)),
+                    ("computed", Json::Bool(*computed)),
+                    ("optional", Json::Bool(false)),
+                ],
+            ),
+            ASTNode::ObjectExpression { properties, .. } => node(
+                "ObjectExpression",
+                span,
+                source,
+                vec![("properties", nodes(properties, source))],
+            ),
+            ASTNode::Property {
+                key,
+                value,
+                computed,
+                shorthand,
+                ..
+            } => node(
+                "Property",
+                span,
+                source,
+                vec![
+                    ("method", Json::Bool(false)),
+                    ("shorthand", Json::Bool(*shorthand)),
+                    ("computed", Json::Bool(*computed)),
+                    ("key", key.to_estree(source)),
+                    ("value", value.to_estree(source)),
+                    ("kind", string("init")),
+                ],
+            ),
+            ASTNode::Literal { value, .. } => {
+                let raw = ("raw", Json::String(source.slice(span)));
+                let fields = match value {
+                    Literal::Number(number) => vec![("value", Json::Number(*number)), raw],
+                    // Lone surrogates are written as `\uD800`-style escapes.
+                    Literal::String(text) => vec![("value", Json::from_utf16(text)), raw],
+                    Literal::Boolean(boolean) => vec![("value", Json::Bool(*boolean)), raw],
+                    Literal::Null => vec![("value", Json::Null), raw],
+                    Literal::BigInt(digits) => vec![("value", Json::Null), raw, ("bigint", string(digits))],
+                    Literal::RegExp { pattern, flags } => vec![
+                        ("value", Json::Null),
+                        raw,
+                        (
+                            "regex",
+                            Json::object(vec![("pattern", string(pattern)), ("flags", string(flags))]),
+                        ),
+                    ],
+                };
+                node("Literal", span, source, fields)
+            }
+            ASTNode::Identifier { name, .. } => node("Identifier", span, source, vec![("name", string(name))]),
+            ASTNode::ThisExpression { .. } => node("ThisExpression", span, source, vec![]),
+        }
+    }
+
+    /// Rebuilds a tree from ESTree JSON, such as the output of `to_estree`
+    /// or of another ESTree-compatible parser.
+    pub fn from_estree(json: &Json) -> Result<ASTNode, EstreeError> {
+        let kind = json
+            .get("type")
+            .and_then(Json::as_str)
+            .ok_or_else(|| EstreeError {
+                message: format!("Node without a type: {}", json),
+            })?;
+        let span = Span::new(offset(json, "start")?, offset(json, "end")?);
+
+        let result = match kind {
+            "Program" => ASTNode::Program {
+                body: list(json, "body")?,
+                span,
+            },
+            "VariableDeclaration" => {
+                let keyword = text(json, "kind")?;
+                ASTNode::VariableDeclaration {
+                    kind: VariableKind::from_keyword(keyword).ok_or_else(|| EstreeError {
+                        message: format!("Unknown declaration kind '{}'", keyword),
+                    })?,
+                    declarations: list(json, "declarations")?,
+                    span,
+                }
+            }
+            "VariableDeclarator" => ASTNode::VariableDeclarator {
+                identifier: child(json, "id")?,
+                value: optional_child(json, "init")?,
+                span,
+            },
+            "FunctionDeclaration" => ASTNode::FunctionDeclaration {
+                name: child(json, "id")?,
+                parameters: list(json, "params")?,
+                body: child(json, "body")?,
+                span,
+            },
+            "IfStatement" => ASTNode::IfStatement {
+                condition: child(json, "test")?,
+                body: child(json, "consequent")?,
+                else_body: optional_child(json, "alternate")?,
+                span,
+            },
+            "BlockStatement" => ASTNode::Block {
+                body: list(json, "body")?,
+                span,
+            },
+            "ExpressionStatement" => ASTNode::ExpressionStatement {
+                expression: child(json, "expression")?,
+                span,
+            },
+            "ReturnStatement" => ASTNode::ReturnStatement {
+                argument: optional_child(json, "argument")?,
+                span,
+            },
+            "BreakStatement" => ASTNode::BreakStatement {
+                label: optional_child(json, "label")?,
+                span,
+            },
+            "ContinueStatement" => ASTNode::ContinueStatement {
+                label: optional_child(json, "label")?,
+                span,
+            },
+            "ThrowStatement" => ASTNode::ThrowStatement {
+                argument: child(json, "argument")?,
+                span,
+            },
+            "TryStatement" => ASTNode::TryStatement {
+                block: child(json, "block")?,
+                handler: optional_child(json, "handler")?,
+                finalizer: optional_child(json, "finalizer")?,
+                span,
+            },
+            "CatchClause" => ASTNode::CatchClause {
+                parameter: optional_child(json, "param")?,
+                body: child(json, "body")?,
+                span,
+            },
+            "LabeledStatement" => ASTNode::LabeledStatement {
+                label: child(json, "label")?,
+                body: child(json, "body")?,
+                span,
+            },
+            "WhileStatement" => ASTNode::WhileStatement {
+                condition: child(json, "test")?,
+                body: child(json, "body")?,
+                span,
+            },
+            "ForStatement" => ASTNode::ForStatement {
+                init: optional_child(json, "init")?,
+                condition: optional_child(json, "test")?,
+                update: optional_child(json, "update")?,
+                body: child(json, "body")?,
+                span,
+            },
+            "AssignmentExpression" => ASTNode::AssignmentExpression {
+                left: child(json, "left")?,
+                operator: operator(json, AssignOp::from_operator)?,
+                right: child(json, "right")?,
+                span,
+            },
+            "BinaryExpression" => ASTNode::BinaryExpression {
+                left: child(json, "left")?,
+                operator: operator(json, BinaryOp::from_operator)?,
+                right: child(json, "right")?,
+                span,
+            },
+            "LogicalExpression" => ASTNode::LogicalExpression {
+                left: child(json, "left")?,
+                operator: operator(json, LogicalOp::from_operator)?,
+                right: child(json, "right")?,
+                span,
+            },
+            "UnaryExpression" => ASTNode::UnaryExpression {
+                operator: operator(json, UnaryOp::from_operator)?,
+                argument: child(json, "argument")?,
+                span,
+            },
+            "UpdateExpression" => ASTNode::UpdateExpression {
+                operator: operator(json, UpdateOp::from_operator)?,
+                prefix: flag(json, "prefix"),
+                argument: child(json, "argument")?,
+                span,
+            },
+            "CallExpression" => ASTNode::CallExpression {
+                callee: child(json, "callee")?,
+                arguments: list(json, "arguments")?,
+                span,
+            },
+            "MemberExpression" => ASTNode::MemberExpression {
+                object: child(json, "object")?,
+                property: child(json, "property")?,
+                computed: flag(json, "computed"),
+                span,
+            },
+            "ObjectExpression" => ASTNode::ObjectExpression {
+                properties: list(json, "properties")?,
+                span,
+            },
+            "Property" => ASTNode::Property {
+                key: child(json, "key")?,
+                value: child(json, "value")?,
+                computed: flag(json, "computed"),
+                shorthand: flag(json, "shorthand"),
+                span,
+            },
+            "Literal" => ASTNode::Literal {
+                value: literal(json)?,
+                span,
+            },
+            "Identifier" => ASTNode::Identifier {
+                name: text(json, "name")?.into(),
+                span,
+            },
+            "ThisExpression" => ASTNode::ThisExpression { span },
+            other => return error(format!("Unsupported ESTree node type '{}'", other)),
+        };
+
+        Ok(result)
+    }
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, EstreeError> {
+    json.get(key).ok_or_else(|| EstreeError {
+        message: format!("Missing field '{}' in {}", key, json),
+    })
+}
+
+fn offset(json: &Json, key: &str) -> Result<usize, EstreeError> {
+    match field(json, key)?.as_f64() {
+        Some(value) if value >= 0.0 => Ok(value as usize),
+        _ => error(format!("Field '{}' is not an offset", key)),
+    }
+}
+
+fn text<'a>(json: &'a Json, key: &str) -> Result<&'a str, EstreeError> {
+    field(json, key)?.as_str().ok_or_else(|| EstreeError {
+        message: format!("Field '{}' is not a string", key),
+    })
+}
+
+fn flag(json: &Json, key: &str) -> bool {
+    json.get(key).and_then(Json::as_bool).unwrap_or(false)
+}
+
+fn child(json: &Json, key: &str) -> Result<Box<ASTNode>, EstreeError> {
+    ASTNode::from_estree(field(json, key)?).map(Box::new)
+}
+
+fn optional_child(json: &Json, key: &str) -> Result<Option<Box<ASTNode>>, EstreeError> {
+    match json.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => ASTNode::from_estree(value).map(|node| Some(Box::new(node))),
+    }
+}
+
+fn list(json: &Json, key: &str) -> Result<Vec<ASTNode>, EstreeError> {
+    field(json, key)?
+        .as_array()
+        .ok_or_else(|| EstreeError {
+            message: format!("Field '{}' is not an array", key),
+        })?
+        .iter()
+        .map(ASTNode::from_estree)
+        .collect()
+}
+
+fn operator<T>(json: &Json, parse: fn(&str) -> Option<T>) -> Result<T, EstreeError> {
+    let op = text(json, "operator")?;
+    parse(op).ok_or_else(|| EstreeError {
+        message: format!("Unknown operator '{}'", op),
+    })
+}
+
+fn literal(json: &Json) -> Result<Literal, EstreeError> {
+    if let Some(regex) = json.get("regex") {
+        return Ok(Literal::RegExp {
+            pattern: text(regex, "pattern")?.to_string(),
+            flags: text(regex, "flags")?.to_string(),
+        });
+    }
+    if let Some(digits) = json.get("bigint").and_then(Json::as_str) {
+        return Ok(Literal::BigInt(digits.to_string()));
+    }
+
+    match field(json, "value")? {
+        Json::Number(value) => Ok(Literal::Number(*value)),
+        Json::String(value) => Ok(Literal::String(value.encode_utf16().collect())),
+        Json::Utf16(units) => Ok(Literal::String(units.to_vec())),
+        Json::Bool(value) => Ok(Literal::Boolean(*value)),
+        Json::Null => Ok(Literal::Null),
+        other => error(format!("Unsupported literal value {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn parse(code: &str) -> ASTNode {
+        ASTNode::parse_program(&Lexer::new(code).tokenize())
+    }
+
+    #[test]
+    fn test_matches_acorn_output() {
+        let code = "let x = a + 1;";
+        let source = SourceFile::new("test.js", code);
+
+        // Output of `acorn.parse(code, {ecmaVersion: 2022, locations: true})`.
+        let expected = r#"{"type":"Program","start":0,"end":14,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":14}},"body":[{"type":"VariableDeclaration","start":0,"end":14,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":14}},"declarations":[{"type":"VariableDeclarator","start":4,"end":13,"loc":{"start":{"line":1,"column":4},"end":{"line":1,"column":13}},"id":{"type":"Identifier","start":4,"end":5,"loc":{"start":{"line":1,"column":4},"end":{"line":1,"column":5}},"name":"x"},"init":{"type":"BinaryExpression","start":8,"end":13,"loc":{"start":{"line":1,"column":8},"end":{"line":1,"column":13}},"left":{"type":"Identifier","start":8,"end":9,"loc":{"start":{"line":1,"column":8},"end":{"line":1,"column":9}},"name":"a"},"operator":"+","right":{"type":"Literal","start":12,"end":13,"loc":{"start":{"line":1,"column":12},"end":{"line":1,"column":13}},"value":1,"raw":"1"}}}],"kind":"let"}],"sourceType":"script"}"#;
+
+        assert_eq!(parse(code).to_estree(&source).stringify(), expected);
+    }
+
+    #[test]
+    fn test_literal_variants() {
+        let code = "/ab/g; 10n; 'x'; null;";
+        let source = SourceFile::new("test.js", code);
+        let json = parse(code).to_estree(&source);
+
+        let body = json.get("body").and_then(Json::as_array).unwrap();
+        let literal = |i: usize| body[i].get("expression").unwrap().clone();
+
+        assert_eq!(
+            literal(0).get("regex").unwrap().stringify(),
+            r#"{"pattern":"ab","flags":"g"}"#
+        );
+        assert_eq!(literal(1).get("bigint").and_then(Json::as_str), Some("10"));
+        assert_eq!(literal(2).get("raw").and_then(Json::as_str), Some("'x'"));
+        assert!(literal(3).get("value").unwrap().is_null());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let code = "var a = 1, b;\nb = typeof a === 'number' && !a++;\nc **= 2;\n\
+                    for (let i = 0; i < 3; i++) { if (i) continue; o[i] = f(i, { k: i, a }).x; }\n\
+                    while (b) break;\nfunction g() { return; }\n\
+                    outer: for (;;) { try { throw b; } catch (e) { continue outer; } finally { break outer; } }\n\
+                    try {} catch {}";
+        let source = SourceFile::new("test.js", code);
+        let ast = parse(code);
+
+        let text = ast.to_estree(&source).pretty(2);
+        let rebuilt = ASTNode::from_estree(&Json::parse(&text).unwrap()).unwrap();
+
+        assert_eq!(rebuilt, ast);
+    }
+
+    #[test]
+    fn test_lone_surrogate_round_trip() {
+        let code = "x = '\\uD800😀';";
+        let source = SourceFile::new("test.js", code);
+        let ast = parse(code);
+
+        let text = ast.to_estree(&source).stringify();
+        assert!(text.contains(r#""value":"\uD800😀""#), "{}", text);
+
+        let rebuilt = ASTNode::from_estree(&Json::parse(&text).unwrap()).unwrap();
+        assert_eq!(rebuilt, ast);
+    }
+
+    #[test]
+    fn test_hand_written_json() {
+        let text = r#"{"type":"IfStatement","start":0,"end":15,
+            "test":{"type":"Identifier","start":4,"end":5,"name":"a"},
+            "consequent":{"type":"BlockStatement","start":7,"end":9,"body":[]},
+            "alternate":null}"#;
+
+        let ast = ASTNode::from_estree(&Json::parse(text).unwrap()).unwrap();
+        assert_eq!(
+            ast.pretty_print(0),
+            "IfStatement:\n  Condition:\n    Identifier: a\n  Then:\n    Block:\n"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let missing_type = Json::parse(r#"{"start":0,"end":1}"#).unwrap();
+        assert!(ASTNode::from_estree(&missing_type).is_err());
+
+        let unknown = Json::parse(r#"{"type":"WithStatement","start":0,"end":1}"#).unwrap();
+        assert_eq!(
+            ASTNode::from_estree(&unknown).unwrap_err().message,
+            "Unsupported ESTree node type 'WithStatement'"
+        );
+
+        let bad_operator = Json::parse(
+            r#"{"type":"UnaryExpression","start":0,"end":2,"operator":"?","argument":{"type":"Identifier","start":1,"end":2,"name":"a"}}"#,
+        )
+        .unwrap();
+        assert!(ASTNode::from_estree(&bad_operator).is_err());
+    }
+}
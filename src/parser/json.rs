@@ -0,0 +1,426 @@
+use crate::parser::numeric::number_to_string;
+use std::fmt;
+
+/// Minimal JSON document model. Objects keep their keys in insertion order so
+/// serialised output is stable and diffable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// A string that is not well-formed UTF-16, such as a JavaScript string
+    /// holding a lone surrogate. Unpaired surrogates serialise as `\uXXXX`
+    /// escapes. Build it with [`Json::from_utf16`], which keeps well-formed
+    /// text in `String` so that equal strings compare equal.
+    Utf16(Box<[u16]>),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Json {
+    pub fn object(entries: Vec<(&str, Json)>) -> Json {
+        Json::Object(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    pub fn from_utf16(units: &[u16]) -> Json {
+        match String::from_utf16(units) {
+            Ok(text) => Json::String(text),
+            Err(_) => Json::Utf16(units.into()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("Unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Compact single-line serialisation.
+    pub fn stringify(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, None, 0);
+        out
+    }
+
+    /// Multi-line serialisation indented by `indent` spaces per level.
+    pub fn pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write(&mut out, Some(indent), 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: Option<usize>, depth: usize) {
+        let newline = |out: &mut String, depth: usize| {
+            if let Some(width) = indent {
+                out.push('\n');
+                out.push_str(&" ".repeat(width * depth));
+            }
+        };
+
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Json::Number(value) => out.push_str(&format_number(*value)),
+            Json::String(value) => write_string(out, value),
+            Json::Utf16(units) => write_units(out, units),
+            Json::Array(items) => {
+                if items.is_empty() {
+                    out.push_str("[]");
+                    return;
+                }
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, depth + 1);
+                    item.write(out, indent, depth + 1);
+                }
+                newline(out, depth);
+                out.push(']');
+            }
+            Json::Object(entries) => {
+                if entries.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, depth + 1);
+                    write_string(out, key);
+                    out.push(':');
+                    if indent.is_some() {
+                        out.push(' ');
+                    }
+                    value.write(out, indent, depth + 1);
+                }
+                newline(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.stringify())
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if !value.is_finite() {
+        // JSON has no representation for NaN or the infinities.
+        return "null".to_string();
+    }
+    number_to_string(value)
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for ch in value.chars() {
+        write_char(out, ch);
+    }
+    out.push('"');
+}
+
+fn write_units(out: &mut String, units: &[u16]) {
+    out.push('"');
+    for unit in char::decode_utf16(units.iter().copied()) {
+        match unit {
+            Ok(ch) => write_char(out, ch),
+            Err(error) => out.push_str(&format!("\\u{:04X}", error.unpaired_surrogate())),
+        }
+    }
+    out.push('"');
+}
+
+fn write_char(out: &mut String, ch: char) {
+    match ch {
+        '"' => out.push_str("\\\""),
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\u{08}' => out.push_str("\\b"),
+        '\u{0C}' => out.push_str("\\f"),
+        ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+        ch => out.push(ch),
+    }
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl JsonParser {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            position: self.position,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.get(self.position), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        if self.chars.get(self.position) == Some(&expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", expected)))
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.chars.get(self.position) {
+            Some('n') => self.expect_word("null", Json::Null),
+            Some('t') => self.expect_word("true", Json::Bool(true)),
+            Some('f') => self.expect_word("false", Json::Bool(false)),
+            Some('"') => self.parse_units().map(|units| Json::from_utf16(&units)),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(ch) if *ch == '-' || ch.is_ascii_digit() => self.parse_number(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, JsonError> {
+        let start = self.position;
+        while let Some(ch) = self.chars.get(self.position) {
+            if ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E') {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse::<f64>().map(Json::Number).map_err(|_| JsonError {
+            message: format!("Invalid number '{}'", text),
+            position: start,
+        })
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let digits: String = self.chars.iter().skip(self.position).take(4).collect();
+        let code = u32::from_str_radix(&digits, 16).map_err(|_| self.error("Invalid unicode escape"))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.parse_units().map(|units| String::from_utf16_lossy(&units))
+    }
+
+    /// A string as UTF-16 code units. `\u` escapes are kept unit by unit, so
+    /// escaped surrogate pairs join up and lone surrogates survive.
+    fn parse_units(&mut self) -> Result<Vec<u16>, JsonError> {
+        self.expect('"')?;
+        let mut units = Vec::new();
+        loop {
+            let ch = *self.chars.get(self.position).ok_or_else(|| self.error("Unterminated string"))?;
+            self.position += 1;
+            let ch = match ch {
+                '"' => return Ok(units),
+                '\\' => {
+                    let escaped = *self.chars.get(self.position).ok_or_else(|| self.error("Unterminated string"))?;
+                    self.position += 1;
+                    match escaped {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{08}',
+                        'f' => '\u{0C}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            units.push(self.parse_hex4()? as u16);
+                            continue;
+                        }
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    }
+                }
+                ch => ch,
+            };
+            units.extend(ch.encode_utf16(&mut [0; 2]).iter());
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&']') {
+            self.position += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.chars.get(self.position) {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&'}') {
+            self.position += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.chars.get(self.position) {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(Json::Object(entries));
+                }
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = r#"{"type":"Literal","value":1.5,"list":[true,null,"a\nb"],"empty":{}}"#;
+        let json = Json::parse(text).unwrap();
+
+        assert_eq!(json.get("type").and_then(Json::as_str), Some("Literal"));
+        assert_eq!(json.get("value").and_then(Json::as_f64), Some(1.5));
+        assert_eq!(json.stringify(), text);
+    }
+
+    #[test]
+    fn test_large_integers() {
+        for value in [1e20, -1e20, 2f64.powi(63), 123456789012345680000.0, 1e21] {
+            let text = Json::Number(value).stringify();
+            assert_eq!(Json::parse(&text).unwrap().as_f64(), Some(value), "{}", text);
+        }
+        assert_eq!(Json::Number(1e20).stringify(), "100000000000000000000");
+    }
+
+    #[test]
+    fn test_unicode_escapes() {
+        let json = Json::parse(r#""\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(json, Json::String("é😀".to_string()));
+    }
+
+    #[test]
+    fn test_lone_surrogates() {
+        let json = Json::parse(r#""a\uD800b""#).unwrap();
+        assert_eq!(json, Json::Utf16(vec![0x61, 0xD800, 0x62].into()));
+        assert_eq!(json.stringify(), r#""a\uD800b""#);
+        assert_eq!(Json::from_utf16(&[0x61, 0x62]), Json::String("ab".to_string()));
+    }
+
+    #[test]
+    fn test_pretty() {
+        let json = Json::object(vec![("a", Json::Array(vec![Json::Number(1.0)]))]);
+        assert_eq!(json.pretty(2), "{\n  \"a\": [\n    1\n  ]\n}");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+}
@@ -1 +1,10 @@
+pub mod arena;
 pub mod ast;
+pub mod builder;
+pub mod codegen;
+pub mod estree;
+pub mod json;
+pub mod numeric;
+pub mod operators;
+pub mod parse;
+pub mod visitor;
@@ -0,0 +1,204 @@
+/// Value of a numeric literal's source text with `_` separators already
+/// removed: decimal, `0x`/`0o`/`0b` prefixed, or legacy octal (`017`).
+/// Text the lexer would not produce, such as `0b2`, is an error.
+pub fn parse_number(digits: &str) -> Result<f64, String> {
+    let radix = match digits.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0o" | "0O") => 8,
+        Some("0b" | "0B") => 2,
+        _ => {
+            if is_legacy_octal(digits) {
+                return integer_value(&digits[1..], 8);
+            }
+            return digits.parse::<f64>().map_err(|_| format!("Invalid number literal: {}", digits));
+        }
+    };
+    integer_value(&digits[2..], radix)
+}
+
+/// Whether `digits` is a legacy octal literal such as `017`. Literals with a
+/// leading zero that contain an 8 or 9 (`019`) are decimal instead.
+pub fn is_legacy_octal(digits: &str) -> bool {
+    digits.len() > 1
+        && digits.starts_with('0')
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && !digits.contains(['8', '9'])
+}
+
+fn integer_value(digits: &str, radix: u32) -> Result<f64, String> {
+    if digits.is_empty() {
+        return Err(format!("Missing digits in base {} literal", radix));
+    }
+    digits.chars().try_fold(0.0, |acc, ch| {
+        let digit = ch
+            .to_digit(radix)
+            .ok_or_else(|| format!("Invalid digit '{}' in base {} literal", ch, radix))?;
+        Ok(acc * radix as f64 + digit as f64)
+    })
+}
+
+/// ECMAScript `Number::toString(x)` for radix 10: the shortest digit string
+/// that round-trips, laid out with the spec's fixed/exponential thresholds.
+pub fn number_to_string(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    if value < 0.0 {
+        return format!("-{}", number_to_string(-value));
+    }
+    if value.is_infinite() {
+        return "Infinity".to_string();
+    }
+
+    // `{:e}` yields the shortest round-tripping digits as `d.ddde±x`.
+    let scientific = format!("{:e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap() + 1;
+
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let e = n - 1;
+        let sign = if e < 0 { '-' } else { '+' };
+        if k == 1 {
+            format!("{}e{}{}", digits, sign, e.abs())
+        } else {
+            format!("{}.{}e{}{}", &digits[..1], &digits[1..], sign, e.abs())
+        }
+    }
+}
+
+/// `Number::toString(x, radix)` for radixes other than 10, following V8:
+/// fraction digits stop once they pin down the value, rounding the last
+/// one, and integer digits beyond the 53 bits of precision are zeros.
+pub fn number_to_radix_string(value: f64, radix: u32) -> String {
+    if !value.is_finite() || value == 0.0 {
+        return number_to_string(value);
+    }
+    if value < 0.0 {
+        return format!("-{}", number_to_radix_string(-value, radix));
+    }
+    let digit_char = |digit: u32| char::from_digit(digit, radix).unwrap();
+    let radix_f = radix as f64;
+
+    let mut integer = value.floor();
+    let mut fraction = value - integer;
+    // Half the gap to the next double: the precision the value has.
+    let mut delta = f64::max(0.5 * (f64::from_bits(value.to_bits() + 1) - value), f64::from_bits(1));
+    let mut fraction_digits = Vec::new();
+    if fraction >= delta {
+        loop {
+            fraction *= radix_f;
+            delta *= radix_f;
+            let digit = fraction as u32;
+            fraction_digits.push(digit);
+            fraction -= digit as f64;
+            if (fraction > 0.5 || (fraction == 0.5 && digit & 1 == 1)) && fraction + delta > 1.0 {
+                // Round up, carrying into earlier digits as needed.
+                loop {
+                    match fraction_digits.pop() {
+                        None => {
+                            integer += 1.0;
+                            break;
+                        }
+                        Some(digit) if digit + 1 < radix => {
+                            fraction_digits.push(digit + 1);
+                            break;
+                        }
+                        Some(_) => {}
+                    }
+                }
+                break;
+            }
+            if fraction < delta {
+                break;
+            }
+        }
+    }
+
+    let mut integer_digits = Vec::new();
+    while integer / radix_f >= 9007199254740992.0 {
+        integer /= radix_f;
+        integer_digits.push('0');
+    }
+    loop {
+        let remainder = integer % radix_f;
+        integer_digits.push(digit_char(remainder as u32));
+        integer = (integer - remainder) / radix_f;
+        if integer <= 0.0 {
+            break;
+        }
+    }
+
+    let mut text: String = integer_digits.iter().rev().collect();
+    if !fraction_digits.is_empty() {
+        text.push('.');
+        text.extend(fraction_digits.into_iter().map(digit_char));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("0xff"), Ok(255.0));
+        assert_eq!(parse_number("0b101"), Ok(5.0));
+        assert_eq!(parse_number("0o17"), Ok(15.0));
+        assert_eq!(parse_number("017"), Ok(15.0));
+        assert_eq!(parse_number("019"), Ok(19.0));
+        assert_eq!(parse_number("1.5e3"), Ok(1500.0));
+        assert_eq!(parse_number("0b12"), Err("Invalid digit '2' in base 2 literal".to_string()));
+        assert_eq!(parse_number("0o"), Err("Missing digits in base 8 literal".to_string()));
+        assert!(parse_number("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_number_to_string() {
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1.0, "1"),
+            (-42.0, "-42"),
+            (0.1, "0.1"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (123.456, "123.456"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (1.5e-7, "1.5e-7"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (5e-324, "5e-324"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (f64::INFINITY, "Infinity"),
+            (f64::NEG_INFINITY, "-Infinity"),
+            (f64::NAN, "NaN"),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(number_to_string(value), expected, "formatting {:e}", value);
+        }
+    }
+
+    #[test]
+    fn test_number_to_radix_string() {
+        assert_eq!(number_to_radix_string(255.0, 16), "ff");
+        assert_eq!(number_to_radix_string(-255.5, 2), "-11111111.1");
+        assert_eq!(number_to_radix_string(0.5, 3), "0.1111111111111111111111111111111112");
+        assert_eq!(number_to_radix_string(0.1, 2), "0.0001100110011001100110011001100110011001100110011001101");
+        assert_eq!(number_to_radix_string(35.0, 36), "z");
+        assert_eq!(number_to_radix_string(2f64.powi(60), 2), format!("1{}", "0".repeat(60)));
+        assert_eq!(number_to_radix_string(f64::NEG_INFINITY, 2), "-Infinity");
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+    Eq,
+    NotEq,
+    StrictEq,
+    StrictNotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    UShr,
+    In,
+    InstanceOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogicalOp {
+    And,
+    Or,
+    NullishCoalescing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssignOp {
+    Assign,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    ModAssign,
+    ExpAssign,
+    ShlAssign,
+    ShrAssign,
+    UShrAssign,
+    BitAndAssign,
+    BitOrAssign,
+    BitXorAssign,
+    AndAssign,
+    OrAssign,
+    NullishAssign,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    Minus,
+    Plus,
+    Not,
+    BitNot,
+    TypeOf,
+    Void,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpdateOp {
+    Increment,
+    Decrement,
+}
+
+impl BinaryOp {
+    pub fn from_operator(op: &str) -> Option<Self> {
+        Some(match op {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Mod,
+            "**" => BinaryOp::Exp,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::NotEq,
+            "===" => BinaryOp::StrictEq,
+            "!==" => BinaryOp::StrictNotEq,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::LtEq,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::GtEq,
+            "&" => BinaryOp::BitAnd,
+            "|" => BinaryOp::BitOr,
+            "^" => BinaryOp::BitXor,
+            "<<" => BinaryOp::Shl,
+            ">>" => BinaryOp::Shr,
+            ">>>" => BinaryOp::UShr,
+            "in" => BinaryOp::In,
+            "instanceof" => BinaryOp::InstanceOf,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Exp => "**",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::StrictEq => "===",
+            BinaryOp::StrictNotEq => "!==",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::UShr => ">>>",
+            BinaryOp::In => "in",
+            BinaryOp::InstanceOf => "instanceof",
+        }
+    }
+
+    /// Binding power of the operator; higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::BitOr => 5,
+            BinaryOp::BitXor => 6,
+            BinaryOp::BitAnd => 7,
+            BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::StrictEq | BinaryOp::StrictNotEq => 8,
+            BinaryOp::Lt
+            | BinaryOp::LtEq
+            | BinaryOp::Gt
+            | BinaryOp::GtEq
+            | BinaryOp::In
+            | BinaryOp::InstanceOf => 9,
+            BinaryOp::Shl | BinaryOp::Shr | BinaryOp::UShr => 10,
+            BinaryOp::Add | BinaryOp::Sub => 11,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 12,
+            BinaryOp::Exp => 13,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, BinaryOp::Exp)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Exp
+        )
+    }
+}
+
+impl LogicalOp {
+    pub fn from_operator(op: &str) -> Option<Self> {
+        match op {
+            "&&" => Some(LogicalOp::And),
+            "||" => Some(LogicalOp::Or),
+            "??" => Some(LogicalOp::NullishCoalescing),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogicalOp::And => "&&",
+            LogicalOp::Or => "||",
+            LogicalOp::NullishCoalescing => "??",
+        }
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            LogicalOp::Or | LogicalOp::NullishCoalescing => 3,
+            LogicalOp::And => 4,
+        }
+    }
+}
+
+impl AssignOp {
+    pub fn from_operator(op: &str) -> Option<Self> {
+        Some(match op {
+            "=" => AssignOp::Assign,
+            "+=" => AssignOp::AddAssign,
+            "-=" => AssignOp::SubAssign,
+            "*=" => AssignOp::MulAssign,
+            "/=" => AssignOp::DivAssign,
+            "%=" => AssignOp::ModAssign,
+            "**=" => AssignOp::ExpAssign,
+            "<<=" => AssignOp::ShlAssign,
+            ">>=" => AssignOp::ShrAssign,
+            ">>>=" => AssignOp::UShrAssign,
+            "&=" => AssignOp::BitAndAssign,
+            "|=" => AssignOp::BitOrAssign,
+            "^=" => AssignOp::BitXorAssign,
+            "&&=" => AssignOp::AndAssign,
+            "||=" => AssignOp::OrAssign,
+            "??=" => AssignOp::NullishAssign,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignOp::Assign => "=",
+            AssignOp::AddAssign => "+=",
+            AssignOp::SubAssign => "-=",
+            AssignOp::MulAssign => "*=",
+            AssignOp::DivAssign => "/=",
+            AssignOp::ModAssign => "%=",
+            AssignOp::ExpAssign => "**=",
+            AssignOp::ShlAssign => "<<=",
+            AssignOp::ShrAssign => ">>=",
+            AssignOp::UShrAssign => ">>>=",
+            AssignOp::BitAndAssign => "&=",
+            AssignOp::BitOrAssign => "|=",
+            AssignOp::BitXorAssign => "^=",
+            AssignOp::AndAssign => "&&=",
+            AssignOp::OrAssign => "||=",
+            AssignOp::NullishAssign => "??=",
+        }
+    }
+
+    /// The arithmetic/bitwise operator a compound assignment applies, if any.
+    pub fn binary_op(&self) -> Option<BinaryOp> {
+        match self {
+            AssignOp::AddAssign => Some(BinaryOp::Add),
+            AssignOp::SubAssign => Some(BinaryOp::Sub),
+            AssignOp::MulAssign => Some(BinaryOp::Mul),
+            AssignOp::DivAssign => Some(BinaryOp::Div),
+            AssignOp::ModAssign => Some(BinaryOp::Mod),
+            AssignOp::ExpAssign => Some(BinaryOp::Exp),
+            AssignOp::ShlAssign => Some(BinaryOp::Shl),
+            AssignOp::ShrAssign => Some(BinaryOp::Shr),
+            AssignOp::UShrAssign => Some(BinaryOp::UShr),
+            AssignOp::BitAndAssign => Some(BinaryOp::BitAnd),
+            AssignOp::BitOrAssign => Some(BinaryOp::BitOr),
+            AssignOp::BitXorAssign => Some(BinaryOp::BitXor),
+            _ => None,
+        }
+    }
+
+    /// The short-circuiting operator a logical assignment applies, if any.
+    pub fn logical_op(&self) -> Option<LogicalOp> {
+        match self {
+            AssignOp::AndAssign => Some(LogicalOp::And),
+            AssignOp::OrAssign => Some(LogicalOp::Or),
+            AssignOp::NullishAssign => Some(LogicalOp::NullishCoalescing),
+            _ => None,
+        }
+    }
+}
+
+impl UnaryOp {
+    pub fn from_operator(op: &str) -> Option<Self> {
+        Some(match op {
+            "-" => UnaryOp::Minus,
+            "+" => UnaryOp::Plus,
+            "!" => UnaryOp::Not,
+            "~" => UnaryOp::BitNot,
+            "typeof" => UnaryOp::TypeOf,
+            "void" => UnaryOp::Void,
+            "delete" => UnaryOp::Delete,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Minus => "-",
+            UnaryOp::Plus => "+",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+            UnaryOp::TypeOf => "typeof",
+            UnaryOp::Void => "void",
+            UnaryOp::Delete => "delete",
+        }
+    }
+
+    pub fn is_keyword(&self) -> bool {
+        matches!(self, UnaryOp::TypeOf | UnaryOp::Void | UnaryOp::Delete)
+    }
+}
+
+impl UpdateOp {
+    pub fn from_operator(op: &str) -> Option<Self> {
+        match op {
+            "++" => Some(UpdateOp::Increment),
+            "--" => Some(UpdateOp::Decrement),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateOp::Increment => "++",
+            UpdateOp::Decrement => "--",
+        }
+    }
+}
+
+macro_rules! impl_display {
+    ($($op:ty),*) => {
+        $(
+            impl fmt::Display for $op {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(self.as_str())
+                }
+            }
+        )*
+    };
+}
+
+impl_display!(BinaryOp, LogicalOp, AssignOp, UnaryOp, UpdateOp);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_op_round_trip() {
+        for op in ["+", "-", "**", "===", "!==", ">>>", "instanceof", "in", "&", "|", "^"] {
+            assert_eq!(BinaryOp::from_operator(op).unwrap().as_str(), op);
+        }
+        assert_eq!(BinaryOp::from_operator("&&"), None);
+    }
+
+    #[test]
+    fn test_logical_ops_are_not_binary() {
+        assert_eq!(LogicalOp::from_operator("&&"), Some(LogicalOp::And));
+        assert_eq!(LogicalOp::from_operator("??"), Some(LogicalOp::NullishCoalescing));
+        assert_eq!(LogicalOp::from_operator("+"), None);
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        assert_eq!(AssignOp::from_operator("+=").unwrap().binary_op(), Some(BinaryOp::Add));
+        assert_eq!(AssignOp::from_operator("||=").unwrap().logical_op(), Some(LogicalOp::Or));
+        assert_eq!(AssignOp::Assign.binary_op(), None);
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert!(BinaryOp::Mul.precedence() > BinaryOp::Add.precedence());
+        assert!(BinaryOp::Add.precedence() > LogicalOp::And.precedence());
+        assert!(LogicalOp::And.precedence() > LogicalOp::Or.precedence());
+    }
+}
@@ -0,0 +1,718 @@
+use crate::lexical::atom::Atom;
+use crate::lexical::lexer::{SpannedToken, Token};
+use crate::lexical::span::Span;
+use crate::parser::ast::{Literal, VariableKind};
+use crate::parser::builder::AstBuilder;
+use crate::parser::numeric;
+use crate::parser::operators::{AssignOp, BinaryOp, LogicalOp, UnaryOp, UpdateOp};
+
+/// Identifiers that are reserved only in strict mode code. `let` and `yield`
+/// are lexed as keywords and never reach identifier positions.
+const STRICT_RESERVED_WORDS: [&str; 7] = [
+    "implements",
+    "interface",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+];
+
+/// Recursive-descent parser over a token slice. Nodes are produced through
+/// the [`AstBuilder`] it owns, so the same grammar can build either the boxed
+/// `ASTNode` tree or the arena representation.
+pub struct Parser<'t, B: AstBuilder> {
+    tokens: &'t [SpannedToken],
+    index: usize,
+    builder: B,
+    /// Whether the code being parsed is strict mode code, either because a
+    /// `"use strict"` directive is in effect or because the caller said so.
+    strict: bool,
+}
+
+impl<'t, B: AstBuilder> Parser<'t, B> {
+    pub fn new(tokens: &'t [SpannedToken], builder: B) -> Self {
+        Parser {
+            tokens,
+            index: 0,
+            builder,
+            strict: false,
+        }
+    }
+
+    /// Parses everything as strict mode code, as for modules.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    pub fn set_position(&mut self, index: usize) {
+        self.index = index;
+    }
+
+    pub fn builder(&self) -> &B {
+        &self.builder
+    }
+
+    pub fn into_builder(self) -> B {
+        self.builder
+    }
+
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.index).map(|t| &t.token)
+    }
+
+    /// Span from the start of token `start` to the end of the last consumed token.
+    fn span_since(&self, start: usize) -> Span {
+        let start_span = self.tokens.get(start).map(|t| t.span).unwrap_or_default();
+        let end_span = self
+            .index
+            .checked_sub(1)
+            .and_then(|last| self.tokens.get(last))
+            .map(|t| t.span)
+            .unwrap_or(start_span);
+        Span::new(start_span.start, end_span.end.max(start_span.start))
+    }
+
+    fn unexpected(&self, message: &str) -> ! {
+        match self.tokens.get(self.index) {
+            Some(SpannedToken { token: Token::Invalid(error), span, .. }) => self.fail_at(error, *span),
+            Some(t) => panic!("{} at {}, found {:?}", message, t.span, t.token),
+            None => panic!("{} at end of input", message),
+        }
+    }
+
+    fn fail_at(&self, message: &str, span: Span) -> ! {
+        panic!("{} at {}", message, span)
+    }
+
+    /// Rejects identifiers that strict mode reserves.
+    fn check_strict_identifier(&self, index: usize) {
+        if let Token::Identifier(name) = &self.tokens[index].token {
+            if STRICT_RESERVED_WORDS.contains(&name.as_str()) {
+                self.fail_at("Unexpected strict mode reserved word", self.tokens[index].span);
+            }
+        }
+    }
+
+    /// Rejects number and string forms that only sloppy mode allows.
+    fn check_strict_literal(&self, index: usize) {
+        let token = &self.tokens[index];
+        match &token.token {
+            Token::Number(raw) if raw.len() > 1 && raw.starts_with('0') && raw.as_bytes()[1].is_ascii_digit() => {
+                let message = if numeric::is_legacy_octal(&raw.replace('_', "")) {
+                    "Octal literals are not allowed in strict mode."
+                } else {
+                    "Decimals with leading zeros are not allowed in strict mode."
+                };
+                self.fail_at(message, token.span);
+            }
+            Token::Literal(_) if token.legacy_escape => {
+                self.fail_at("Octal escape sequences are not allowed in strict mode.", token.span)
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the token at `index` starts a directive: a string literal
+    /// forming a whole expression statement.
+    fn directive_at(&self, index: usize) -> Option<&'t SpannedToken> {
+        let token = self.tokens.get(index)?;
+        let ends_statement = match self.tokens.get(index + 1).map(|t| &t.token) {
+            Some(Token::Delimiter(d)) => d == ";" || d == "}",
+            Some(Token::EOF) | None => true,
+            _ => false,
+        };
+        (matches!(token.token, Token::Literal(_)) && ends_statement).then_some(token)
+    }
+
+    /// Parses statements until `}` or the end of input. A leading run of
+    /// directives is the prologue; `"use strict"` written without escapes or
+    /// line continuations switches the rest of the body to strict mode.
+    fn parse_body(&mut self) -> Vec<B::Node> {
+        let mut body = Vec::new();
+        let mut in_prologue = true;
+        let mut legacy_escape = None;
+
+        while !self.is_delimiter("}") && !matches!(self.peek(), Some(Token::EOF) | None) {
+            if in_prologue {
+                match self.directive_at(self.index) {
+                    Some(token) => {
+                        // Exactly 12 characters rules out escapes in the directive.
+                        if token.token == Token::Literal("use strict".encode_utf16().collect()) && token.span.len() == 12 {
+                            self.strict = true;
+                            if let Some(span) = legacy_escape {
+                                self.fail_at("Octal escape sequences are not allowed in strict mode.", span);
+                            }
+                        } else if token.legacy_escape {
+                            legacy_escape.get_or_insert(token.span);
+                        }
+                    }
+                    None => in_prologue = false,
+                }
+            }
+            body.push(self.parse_statement());
+        }
+
+        body
+    }
+
+    fn is_delimiter(&self, delimiter: &str) -> bool {
+        matches!(self.peek(), Some(Token::Delimiter(d)) if d == delimiter)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(kw)) if kw == keyword)
+    }
+
+    fn expect_delimiter(&mut self, delimiter: &str) {
+        if !self.is_delimiter(delimiter) {
+            self.unexpected(&format!("Expected '{}'", delimiter));
+        }
+        self.index += 1;
+    }
+
+    fn parse_binding_identifier(&mut self, message: &str) -> B::Node {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                if self.strict {
+                    self.check_strict_identifier(self.index);
+                }
+                let span = self.tokens[self.index].span;
+                self.index += 1;
+                self.builder.identifier(*name, span)
+            }
+            _ => self.unexpected(message),
+        }
+    }
+
+    fn consume_semicolon(&mut self) {
+        if self.is_delimiter(";") {
+            self.index += 1;
+        }
+    }
+
+    pub fn parse_program(&mut self) -> B::Node {
+        let nodes = self.parse_body();
+        if !matches!(self.peek(), Some(Token::EOF) | None) {
+            self.unexpected("Unexpected token");
+        }
+
+        let end = self.tokens.last().map(|t| t.span.end).unwrap_or(0);
+        self.builder.program(nodes, Span::new(0, end))
+    }
+
+    pub fn parse_statement(&mut self) -> B::Node {
+        let start = self.index;
+        match self.peek() {
+            Some(Token::Keyword(kw)) if VariableKind::from_keyword(kw).is_some() => {
+                self.parse_variable_declaration(true)
+            }
+            Some(Token::Keyword(kw)) if kw == "function" => self.parse_function_declaration(),
+            Some(Token::Keyword(kw)) if kw == "if" => self.parse_if_statement(),
+            Some(Token::Keyword(kw)) if kw == "while" => self.parse_while_statement(),
+            Some(Token::Keyword(kw)) if kw == "with" => {
+                if self.strict {
+                    self.unexpected("Strict mode code may not include a with statement");
+                }
+                self.unexpected("'with' statements are not supported")
+            }
+            Some(Token::Keyword(kw)) if kw == "for" => self.parse_for_statement(),
+            Some(Token::Keyword(kw)) if kw == "return" => {
+                self.index += 1;
+                let argument = if self.is_delimiter(";") || self.is_delimiter("}") || matches!(self.peek(), Some(Token::EOF) | None) {
+                    None
+                } else {
+                    Some(self.parse_expression())
+                };
+                self.consume_semicolon();
+                let span = self.span_since(start);
+                self.builder.return_statement(argument, span)
+            }
+            Some(Token::Keyword(kw)) if kw == "break" || kw == "continue" => {
+                let is_break = kw == "break";
+                self.index += 1;
+                let label = match self.peek() {
+                    Some(Token::Identifier(_)) => Some(self.parse_binding_identifier("Expected label")),
+                    _ => None,
+                };
+                self.consume_semicolon();
+                let span = self.span_since(start);
+                if is_break {
+                    self.builder.break_statement(label, span)
+                } else {
+                    self.builder.continue_statement(label, span)
+                }
+            }
+            Some(Token::Keyword(kw)) if kw == "throw" => {
+                self.index += 1;
+                let argument = self.parse_expression();
+                self.consume_semicolon();
+                let span = self.span_since(start);
+                self.builder.throw_statement(argument, span)
+            }
+            Some(Token::Keyword(kw)) if kw == "try" => self.parse_try_statement(),
+            Some(Token::Identifier(_))
+                if matches!(self.tokens.get(self.index + 1).map(|t| &t.token), Some(Token::Delimiter(d)) if d == ":") =>
+            {
+                let label = self.parse_binding_identifier("Expected label");
+                self.index += 1;
+                let body = self.parse_statement();
+                let span = self.span_since(start);
+                self.builder.labeled_statement(label, body, span)
+            }
+            Some(Token::Delimiter(d)) if d == "{" => self.parse_block(),
+            Some(Token::EOF) | None => self.unexpected("Unsupported statement or token"),
+            _ => {
+                let expression = self.parse_expression();
+                self.consume_semicolon();
+
+                let span = self.span_since(start);
+                self.builder.expression_statement(expression, span)
+            }
+        }
+    }
+
+    fn parse_block(&mut self) -> B::Node {
+        let start = self.index;
+        self.expect_delimiter("{");
+
+        let mut body = Vec::new();
+        while !self.is_delimiter("}") {
+            body.push(self.parse_statement());
+        }
+        self.index += 1;
+
+        let span = self.span_since(start);
+        self.builder.block(body, span)
+    }
+
+    fn parse_function_declaration(&mut self) -> B::Node {
+        let start = self.index;
+        self.index += 1;
+        let mut binding_tokens = vec![self.index];
+        let name = self.parse_binding_identifier("Expected function name");
+
+        self.expect_delimiter("(");
+        let mut parameters = Vec::new();
+        while !self.is_delimiter(")") {
+            binding_tokens.push(self.index);
+            parameters.push(self.parse_binding_identifier("Expected parameter name"));
+            if !self.is_delimiter(")") {
+                self.expect_delimiter(",");
+            }
+        }
+        self.index += 1;
+
+        let outer_strict = self.strict;
+        let body_start = self.index;
+        self.expect_delimiter("{");
+        let statements = self.parse_body();
+        self.expect_delimiter("}");
+        let body_span = self.span_since(body_start);
+        let body = self.builder.block(statements, body_span);
+
+        // A "use strict" in the body also applies to the name and parameters.
+        if self.strict && !outer_strict {
+            for index in binding_tokens {
+                self.check_strict_identifier(index);
+            }
+        }
+        self.strict = outer_strict;
+
+        let span = self.span_since(start);
+        self.builder.function_declaration(name, parameters, body, span)
+    }
+
+    fn parse_try_statement(&mut self) -> B::Node {
+        let start = self.index;
+        self.index += 1;
+        let block = self.parse_block();
+
+        let handler = if self.is_keyword("catch") {
+            let catch_start = self.index;
+            self.index += 1;
+            let parameter = if self.is_delimiter("(") {
+                self.index += 1;
+                let parameter = self.parse_binding_identifier("Expected catch parameter");
+                self.expect_delimiter(")");
+                Some(parameter)
+            } else {
+                None
+            };
+            let body = self.parse_block();
+            let span = self.span_since(catch_start);
+            Some(self.builder.catch_clause(parameter, body, span))
+        } else {
+            None
+        };
+        let finalizer = if self.is_keyword("finally") {
+            self.index += 1;
+            Some(self.parse_block())
+        } else {
+            None
+        };
+        if handler.is_none() && finalizer.is_none() {
+            self.unexpected("Missing catch or finally after try");
+        }
+
+        let span = self.span_since(start);
+        self.builder.try_statement(block, handler, finalizer, span)
+    }
+
+    fn parse_if_statement(&mut self) -> B::Node {
+        let start = self.index;
+        self.index += 1;
+
+        self.expect_delimiter("(");
+        let condition = self.parse_expression();
+        self.expect_delimiter(")");
+
+        let body = self.parse_statement();
+        let else_body = if self.is_keyword("else") {
+            self.index += 1;
+            Some(self.parse_statement())
+        } else {
+            None
+        };
+
+        let span = self.span_since(start);
+        self.builder.if_statement(condition, body, else_body, span)
+    }
+
+    fn parse_while_statement(&mut self) -> B::Node {
+        let start = self.index;
+        self.index += 1;
+
+        self.expect_delimiter("(");
+        let condition = self.parse_expression();
+        self.expect_delimiter(")");
+        let body = self.parse_statement();
+
+        let span = self.span_since(start);
+        self.builder.while_statement(condition, body, span)
+    }
+
+    fn parse_for_statement(&mut self) -> B::Node {
+        let start = self.index;
+        self.index += 1;
+        self.expect_delimiter("(");
+
+        let init = match self.peek() {
+            Some(Token::Delimiter(d)) if d == ";" => None,
+            Some(Token::Keyword(kw)) if VariableKind::from_keyword(kw).is_some() => {
+                Some(self.parse_variable_declaration(false))
+            }
+            _ => Some(self.parse_expression()),
+        };
+        self.expect_delimiter(";");
+
+        let condition = (!self.is_delimiter(";")).then(|| self.parse_expression());
+        self.expect_delimiter(";");
+
+        let update = (!self.is_delimiter(")")).then(|| self.parse_expression());
+        self.expect_delimiter(")");
+
+        let body = self.parse_statement();
+        let span = self.span_since(start);
+        self.builder.for_statement(init, condition, update, body, span)
+    }
+
+    /// Parses `let a = 1, b`, plus the trailing semicolon when `statement` is set.
+    fn parse_variable_declaration(&mut self, statement: bool) -> B::Node {
+        let start = self.index;
+        let kind = match self.peek() {
+            Some(Token::Keyword(kw)) => VariableKind::from_keyword(kw),
+            _ => None,
+        }
+        .unwrap_or_else(|| self.unexpected("Expected 'var', 'let' or 'const'"));
+        self.index += 1;
+
+        let mut declarations = Vec::new();
+        loop {
+            let declarator_start = self.index;
+            let identifier = self.parse_binding_identifier(&format!("Expected identifier after '{}'", kind));
+
+            let value = match self.peek() {
+                Some(Token::Operator(op)) if op == "=" => {
+                    self.index += 1;
+                    Some(self.parse_assignment())
+                }
+                _ if kind == VariableKind::Const => self.unexpected("Missing initializer in const declaration"),
+                _ => None,
+            };
+
+            let span = self.span_since(declarator_start);
+            declarations.push(self.builder.variable_declarator(identifier, value, span));
+
+            if self.is_delimiter(",") {
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+
+        if statement {
+            self.consume_semicolon();
+        }
+
+        let span = self.span_since(start);
+        self.builder.variable_declaration(kind, declarations, span)
+    }
+
+    pub fn parse_expression(&mut self) -> B::Node {
+        self.parse_assignment()
+    }
+
+    fn parse_assignment(&mut self) -> B::Node {
+        let start = self.index;
+        let left = self.parse_binary(0);
+
+        if let Some(Token::Operator(op)) = self.peek() {
+            if let Some(operator) = AssignOp::from_operator(op) {
+                self.index += 1;
+                // Assignment is right-associative: `a = b = c` is `a = (b = c)`.
+                let right = self.parse_assignment();
+                let span = self.span_since(start);
+                return self.builder.assignment_expression(left, operator, right, span);
+            }
+        }
+
+        left
+    }
+
+    /// Precedence climbing over binary and logical operators. Only operators
+    /// binding tighter than `min_precedence` are consumed at this level.
+    fn parse_binary(&mut self, min_precedence: u8) -> B::Node {
+        let start = self.index;
+        let mut left = self.parse_unary();
+
+        loop {
+            let op = match self.peek() {
+                Some(Token::Operator(op)) => op.as_str(),
+                Some(Token::Keyword(kw)) if kw == "in" || kw == "instanceof" => kw.as_str(),
+                _ => break,
+            };
+
+            if let Some(operator) = LogicalOp::from_operator(op) {
+                let precedence = operator.precedence();
+                if precedence <= min_precedence {
+                    break;
+                }
+                self.index += 1;
+                let right = self.parse_binary(precedence);
+                let span = self.span_since(start);
+                left = self.builder.logical_expression(left, operator, right, span);
+            } else if let Some(operator) = BinaryOp::from_operator(op) {
+                let precedence = operator.precedence();
+                if precedence <= min_precedence {
+                    break;
+                }
+                self.index += 1;
+                let next_min = if operator.is_right_associative() {
+                    precedence - 1
+                } else {
+                    precedence
+                };
+                let right = self.parse_binary(next_min);
+                let span = self.span_since(start);
+                left = self.builder.binary_expression(left, operator, right, span);
+            } else {
+                break;
+            }
+        }
+
+        left
+    }
+
+    fn parse_unary(&mut self) -> B::Node {
+        let start = self.index;
+        let op = match self.peek() {
+            Some(Token::Operator(op)) => Some(op.as_str()),
+            Some(Token::Keyword(kw)) => Some(kw.as_str()),
+            _ => None,
+        };
+
+        if let Some(op) = op {
+            if let Some(operator) = UpdateOp::from_operator(op) {
+                self.index += 1;
+                let argument = self.parse_unary();
+                let span = self.span_since(start);
+                return self.builder.update_expression(operator, true, argument, span);
+            }
+            if let Some(operator) = UnaryOp::from_operator(op) {
+                self.index += 1;
+                let argument = self.parse_unary();
+                let span = self.span_since(start);
+                return self.builder.unary_expression(operator, argument, span);
+            }
+        }
+
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> B::Node {
+        let start = self.index;
+        let expression = self.parse_left_hand_side();
+
+        if let Some(Token::Operator(op)) = self.peek() {
+            if let Some(operator) = UpdateOp::from_operator(op) {
+                self.index += 1;
+                let span = self.span_since(start);
+                return self.builder.update_expression(operator, false, expression, span);
+            }
+        }
+
+        expression
+    }
+
+    /// Member accesses and calls chained onto a primary expression.
+    fn parse_left_hand_side(&mut self) -> B::Node {
+        let start = self.index;
+        let mut expression = self.parse_primary();
+
+        loop {
+            if self.is_delimiter(".") {
+                self.index += 1;
+                let property = self.parse_property_name();
+                let span = self.span_since(start);
+                expression = self.builder.member_expression(expression, property, false, span);
+            } else if self.is_delimiter("[") {
+                self.index += 1;
+                let property = self.parse_expression();
+                self.expect_delimiter("]");
+                let span = self.span_since(start);
+                expression = self.builder.member_expression(expression, property, true, span);
+            } else if self.is_delimiter("(") {
+                self.index += 1;
+                let mut arguments = Vec::new();
+                while !self.is_delimiter(")") {
+                    arguments.push(self.parse_assignment());
+                    if !self.is_delimiter(")") {
+                        self.expect_delimiter(",");
+                    }
+                }
+                self.index += 1;
+                let span = self.span_since(start);
+                expression = self.builder.call_expression(expression, arguments, span);
+            } else {
+                return expression;
+            }
+        }
+    }
+
+    /// An identifier used as a property name, where keywords are allowed too.
+    fn parse_property_name(&mut self) -> B::Node {
+        let name = match self.peek() {
+            Some(Token::Identifier(name)) => *name,
+            Some(Token::Keyword(keyword)) => Atom::new(keyword),
+            _ => self.unexpected("Expected property name"),
+        };
+        let span = self.tokens[self.index].span;
+        self.index += 1;
+        self.builder.identifier(name, span)
+    }
+
+    fn parse_object(&mut self) -> B::Node {
+        let start = self.index;
+        self.expect_delimiter("{");
+
+        let mut properties = Vec::new();
+        while !self.is_delimiter("}") {
+            let property_start = self.index;
+            let mut computed = false;
+            let key = match self.peek() {
+                Some(Token::Delimiter(d)) if d == "[" => {
+                    self.index += 1;
+                    computed = true;
+                    let key = self.parse_assignment();
+                    self.expect_delimiter("]");
+                    key
+                }
+                Some(Token::Literal(_) | Token::Number(_)) => self.parse_primary(),
+                _ => self.parse_property_name(),
+            };
+
+            let shorthand = !computed
+                && (self.is_delimiter(",") || self.is_delimiter("}"))
+                && matches!(self.tokens[property_start].token, Token::Identifier(_));
+            let value = if shorthand {
+                let Token::Identifier(name) = &self.tokens[property_start].token else {
+                    unreachable!()
+                };
+                let span = self.tokens[property_start].span;
+                self.builder.identifier(*name, span)
+            } else {
+                self.expect_delimiter(":");
+                self.parse_assignment()
+            };
+
+            let span = self.span_since(property_start);
+            properties.push(self.builder.property(key, value, computed, shorthand, span));
+
+            if !self.is_delimiter("}") {
+                self.expect_delimiter(",");
+            }
+        }
+        self.index += 1;
+
+        let span = self.span_since(start);
+        self.builder.object_expression(properties, span)
+    }
+
+    fn parse_primary(&mut self) -> B::Node {
+        let span = match self.tokens.get(self.index) {
+            Some(t) => t.span,
+            None => self.unexpected("Expected expression"),
+        };
+
+        if self.strict {
+            self.check_strict_literal(self.index);
+        }
+
+        let node = match self.peek() {
+            Some(Token::Number(raw)) => {
+                let literal = Literal::from_numeric(raw).unwrap_or_else(|message| self.fail_at(&message, span));
+                self.builder.literal(literal, span)
+            }
+            Some(Token::Literal(value)) => self.builder.literal(Literal::String(value.clone()), span),
+            Some(Token::RegExp(pattern, flags)) => self.builder.literal(
+                Literal::RegExp {
+                    pattern: pattern.clone(),
+                    flags: flags.clone(),
+                },
+                span,
+            ),
+            Some(Token::Keyword(kw)) if kw == "true" => self.builder.literal(Literal::Boolean(true), span),
+            Some(Token::Keyword(kw)) if kw == "false" => self.builder.literal(Literal::Boolean(false), span),
+            Some(Token::Keyword(kw)) if kw == "null" => self.builder.literal(Literal::Null, span),
+            Some(Token::Keyword(kw)) if kw == "undefined" => self.builder.identifier(Atom::new(kw), span),
+            Some(Token::Keyword(kw)) if kw == "this" => self.builder.this_expression(span),
+            Some(Token::Identifier(name)) => {
+                if self.strict {
+                    self.check_strict_identifier(self.index);
+                }
+                self.builder.identifier(*name, span)
+            }
+            Some(Token::Delimiter(d)) if d == "{" => return self.parse_object(),
+            Some(Token::Delimiter(d)) if d == "(" => {
+                self.index += 1;
+                let expression = self.parse_expression();
+                if !self.is_delimiter(")") {
+                    self.unexpected("Expected ')'");
+                }
+                expression
+            }
+            _ => self.unexpected("Unsupported expression"),
+        };
+        self.index += 1;
+        node
+    }
+}
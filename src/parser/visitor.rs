@@ -0,0 +1,231 @@
+use crate::parser::arena::{AstArena, NodeId, NodeKind};
+
+/// Read-only traversal over an [`AstArena`].
+///
+/// `visit_node` dispatches to one `visit_*` method per node kind. Every
+/// `visit_*` method defaults to [`walk_children`], so an implementation only
+/// overrides the kinds it cares about and calls `walk_children` itself when it
+/// still wants to descend. Overrides read the node's fields back out of
+/// [`AstArena::kind`].
+pub trait Visitor<'a>: Sized {
+    /// The arena the visited nodes belong to.
+    fn arena(&self) -> &'a AstArena;
+
+    fn visit_node(&mut self, node: NodeId) {
+        walk_node(self, node);
+    }
+    fn visit_program(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_variable_declaration(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_variable_declarator(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_function_declaration(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_if_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_block(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_expression_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_return_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_break_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_continue_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_throw_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_try_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_catch_clause(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_labeled_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_while_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_for_statement(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_assignment_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_binary_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_logical_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_unary_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_update_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_call_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_member_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_object_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_property(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_literal(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_identifier(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+    fn visit_this_expression(&mut self, node: NodeId) {
+        walk_children(self, node);
+    }
+}
+
+/// Calls the `visit_*` method matching the kind of `node`.
+pub fn walk_node<'a, V: Visitor<'a>>(visitor: &mut V, node: NodeId) {
+    match visitor.arena().kind(node) {
+        NodeKind::Program { .. } => visitor.visit_program(node),
+        NodeKind::VariableDeclaration { .. } => visitor.visit_variable_declaration(node),
+        NodeKind::VariableDeclarator { .. } => visitor.visit_variable_declarator(node),
+        NodeKind::FunctionDeclaration { .. } => visitor.visit_function_declaration(node),
+        NodeKind::IfStatement { .. } => visitor.visit_if_statement(node),
+        NodeKind::Block { .. } => visitor.visit_block(node),
+        NodeKind::ExpressionStatement { .. } => visitor.visit_expression_statement(node),
+        NodeKind::ReturnStatement { .. } => visitor.visit_return_statement(node),
+        NodeKind::BreakStatement { .. } => visitor.visit_break_statement(node),
+        NodeKind::ContinueStatement { .. } => visitor.visit_continue_statement(node),
+        NodeKind::ThrowStatement { .. } => visitor.visit_throw_statement(node),
+        NodeKind::TryStatement { .. } => visitor.visit_try_statement(node),
+        NodeKind::CatchClause { .. } => visitor.visit_catch_clause(node),
+        NodeKind::LabeledStatement { .. } => visitor.visit_labeled_statement(node),
+        NodeKind::WhileStatement { .. } => visitor.visit_while_statement(node),
+        NodeKind::ForStatement { .. } => visitor.visit_for_statement(node),
+        NodeKind::AssignmentExpression { .. } => visitor.visit_assignment_expression(node),
+        NodeKind::BinaryExpression { .. } => visitor.visit_binary_expression(node),
+        NodeKind::LogicalExpression { .. } => visitor.visit_logical_expression(node),
+        NodeKind::UnaryExpression { .. } => visitor.visit_unary_expression(node),
+        NodeKind::UpdateExpression { .. } => visitor.visit_update_expression(node),
+        NodeKind::CallExpression { .. } => visitor.visit_call_expression(node),
+        NodeKind::MemberExpression { .. } => visitor.visit_member_expression(node),
+        NodeKind::ObjectExpression { .. } => visitor.visit_object_expression(node),
+        NodeKind::Property { .. } => visitor.visit_property(node),
+        NodeKind::Literal(_) => visitor.visit_literal(node),
+        NodeKind::Identifier(_) => visitor.visit_identifier(node),
+        NodeKind::ThisExpression => visitor.visit_this_expression(node),
+    }
+}
+
+/// Visits each direct child of `node` in source order.
+pub fn walk_children<'a, V: Visitor<'a>>(visitor: &mut V, node: NodeId) {
+    for child in visitor.arena().children(node) {
+        visitor.visit_node(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn parse(code: &str) -> AstArena {
+        AstArena::parse(&Lexer::new(code).tokenize())
+    }
+
+    struct IdentifierCollector<'a> {
+        arena: &'a AstArena,
+        names: Vec<String>,
+        declarations: usize,
+    }
+
+    impl<'a> Visitor<'a> for IdentifierCollector<'a> {
+        fn arena(&self) -> &'a AstArena {
+            self.arena
+        }
+
+        fn visit_variable_declaration(&mut self, node: NodeId) {
+            self.declarations += 1;
+            walk_children(self, node);
+        }
+
+        fn visit_identifier(&mut self, node: NodeId) {
+            if let NodeKind::Identifier(name) = *self.arena.kind(node) {
+                self.names.push(name.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor_walks_in_source_order() {
+        let arena = parse("let a = b + c; d = a; let e;");
+        let mut collector = IdentifierCollector {
+            arena: &arena,
+            names: Vec::new(),
+            declarations: 0,
+        };
+        collector.visit_node(arena.root());
+
+        assert_eq!(collector.names, vec!["a", "b", "c", "d", "a", "e"]);
+        assert_eq!(collector.declarations, 2);
+    }
+
+    #[test]
+    fn test_visitor_can_skip_subtrees() {
+        struct TopLevelOnly<'a>(&'a AstArena, usize);
+
+        impl<'a> Visitor<'a> for TopLevelOnly<'a> {
+            fn arena(&self) -> &'a AstArena {
+                self.0
+            }
+            fn visit_expression_statement(&mut self, _node: NodeId) {
+                self.1 += 1;
+            }
+            fn visit_identifier(&mut self, _node: NodeId) {
+                panic!("identifiers inside statements are not visited");
+            }
+        }
+
+        let arena = parse("a; b + c;");
+        let mut visitor = TopLevelOnly(&arena, 0);
+        visitor.visit_node(arena.root());
+        assert_eq!(visitor.1, 2);
+    }
+
+    #[test]
+    fn test_visit_node_sees_every_node() {
+        struct Counter<'a>(&'a AstArena, usize);
+
+        impl<'a> Visitor<'a> for Counter<'a> {
+            fn arena(&self) -> &'a AstArena {
+                self.0
+            }
+            fn visit_node(&mut self, node: NodeId) {
+                self.1 += 1;
+                walk_node(self, node);
+            }
+        }
+
+        let arena = parse("function f(a) { return a.b + 1; } f(2);");
+        let mut counter = Counter(&arena, 0);
+        counter.visit_node(arena.root());
+        assert_eq!(counter.1, arena.ids().count());
+    }
+}
@@ -0,0 +1,564 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// An arbitrary-precision integer, the value of a JavaScript BigInt.
+///
+/// Stored as a sign and a little-endian magnitude of 32-bit limbs without
+/// leading zero limbs, so zero is the empty magnitude and never negative.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The value of a BigInt literal's digits, without the `n` suffix:
+    /// decimal or `0x`/`0o`/`0b` prefixed.
+    pub fn parse_literal(digits: &str) -> Option<Self> {
+        let (radix, digits) = match digits.get(..2) {
+            Some("0x" | "0X") => (16, &digits[2..]),
+            Some("0o" | "0O") => (8, &digits[2..]),
+            Some("0b" | "0B") => (2, &digits[2..]),
+            _ => (10, digits),
+        };
+        Self::parse_digits(digits, radix)
+    }
+
+    /// StringToBigInt: like the numeric string grammar, but integers only,
+    /// with no `Infinity`, and the empty string is `0n`.
+    pub fn from_js_string(text: &str) -> Option<Self> {
+        let text = text.trim_matches(crate::runtime::value::is_js_whitespace);
+        if text.is_empty() {
+            return Some(BigInt::zero());
+        }
+        if let Some(prefix) = text.get(..2) {
+            if matches!(prefix, "0x" | "0X" | "0o" | "0O" | "0b" | "0B") {
+                return Self::parse_literal(text);
+            }
+        }
+        let (negative, digits) = match text.as_bytes()[0] {
+            b'-' => (true, &text[1..]),
+            b'+' => (false, &text[1..]),
+            _ => (false, text),
+        };
+        let value = Self::parse_digits(digits, 10)?;
+        Some(if negative { -value } else { value })
+    }
+
+    fn parse_digits(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+        let mut value = BigInt::zero();
+        for ch in digits.chars() {
+            let digit = ch.to_digit(radix)?;
+            value.mul_add_small(radix, digit);
+        }
+        Some(value)
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        let mut magnitude = vec![value.unsigned_abs() as u32, (value.unsigned_abs() >> 32) as u32];
+        trim(&mut magnitude);
+        BigInt::new(value < 0, magnitude)
+    }
+
+    /// NumberToBigInt: the exact value of an integral Number, or `None` for
+    /// fractions, NaN and infinities.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.trunc() != value {
+            return None;
+        }
+        let bits = value.abs().to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let mantissa = if exponent == 0 {
+            (bits & ((1 << 52) - 1)) << 1
+        } else {
+            (bits & ((1 << 52) - 1)) | (1 << 52)
+        };
+        // value = mantissa * 2^(exponent - 1075)
+        let shift = exponent - 1075;
+        let mut magnitude = vec![mantissa as u32, (mantissa >> 32) as u32];
+        trim(&mut magnitude);
+        let magnitude = if shift >= 0 {
+            shift_left(&magnitude, shift as usize)
+        } else {
+            shift_right(&magnitude, (-shift) as usize)
+        };
+        Some(BigInt::new(value < 0.0, magnitude))
+    }
+
+    /// The Number closest to this value, ties to even, as `Number(x)` does.
+    pub fn to_f64(&self) -> f64 {
+        let bits = bit_length(&self.magnitude);
+        let magnitude = if bits <= 64 {
+            to_u64(&self.magnitude) as f64
+        } else {
+            // Keep the top 64 bits and fold everything below into a sticky
+            // bit, which is enough to round correctly to 53 bits.
+            let shift = bits - 64;
+            let mut top = to_u64(&shift_right(&self.magnitude, shift));
+            if has_bits_below(&self.magnitude, shift) {
+                top |= 1;
+            }
+            scale(top as f64, shift)
+        };
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// Compares with a Number, as the relational operators do; `None` when
+    /// the Number is NaN.
+    pub fn compare_f64(&self, other: f64) -> Option<Ordering> {
+        if other.is_nan() {
+            return None;
+        }
+        if other.is_infinite() {
+            return Some(if other > 0.0 { Ordering::Less } else { Ordering::Greater });
+        }
+        let integral = other.trunc();
+        let ordering = self.cmp(&BigInt::from_f64(integral).unwrap());
+        Some(match ordering {
+            Ordering::Equal if other > integral => Ordering::Less,
+            Ordering::Equal if other < integral => Ordering::Greater,
+            ordering => ordering,
+        })
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitudes(&self.magnitude, &other.magnitude));
+        }
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Equal => BigInt::zero(),
+            Ordering::Greater => BigInt::new(self.negative, sub_magnitudes(&self.magnitude, &other.magnitude)),
+            Ordering::Less => BigInt::new(other.negative, sub_magnitudes(&other.magnitude, &self.magnitude)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&-other.clone())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        let mut product = vec![0u32; self.magnitude.len() + other.magnitude.len()];
+        for (i, &a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.magnitude.iter().enumerate() {
+                let sum = product[i + j] as u64 + a as u64 * b as u64 + carry;
+                product[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            product[i + other.magnitude.len()] = carry as u32;
+        }
+        trim(&mut product);
+        BigInt::new(self.negative != other.negative, product)
+    }
+
+    /// Division truncating towards zero, and the matching remainder, which
+    /// takes the sign of the dividend. `None` when dividing by zero.
+    pub fn div_rem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let mut quotient = vec![0u32; self.magnitude.len()];
+        let mut remainder = Vec::new();
+        for bit in (0..bit_length(&self.magnitude)).rev() {
+            remainder = shift_left(&remainder, 1);
+            if (self.magnitude[bit / 32] >> (bit % 32)) & 1 == 1 {
+                if remainder.is_empty() {
+                    remainder.push(1);
+                } else {
+                    remainder[0] |= 1;
+                }
+            }
+            if compare_magnitudes(&remainder, &divisor.magnitude) != Ordering::Less {
+                remainder = sub_magnitudes(&remainder, &divisor.magnitude);
+                quotient[bit / 32] |= 1 << (bit % 32);
+            }
+        }
+        trim(&mut quotient);
+        Some((
+            BigInt::new(self.negative != divisor.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+
+    /// `self ** exponent` for a non-negative exponent.
+    pub fn pow(&self, mut exponent: u64) -> BigInt {
+        let mut result = BigInt::from_i64(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base);
+            }
+        }
+        result
+    }
+
+    /// `self << shift`, where a negative shift shifts right. Right shifts
+    /// round towards negative infinity, as on two's complement integers.
+    pub fn shl(&self, shift: i64) -> BigInt {
+        if shift >= 0 {
+            return BigInt::new(self.negative, shift_left(&self.magnitude, shift as usize));
+        }
+        let shift = shift.unsigned_abs() as usize;
+        let truncated = BigInt::new(self.negative, shift_right(&self.magnitude, shift));
+        if self.negative && has_bits_below(&self.magnitude, shift) {
+            truncated.sub(&BigInt::from_i64(1))
+        } else {
+            truncated
+        }
+    }
+
+    pub fn bitand(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a & b)
+    }
+
+    pub fn bitor(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a | b)
+    }
+
+    pub fn bitxor(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a ^ b)
+    }
+
+    /// `~self`, which is `-self - 1`.
+    pub fn not(&self) -> BigInt {
+        (-self.clone()).sub(&BigInt::from_i64(1))
+    }
+
+    /// Applies a bitwise operator limb by limb to the two's complement
+    /// forms, sign-extended to a common length.
+    fn bitwise(&self, other: &BigInt, op: fn(u32, u32) -> u32) -> BigInt {
+        let len = self.magnitude.len().max(other.magnitude.len()) + 1;
+        let a = self.twos_complement(len);
+        let b = other.twos_complement(len);
+        let limbs: Vec<u32> = a.iter().zip(&b).map(|(&a, &b)| op(a, b)).collect();
+        BigInt::from_twos_complement(limbs)
+    }
+
+    fn twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut limbs = self.magnitude.clone();
+        limbs.resize(len, 0);
+        if self.negative {
+            let mut carry = true;
+            for limb in &mut limbs {
+                *limb = !*limb;
+                if carry {
+                    let (sum, overflow) = limb.overflowing_add(1);
+                    *limb = sum;
+                    carry = overflow;
+                }
+            }
+        }
+        limbs
+    }
+
+    fn from_twos_complement(mut limbs: Vec<u32>) -> BigInt {
+        let negative = limbs.last().is_some_and(|&top| top >> 31 == 1);
+        if negative {
+            let positive = BigInt {
+                negative: true,
+                magnitude: limbs.clone(),
+            }
+            .twos_complement(limbs.len());
+            limbs = positive;
+        }
+        trim(&mut limbs);
+        BigInt::new(negative, limbs)
+    }
+
+    fn new(negative: bool, magnitude: Vec<u32>) -> Self {
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    fn mul_add_small(&mut self, factor: u32, addend: u32) {
+        let mut carry = addend as u64;
+        for limb in &mut self.magnitude {
+            let value = *limb as u64 * factor as u64 + carry;
+            *limb = value as u32;
+            carry = value >> 32;
+        }
+        if carry > 0 {
+            self.magnitude.push(carry as u32);
+        }
+    }
+
+    /// Divides the magnitude in place by `divisor`, returning the remainder.
+    /// The digits in `radix`, from 2 to 36, as `BigInt.prototype.toString`
+    /// gives them.
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        if radix == 10 {
+            return self.to_string();
+        }
+        let mut rest = self.clone();
+        let mut digits = Vec::new();
+        loop {
+            digits.push(char::from_digit(rest.div_rem_small(radix), radix).unwrap());
+            if rest.is_zero() {
+                break;
+            }
+        }
+        if self.negative {
+            digits.push('-');
+        }
+        digits.iter().rev().collect()
+    }
+
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for limb in self.magnitude.iter_mut().rev() {
+            let value = (remainder << 32) | *limb as u64;
+            *limb = (value / divisor as u64) as u32;
+            remainder = value % divisor as u64;
+        }
+        trim(&mut self.magnitude);
+        remainder as u32
+    }
+}
+
+impl std::ops::Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    /// Decimal digits, as BigInt's ToString produces; no `n` suffix.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        let mut rest = self.clone();
+        let mut chunks = Vec::new();
+        while !rest.is_zero() {
+            chunks.push(rest.div_rem_small(1_000_000_000));
+        }
+
+        let mut text = String::new();
+        if self.negative {
+            text.push('-');
+        }
+        text.push_str(&chunks.pop().unwrap().to_string());
+        for chunk in chunks.iter().rev() {
+            text.push_str(&format!("{:09}", chunk));
+        }
+        f.write_str(&text)
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let value = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(value as u32);
+        carry = value >> 32;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+/// `a - b` where `a >= b`.
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut value = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = (value < 0) as i64;
+        if value < 0 {
+            value += 1 << 32;
+        }
+        difference.push(value as u32);
+    }
+    trim(&mut difference);
+    difference
+}
+
+fn bit_length(magnitude: &[u32]) -> usize {
+    match magnitude.last() {
+        Some(top) => magnitude.len() * 32 - top.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn shift_left(magnitude: &[u32], shift: usize) -> Vec<u32> {
+    if magnitude.is_empty() {
+        return Vec::new();
+    }
+    let (limbs, bits) = (shift / 32, shift % 32);
+    let mut shifted = vec![0u32; limbs];
+    let mut carry = 0u32;
+    for &limb in magnitude {
+        shifted.push(if bits == 0 { limb } else { (limb << bits) | carry });
+        carry = if bits == 0 { 0 } else { limb >> (32 - bits) };
+    }
+    if carry > 0 {
+        shifted.push(carry);
+    }
+    shifted
+}
+
+fn shift_right(magnitude: &[u32], shift: usize) -> Vec<u32> {
+    let (limbs, bits) = (shift / 32, shift % 32);
+    if limbs >= magnitude.len() {
+        return Vec::new();
+    }
+    let mut shifted: Vec<u32> = magnitude[limbs..]
+        .iter()
+        .enumerate()
+        .map(|(i, &limb)| {
+            let high = magnitude.get(limbs + i + 1).copied().unwrap_or(0);
+            if bits == 0 {
+                limb
+            } else {
+                (limb >> bits) | (high << (32 - bits))
+            }
+        })
+        .collect();
+    trim(&mut shifted);
+    shifted
+}
+
+fn has_bits_below(magnitude: &[u32], shift: usize) -> bool {
+    let (limbs, bits) = (shift / 32, shift % 32);
+    magnitude.iter().take(limbs).any(|&limb| limb != 0)
+        || (bits > 0 && magnitude.get(limbs).is_some_and(|&limb| limb & ((1 << bits) - 1) != 0))
+}
+
+fn to_u64(magnitude: &[u32]) -> u64 {
+    magnitude.iter().rev().fold(0, |acc, &limb| (acc << 32) | limb as u64)
+}
+
+/// `value * 2^exponent` without intermediate overflow of the power.
+fn scale(mut value: f64, mut exponent: usize) -> f64 {
+    while exponent > 0 && value.is_finite() {
+        let step = exponent.min(1000);
+        value *= 2f64.powi(step as i32);
+        exponent -= step;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::from_js_string(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(big("123456789012345678901234567890").to_string(), "123456789012345678901234567890");
+        assert_eq!(big("-42").to_string(), "-42");
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("  0xff  ").to_string(), "255");
+        assert_eq!(BigInt::parse_literal("0b1010").unwrap().to_string(), "10");
+        assert_eq!(big("").to_string(), "0");
+        assert_eq!(big("-255").to_string_radix(16), "-ff");
+        assert_eq!(big("0x123456789abcdef0123").to_string_radix(16), "123456789abcdef0123");
+        assert_eq!(big("0").to_string_radix(2), "0");
+        assert_eq!(BigInt::from_js_string("1.5"), None);
+        assert_eq!(BigInt::from_js_string("-0x1"), None);
+        assert_eq!(BigInt::from_js_string("Infinity"), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = big("99999999999999999999");
+        let b = big("-12345678901234567890");
+        assert_eq!(a.add(&b).to_string(), "87654321098765432109");
+        assert_eq!(b.sub(&a).to_string(), "-112345678901234567889");
+        assert_eq!(a.mul(&b).to_string(), "-1234567890123456788987654321098765432110");
+
+        let (quotient, remainder) = big("-7").div_rem(&big("2")).unwrap();
+        assert_eq!((quotient.to_string(), remainder.to_string()), ("-3".to_string(), "-1".to_string()));
+        assert_eq!(a.div_rem(&BigInt::zero()), None);
+        assert_eq!(big("2").pow(100).to_string(), "1267650600228229401496703205376");
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(big("-5").shl(-1).to_string(), "-3");
+        assert_eq!(big("5").shl(-1).to_string(), "2");
+        assert_eq!(big("1").shl(64).to_string(), "18446744073709551616");
+        assert_eq!(big("-6").bitand(&big("7")).to_string(), "2");
+        assert_eq!(big("-6").bitor(&big("3")).to_string(), "-5");
+        assert_eq!(big("-1").bitxor(&big("5")).to_string(), "-6");
+        assert_eq!(big("5").not().to_string(), "-6");
+    }
+
+    #[test]
+    fn test_number_conversions() {
+        assert_eq!(BigInt::from_f64(1e21).unwrap().to_string(), "1000000000000000000000");
+        assert_eq!(BigInt::from_f64(-3.0).unwrap().to_string(), "-3");
+        assert_eq!(BigInt::from_f64(0.5), None);
+        assert_eq!(BigInt::from_f64(f64::NAN), None);
+
+        assert_eq!(big("9007199254740993").to_f64(), 9007199254740992.0);
+        assert_eq!(big("9007199254740995").to_f64(), 9007199254740996.0);
+        assert_eq!(big("-18446744073709551617").to_f64(), -18446744073709551616.0);
+        assert_eq!(big("1").shl(1100).to_f64(), f64::INFINITY);
+
+        assert_eq!(big("2").compare_f64(2.5), Some(Ordering::Less));
+        assert_eq!(big("-2").compare_f64(-2.5), Some(Ordering::Greater));
+        assert_eq!(big("3").compare_f64(3.0), Some(Ordering::Equal));
+        assert_eq!(big("3").compare_f64(f64::INFINITY), Some(Ordering::Less));
+        assert_eq!(big("3").compare_f64(f64::NAN), None);
+    }
+}
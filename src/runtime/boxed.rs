@@ -0,0 +1,466 @@
+use crate::parser::operators::BinaryOp;
+use crate::runtime::bigint::BigInt;
+use crate::runtime::gc::{Trace, Tracer};
+use crate::runtime::object::ObjectRef;
+use crate::runtime::string::JsString;
+use crate::runtime::symbol::Symbol;
+use crate::runtime::value::{to_int32, to_uint32, JsValue};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop};
+use std::rc::Rc;
+
+// Heap pointers are stored in the low 48 bits of a value.
+const _: () = assert!(mem::size_of::<usize>() == 8, "NaN-boxing needs 64-bit pointers");
+
+/// The top 16 bits of every boxed value that is not a double: sign set,
+/// exponent all ones and the quiet bit set, a NaN arithmetic never makes
+/// once NaNs are stored as [`CANONICAL_NAN`].
+const SPECIAL: u64 = 0xFFF8;
+const INT: u64 = 0xFFF9;
+const STRING: u64 = 0xFFFA;
+const SYMBOL: u64 = 0xFFFB;
+const BIGINT: u64 = 0xFFFC;
+const OBJECT: u64 = 0xFFFD;
+
+const TAG_SHIFT: u32 = 48;
+const PAYLOAD: u64 = (1 << TAG_SHIFT) - 1;
+const CANONICAL_NAN: u64 = 0x7FF8_0000_0000_0000;
+
+/// A JavaScript value in 64 bits, as the VM's operand stack, registers and
+/// scopes and objects' property slots hold them: a double, unless its bits
+/// are one of the NaNs reserved for the other types, which carry a small
+/// integer, a constant such as `undefined`, or a pointer to a string,
+/// symbol, BigInt or object.
+///
+/// Numbers that are integers in the int32 range are stored as such, which
+/// lets [`arithmetic`] skip floating point for them. A boxed value owns a
+/// reference count of what it points to, like the [`JsValue`] it converts
+/// to and from.
+pub struct Value {
+    bits: u64,
+    /// Pointers are to this thread's heap.
+    _local: PhantomData<*const ()>,
+}
+
+/// Heap handles a [`Value`] stores as a thin pointer.
+trait Pointer: Clone {
+    const TAG: u64;
+    fn into_raw(self) -> *const ();
+    /// # Safety
+    /// `pointer` must come from [`Pointer::into_raw`] of the same type, and
+    /// the count it owns passes to the handle.
+    unsafe fn from_raw(pointer: *const ()) -> Self;
+}
+
+impl Pointer for JsString {
+    const TAG: u64 = STRING;
+
+    fn into_raw(self) -> *const () {
+        JsString::into_raw(self)
+    }
+
+    unsafe fn from_raw(pointer: *const ()) -> Self {
+        JsString::from_raw(pointer)
+    }
+}
+
+impl Pointer for Symbol {
+    const TAG: u64 = SYMBOL;
+
+    fn into_raw(self) -> *const () {
+        Symbol::into_raw(self)
+    }
+
+    unsafe fn from_raw(pointer: *const ()) -> Self {
+        Symbol::from_raw(pointer)
+    }
+}
+
+impl Pointer for Rc<BigInt> {
+    const TAG: u64 = BIGINT;
+
+    fn into_raw(self) -> *const () {
+        Rc::into_raw(self) as *const ()
+    }
+
+    unsafe fn from_raw(pointer: *const ()) -> Self {
+        Rc::from_raw(pointer as *const BigInt)
+    }
+}
+
+impl Pointer for ObjectRef {
+    const TAG: u64 = OBJECT;
+
+    fn into_raw(self) -> *const () {
+        ObjectRef::into_raw(self)
+    }
+
+    unsafe fn from_raw(pointer: *const ()) -> Self {
+        ObjectRef::from_raw(pointer)
+    }
+}
+
+impl Value {
+    pub const UNDEFINED: Value = Value::from_bits(SPECIAL << TAG_SHIFT);
+    pub const NULL: Value = Value::from_bits(SPECIAL << TAG_SHIFT | 1);
+    pub const FALSE: Value = Value::from_bits(SPECIAL << TAG_SHIFT | 2);
+    pub const TRUE: Value = Value::from_bits(SPECIAL << TAG_SHIFT | 3);
+    /// An uninitialized register or scope slot, in its temporal dead zone.
+    /// It never converts to a [`JsValue`].
+    pub const HOLE: Value = Value::from_bits(SPECIAL << TAG_SHIFT | 4);
+
+    const fn from_bits(bits: u64) -> Value {
+        Value {
+            bits,
+            _local: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn int(value: i32) -> Value {
+        Value::from_bits(INT << TAG_SHIFT | value as u32 as u64)
+    }
+
+    /// `number`, as a small integer when it is one and not `-0`.
+    #[inline]
+    pub fn number(number: f64) -> Value {
+        let int = number as i32;
+        if int as f64 == number && !(int == 0 && number.is_sign_negative()) {
+            return Value::int(int);
+        }
+        if number.is_nan() {
+            return Value::from_bits(CANONICAL_NAN);
+        }
+        Value::from_bits(number.to_bits())
+    }
+
+    pub fn boolean(value: bool) -> Value {
+        if value {
+            Value::TRUE
+        } else {
+            Value::FALSE
+        }
+    }
+
+    fn pointer<P: Pointer>(handle: P) -> Value {
+        let address = handle.into_raw() as u64;
+        assert_eq!(address & !PAYLOAD, 0, "heap pointers fit in 48 bits");
+        Value::from_bits(P::TAG << TAG_SHIFT | address)
+    }
+
+    #[inline]
+    fn tag(&self) -> u64 {
+        self.bits >> TAG_SHIFT
+    }
+
+    #[inline]
+    fn is_double(&self) -> bool {
+        self.tag() < SPECIAL
+    }
+
+    /// The handle this value points to, without taking over its count.
+    ///
+    /// # Safety
+    /// The value must have `P`'s tag.
+    unsafe fn borrow<P: Pointer>(&self) -> ManuallyDrop<P> {
+        debug_assert_eq!(self.tag(), P::TAG);
+        ManuallyDrop::new(P::from_raw((self.bits & PAYLOAD) as *const ()))
+    }
+
+    pub fn is_hole(&self) -> bool {
+        self.bits == Value::HOLE.bits
+    }
+
+    pub fn is_nullish(&self) -> bool {
+        self.bits == Value::UNDEFINED.bits || self.bits == Value::NULL.bits
+    }
+
+    #[inline]
+    pub fn as_int(&self) -> Option<i32> {
+        (self.tag() == INT).then_some(self.bits as u32 as i32)
+    }
+
+    #[inline]
+    pub fn as_number(&self) -> Option<f64> {
+        match self.as_int() {
+            Some(int) => Some(int as f64),
+            None => self.is_double().then(|| f64::from_bits(self.bits)),
+        }
+    }
+
+    /// ToBoolean, without converting to a [`JsValue`].
+    pub fn to_boolean(&self) -> bool {
+        match self.tag() {
+            SPECIAL => self.bits == Value::TRUE.bits,
+            INT => self.bits as u32 != 0,
+            // SAFETY: the tags say what the pointers are.
+            STRING => unsafe { !self.borrow::<JsString>().is_empty() },
+            BIGINT => unsafe { !self.borrow::<Rc<BigInt>>().is_zero() },
+            SYMBOL | OBJECT => true,
+            _ => {
+                let number = f64::from_bits(self.bits);
+                !(number.is_nan() || number == 0.0)
+            }
+        }
+    }
+
+    /// The [`JsValue`] this value holds, sharing what it points to.
+    pub fn to_js(&self) -> JsValue {
+        // SAFETY: the tags say what the pointers are, and the handles are
+        // cloned rather than taken.
+        unsafe {
+            match self.tag() {
+                SPECIAL => self.special(),
+                INT => JsValue::Number(self.bits as u32 as i32 as f64),
+                STRING => JsValue::String((*self.borrow::<JsString>()).clone()),
+                SYMBOL => JsValue::Symbol((*self.borrow::<Symbol>()).clone()),
+                BIGINT => JsValue::BigInt((*self.borrow::<Rc<BigInt>>()).clone()),
+                OBJECT => JsValue::Object((*self.borrow::<ObjectRef>()).clone()),
+                _ => JsValue::Number(f64::from_bits(self.bits)),
+            }
+        }
+    }
+
+    /// The [`JsValue`] this value holds, handing over its reference.
+    pub fn into_js(self) -> JsValue {
+        let value = ManuallyDrop::new(self);
+        let pointer = (value.bits & PAYLOAD) as *const ();
+        // SAFETY: as for `to_js`; `value` is not dropped, so its count
+        // moves to the result.
+        unsafe {
+            match value.tag() {
+                STRING => JsValue::String(JsString::from_raw(pointer)),
+                SYMBOL => JsValue::Symbol(Symbol::from_raw(pointer)),
+                BIGINT => JsValue::BigInt(<Rc<BigInt> as Pointer>::from_raw(pointer)),
+                OBJECT => JsValue::Object(ObjectRef::from_raw(pointer)),
+                _ => value.to_js(),
+            }
+        }
+    }
+
+    fn special(&self) -> JsValue {
+        match self.bits & PAYLOAD {
+            0 => JsValue::Undefined,
+            1 => JsValue::Null,
+            2 => JsValue::Boolean(false),
+            3 => JsValue::Boolean(true),
+            _ => panic!("a hole read as a value"),
+        }
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        // SAFETY: the tags say what the pointers are; the clones' counts
+        // become the copy's.
+        unsafe {
+            match self.tag() {
+                STRING => mem::forget((*self.borrow::<JsString>()).clone()),
+                SYMBOL => mem::forget((*self.borrow::<Symbol>()).clone()),
+                BIGINT => mem::forget((*self.borrow::<Rc<BigInt>>()).clone()),
+                OBJECT => mem::forget((*self.borrow::<ObjectRef>()).clone()),
+                _ => {}
+            }
+        }
+        Value::from_bits(self.bits)
+    }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) {
+        // SAFETY: the tags say what the pointers are, and this value's
+        // count is given back.
+        unsafe {
+            match self.tag() {
+                STRING => drop(ManuallyDrop::into_inner(self.borrow::<JsString>())),
+                SYMBOL => drop(ManuallyDrop::into_inner(self.borrow::<Symbol>())),
+                BIGINT => drop(ManuallyDrop::into_inner(self.borrow::<Rc<BigInt>>())),
+                OBJECT => drop(ManuallyDrop::into_inner(self.borrow::<ObjectRef>())),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl From<JsValue> for Value {
+    fn from(value: JsValue) -> Self {
+        match value {
+            JsValue::Undefined => Value::UNDEFINED,
+            JsValue::Null => Value::NULL,
+            JsValue::Boolean(value) => Value::boolean(value),
+            JsValue::Number(number) => Value::number(number),
+            JsValue::String(text) => Value::pointer(text),
+            JsValue::Symbol(symbol) => Value::pointer(symbol),
+            JsValue::BigInt(value) => Value::pointer(value),
+            JsValue::Object(object) => Value::pointer(object),
+        }
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::UNDEFINED
+    }
+}
+
+impl Trace for Value {
+    fn trace(&self, tracer: &mut Tracer) {
+        if self.tag() == OBJECT {
+            // SAFETY: the tag says the pointer is an object's.
+            unsafe { self.borrow::<ObjectRef>().trace(tracer) }
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_hole() {
+            true => f.write_str("<hole>"),
+            false => self.to_js().fmt(f),
+        }
+    }
+}
+
+/// `left operator right` when both are numbers and the operator needs no
+/// conversions, staying in int32 arithmetic while the result fits. `None`
+/// leaves the operation to [`crate::runtime::operations::binary`].
+#[inline]
+pub fn arithmetic(operator: BinaryOp, left: &Value, right: &Value) -> Option<Value> {
+    // Adding two ints is most of the arithmetic loops do; checking for it
+    // alone keeps it small enough to inline into the dispatch loop.
+    if let (BinaryOp::Add, Some(a), Some(b)) = (operator, left.as_int(), right.as_int()) {
+        if let Some(sum) = a.checked_add(b) {
+            return Some(Value::int(sum));
+        }
+    }
+    general_arithmetic(operator, left, right)
+}
+
+#[inline(never)]
+fn general_arithmetic(operator: BinaryOp, left: &Value, right: &Value) -> Option<Value> {
+    if let (Some(a), Some(b)) = (left.as_int(), right.as_int()) {
+        let int = match operator {
+            BinaryOp::Add => a.checked_add(b),
+            BinaryOp::Sub => a.checked_sub(b),
+            // A zero product of a negative number is -0.
+            BinaryOp::Mul => a.checked_mul(b).filter(|&product| product != 0 || (a >= 0 && b >= 0)),
+            BinaryOp::BitAnd => Some(a & b),
+            BinaryOp::BitOr => Some(a | b),
+            BinaryOp::BitXor => Some(a ^ b),
+            BinaryOp::Shl => Some(a.wrapping_shl(b as u32 & 31)),
+            BinaryOp::Shr => Some(a >> (b as u32 & 31)),
+            BinaryOp::Lt => return Some(Value::boolean(a < b)),
+            BinaryOp::LtEq => return Some(Value::boolean(a <= b)),
+            BinaryOp::Gt => return Some(Value::boolean(a > b)),
+            BinaryOp::GtEq => return Some(Value::boolean(a >= b)),
+            BinaryOp::Eq | BinaryOp::StrictEq => return Some(Value::boolean(a == b)),
+            BinaryOp::NotEq | BinaryOp::StrictNotEq => return Some(Value::boolean(a != b)),
+            _ => None,
+        };
+        if let Some(int) = int {
+            return Some(Value::int(int));
+        }
+    }
+    let (a, b) = (left.as_number()?, right.as_number()?);
+    Some(match operator {
+        BinaryOp::Add => Value::number(a + b),
+        BinaryOp::Sub => Value::number(a - b),
+        BinaryOp::Mul => Value::number(a * b),
+        BinaryOp::Div => Value::number(a / b),
+        BinaryOp::Mod => Value::number(a % b),
+        BinaryOp::BitAnd => Value::int(to_int32(a) & to_int32(b)),
+        BinaryOp::BitOr => Value::int(to_int32(a) | to_int32(b)),
+        BinaryOp::BitXor => Value::int(to_int32(a) ^ to_int32(b)),
+        BinaryOp::Shl => Value::int(to_int32(a).wrapping_shl(to_uint32(b) & 31)),
+        BinaryOp::Shr => Value::int(to_int32(a) >> (to_uint32(b) & 31)),
+        BinaryOp::UShr => Value::number((to_uint32(a) >> (to_uint32(b) & 31)) as f64),
+        BinaryOp::Lt => Value::boolean(a < b),
+        BinaryOp::LtEq => Value::boolean(a <= b),
+        BinaryOp::Gt => Value::boolean(a > b),
+        BinaryOp::GtEq => Value::boolean(a >= b),
+        BinaryOp::Eq | BinaryOp::StrictEq => Value::boolean(a == b),
+        BinaryOp::NotEq | BinaryOp::StrictNotEq => Value::boolean(a != b),
+        _ => return None,
+    })
+}
+
+/// `value + delta` for `++` and `--` on a number.
+pub fn increment(value: &Value, delta: i32) -> Option<Value> {
+    match value.as_int() {
+        Some(int) => Some(int.checked_add(delta).map_or_else(|| Value::number(int as f64 + delta as f64), Value::int)),
+        None => value.as_number().map(|number| Value::number(number + delta as f64)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: JsValue) -> String {
+        let boxed = Value::from(value);
+        let copy = boxed.clone();
+        assert_eq!(format!("{:?}", copy.to_js()), format!("{:?}", boxed.to_js()));
+        format!("{:?}", boxed.into_js())
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(mem::size_of::<Value>(), 8);
+        assert_eq!(Value::number(5.0).as_int(), Some(5));
+        assert_eq!(Value::number(-0.0).as_int(), None);
+        assert!(Value::number(-0.0).as_number().unwrap().is_sign_negative());
+        assert_eq!(Value::number(2.5).as_number(), Some(2.5));
+        assert_eq!(Value::number(1e10).as_int(), None);
+        // Every NaN is stored as the same one, so none looks like a tag.
+        let negative_nan = f64::from_bits(0xFFFF_0000_0000_0001);
+        assert!(Value::number(negative_nan).as_number().unwrap().is_nan());
+        assert_eq!(Value::number(f64::NEG_INFINITY).as_number(), Some(f64::NEG_INFINITY));
+
+        assert_eq!(round_trip(JsValue::Undefined), "Undefined");
+        assert_eq!(round_trip(JsValue::Boolean(true)), "Boolean(true)");
+        assert_eq!(round_trip(JsValue::Number(-1.5)), "Number(-1.5)");
+        assert_eq!(round_trip(JsValue::String("boxed".into())), "String(\"boxed\")");
+        assert_eq!(round_trip(JsValue::Symbol(Symbol::new(Some("s")))), "Symbol(Symbol(s))");
+        assert_eq!(Value::from(JsValue::from(BigInt::from_js_string("12").unwrap())).into_js().to_string(), "12");
+        assert!(!Value::from(JsValue::String("".into())).to_boolean());
+        assert!(Value::HOLE.is_hole() && !Value::UNDEFINED.is_hole());
+        assert!(Value::NULL.is_nullish() && !Value::FALSE.is_nullish());
+    }
+
+    #[test]
+    fn test_reference_counts() {
+        let object = ObjectRef::ordinary(None);
+        let handle = object.clone();
+        let boxed = Value::from(JsValue::Object(object));
+        let copies = vec![boxed.clone(), boxed.clone()];
+        let JsValue::Object(back) = boxed.to_js() else { panic!("an object") };
+        assert!(ObjectRef::ptr_eq(&back, &handle));
+        drop(copies);
+        drop(back);
+        let JsValue::Object(taken) = boxed.into_js() else { panic!("an object") };
+        assert!(ObjectRef::ptr_eq(&taken, &handle));
+        // No count is left behind once the handles are gone.
+        let weak = handle.downgrade();
+        drop((handle, taken));
+        assert!(!weak.is_alive());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let compute = |operator, a: f64, b: f64| arithmetic(operator, &Value::number(a), &Value::number(b)).map(|value| value.to_js().to_string());
+        assert_eq!(compute(BinaryOp::Add, 1.0, 2.0).as_deref(), Some("3"));
+        assert_eq!(compute(BinaryOp::Add, i32::MAX as f64, 1.0).as_deref(), Some("2147483648"));
+        assert!(arithmetic(BinaryOp::Mul, &Value::int(0), &Value::int(-3)).unwrap().as_number().unwrap().is_sign_negative());
+        assert_eq!(compute(BinaryOp::Div, 7.0, 2.0).as_deref(), Some("3.5"));
+        assert_eq!(compute(BinaryOp::Mod, -7.0, 2.0).as_deref(), Some("-1"));
+        assert_eq!(compute(BinaryOp::Shl, 1.0, 33.0).as_deref(), Some("2"));
+        assert_eq!(compute(BinaryOp::UShr, -1.0, 0.0).as_deref(), Some("4294967295"));
+        assert_eq!(compute(BinaryOp::Lt, 1.5, 2.0).as_deref(), Some("true"));
+        assert_eq!(compute(BinaryOp::StrictEq, f64::NAN, f64::NAN).as_deref(), Some("false"));
+        let text = Value::from(JsValue::String("1".into()));
+        assert!(arithmetic(BinaryOp::Add, &text, &Value::int(1)).is_none());
+        assert_eq!(increment(&Value::int(i32::MAX), 1).unwrap().as_number(), Some(2147483648.0));
+        assert_eq!(increment(&Value::number(0.5), -1).unwrap().as_number(), Some(-0.5));
+    }
+}
@@ -0,0 +1,375 @@
+//! The type conversion abstract operations of ECMAScript §7.1 and the
+//! equality comparisons of §7.2. Those that can reach an object may call
+//! its `valueOf`, `toString` or `@@toPrimitive` methods, so they take the
+//! [`Context`] that runs script code.
+
+use crate::runtime::bigint::BigInt;
+use crate::runtime::error::JsError;
+use crate::runtime::object::ObjectRef;
+use crate::runtime::property::PropertyKey;
+use crate::runtime::realm::Realm;
+use crate::runtime::string::JsString;
+use crate::runtime::symbol::Symbol;
+use crate::runtime::value::{string_to_number, JsValue};
+use std::mem;
+use std::rc::Rc;
+
+/// What conversions need from the engine: a way to call functions, and
+/// the prototypes of the objects they create.
+pub trait Context {
+    fn call(&mut self, function: &JsValue, this: JsValue, args: &[JsValue]) -> Result<JsValue, JsError>;
+
+    fn realm(&self) -> &Realm;
+}
+
+/// The hint ToPrimitive passes on to `@@toPrimitive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreferredType {
+    Default,
+    String,
+    Number,
+}
+
+impl PreferredType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreferredType::Default => "default",
+            PreferredType::String => "string",
+            PreferredType::Number => "number",
+        }
+    }
+}
+
+/// The result of ToNumeric.
+#[derive(Debug, Clone)]
+pub enum Numeric {
+    Number(f64),
+    BigInt(Rc<BigInt>),
+}
+
+/// ToPrimitive: primitives are returned as they are; objects are asked for
+/// a primitive through `@@toPrimitive`, or else `valueOf` and `toString` in
+/// the order the hint prefers.
+pub fn to_primitive(ctx: &mut dyn Context, value: &JsValue, preferred: PreferredType) -> Result<JsValue, JsError> {
+    let JsValue::Object(object) = value else {
+        return Ok(value.clone());
+    };
+
+    let exotic = object.get(ctx, &Symbol::to_primitive().into(), value)?;
+    if !exotic.is_nullish() {
+        if !exotic.is_callable() {
+            return Err(JsError::type_error("Symbol.toPrimitive is not a function"));
+        }
+        let result = ctx.call(&exotic, value.clone(), &[preferred.as_str().into()])?;
+        if result.as_object().is_some() {
+            return Err(JsError::type_error("Cannot convert object to primitive value"));
+        }
+        return Ok(result);
+    }
+
+    let method_names = match preferred {
+        PreferredType::String => ["toString", "valueOf"],
+        PreferredType::Default | PreferredType::Number => ["valueOf", "toString"],
+    };
+    for name in method_names {
+        let method = object.get(ctx, &name.into(), value)?;
+        if method.is_callable() {
+            let result = ctx.call(&method, value.clone(), &[])?;
+            if result.as_object().is_none() {
+                return Ok(result);
+            }
+        }
+    }
+    Err(JsError::type_error("Cannot convert object to primitive value"))
+}
+
+/// ToNumber. Symbols and BigInts throw rather than convert implicitly.
+pub fn to_number(ctx: &mut dyn Context, value: &JsValue) -> Result<f64, JsError> {
+    match value {
+        JsValue::Undefined => Ok(f64::NAN),
+        JsValue::Null => Ok(0.0),
+        JsValue::Boolean(value) => Ok(*value as u8 as f64),
+        JsValue::Number(value) => Ok(*value),
+        JsValue::String(text) => Ok(string_to_number(&text.to_std_string())),
+        JsValue::Symbol(_) => Err(JsError::type_error("Cannot convert a Symbol value to a number")),
+        JsValue::BigInt(_) => Err(JsError::type_error("Cannot convert a BigInt value to a number")),
+        JsValue::Object(_) => {
+            let primitive = to_primitive(ctx, value, PreferredType::Number)?;
+            to_number(ctx, &primitive)
+        }
+    }
+}
+
+/// ToNumeric: like ToNumber, but BigInts stay BigInts.
+pub fn to_numeric(ctx: &mut dyn Context, value: &JsValue) -> Result<Numeric, JsError> {
+    match to_primitive(ctx, value, PreferredType::Number)? {
+        JsValue::BigInt(value) => Ok(Numeric::BigInt(value)),
+        primitive => Ok(Numeric::Number(to_number(ctx, &primitive)?)),
+    }
+}
+
+/// ToString, with Numbers formatted by the shortest round-tripping
+/// algorithm of Number::toString.
+pub fn to_string(ctx: &mut dyn Context, value: &JsValue) -> Result<JsString, JsError> {
+    match value {
+        JsValue::String(text) => Ok(text.clone()),
+        JsValue::Symbol(_) => Err(JsError::type_error("Cannot convert a Symbol value to a string")),
+        JsValue::Object(_) => {
+            let primitive = to_primitive(ctx, value, PreferredType::String)?;
+            to_string(ctx, &primitive)
+        }
+        primitive => Ok(primitive.to_string().into()),
+    }
+}
+
+/// ToObject: objects as they are, other values in a wrapper object.
+pub fn to_object(ctx: &dyn Context, value: &JsValue) -> Result<ObjectRef, JsError> {
+    match value {
+        JsValue::Undefined | JsValue::Null => Err(JsError::type_error("Cannot convert undefined or null to object")),
+        JsValue::Object(object) => Ok(object.clone()),
+        primitive => Ok(ObjectRef::wrapper(ctx.realm().wrapper_prototype(primitive), primitive.clone())),
+    }
+}
+
+/// ToPropertyKey: symbols stay symbols, everything else becomes a string.
+pub fn to_property_key(ctx: &mut dyn Context, value: &JsValue) -> Result<PropertyKey, JsError> {
+    match to_primitive(ctx, value, PreferredType::String)? {
+        JsValue::Symbol(symbol) => Ok(PropertyKey::Symbol(symbol)),
+        primitive => Ok(PropertyKey::from(to_string(ctx, &primitive)?)),
+    }
+}
+
+/// IsLooselyEqual, the `==` operator.
+pub fn is_loosely_equal(ctx: &mut dyn Context, x: &JsValue, y: &JsValue) -> Result<bool, JsError> {
+    if mem::discriminant(x) == mem::discriminant(y) {
+        return Ok(x.strict_equals(y));
+    }
+    Ok(match (x, y) {
+        (x, y) if x.is_nullish() || y.is_nullish() => x.is_nullish() && y.is_nullish(),
+        (JsValue::Number(x), JsValue::String(y)) | (JsValue::String(y), JsValue::Number(x)) => {
+            *x == string_to_number(&y.to_std_string())
+        }
+        (JsValue::BigInt(x), JsValue::String(y)) | (JsValue::String(y), JsValue::BigInt(x)) => {
+            BigInt::from_js_string(&y.to_std_string()).is_some_and(|y| **x == y)
+        }
+        (JsValue::Boolean(x), y) | (y, JsValue::Boolean(x)) => {
+            return is_loosely_equal(ctx, &JsValue::Number(*x as u8 as f64), y);
+        }
+        (JsValue::Object(_), JsValue::Object(_)) => unreachable!(),
+        (JsValue::Object(_), primitive) | (primitive, JsValue::Object(_)) => {
+            let object = if x.as_object().is_some() { x } else { y };
+            let converted = to_primitive(ctx, object, PreferredType::Default)?;
+            return is_loosely_equal(ctx, primitive, &converted);
+        }
+        (JsValue::BigInt(x), JsValue::Number(y)) | (JsValue::Number(y), JsValue::BigInt(x)) => {
+            x.compare_f64(*y) == Some(std::cmp::Ordering::Equal)
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    /// An object inheriting the built-in `valueOf` and `toString`.
+    fn plain(interpreter: &Interpreter) -> JsValue {
+        interpreter.realm().ordinary_object().into()
+    }
+
+    fn wrap(interpreter: &Interpreter, primitive: JsValue) -> JsValue {
+        to_object(interpreter, &primitive).unwrap().into()
+    }
+
+    fn big(text: &str) -> JsValue {
+        BigInt::from_js_string(text).unwrap().into()
+    }
+
+    #[test]
+    fn test_to_primitive_order_follows_hint() {
+        let mut interpreter = Interpreter::new();
+        let object = interpreter
+            .eval(
+                "var log = '';
+                 function v() { log += 'v'; return {}; }
+                 function s() { log += 's'; return 'str'; }
+                 ({ valueOf: v, toString: s });",
+            )
+            .unwrap();
+
+        let primitive = to_primitive(&mut interpreter, &object, PreferredType::Number).unwrap();
+        assert!(primitive.strict_equals(&"str".into()));
+        let primitive = to_primitive(&mut interpreter, &object, PreferredType::String).unwrap();
+        assert!(primitive.strict_equals(&"str".into()));
+        assert!(interpreter.eval("log;").unwrap().strict_equals(&"vss".into()));
+    }
+
+    #[test]
+    fn test_to_primitive_uses_symbol_to_primitive() {
+        let mut interpreter = Interpreter::new();
+        let object = interpreter.eval("function p(hint) { return hint; } ({ valueOf: p });").unwrap();
+        let hook = interpreter.eval("p;").unwrap();
+        object.as_object().unwrap().create_data_property(Symbol::to_primitive(), hook);
+
+        for preferred in [PreferredType::Default, PreferredType::String, PreferredType::Number] {
+            let primitive = to_primitive(&mut interpreter, &object, preferred).unwrap();
+            assert!(primitive.strict_equals(&preferred.as_str().into()));
+        }
+
+        object.as_object().unwrap().create_data_property(Symbol::to_primitive(), 1.0.into());
+        let error = to_primitive(&mut interpreter, &object, PreferredType::Default).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Symbol.toPrimitive is not a function");
+    }
+
+    #[test]
+    fn test_to_primitive_rejects_objects_from_every_method() {
+        let mut interpreter = Interpreter::new();
+        let object = interpreter
+            .eval("function o() { return {}; } ({ valueOf: o, toString: o });")
+            .unwrap();
+        let error = to_primitive(&mut interpreter, &object, PreferredType::Default).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot convert object to primitive value");
+
+        // A present but non-callable method is skipped, not replaced.
+        let object = interpreter.eval("({ valueOf: 1, toString: 2 });").unwrap();
+        assert!(to_primitive(&mut interpreter, &object, PreferredType::Default).is_err());
+    }
+
+    #[test]
+    fn test_to_number() {
+        let mut interpreter = Interpreter::new();
+        let cases: [(JsValue, f64); 7] = [
+            (JsValue::Null, 0.0),
+            (true.into(), 1.0),
+            (" 0x10 ".into(), 16.0),
+            ("-1.5e3".into(), -1500.0),
+            ("".into(), 0.0),
+            (wrap(&interpreter, 7.0.into()), 7.0),
+            (wrap(&interpreter, "8".into()), 8.0),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(to_number(&mut interpreter, &value).unwrap(), expected, "{:?}", value);
+        }
+        assert!(to_number(&mut interpreter, &JsValue::Undefined).unwrap().is_nan());
+        let object = plain(&interpreter);
+        assert!(to_number(&mut interpreter, &object).unwrap().is_nan());
+
+        let error = to_number(&mut interpreter, &big("1")).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot convert a BigInt value to a number");
+        let error = to_number(&mut interpreter, &Symbol::new(None).into()).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot convert a Symbol value to a number");
+    }
+
+    #[test]
+    fn test_to_numeric() {
+        let mut interpreter = Interpreter::new();
+        let wrapped = wrap(&interpreter, big("12"));
+        assert!(matches!(to_numeric(&mut interpreter, &wrapped).unwrap(), Numeric::BigInt(value) if value.to_string() == "12"));
+        assert!(matches!(to_numeric(&mut interpreter, &"3".into()).unwrap(), Numeric::Number(value) if value == 3.0));
+    }
+
+    #[test]
+    fn test_to_string() {
+        let mut interpreter = Interpreter::new();
+        let cases: [(JsValue, &str); 10] = [
+            (JsValue::Undefined, "undefined"),
+            (JsValue::Null, "null"),
+            (false.into(), "false"),
+            ((-0.0).into(), "0"),
+            (123e-20.into(), "1.23e-18"),
+            (0.000001.into(), "0.000001"),
+            (f64::NEG_INFINITY.into(), "-Infinity"),
+            (big("-123456789012345678901234567890"), "-123456789012345678901234567890"),
+            (plain(&interpreter), "[object Object]"),
+            (wrap(&interpreter, true.into()), "true"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(to_string(&mut interpreter, &value).unwrap(), expected);
+        }
+
+        let error = to_string(&mut interpreter, &Symbol::new(Some("s")).into()).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot convert a Symbol value to a string");
+        let function = interpreter.eval("function f(a) { return a; } f;").unwrap();
+        assert_eq!(to_string(&mut interpreter, &function).unwrap(), "function f(a) {\n  return a;\n}");
+    }
+
+    #[test]
+    fn test_to_object() {
+        let interpreter = Interpreter::new();
+        let object = ObjectRef::default();
+        assert!(ObjectRef::ptr_eq(&to_object(&interpreter, &object.clone().into()).unwrap(), &object));
+
+        let wrapper = to_object(&interpreter, &"abc".into()).unwrap();
+        assert!(wrapper.primitive_value().unwrap().strict_equals(&"abc".into()));
+        let prototype = wrapper.get_prototype_of().unwrap();
+        assert!(ObjectRef::ptr_eq(&prototype, &interpreter.realm().string_prototype));
+        assert!(to_object(&interpreter, &1.0.into()).unwrap().primitive_value().is_some());
+
+        for value in [JsValue::Undefined, JsValue::Null] {
+            let error = to_object(&interpreter, &value).unwrap_err();
+            assert_eq!(error.to_string(), "TypeError: Cannot convert undefined or null to object");
+        }
+    }
+
+    #[test]
+    fn test_to_property_key() {
+        let mut interpreter = Interpreter::new();
+        let symbol = Symbol::new(Some("key"));
+        assert_eq!(
+            to_property_key(&mut interpreter, &symbol.clone().into()).unwrap(),
+            PropertyKey::Symbol(symbol.clone())
+        );
+        let wrapper = wrap(&interpreter, symbol.clone().into());
+        assert_eq!(
+            to_property_key(&mut interpreter, &wrapper).unwrap(),
+            PropertyKey::Symbol(symbol)
+        );
+        assert_eq!(to_property_key(&mut interpreter, &1.5.into()).unwrap(), "1.5".into());
+        assert_eq!(to_property_key(&mut interpreter, &JsValue::Null).unwrap(), "null".into());
+        assert_eq!(to_property_key(&mut interpreter, &big("10")).unwrap(), "10".into());
+    }
+
+    #[test]
+    fn test_loose_equality() {
+        let mut interpreter = Interpreter::new();
+        let object = interpreter.realm().ordinary_object();
+        let symbol = Symbol::new(None);
+        let equal: [(JsValue, JsValue); 13] = [
+            (JsValue::Null, JsValue::Undefined),
+            ("1".into(), 1.0.into()),
+            (" \n".into(), 0.0.into()),
+            (true.into(), 1.0.into()),
+            (false.into(), "0".into()),
+            (big("1"), true.into()),
+            (big("10"), "10".into()),
+            (big("10"), 10.0.into()),
+            (big("-0"), (-0.0).into()),
+            (wrap(&interpreter, 2.0.into()), "2".into()),
+            (object.clone().into(), "[object Object]".into()),
+            (object.clone().into(), object.into()),
+            (wrap(&interpreter, symbol.clone().into()), symbol.into()),
+        ];
+        for (x, y) in equal {
+            assert!(is_loosely_equal(&mut interpreter, &x, &y).unwrap(), "{:?} == {:?}", x, y);
+            assert!(is_loosely_equal(&mut interpreter, &y, &x).unwrap(), "{:?} == {:?}", y, x);
+        }
+
+        let unequal: [(JsValue, JsValue); 10] = [
+            (JsValue::Null, 0.0.into()),
+            (JsValue::Undefined, false.into()),
+            (f64::NAN.into(), f64::NAN.into()),
+            ("a".into(), f64::NAN.into()),
+            (big("1"), "1.0".into()),
+            (big("1"), 1.5.into()),
+            (big("1"), f64::NAN.into()),
+            (Symbol::new(None).into(), Symbol::new(None).into()),
+            (plain(&interpreter), plain(&interpreter)),
+            ("true".into(), true.into()),
+        ];
+        for (x, y) in unequal {
+            assert!(!is_loosely_equal(&mut interpreter, &x, &y).unwrap(), "{:?} != {:?}", x, y);
+            assert!(!is_loosely_equal(&mut interpreter, &y, &x).unwrap(), "{:?} != {:?}", y, x);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::runtime::object::{ObjectKind, ObjectRef};
+use crate::runtime::value::JsValue;
+use std::fmt;
+
+/// The native error constructor an engine error corresponds to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Error,
+    TypeError,
+    ReferenceError,
+    RangeError,
+    SyntaxError,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Error => "Error",
+            ErrorKind::TypeError => "TypeError",
+            ErrorKind::ReferenceError => "ReferenceError",
+            ErrorKind::RangeError => "RangeError",
+            ErrorKind::SyntaxError => "SyntaxError",
+        }
+    }
+}
+
+/// An error raised while running a script, e.g. calling something that is
+/// not a function. Displays as `TypeError: x is not a function`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RuntimeError {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::TypeError, message)
+    }
+
+    pub fn reference_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::ReferenceError, message)
+    }
+
+    pub fn range_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::RangeError, message)
+    }
+
+    pub fn syntax_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::SyntaxError, message)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.as_str(), self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// An exception unwinding through the engine: the throw completion of the
+/// spec. Engine errors stay [`JsError::Native`] until script code can see
+/// them, at which point the interpreter turns them into error objects, so
+/// the common case of an error that is never caught allocates nothing.
+#[derive(Debug, Clone)]
+pub enum JsError {
+    Native(RuntimeError),
+    Thrown(JsValue),
+}
+
+impl JsError {
+    pub fn type_error(message: impl Into<String>) -> Self {
+        JsError::Native(RuntimeError::type_error(message))
+    }
+
+    pub fn reference_error(message: impl Into<String>) -> Self {
+        JsError::Native(RuntimeError::reference_error(message))
+    }
+
+    pub fn range_error(message: impl Into<String>) -> Self {
+        JsError::Native(RuntimeError::range_error(message))
+    }
+
+    pub fn syntax_error(message: impl Into<String>) -> Self {
+        JsError::Native(RuntimeError::syntax_error(message))
+    }
+
+    /// The thrown value, if it has been materialized.
+    pub fn value(&self) -> Option<&JsValue> {
+        match self {
+            JsError::Native(_) => None,
+            JsError::Thrown(value) => Some(value),
+        }
+    }
+}
+
+impl From<RuntimeError> for JsError {
+    fn from(error: RuntimeError) -> Self {
+        JsError::Native(error)
+    }
+}
+
+impl fmt::Display for JsError {
+    /// Error objects show as `Error.prototype.toString` would, reading only
+    /// data properties; other values as [`JsValue`]'s `Display` does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsError::Native(error) => error.fmt(f),
+            JsError::Thrown(JsValue::Object(object)) if matches!(object.borrow().kind, ObjectKind::Error) => {
+                f.write_str(&error_to_string(object))
+            }
+            JsError::Thrown(value) => value.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for JsError {}
+
+/// `name: message` from the data properties of `object` or its
+/// prototypes, leaving out whichever part is empty.
+pub fn error_to_string(object: &ObjectRef) -> String {
+    let text = |key: &str, default: &str| match object.find_property(&key.into()).and_then(|property| property.value) {
+        Some(JsValue::Undefined) | None => default.to_string(),
+        Some(value) => value.to_string(),
+    };
+    let (name, message) = (text("name", "Error"), text("message", ""));
+    match (name.is_empty(), message.is_empty()) {
+        (_, true) => name,
+        (true, false) => message,
+        (false, false) => format!("{}: {}", name, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::object::JsObject;
+
+    #[test]
+    fn test_display() {
+        let error = RuntimeError::reference_error("x is not defined");
+        assert_eq!(error.to_string(), "ReferenceError: x is not defined");
+        assert_eq!(JsError::from(error).to_string(), "ReferenceError: x is not defined");
+        assert_eq!(JsError::Thrown(3.0.into()).to_string(), "3");
+
+        let prototype = ObjectRef::ordinary(None);
+        prototype.create_data_property("name", "RangeError".into());
+        let error = ObjectRef::new(JsObject::new(Some(prototype), ObjectKind::Error));
+        assert_eq!(JsError::Thrown(error.clone().into()).to_string(), "RangeError");
+        error.create_data_property("message", "too deep".into());
+        assert_eq!(JsError::Thrown(error.into()).to_string(), "RangeError: too deep");
+    }
+}
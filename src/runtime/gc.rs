@@ -0,0 +1,825 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+
+/// Allocations between collections while the heap is small. After each
+/// full collection the budget grows with the values that survived, so the
+/// work of collecting stays proportional to the work of allocating.
+const MIN_BUDGET: usize = 4096;
+
+/// Allocations between minor collections in [`Mode::Generational`].
+const NURSERY_SIZE: usize = 1024;
+
+/// A value that can live on the garbage-collected heap, or be part of one
+/// that does: it reports the handles it holds, and lets go of them when the
+/// collector finds it unreachable.
+pub trait Trace {
+    /// Calls [`Tracer::edge`] for every [`Gc`] handle the value holds, once
+    /// per handle. Reporting a handle the value does not hold can get a
+    /// reachable value freed; missing one only keeps values alive longer.
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// Drops the handles the value holds, to break the reference cycles
+    /// keeping garbage alive. Values without interior mutability cannot, and
+    /// rely on every cycle through them also passing through one that can.
+    fn unlink(&self) {}
+
+    /// Bytes the value owns outside its own allocation, for [`HeapStats`].
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+
+    /// Lets go of what the value keeps for weak handles whose targets have
+    /// been freed. Called after each collection on the surviving values
+    /// that reported a weak handle while being traced.
+    fn sweep(&self) {}
+}
+
+/// Collects the handles one heap value holds.
+pub struct Tracer {
+    edges: Vec<*const ()>,
+    /// The key of each ephemeron, unless it has been freed, and the
+    /// handles its value holds.
+    ephemerons: Vec<(Option<*const ()>, Vec<*const ()>)>,
+    complete: bool,
+    weak: bool,
+}
+
+impl Tracer {
+    fn new() -> Self {
+        Tracer {
+            edges: Vec::new(),
+            ephemerons: Vec::new(),
+            complete: true,
+            weak: false,
+        }
+    }
+
+    pub fn edge<T: Trace + 'static>(&mut self, handle: &Gc<T>) {
+        self.edges.push(handle.address());
+    }
+
+    /// Reports an entry whose value is reachable through this one only as
+    /// long as its key is reachable some other way, as in a `WeakMap`. The
+    /// handles `value` holds do not keep `key` alive, even through cycles.
+    pub fn ephemeron<K: Trace + 'static>(&mut self, key: &WeakGc<K>, value: &impl Trace) {
+        let mut inner = Tracer::new();
+        value.trace(&mut inner);
+        self.complete &= inner.complete;
+        let key = (key.0.strong_count() > 0).then(|| key.address());
+        self.ephemerons.push((key, inner.edges));
+        self.weak = true;
+    }
+
+    /// Notes a weak handle. It is not an edge, but it has the value swept
+    /// after the collection; see [`Trace::sweep`].
+    pub fn weak<T: Trace + 'static>(&mut self, _handle: &WeakGc<T>) {
+        self.weak = true;
+    }
+
+    /// Notes that part of the value could not be traced, because it is
+    /// mutably borrowed. The collector then treats the value as a root, and
+    /// whatever it holds as reachable.
+    pub fn incomplete(&mut self) {
+        self.complete = false;
+    }
+}
+
+/// A handle to a value on the garbage-collected heap.
+///
+/// Handles are reference counted, so values nothing refers to are freed at
+/// once; the collector is for the cycles that counting cannot free. There
+/// is no explicit rooting: the roots are the values with more handles than
+/// the heap itself accounts for, which are exactly those held from outside
+/// it, by the VM's stack and registers or by Rust code.
+///
+/// The collector cannot tell those apart from handles a heap value holds
+/// but does not report from [`Trace::trace`], nor from handles given up
+/// with [`Gc::into_raw`]. They all count as roots, so a missed edge never
+/// frees a live value, but a cycle running through one is never freed
+/// either: what the heap cannot see, it keeps.
+pub struct Gc<T: Trace + 'static>(Rc<T>);
+
+impl<T: Trace + 'static> Gc<T> {
+    /// Moves `value` to the heap. In stress mode, or once the allocation
+    /// budget is spent, this collects garbage first.
+    pub fn new(value: T) -> Self {
+        HEAP.with(Heap::allocating);
+        let handle = Rc::new(value);
+        let weak: Weak<dyn Trace> = Rc::downgrade(&handle) as Weak<dyn Trace>;
+        HEAP.with(|heap| heap.young.borrow_mut().push(weak));
+        Gc(handle)
+    }
+
+    pub fn ptr_eq(a: &Gc<T>, b: &Gc<T>) -> bool {
+        Rc::ptr_eq(&a.0, &b.0)
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        Rc::as_ptr(&self.0)
+    }
+
+    /// Gives up the handle as a pointer, for compact value encodings. The
+    /// value stays alive until [`Gc::from_raw`] takes the handle back.
+    pub fn into_raw(self) -> *const T {
+        Rc::into_raw(self.0)
+    }
+
+    /// # Safety
+    /// `pointer` must come from [`Gc::into_raw`], and the handle it gave up
+    /// passes to the result.
+    pub unsafe fn from_raw(pointer: *const T) -> Self {
+        Gc(Rc::from_raw(pointer))
+    }
+
+    /// A handle that does not keep the value alive.
+    pub fn downgrade(&self) -> WeakGc<T> {
+        WeakGc(Rc::downgrade(&self.0))
+    }
+
+    fn address(&self) -> *const () {
+        Rc::as_ptr(&self.0) as *const ()
+    }
+}
+
+impl<T: Trace + 'static> Clone for Gc<T> {
+    fn clone(&self) -> Self {
+        Gc(self.0.clone())
+    }
+}
+
+impl<T: Trace + 'static> Deref for Gc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Trace + Default + 'static> Default for Gc<T> {
+    fn default() -> Self {
+        Gc::new(T::default())
+    }
+}
+
+impl<T: Trace + fmt::Debug + 'static> fmt::Debug for Gc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A handle to a heap value that lets it be freed, after which
+/// [`WeakGc::upgrade`] gives `None`. Tracing reports it with
+/// [`Tracer::weak`] or [`Tracer::ephemeron`] rather than as an edge.
+pub struct WeakGc<T: Trace + 'static>(Weak<T>);
+
+impl<T: Trace + 'static> WeakGc<T> {
+    pub fn upgrade(&self) -> Option<Gc<T>> {
+        self.0.upgrade().map(Gc)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+
+    /// Whether this is a handle to the value `handle` refers to. The
+    /// allocation outlives the value while weak handles remain, so a freed
+    /// target is never mistaken for a newer value.
+    pub fn points_to(&self, handle: &Gc<T>) -> bool {
+        std::ptr::eq(self.0.as_ptr(), handle.as_ptr())
+    }
+
+    fn address(&self) -> *const () {
+        self.0.as_ptr() as *const ()
+    }
+}
+
+impl<T: Trace + 'static> Clone for WeakGc<T> {
+    fn clone(&self) -> Self {
+        WeakGc(self.0.clone())
+    }
+}
+
+impl<T: Trace + 'static> Trace for Gc<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.edge(self);
+    }
+}
+
+impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(value) = self {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for Vec<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.iter().for_each(|value| value.trace(tracer));
+    }
+}
+
+/// How the heap decides what to collect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Every collection traces the whole heap.
+    #[default]
+    Full,
+    /// Values start in a nursery, which is collected on its own when it
+    /// fills up; those that survive move to the old generation, which is
+    /// only traced once it has doubled. Short-lived garbage is then freed
+    /// in pauses proportional to the nursery rather than the heap.
+    ///
+    /// Handles from old values to young ones need no write barrier: the
+    /// old values are not traced in a minor collection, so those handles
+    /// count as references from outside the nursery, making their targets
+    /// roots just as a remembered set would. That holds however late the
+    /// handle is stored: the count goes up on the store itself, so property
+    /// writes and the like need no hook of their own.
+    Generational,
+}
+
+/// Counters describing this thread's heap; see [`stats`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeapStats {
+    /// Values on the heap that have not been freed.
+    pub live_values: usize,
+    /// Memory those values take up, including what they own outside their
+    /// own allocation, such as property tables.
+    pub live_bytes: usize,
+    /// Collections of the nursery alone.
+    pub minor_collections: usize,
+    /// Collections of the whole heap.
+    pub major_collections: usize,
+    /// Values the collector found unreachable, over all collections.
+    pub freed_values: usize,
+    pub total_pause: Duration,
+    pub max_pause: Duration,
+}
+
+impl fmt::Display for HeapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "gc: {} minor and {} major collections freed {} values",
+            self.minor_collections, self.major_collections, self.freed_values
+        )?;
+        writeln!(f, "gc: pauses took {:?} in total, {:?} at most", self.total_pause, self.max_pause)?;
+        write!(f, "gc: {} values live in {} bytes", self.live_values, self.live_bytes)
+    }
+}
+
+/// Every value allocated with [`Gc::new`] on this thread, by generation.
+/// In [`Mode::Full`] values stay young until the next collection.
+struct Heap {
+    young: RefCell<Vec<Weak<dyn Trace>>>,
+    old: RefCell<Vec<Weak<dyn Trace>>>,
+    mode: Cell<Mode>,
+    /// Allocations before the next collection in [`Mode::Full`], and old
+    /// values before the next major collection in [`Mode::Generational`].
+    budget: Cell<usize>,
+    stress: Cell<bool>,
+    collecting: Cell<bool>,
+    stats: RefCell<HeapStats>,
+}
+
+thread_local! {
+    static HEAP: Heap = Heap {
+        young: RefCell::new(Vec::new()),
+        old: RefCell::new(Vec::new()),
+        mode: Cell::new(Mode::default()),
+        budget: Cell::new(MIN_BUDGET),
+        stress: Cell::new(false),
+        collecting: Cell::new(false),
+        stats: RefCell::new(HeapStats::default()),
+    };
+}
+
+impl Heap {
+    fn allocating(&self) {
+        if self.stress.get() {
+            self.collect(true);
+            return;
+        }
+        let allocated = self.young.borrow().len();
+        match self.mode.get() {
+            Mode::Full if allocated >= self.budget.get() => {
+                self.collect(true);
+            }
+            Mode::Generational if allocated >= NURSERY_SIZE => {
+                self.collect(false);
+                if self.old.borrow().len() >= self.budget.get() {
+                    // Most old values may have been freed by counting alone.
+                    self.old.borrow_mut().retain(|node| node.strong_count() > 0);
+                    if self.old.borrow().len() >= self.budget.get() {
+                        self.collect(true);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Mark and sweep over the nursery, or over the whole heap if `major`.
+    /// Tracing the values collected counts the handles they hold to each
+    /// other; values with more handles than that are roots. What the roots
+    /// do not reach is garbage, which is unlinked so that the reference
+    /// counts of its cycles drop to zero. Survivors become old, and those
+    /// holding weak handles are swept once the garbage is gone.
+    ///
+    /// An ephemeron's value is marked only once both the value holding it
+    /// and its key are, so marking repeats until no more ephemerons apply.
+    /// Keys outside the values collected count as marked.
+    fn collect(&self, major: bool) -> usize {
+        if self.collecting.replace(true) {
+            return 0;
+        }
+        let start = Instant::now();
+        let mut candidates = std::mem::take(&mut *self.young.borrow_mut());
+        if major {
+            candidates.append(&mut self.old.borrow_mut());
+        }
+        let nodes: Vec<Rc<dyn Trace>> = candidates.iter().filter_map(Weak::upgrade).collect();
+        drop(candidates);
+        let index: HashMap<*const (), usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(position, node)| (Rc::as_ptr(node) as *const (), position))
+            .collect();
+
+        let mut internal = vec![0; nodes.len()];
+        let mut roots = Vec::new();
+        let mut edges = Vec::with_capacity(nodes.len());
+        // The holder, the key and the value's targets of each ephemeron
+        // whose key is among the values collected.
+        let mut ephemerons = Vec::new();
+        let mut weak = Vec::new();
+        let resolve = |addresses: &[*const ()]| -> Vec<usize> {
+            addresses.iter().filter_map(|address| index.get(address).copied()).collect()
+        };
+        for (position, node) in nodes.iter().enumerate() {
+            let mut tracer = Tracer::new();
+            node.trace(&mut tracer);
+            if !tracer.complete {
+                roots.push(position);
+            }
+            if tracer.weak {
+                weak.push(position);
+            }
+            let mut targets = resolve(&tracer.edges);
+            targets.iter().for_each(|&target| internal[target] += 1);
+            for (key, values) in tracer.ephemerons {
+                let values = resolve(&values);
+                values.iter().for_each(|&target| internal[target] += 1);
+                match key.map(|key| index.get(&key).copied()) {
+                    // A freed key's value is unreachable through this entry.
+                    None => {}
+                    Some(Some(key)) => ephemerons.push((position, key, values)),
+                    Some(None) => targets.extend(values),
+                }
+            }
+            edges.push(targets);
+        }
+        // `nodes` holds one more handle to each value.
+        for (position, node) in nodes.iter().enumerate() {
+            if Rc::strong_count(node) - 1 > internal[position] {
+                roots.push(position);
+            }
+        }
+
+        let mut marked = vec![false; nodes.len()];
+        loop {
+            while let Some(position) = roots.pop() {
+                if !std::mem::replace(&mut marked[position], true) {
+                    roots.extend(&edges[position]);
+                }
+            }
+            ephemerons.retain(|(holder, key, values): &(usize, usize, Vec<usize>)| {
+                let applies = marked[*holder] && marked[*key];
+                if applies {
+                    roots.extend(values);
+                }
+                !applies
+            });
+            if roots.is_empty() {
+                break;
+            }
+        }
+        let swept: Vec<Rc<dyn Trace>> = weak
+            .into_iter()
+            .filter(|&position| marked[position])
+            .map(|position| nodes[position].clone())
+            .collect();
+        let mut garbage = Vec::new();
+        let mut old = self.old.borrow_mut();
+        for (node, marked) in nodes.into_iter().zip(marked) {
+            if marked {
+                old.push(Rc::downgrade(&node));
+            } else {
+                garbage.push(node);
+            }
+        }
+        let live = old.len();
+        drop(old);
+        garbage.iter().for_each(|node| node.unlink());
+        let freed = garbage.len();
+        drop(garbage);
+        swept.iter().for_each(|node| node.sweep());
+        drop(swept);
+
+        if major || self.mode.get() == Mode::Full {
+            self.budget.set(match self.mode.get() {
+                Mode::Full => live.max(MIN_BUDGET),
+                Mode::Generational => (2 * live).max(MIN_BUDGET),
+            });
+        }
+        let pause = start.elapsed();
+        let mut stats = self.stats.borrow_mut();
+        if major {
+            stats.major_collections += 1;
+        } else {
+            stats.minor_collections += 1;
+        }
+        stats.freed_values += freed;
+        stats.total_pause += pause;
+        stats.max_pause = stats.max_pause.max(pause);
+        self.collecting.set(false);
+        freed
+    }
+
+    fn stats(&self) -> HeapStats {
+        let mut stats = self.stats.borrow().clone();
+        let young = self.young.borrow();
+        let old = self.old.borrow();
+        let live: Vec<Rc<dyn Trace>> = young.iter().chain(old.iter()).filter_map(Weak::upgrade).collect();
+        stats.live_values = live.len();
+        stats.live_bytes = live
+            .iter()
+            .map(|node| RC_HEADER + std::mem::size_of_val::<dyn Trace>(&**node) + node.heap_bytes())
+            .sum();
+        stats
+    }
+}
+
+/// The reference counts in front of every value.
+const RC_HEADER: usize = 2 * std::mem::size_of::<usize>();
+
+/// Collects garbage in the whole heap of this thread now, returning how
+/// many values were found unreachable.
+pub fn collect() -> usize {
+    HEAP.with(|heap| heap.collect(true))
+}
+
+/// Collects only the nursery; see [`Mode::Generational`].
+pub fn collect_young() -> usize {
+    HEAP.with(|heap| heap.collect(false))
+}
+
+pub fn set_mode(mode: Mode) {
+    HEAP.with(|heap| heap.mode.set(mode));
+}
+
+/// In stress mode, every allocation on this thread collects the whole heap
+/// first, so that a value freed while still in use fails fast instead of
+/// rarely.
+pub fn set_stress(stress: bool) {
+    HEAP.with(|heap| heap.stress.set(stress));
+}
+
+pub fn stats() -> HeapStats {
+    HEAP.with(Heap::stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Engine, Interpreter};
+
+    /// A heap value with a mutable list of handles, like an object's
+    /// properties.
+    #[derive(Default)]
+    struct Node {
+        children: RefCell<Vec<Gc<Node>>>,
+    }
+
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            match self.children.try_borrow() {
+                Ok(children) => children.trace(tracer),
+                Err(_) => tracer.incomplete(),
+            }
+        }
+
+        fn unlink(&self) {
+            if let Ok(mut children) = self.children.try_borrow_mut() {
+                children.clear();
+            }
+        }
+    }
+
+    fn link(from: &Gc<Node>, to: &Gc<Node>) {
+        from.children.borrow_mut().push(to.clone());
+    }
+
+    /// A heap value mapping weakly held keys to values, like a `WeakMap`.
+    #[derive(Default)]
+    struct Table {
+        entries: RefCell<Vec<(WeakGc<Node>, Gc<Node>)>>,
+    }
+
+    impl Trace for Table {
+        fn trace(&self, tracer: &mut Tracer) {
+            for (key, value) in self.entries.borrow().iter() {
+                tracer.ephemeron(key, value);
+            }
+        }
+
+        fn sweep(&self) {
+            self.entries.borrow_mut().retain(|(key, _)| key.is_alive());
+        }
+    }
+
+    #[test]
+    fn test_frees_unreachable_cycles() {
+        collect();
+        let parent = Gc::new(Node::default());
+        let child = Gc::new(Node::default());
+        link(&parent, &child);
+        link(&child, &parent);
+        link(&child, &child);
+        let observer = Rc::downgrade(&child.0);
+
+        assert_eq!(collect(), 0);
+        drop(parent);
+        assert_eq!(collect(), 0, "the child is still held");
+        drop(child);
+        assert!(observer.upgrade().is_some(), "counting alone cannot free a cycle");
+        assert_eq!(collect(), 2);
+        assert!(observer.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_handles_outside_the_heap_are_roots() {
+        collect();
+        let root = Gc::new(Node::default());
+        let held = Gc::new(Node::default());
+        link(&root, &held);
+        link(&held, &root);
+        // Reachable only through a borrowed value, which cannot be traced.
+        let hidden = Gc::new(Node::default());
+        link(&hidden, &hidden);
+        link(&held, &hidden);
+        drop(hidden);
+        let borrowed = held.children.borrow_mut();
+
+        let rust_held = vec![root.clone()];
+        drop(root);
+        assert_eq!(collect(), 0);
+        drop(borrowed);
+        assert_eq!(collect(), 0);
+        drop(held);
+        drop(rust_held);
+        assert_eq!(collect(), 3);
+    }
+
+    #[test]
+    fn test_external_clones_are_roots() {
+        collect();
+        let first = Gc::new(Node::default());
+        let second = Gc::new(Node::default());
+        link(&first, &second);
+        link(&second, &first);
+
+        // A clone kept by Rust code the heap knows nothing about.
+        let external: HashMap<&str, Gc<Node>> = HashMap::from([("second", second.clone())]);
+        drop(first);
+        drop(second);
+        assert_eq!(collect(), 0);
+        let second = &external["second"];
+        assert_eq!(second.children.borrow()[0].children.borrow().len(), 1);
+
+        drop(external);
+        assert_eq!(collect(), 2);
+    }
+
+    #[test]
+    fn test_untraced_handles_keep_cycles_alive() {
+        /// Reports `traced` but not `hidden`.
+        #[derive(Default)]
+        struct Half {
+            traced: RefCell<Option<Gc<Half>>>,
+            hidden: RefCell<Option<Gc<Half>>>,
+        }
+
+        impl Trace for Half {
+            fn trace(&self, tracer: &mut Tracer) {
+                self.traced.borrow().trace(tracer);
+            }
+
+            fn unlink(&self) {
+                self.traced.borrow_mut().take();
+                self.hidden.borrow_mut().take();
+            }
+        }
+
+        collect();
+        let first = Gc::new(Half::default());
+        let second = Gc::new(Half::default());
+        *first.hidden.borrow_mut() = Some(second.clone());
+        *second.traced.borrow_mut() = Some(first.clone());
+        let observer = first.downgrade();
+        drop(first);
+        drop(second);
+
+        // `second` looks held from outside the heap, and keeps `first`.
+        assert_eq!(collect(), 0);
+        let first = observer.upgrade().unwrap();
+        first.hidden.borrow_mut().take();
+        drop(first);
+        assert!(!observer.is_alive());
+    }
+
+    #[test]
+    fn test_frees_script_cycles() {
+        let code = "function make() { let o = {}; o.self = o; function f() { return o; } o.f = f; return f; }
+            for (let i = 0; i < 100; i++) make();
+            let kept = make(); kept() === kept().self;";
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            assert_eq!(interpreter.eval(code).unwrap().to_string(), "true");
+            // Each call leaves at least an object, a function and its
+            // prototype in a cycle.
+            assert!(collect() >= 300, "{:?}", engine);
+            assert_eq!(interpreter.eval("kept().self.f === kept;").unwrap().to_string(), "true");
+            drop(interpreter);
+            assert!(collect() > 0);
+            assert_eq!(collect(), 0);
+        }
+    }
+
+    #[test]
+    fn test_ephemerons() {
+        collect();
+        let table = Gc::new(Table::default());
+        let key = Gc::new(Node::default());
+        let value = Gc::new(Node::default());
+        link(&value, &key);
+        table.entries.borrow_mut().push((key.downgrade(), value));
+        assert_eq!(collect(), 0);
+        assert_eq!(table.entries.borrow().len(), 1);
+
+        // The value holds the key, but only through the table.
+        let weak = key.downgrade();
+        drop(key);
+        assert!(weak.is_alive());
+        assert_eq!(collect(), 2);
+        assert!(weak.upgrade().is_none());
+        assert!(table.entries.borrow().is_empty());
+
+        // A key reachable only through another entry's value keeps its own
+        // value alive, whichever entry comes first.
+        let first = Gc::new(Node::default());
+        let second = Gc::new(Node::default());
+        let (first_value, second_value) = (Gc::new(Node::default()), Gc::new(Node::default()));
+        link(&first_value, &second);
+        link(&second_value, &second_value);
+        table.entries.borrow_mut().push((second.downgrade(), second_value));
+        table.entries.borrow_mut().push((first.downgrade(), first_value));
+        drop(second);
+        assert_eq!(collect(), 0);
+        assert_eq!(table.entries.borrow().len(), 2);
+        // Counting frees `first` itself, leaving both values and `second`.
+        drop(first);
+        assert_eq!(collect(), 3);
+        assert!(table.entries.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_generations() {
+        set_mode(Mode::Generational);
+        collect();
+        let old = Gc::new(Node::default());
+        let cycle = Gc::new(Node::default());
+        link(&cycle, &cycle);
+        assert_eq!(collect_young(), 0);
+        drop(cycle);
+        assert_eq!(collect_young(), 0, "old garbage waits for a major collection");
+
+        // Held only by an old value, which a minor collection does not trace.
+        let young = Gc::new(Node::default());
+        link(&young, &young);
+        link(&old, &young);
+        drop(young);
+        let temporary = Gc::new(Node::default());
+        link(&temporary, &temporary);
+        drop(temporary);
+        assert_eq!(collect_young(), 1);
+        assert_eq!(old.children.borrow().len(), 1);
+
+        assert_eq!(collect(), 1);
+        // Counting frees `old` itself, leaving the young value's cycle.
+        drop(old);
+        assert_eq!(collect(), 1);
+        set_mode(Mode::Full);
+    }
+
+    #[test]
+    fn test_old_to_young_stores_survive_minor_collections() {
+        set_mode(Mode::Generational);
+        collect();
+        let old = Gc::new(Node::default());
+        assert_eq!(collect_young(), 0);
+
+        // Written into `old` after it was promoted: a young chain ending in
+        // a cycle, reachable only through the store.
+        let young = Gc::new(Node::default());
+        let grandchild = Gc::new(Node::default());
+        link(&grandchild, &grandchild);
+        link(&young, &grandchild);
+        link(&old, &young);
+        let observers = [young.downgrade(), grandchild.downgrade()];
+        drop(young);
+        drop(grandchild);
+
+        assert_eq!(collect_young(), 0);
+        assert!(observers.iter().all(WeakGc::is_alive));
+        let young = old.children.borrow()[0].clone();
+        assert_eq!(young.children.borrow()[0].children.borrow().len(), 1);
+        drop(young);
+
+        // Once the store is undone, the young values are garbage; they were
+        // promoted, so it takes a major collection to find them.
+        old.children.borrow_mut().clear();
+        assert_eq!(collect_young(), 0);
+        assert_eq!(collect(), 1);
+        assert!(observers.iter().all(|observer| !observer.is_alive()));
+        set_mode(Mode::Full);
+    }
+
+    #[test]
+    fn test_script_stores_into_old_objects() {
+        set_mode(Mode::Generational);
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            interpreter.eval("var old = {};").unwrap();
+            collect_young();
+            interpreter
+                .eval("old.young = { value: 42 }; old.young.self = old.young; 0;")
+                .unwrap();
+            collect_young();
+            let result = interpreter.eval("old.young.self.value;").unwrap();
+            assert_eq!(result.to_string(), "42", "{:?}", engine);
+        }
+        set_mode(Mode::Full);
+    }
+
+    #[test]
+    fn test_stats() {
+        collect();
+        let before = stats();
+        let nodes: Vec<Gc<Node>> = (0..10).map(|_| Gc::new(Node::default())).collect();
+        nodes.iter().for_each(|node| link(node, node));
+        let during = stats();
+        assert_eq!(during.live_values, before.live_values + 10);
+        assert!(during.live_bytes >= before.live_bytes + 10 * std::mem::size_of::<Node>());
+
+        drop(nodes);
+        collect();
+        let after = stats();
+        assert_eq!(after.live_values, before.live_values);
+        assert_eq!(after.freed_values, before.freed_values + 10);
+        assert_eq!(after.major_collections, before.major_collections + 1);
+        assert!(after.max_pause <= after.total_pause && after.total_pause > before.total_pause);
+        assert!(after.to_string().starts_with("gc: 0 minor and "), "{}", after);
+    }
+
+    #[test]
+    fn test_stress_mode() {
+        set_stress(true);
+        let list = Gc::new(Node::default());
+        for _ in 0..100 {
+            let node = Gc::new(Node::default());
+            link(&node, &list);
+            link(&list, &node);
+        }
+        assert_eq!(list.children.borrow().len(), 100);
+        drop(list);
+        set_stress(false);
+        assert_eq!(collect(), 101);
+
+        set_stress(true);
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(Engine::Bytecode);
+        let code = "function counter() { let count = 0; function next() { count += 1; return count; } return next; }
+            let next = counter(); let object = { a: next(), b: {} };
+            try { next(); throw object; } catch (e) { e.a + next(); }";
+        assert_eq!(interpreter.eval(code).unwrap().to_string(), "4");
+        set_stress(false);
+    }
+}
@@ -0,0 +1,14 @@
+pub mod bigint;
+pub mod boxed;
+pub mod conversions;
+pub mod error;
+pub mod gc;
+pub mod object;
+pub mod operations;
+pub mod property;
+pub mod realm;
+pub mod shape;
+pub mod string;
+pub mod symbol;
+pub mod value;
+pub mod weak;
@@ -0,0 +1,635 @@
+use crate::interpreter::vm::BytecodeClosure;
+use crate::interpreter::{Closure, Interpreter};
+use crate::lexical::atom::Atom;
+use crate::runtime::conversions::Context;
+use crate::runtime::error::{error_to_string, JsError};
+use crate::runtime::gc::{Gc, Trace, Tracer, WeakGc};
+use crate::runtime::property::{PropertyDescriptor, PropertyKey, PropertySlot};
+use crate::runtime::shape::PropertyMap;
+use crate::runtime::string::JsString;
+use crate::runtime::value::JsValue;
+use crate::runtime::weak::{FinalizationCells, WeakTable};
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt;
+use std::rc::Rc;
+
+/// Signature of functions implemented in Rust: the interpreter, the `this`
+/// value and the arguments.
+pub type NativeFn = fn(&mut Interpreter, &JsValue, &[JsValue]) -> Result<JsValue, JsError>;
+
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub call: NativeFn,
+}
+
+/// How a function object runs when called.
+#[derive(Clone)]
+pub enum FunctionKind {
+    Script(Gc<Closure>),
+    Bytecode(Gc<BytecodeClosure>),
+    Native(NativeFunction),
+}
+
+#[derive(Clone, Default)]
+pub enum ObjectKind {
+    #[default]
+    Ordinary,
+    Function(FunctionKind),
+    /// A Boolean, Number, String, Symbol or BigInt object made by ToObject,
+    /// holding the primitive it wraps.
+    Primitive(JsValue),
+    /// An object made by one of the Error constructors, or by the engine
+    /// for an error that script code caught.
+    Error,
+    WeakMap(WeakTable),
+    /// A `WeakSet`, whose table maps its members to `undefined`.
+    WeakSet(WeakTable),
+    WeakRef(WeakObjectRef),
+    FinalizationRegistry(FinalizationCells),
+}
+
+/// An object's own properties, in insertion order, its prototype and what
+/// kind of object it is.
+pub struct JsObject {
+    properties: PropertyMap,
+    prototype: Option<ObjectRef>,
+    extensible: bool,
+    pub kind: ObjectKind,
+}
+
+impl Default for JsObject {
+    fn default() -> Self {
+        Self::new(None, ObjectKind::Ordinary)
+    }
+}
+
+impl JsObject {
+    pub fn new(prototype: Option<ObjectRef>, kind: ObjectKind) -> Self {
+        JsObject {
+            properties: PropertyMap::default(),
+            prototype,
+            extensible: true,
+            kind,
+        }
+    }
+
+    pub fn properties(&self) -> &PropertyMap {
+        &self.properties
+    }
+
+    /// The own properties, for inline caches that write a slot they have
+    /// already checked.
+    pub fn properties_mut(&mut self) -> &mut PropertyMap {
+        &mut self.properties
+    }
+
+    pub fn prototype(&self) -> Option<&ObjectRef> {
+        self.prototype.as_ref()
+    }
+
+    /// The `length` and index properties a String object has for the
+    /// UTF-16 code units of the string it wraps.
+    fn string_property(&self, key: &PropertyKey) -> Option<PropertyDescriptor> {
+        let ObjectKind::Primitive(JsValue::String(text)) = &self.kind else {
+            return None;
+        };
+        if *key == PropertyKey::String(Atom::LENGTH) {
+            return Some(PropertyDescriptor::data((text.len() as f64).into(), false, false, false));
+        }
+        let unit = text.code_unit(key.array_index()? as usize)?;
+        Some(PropertyDescriptor::data(JsString::from_code_units(&[unit]).into(), false, true, false))
+    }
+}
+
+impl Trace for FunctionKind {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            FunctionKind::Script(closure) => tracer.edge(closure),
+            FunctionKind::Bytecode(closure) => tracer.edge(closure),
+            FunctionKind::Native(_) => {}
+        }
+    }
+}
+
+impl Trace for RefCell<JsObject> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(object) = self.try_borrow() else {
+            return tracer.incomplete();
+        };
+        for slot in object.properties.values() {
+            slot.trace(tracer);
+        }
+        object.prototype.trace(tracer);
+        match &object.kind {
+            ObjectKind::Function(kind) => kind.trace(tracer),
+            ObjectKind::WeakMap(table) | ObjectKind::WeakSet(table) => table.trace(tracer),
+            ObjectKind::FinalizationRegistry(cells) => cells.trace(tracer),
+            _ => {}
+        }
+    }
+
+    fn unlink(&self) {
+        if let Ok(mut object) = self.try_borrow_mut() {
+            object.properties.clear();
+            object.prototype = None;
+            object.kind = ObjectKind::Ordinary;
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.try_borrow().map_or(0, |object| object.properties.heap_bytes())
+    }
+
+    fn sweep(&self) {
+        // A borrowed object is swept after a later collection instead.
+        let Ok(mut object) = self.try_borrow_mut() else {
+            return;
+        };
+        match &mut object.kind {
+            ObjectKind::WeakMap(table) | ObjectKind::WeakSet(table) => table.sweep(),
+            ObjectKind::FinalizationRegistry(cells) => cells.sweep(),
+            _ => {}
+        }
+    }
+}
+
+impl Trace for ObjectRef {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.edge(&self.0);
+    }
+}
+
+/// A shared, mutable handle to an object. Equality of objects is identity
+/// of the handles, see [`ObjectRef::ptr_eq`].
+///
+/// The methods named after internal methods, such as [`ObjectRef::get`]
+/// for `[[Get]]`, implement them for ordinary objects.
+#[derive(Clone, Default)]
+pub struct ObjectRef(Gc<RefCell<JsObject>>);
+
+impl ObjectRef {
+    pub fn new(object: JsObject) -> Self {
+        ObjectRef(Gc::new(RefCell::new(object)))
+    }
+
+    /// An empty ordinary object, with `null` as its prototype if `prototype`
+    /// is `None`.
+    pub fn ordinary(prototype: Option<ObjectRef>) -> Self {
+        Self::new(JsObject::new(prototype, ObjectKind::Ordinary))
+    }
+
+    pub fn function(prototype: &ObjectRef, kind: FunctionKind) -> Self {
+        Self::new(JsObject::new(Some(prototype.clone()), ObjectKind::Function(kind)))
+    }
+
+    /// A wrapper object for `primitive`, as ToObject creates.
+    pub fn wrapper(prototype: &ObjectRef, primitive: JsValue) -> Self {
+        Self::new(JsObject::new(Some(prototype.clone()), ObjectKind::Primitive(primitive)))
+    }
+
+    pub fn borrow(&self) -> Ref<'_, JsObject> {
+        self.0.borrow()
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, JsObject> {
+        self.0.borrow_mut()
+    }
+
+    /// Gives up the handle as a pointer; see [`Gc::into_raw`].
+    pub fn into_raw(self) -> *const () {
+        self.0.into_raw() as *const ()
+    }
+
+    /// # Safety
+    /// `pointer` must come from [`ObjectRef::into_raw`], and the handle it
+    /// gave up passes to the result.
+    pub unsafe fn from_raw(pointer: *const ()) -> Self {
+        ObjectRef(Gc::from_raw(pointer as *const RefCell<JsObject>))
+    }
+
+    /// A handle to this object that does not keep it alive.
+    pub fn downgrade(&self) -> WeakObjectRef {
+        WeakObjectRef(self.0.downgrade())
+    }
+
+    pub fn ptr_eq(a: &ObjectRef, b: &ObjectRef) -> bool {
+        Gc::ptr_eq(&a.0, &b.0)
+    }
+
+    /// The object's address. No other object is allocated there while the
+    /// object, or any weak handle to it, is alive.
+    pub fn address(&self) -> usize {
+        self.0.as_ptr() as usize
+    }
+
+    /// `[[GetPrototypeOf]]`.
+    pub fn get_prototype_of(&self) -> Option<ObjectRef> {
+        self.borrow().prototype.clone()
+    }
+
+    /// `[[SetPrototypeOf]]`: fails for non-extensible objects and when the
+    /// prototype chain would become a cycle.
+    pub fn set_prototype_of(&self, prototype: Option<ObjectRef>) -> bool {
+        let current = self.get_prototype_of();
+        let unchanged = match (&current, &prototype) {
+            (Some(current), Some(prototype)) => ObjectRef::ptr_eq(current, prototype),
+            (current, prototype) => current.is_none() && prototype.is_none(),
+        };
+        if unchanged {
+            return true;
+        }
+        if !self.is_extensible() {
+            return false;
+        }
+        let mut ancestor = prototype.clone();
+        while let Some(object) = ancestor {
+            if ObjectRef::ptr_eq(&object, self) {
+                return false;
+            }
+            ancestor = object.get_prototype_of();
+        }
+        self.borrow_mut().prototype = prototype;
+        true
+    }
+
+    /// `[[IsExtensible]]`.
+    pub fn is_extensible(&self) -> bool {
+        self.borrow().extensible
+    }
+
+    /// `[[PreventExtensions]]`: no properties can be added afterwards, and
+    /// the prototype is fixed.
+    pub fn prevent_extensions(&self) -> bool {
+        self.borrow_mut().extensible = false;
+        true
+    }
+
+    /// `[[GetOwnProperty]]`.
+    pub fn get_own_property(&self, key: &PropertyKey) -> Option<PropertyDescriptor> {
+        let object = self.borrow();
+        if let Some(descriptor) = object.string_property(key) {
+            return Some(descriptor);
+        }
+        object.properties.get(key).map(PropertySlot::descriptor)
+    }
+
+    /// `[[DefineOwnProperty]]`, ValidateAndApplyPropertyDescriptor: creates
+    /// or changes the property, or returns `false` if that is not allowed.
+    pub fn define_own_property(&self, key: PropertyKey, descriptor: PropertyDescriptor) -> bool {
+        let mut object = self.borrow_mut();
+        if let Some(current) = object.string_property(&key) {
+            // Nothing about these can change, so there is nothing to apply.
+            return current.allows(&descriptor);
+        }
+        match object.properties.position(&key) {
+            Some(index) => {
+                let slot = object.properties.at_mut(index);
+                let mut current = slot.descriptor();
+                if !current.allows(&descriptor) {
+                    return false;
+                }
+                current.apply(descriptor);
+                *slot = PropertySlot::new(current);
+                true
+            }
+            None if object.extensible => {
+                object.properties.insert(key, PropertySlot::new(descriptor));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `[[HasProperty]]`: whether the object or its prototype chain has `key`.
+    pub fn has_property(&self, key: &PropertyKey) -> bool {
+        let mut object = self.clone();
+        loop {
+            if object.get_own_property(key).is_some() {
+                return true;
+            }
+            match object.get_prototype_of() {
+                Some(prototype) => object = prototype,
+                None => return false,
+            }
+        }
+    }
+
+    /// `[[Get]]`: the value of `key` found along the prototype chain, with
+    /// getters called on `receiver`.
+    pub fn get(&self, ctx: &mut dyn Context, key: &PropertyKey, receiver: &JsValue) -> Result<JsValue, JsError> {
+        match self.find_property(key) {
+            Some(descriptor) if descriptor.is_accessor() => match descriptor.get {
+                Some(getter) if getter.is_callable() => ctx.call(&getter, receiver.clone(), &[]),
+                _ => Ok(JsValue::Undefined),
+            },
+            Some(descriptor) => Ok(descriptor.value.unwrap_or(JsValue::Undefined)),
+            None => Ok(JsValue::Undefined),
+        }
+    }
+
+    /// `[[Set]]`, OrdinarySet: calls the setter found along the prototype
+    /// chain, or creates or updates a data property on `receiver`. Returns
+    /// `false` when a read-only property, a missing setter or a
+    /// non-extensible receiver prevents the assignment.
+    pub fn set(
+        &self,
+        ctx: &mut dyn Context,
+        key: PropertyKey,
+        value: JsValue,
+        receiver: &JsValue,
+    ) -> Result<bool, JsError> {
+        let descriptor = self
+            .find_property(&key)
+            .unwrap_or_else(|| PropertyDescriptor::data(JsValue::Undefined, true, true, true));
+        if descriptor.is_accessor() {
+            return match descriptor.set {
+                Some(setter) if setter.is_callable() => {
+                    ctx.call(&setter, receiver.clone(), &[value])?;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            };
+        }
+
+        let JsValue::Object(receiver) = receiver else {
+            return Ok(false);
+        };
+        if !descriptor.is_writable() {
+            return Ok(false);
+        }
+        Ok(match receiver.get_own_property(&key) {
+            Some(existing) if existing.is_accessor() || !existing.is_writable() => false,
+            Some(_) => receiver.define_own_property(
+                key,
+                PropertyDescriptor {
+                    value: Some(value),
+                    ..Default::default()
+                },
+            ),
+            None => receiver.create_data_property(key, value),
+        })
+    }
+
+    /// `[[Delete]]`: removes an own property unless it is non-configurable.
+    pub fn delete(&self, key: &PropertyKey) -> bool {
+        match self.get_own_property(key) {
+            Some(descriptor) if !descriptor.is_configurable() => false,
+            Some(_) => {
+                self.borrow_mut().properties.remove(key);
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// `[[OwnPropertyKeys]]`: array indices in ascending order, then the
+    /// other strings and then the symbols, both in the order they were
+    /// added.
+    pub fn own_property_keys(&self) -> Vec<PropertyKey> {
+        let object = self.borrow();
+        let mut indices = Vec::new();
+        let mut strings = Vec::new();
+        let mut symbols = Vec::new();
+        if let ObjectKind::Primitive(JsValue::String(text)) = &object.kind {
+            let length = text.len();
+            indices.extend((0..length as u32).map(|index| (index, index.to_string().into())));
+            strings.push(Atom::LENGTH.into());
+        }
+        for (key, _) in object.properties.iter() {
+            match (key.array_index(), &key) {
+                (Some(index), _) => indices.push((index, key)),
+                (None, PropertyKey::String(_) | PropertyKey::Text(_)) => strings.push(key),
+                (None, PropertyKey::Symbol(_)) => symbols.push(key),
+            }
+        }
+        indices.sort_by_key(|(index, _)| *index);
+        indices.into_iter().map(|(_, key)| key).chain(strings).chain(symbols).collect()
+    }
+
+    /// CreateDataProperty: an enumerable, writable and configurable property.
+    pub fn create_data_property(&self, key: impl Into<PropertyKey>, value: JsValue) -> bool {
+        self.define_own_property(key.into(), PropertyDescriptor::data(value, true, true, true))
+    }
+
+    /// The property `key` names on this object or the nearest prototype
+    /// that has one.
+    pub fn find_property(&self, key: &PropertyKey) -> Option<PropertyDescriptor> {
+        let mut object = self.clone();
+        loop {
+            if let Some(descriptor) = object.get_own_property(key) {
+                return Some(descriptor);
+            }
+            object = object.get_prototype_of()?;
+        }
+    }
+
+    pub fn function_kind(&self) -> Option<FunctionKind> {
+        match &self.borrow().kind {
+            ObjectKind::Function(kind) => Some(kind.clone()),
+            _ => None,
+        }
+    }
+
+    /// The primitive a wrapper object holds.
+    pub fn primitive_value(&self) -> Option<JsValue> {
+        match &self.borrow().kind {
+            ObjectKind::Primitive(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_callable(&self) -> bool {
+        matches!(self.borrow().kind, ObjectKind::Function(_))
+    }
+
+    /// The function's name, or `""` for anonymous functions and other objects.
+    pub fn function_name(&self) -> Rc<str> {
+        match self.function_kind() {
+            Some(FunctionKind::Script(closure)) => closure.code.name.as_str().into(),
+            Some(FunctionKind::Bytecode(closure)) => closure.template.name.clone(),
+            Some(FunctionKind::Native(native)) => native.name.into(),
+            None => "".into(),
+        }
+    }
+
+    /// What the built-in `toString` methods return for this object: source
+    /// text for functions, the wrapped value for wrappers, and
+    /// `[object Object]` otherwise. Runs no script code, unlike ToString.
+    pub fn builtin_to_string(&self) -> Rc<str> {
+        match &self.borrow().kind {
+            ObjectKind::Function(FunctionKind::Script(closure)) => closure.code.source.as_str().into(),
+            ObjectKind::Function(FunctionKind::Bytecode(closure)) => closure.template.source.clone(),
+            ObjectKind::Function(FunctionKind::Native(native)) => {
+                format!("function {}() {{ [native code] }}", native.name).into()
+            }
+            ObjectKind::Primitive(primitive) => primitive.to_string().into(),
+            ObjectKind::Error => error_to_string(self).into(),
+            _ => "[object Object]".into(),
+        }
+    }
+}
+
+impl fmt::Debug for ObjectRef {
+    // Objects can be cyclic, so only print the identity.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ObjectRef({:p})", self.0.as_ptr())
+    }
+}
+
+/// A handle to an object that lets the collector free it, for `WeakRef`s
+/// and the keys of weak collections.
+#[derive(Clone)]
+pub struct WeakObjectRef(WeakGc<RefCell<JsObject>>);
+
+impl WeakObjectRef {
+    /// The object, unless it has been freed.
+    pub fn upgrade(&self) -> Option<ObjectRef> {
+        self.0.upgrade().map(ObjectRef)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    pub fn points_to(&self, object: &ObjectRef) -> bool {
+        self.0.points_to(&object.0)
+    }
+
+    /// Reports this handle as the key of an entry holding `value`; see
+    /// [`Tracer::ephemeron`].
+    pub fn trace_ephemeron(&self, value: &JsValue, tracer: &mut Tracer) {
+        tracer.ephemeron(&self.0, value);
+    }
+
+    pub fn trace_weak(&self, tracer: &mut Tracer) {
+        tracer.weak(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::runtime::realm::Realm;
+    use crate::runtime::symbol::Symbol;
+
+    fn keys(object: &ObjectRef) -> Vec<String> {
+        object.own_property_keys().iter().map(PropertyKey::to_string).collect()
+    }
+
+    fn get(object: &ObjectRef, key: &str) -> JsValue {
+        object.get(&mut Interpreter::new(), &key.into(), &object.clone().into()).unwrap()
+    }
+
+    #[test]
+    fn test_own_property_keys_order() {
+        let object = ObjectRef::default();
+        let symbol = Symbol::new(Some("s"));
+        for key in ["b", "10", "a", "2", "4294967295", "01", "0"] {
+            object.create_data_property(key, JsValue::Null);
+        }
+        object.create_data_property(symbol.clone(), JsValue::Null);
+        object.create_data_property("b", JsValue::from(1.0));
+        assert_eq!(keys(&object), ["0", "2", "10", "b", "a", "4294967295", "01", "Symbol(s)"]);
+
+        assert!(object.delete(&"b".into()));
+        assert!(object.delete(&"missing".into()));
+        assert!(object.get_own_property(&"b".into()).is_none());
+        assert!(object.get_own_property(&symbol.into()).is_some());
+    }
+
+    #[test]
+    fn test_define_own_property() {
+        let object = ObjectRef::default();
+        assert!(object.define_own_property("x".into(), PropertyDescriptor {
+            value: Some(1.0.into()),
+            ..Default::default()
+        }));
+        let x = object.get_own_property(&"x".into()).unwrap();
+        assert!(!x.is_writable() && !x.is_enumerable() && !x.is_configurable());
+
+        assert!(!object.define_own_property("x".into(), PropertyDescriptor::data(2.0.into(), true, true, true)));
+        assert!(object.define_own_property("x".into(), PropertyDescriptor::data(1.0.into(), false, false, false)));
+        assert!(!object.delete(&"x".into()));
+
+        object.prevent_extensions();
+        assert!(!object.is_extensible());
+        assert!(!object.create_data_property("y", JsValue::Null));
+        assert!(!object.set_prototype_of(Some(ObjectRef::default())));
+        assert!(object.set_prototype_of(None));
+    }
+
+    #[test]
+    fn test_prototype_chain() {
+        let mut interpreter = Interpreter::new();
+        let grandparent = ObjectRef::default();
+        grandparent.create_data_property("a", JsValue::from(1.0));
+        grandparent.define_own_property("fixed".into(), PropertyDescriptor::data(2.0.into(), false, true, true));
+        let parent = ObjectRef::ordinary(Some(grandparent.clone()));
+        let child = ObjectRef::ordinary(Some(parent.clone()));
+
+        assert!(get(&child, "a").strict_equals(&1.0.into()));
+        assert!(child.has_property(&"a".into()));
+        assert!(child.get_own_property(&"a".into()).is_none());
+
+        // Assignment shadows the inherited property rather than changing it.
+        let receiver = JsValue::from(child.clone());
+        assert!(child.set(&mut interpreter, "a".into(), 3.0.into(), &receiver).unwrap());
+        assert!(get(&child, "a").strict_equals(&3.0.into()));
+        assert!(get(&grandparent, "a").strict_equals(&1.0.into()));
+
+        // An inherited read-only property cannot be shadowed by assignment.
+        assert!(!child.set(&mut interpreter, "fixed".into(), 4.0.into(), &receiver).unwrap());
+        assert!(child.get_own_property(&"fixed".into()).is_none());
+
+        assert!(!grandparent.set_prototype_of(Some(child.clone())));
+        assert!(ObjectRef::ptr_eq(&child.get_prototype_of().unwrap(), &parent));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut interpreter = Interpreter::new();
+        let getter = interpreter.eval("var hidden; function get() { return hidden * 2; } get;").unwrap();
+        let setter = interpreter.eval("function set(v) { hidden = v; } set;").unwrap();
+
+        let prototype = ObjectRef::default();
+        prototype.define_own_property("double".into(), PropertyDescriptor::accessor(getter, setter, false, true));
+        prototype.define_own_property(
+            "readOnly".into(),
+            PropertyDescriptor::accessor(JsValue::Undefined, JsValue::Undefined, false, true),
+        );
+        let object = ObjectRef::ordinary(Some(prototype));
+        let receiver = JsValue::from(object.clone());
+
+        assert!(object.set(&mut interpreter, "double".into(), 5.0.into(), &receiver).unwrap());
+        assert!(interpreter.eval("hidden;").unwrap().strict_equals(&5.0.into()));
+        assert!(object.get(&mut interpreter, &"double".into(), &receiver).unwrap().strict_equals(&10.0.into()));
+        assert!(object.get_own_property(&"double".into()).is_none());
+        assert!(!object.set(&mut interpreter, "readOnly".into(), 1.0.into(), &receiver).unwrap());
+        assert!(matches!(get(&object, "readOnly"), JsValue::Undefined));
+    }
+
+    #[test]
+    fn test_string_wrapper() {
+        let realm = Realm::new();
+        let wrapper = ObjectRef::wrapper(&realm.string_prototype, "ab".into());
+        wrapper.create_data_property("5", JsValue::Null);
+        wrapper.create_data_property("x", JsValue::Null);
+        assert_eq!(keys(&wrapper), ["0", "1", "5", "length", "x"]);
+        assert!(get(&wrapper, "1").strict_equals(&"b".into()));
+        assert!(get(&wrapper, "length").strict_equals(&2.0.into()));
+        assert!(!wrapper.create_data_property("0", JsValue::Null));
+        assert!(!wrapper.delete(&"length".into()));
+    }
+
+    #[test]
+    fn test_identity() {
+        let a = ObjectRef::default();
+        assert!(ObjectRef::ptr_eq(&a, &a.clone()));
+        assert!(!ObjectRef::ptr_eq(&a, &ObjectRef::default()));
+        assert_eq!(&*a.builtin_to_string(), "[object Object]");
+        let realm = Realm::new();
+        assert_eq!(&*ObjectRef::wrapper(&realm.number_prototype, 1e21.into()).builtin_to_string(), "1e+21");
+    }
+}
@@ -0,0 +1,293 @@
+use crate::lexical::atom::Atom;
+use crate::parser::operators::{BinaryOp, UnaryOp};
+use crate::runtime::bigint::BigInt;
+use crate::runtime::conversions::{
+    is_loosely_equal, to_number, to_numeric, to_primitive, to_property_key, to_string, Context, Numeric, PreferredType,
+};
+use crate::runtime::error::JsError;
+use crate::runtime::object::ObjectRef;
+use crate::runtime::value::{to_int32, to_uint32, JsValue};
+use std::cmp::Ordering;
+
+/// Applies a binary operator to two evaluated operands.
+pub fn binary(ctx: &mut dyn Context, operator: BinaryOp, left: &JsValue, right: &JsValue) -> Result<JsValue, JsError> {
+    Ok(match operator {
+        BinaryOp::Add => return add(ctx, left, right),
+        BinaryOp::Eq => is_loosely_equal(ctx, left, right)?.into(),
+        BinaryOp::NotEq => (!is_loosely_equal(ctx, left, right)?).into(),
+        BinaryOp::StrictEq => left.strict_equals(right).into(),
+        BinaryOp::StrictNotEq => (!left.strict_equals(right)).into(),
+        BinaryOp::Lt => (less_than(ctx, left, right, true)? == Some(true)).into(),
+        BinaryOp::Gt => (less_than(ctx, right, left, false)? == Some(true)).into(),
+        BinaryOp::LtEq => (less_than(ctx, right, left, false)? == Some(false)).into(),
+        BinaryOp::GtEq => (less_than(ctx, left, right, true)? == Some(false)).into(),
+        BinaryOp::In => {
+            let Some(object) = right.as_object() else {
+                return Err(JsError::type_error(format!(
+                    "Cannot use 'in' operator to search for '{}' in {}",
+                    left, right
+                )));
+            };
+            let key = to_property_key(ctx, left)?;
+            object.has_property(&key).into()
+        }
+        BinaryOp::InstanceOf => ordinary_has_instance(ctx, right, left)?.into(),
+        _ => {
+            let left = to_numeric(ctx, left)?;
+            let right = to_numeric(ctx, right)?;
+            return numeric(operator, left, right);
+        }
+    })
+}
+
+/// Applies a unary operator other than `typeof` and `delete`, which need
+/// the unevaluated operand.
+pub fn unary(ctx: &mut dyn Context, operator: UnaryOp, argument: &JsValue) -> Result<JsValue, JsError> {
+    Ok(match operator {
+        UnaryOp::Minus => match to_numeric(ctx, argument)? {
+            Numeric::Number(value) => JsValue::Number(-value),
+            Numeric::BigInt(value) => (-(*value).clone()).into(),
+        },
+        UnaryOp::Plus => JsValue::Number(to_number(ctx, argument)?),
+        UnaryOp::Not => (!argument.to_boolean()).into(),
+        UnaryOp::BitNot => match to_numeric(ctx, argument)? {
+            Numeric::Number(value) => JsValue::Number(!to_int32(value) as f64),
+            Numeric::BigInt(value) => value.not().into(),
+        },
+        UnaryOp::TypeOf => argument.type_of().into(),
+        UnaryOp::Void => JsValue::Undefined,
+        UnaryOp::Delete => true.into(),
+    })
+}
+
+/// `++` and `--`: the operand as a Number or BigInt, plus `delta`.
+pub fn increment(ctx: &mut dyn Context, value: &JsValue, delta: i64) -> Result<(JsValue, JsValue), JsError> {
+    Ok(match to_numeric(ctx, value)? {
+        Numeric::Number(old) => (JsValue::Number(old), JsValue::Number(old + delta as f64)),
+        Numeric::BigInt(old) => {
+            let new = old.add(&BigInt::from_i64(delta));
+            (JsValue::BigInt(old), new.into())
+        }
+    })
+}
+
+/// `+`: string concatenation when either side is a string once objects are
+/// converted, numeric addition otherwise.
+fn add(ctx: &mut dyn Context, left: &JsValue, right: &JsValue) -> Result<JsValue, JsError> {
+    let left = to_primitive(ctx, left, PreferredType::Default)?;
+    let right = to_primitive(ctx, right, PreferredType::Default)?;
+    if matches!(left, JsValue::String(_)) || matches!(right, JsValue::String(_)) {
+        let left = to_string(ctx, &left)?;
+        return Ok(left.concat(&to_string(ctx, &right)?).into());
+    }
+    let left = to_numeric(ctx, &left)?;
+    let right = to_numeric(ctx, &right)?;
+    numeric(BinaryOp::Add, left, right)
+}
+
+/// The arithmetic and bitwise operators on operands already converted by
+/// ToNumeric, which must both be Numbers or both be BigInts.
+fn numeric(operator: BinaryOp, left: Numeric, right: Numeric) -> Result<JsValue, JsError> {
+    match (left, right) {
+        (Numeric::Number(a), Numeric::Number(b)) => Ok(JsValue::Number(number_operation(operator, a, b))),
+        (Numeric::BigInt(a), Numeric::BigInt(b)) => bigint_operation(operator, &a, &b),
+        _ => Err(JsError::type_error("Cannot mix BigInt and other types, use explicit conversions")),
+    }
+}
+
+fn number_operation(operator: BinaryOp, a: f64, b: f64) -> f64 {
+    let int32 = |f: fn(i32, i32) -> i32| f(to_int32(a), to_int32(b)) as f64;
+    match operator {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a / b,
+        BinaryOp::Mod => a % b,
+        BinaryOp::Exp => exponentiate(a, b),
+        BinaryOp::BitAnd => int32(|a, b| a & b),
+        BinaryOp::BitOr => int32(|a, b| a | b),
+        BinaryOp::BitXor => int32(|a, b| a ^ b),
+        BinaryOp::Shl => int32(|a, b| a.wrapping_shl(b as u32 & 31)),
+        BinaryOp::Shr => int32(|a, b| a >> (b as u32 & 31)),
+        BinaryOp::UShr => (to_uint32(a) >> (to_uint32(b) & 31)) as f64,
+        other => unreachable!("{} is not a numeric operator", other.as_str()),
+    }
+}
+
+fn bigint_operation(operator: BinaryOp, a: &BigInt, b: &BigInt) -> Result<JsValue, JsError> {
+    let division_by_zero = || JsError::range_error("Division by zero");
+    Ok(match operator {
+        BinaryOp::Add => a.add(b),
+        BinaryOp::Sub => a.sub(b),
+        BinaryOp::Mul => a.mul(b),
+        BinaryOp::Div => a.div_rem(b).ok_or_else(division_by_zero)?.0,
+        BinaryOp::Mod => a.div_rem(b).ok_or_else(division_by_zero)?.1,
+        BinaryOp::Exp => {
+            if b.is_negative() {
+                return Err(JsError::range_error("Exponent must be non-negative"));
+            }
+            let exponent = b.to_f64();
+            if exponent > u32::MAX as f64 {
+                return Err(JsError::range_error("Maximum BigInt size exceeded"));
+            }
+            a.pow(exponent as u64)
+        }
+        BinaryOp::BitAnd => a.bitand(b),
+        BinaryOp::BitOr => a.bitor(b),
+        BinaryOp::BitXor => a.bitxor(b),
+        BinaryOp::Shl | BinaryOp::Shr => {
+            let shift = b.to_f64();
+            if shift.abs() > u32::MAX as f64 {
+                return Err(JsError::range_error("Maximum BigInt size exceeded"));
+            }
+            let shift = if operator == BinaryOp::Shl { shift } else { -shift };
+            a.shl(shift as i64)
+        }
+        BinaryOp::UShr => return Err(JsError::type_error("BigInts have no unsigned right shift, use >> instead")),
+        other => unreachable!("{} is not a numeric operator", other.as_str()),
+    }
+    .into())
+}
+
+/// IsLessThan: `left < right`, or `None` when a NaN makes the operands
+/// unordered. `left_first` says which operand to convert first, so that
+/// `a > b` still converts `a` before `b`.
+fn less_than(ctx: &mut dyn Context, left: &JsValue, right: &JsValue, left_first: bool) -> Result<Option<bool>, JsError> {
+    let (left, right) = if left_first {
+        let left = to_primitive(ctx, left, PreferredType::Number)?;
+        (left, to_primitive(ctx, right, PreferredType::Number)?)
+    } else {
+        let right = to_primitive(ctx, right, PreferredType::Number)?;
+        (to_primitive(ctx, left, PreferredType::Number)?, right)
+    };
+
+    match (&left, &right) {
+        // Strings compare by UTF-16 code units, not by code points.
+        (JsValue::String(a), JsValue::String(b)) => return Ok(Some(a < b)),
+        (JsValue::BigInt(a), JsValue::String(b)) => {
+            return Ok(BigInt::from_js_string(&b.to_std_string()).map(|b| **a < b));
+        }
+        (JsValue::String(a), JsValue::BigInt(b)) => {
+            return Ok(BigInt::from_js_string(&a.to_std_string()).map(|a| a < **b));
+        }
+        _ => {}
+    }
+
+    let ordering = match (to_numeric(ctx, &left)?, to_numeric(ctx, &right)?) {
+        (Numeric::Number(a), Numeric::Number(b)) => a.partial_cmp(&b),
+        (Numeric::BigInt(a), Numeric::BigInt(b)) => Some(a.cmp(&b)),
+        (Numeric::BigInt(a), Numeric::Number(b)) => a.compare_f64(b),
+        (Numeric::Number(a), Numeric::BigInt(b)) => b.compare_f64(a).map(Ordering::reverse),
+    };
+    Ok(ordering.map(Ordering::is_lt))
+}
+
+/// `**`, which differs from `powf` in giving NaN for `(±1) ** ±Infinity`
+/// and `1 ** NaN`.
+/// The `instanceof` operator, OrdinaryHasInstance: whether
+/// `constructor.prototype` is on the prototype chain of `value`.
+fn ordinary_has_instance(ctx: &mut dyn Context, constructor: &JsValue, value: &JsValue) -> Result<bool, JsError> {
+    let Some(function) = constructor.as_object().filter(|object| object.is_callable()) else {
+        return Err(JsError::type_error("Right-hand side of 'instanceof' is not callable"));
+    };
+    let JsValue::Object(object) = value else {
+        return Ok(false);
+    };
+    let prototype = function.get(ctx, &Atom::PROTOTYPE.into(), constructor)?;
+    let Some(prototype) = prototype.as_object() else {
+        return Err(JsError::type_error(format!(
+            "Function has non-object prototype '{}' in instanceof check",
+            prototype
+        )));
+    };
+    let mut ancestor = object.get_prototype_of();
+    while let Some(object) = ancestor {
+        if ObjectRef::ptr_eq(&object, prototype) {
+            return Ok(true);
+        }
+        ancestor = object.get_prototype_of();
+    }
+    Ok(false)
+}
+
+fn exponentiate(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() || (base.abs() == 1.0 && exponent.is_infinite()) {
+        return f64::NAN;
+    }
+    base.powf(exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn eval(operator: BinaryOp, left: impl Into<JsValue>, right: impl Into<JsValue>) -> JsValue {
+        binary(&mut Interpreter::new(), operator, &left.into(), &right.into()).unwrap()
+    }
+
+    fn big(value: i64) -> BigInt {
+        BigInt::from_i64(value)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert!(eval(BinaryOp::Add, "a", 1.0).strict_equals(&"a1".into()));
+        assert!(eval(BinaryOp::Add, true, 1.0).strict_equals(&2.0.into()));
+        assert!(eval(BinaryOp::Sub, "5", 2.0).strict_equals(&3.0.into()));
+        assert!(eval(BinaryOp::Mod, -7.0, 2.0).strict_equals(&(-1.0).into()));
+        assert!(eval(BinaryOp::Exp, 2.0, 10.0).strict_equals(&1024.0.into()));
+        assert!(eval(BinaryOp::Exp, 1.0, f64::INFINITY).same_value(&f64::NAN.into()));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert!(eval(BinaryOp::BitOr, 4294967295.0, 0.0).strict_equals(&(-1.0).into()));
+        assert!(eval(BinaryOp::Shl, 1.0, 33.0).strict_equals(&2.0.into()));
+        assert!(eval(BinaryOp::Shr, -8.0, 1.0).strict_equals(&(-4.0).into()));
+        assert!(eval(BinaryOp::UShr, -1.0, 0.0).strict_equals(&4294967295.0.into()));
+        let not = unary(&mut Interpreter::new(), UnaryOp::BitNot, &5.0.into()).unwrap();
+        assert!(not.strict_equals(&(-6.0).into()));
+    }
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        assert!(eval(BinaryOp::Add, big(2), big(3)).strict_equals(&big(5).into()));
+        assert!(eval(BinaryOp::Div, big(-7), big(2)).strict_equals(&big(-3).into()));
+        assert!(eval(BinaryOp::Mod, big(-7), big(2)).strict_equals(&big(-1).into()));
+        assert!(eval(BinaryOp::Exp, big(3), big(4)).strict_equals(&big(81).into()));
+        assert!(eval(BinaryOp::Shr, big(-9), big(1)).strict_equals(&big(-5).into()));
+        assert!(eval(BinaryOp::Add, big(1), "x").strict_equals(&"1x".into()));
+
+        let mut interpreter = Interpreter::new();
+        let error = binary(&mut interpreter, BinaryOp::Add, &big(1).into(), &1.0.into()).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot mix BigInt and other types, use explicit conversions");
+        let error = binary(&mut interpreter, BinaryOp::Div, &big(1).into(), &big(0).into()).unwrap_err();
+        assert_eq!(error.to_string(), "RangeError: Division by zero");
+        let error = unary(&mut interpreter, UnaryOp::Plus, &big(1).into()).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot convert a BigInt value to a number");
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert!(eval(BinaryOp::Lt, "a", "b").to_boolean());
+        assert!(eval(BinaryOp::Lt, "10", "9").to_boolean());
+        assert!(!eval(BinaryOp::Lt, "10", 9.0).to_boolean());
+        assert!(!eval(BinaryOp::LtEq, f64::NAN, 1.0).to_boolean());
+        assert!(!eval(BinaryOp::GtEq, f64::NAN, 1.0).to_boolean());
+        assert!(eval(BinaryOp::GtEq, 1.0, 1.0).to_boolean());
+        // U+FF61 sorts before U+1F600 by code point but after its lead
+        // surrogate U+D83D by code unit.
+        assert!(!eval(BinaryOp::Lt, "\u{FF61}", "\u{1F600}").to_boolean());
+        assert!(eval(BinaryOp::Lt, big(1), 1.5).to_boolean());
+        assert!(eval(BinaryOp::Gt, big(2), "1").to_boolean());
+        assert!(!eval(BinaryOp::Lt, big(2), "x").to_boolean());
+        assert!(!eval(BinaryOp::GtEq, big(2), "x").to_boolean());
+    }
+
+    #[test]
+    fn test_in_requires_an_object() {
+        let error = binary(&mut Interpreter::new(), BinaryOp::In, &"x".into(), &1.0.into()).unwrap_err();
+        assert_eq!(error.to_string(), "TypeError: Cannot use 'in' operator to search for 'x' in 1");
+    }
+}
@@ -0,0 +1,506 @@
+use crate::lexical::atom::{Atom, Interned, KeyAtom};
+use crate::runtime::boxed::Value;
+use crate::runtime::conversions::Context;
+use crate::runtime::error::JsError;
+use crate::runtime::gc::{Trace, Tracer};
+use crate::runtime::object::ObjectRef;
+use crate::runtime::string::JsString;
+use crate::runtime::symbol::Symbol;
+use crate::runtime::value::JsValue;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The key of a property: a string or a symbol, as ToPropertyKey produces.
+///
+/// Names in the program are atoms, interned by the compiler. Strings made
+/// at run time become atom keys if their text is an atom already, and
+/// [`KeyAtom`]s otherwise, which are freed once unused so that computed keys
+/// never grow the atom table for good. Both kinds share one space of ids,
+/// so string keys compare and hash by id without looking at their text.
+#[derive(Debug, Clone)]
+pub enum PropertyKey {
+    String(Atom),
+    /// A run-time string whose text was not an atom.
+    Text(KeyAtom),
+    Symbol(Symbol),
+}
+
+impl PropertyKey {
+    /// The index an array index key stands for: a canonical numeric string
+    /// of an integer below 2^32 - 1. Such keys come first, in ascending
+    /// order, when listing an object's own keys.
+    pub fn array_index(&self) -> Option<u32> {
+        let key = match self {
+            PropertyKey::String(key) => key.to_string(),
+            PropertyKey::Text(key) => String::from_utf16(key.code_units()).ok()?,
+            PropertyKey::Symbol(_) => return None,
+        };
+        let index: u32 = key.parse().ok()?;
+        (index != u32::MAX && index.to_string() == key).then_some(index)
+    }
+
+    /// The key as a value: a string or a symbol.
+    pub fn to_value(&self) -> JsValue {
+        match self {
+            PropertyKey::String(key) => JsValue::String(key.as_str().into()),
+            PropertyKey::Text(key) => JsValue::String(JsString::from_code_units(key.code_units())),
+            PropertyKey::Symbol(symbol) => JsValue::Symbol(symbol.clone()),
+        }
+    }
+}
+
+impl PartialEq for PropertyKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PropertyKey::String(a), PropertyKey::String(b)) => a == b,
+            (PropertyKey::Text(a), PropertyKey::Text(b)) => a == b,
+            (PropertyKey::String(atom), PropertyKey::Text(key)) | (PropertyKey::Text(key), PropertyKey::String(atom)) => {
+                atom.id() == key.id()
+            }
+            (PropertyKey::Symbol(a), PropertyKey::Symbol(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PropertyKey {}
+
+impl Hash for PropertyKey {
+    /// By id for both kinds of string key, which may be equal.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            PropertyKey::String(key) => key.id().hash(state),
+            PropertyKey::Text(key) => key.id().hash(state),
+            PropertyKey::Symbol(symbol) => symbol.hash(state),
+        }
+    }
+}
+
+impl From<Atom> for PropertyKey {
+    fn from(key: Atom) -> Self {
+        PropertyKey::String(key)
+    }
+}
+
+impl From<&str> for PropertyKey {
+    fn from(key: &str) -> Self {
+        PropertyKey::from(&*key.encode_utf16().collect::<Vec<_>>())
+    }
+}
+
+impl From<&[u16]> for PropertyKey {
+    /// The atom for `key` if there is one, else a key atom.
+    fn from(key: &[u16]) -> Self {
+        match KeyAtom::intern(key) {
+            Interned::Atom(atom) => PropertyKey::String(atom),
+            Interned::Key(key) => PropertyKey::Text(key),
+        }
+    }
+}
+
+impl From<String> for PropertyKey {
+    fn from(key: String) -> Self {
+        PropertyKey::from(key.as_str())
+    }
+}
+
+impl From<JsString> for PropertyKey {
+    /// The atom for `key` if there is one. Atoms hold Rust strings, so a
+    /// key with lone surrogates never has one.
+    fn from(key: JsString) -> Self {
+        PropertyKey::from(&*key.code_units().collect::<Vec<_>>())
+    }
+}
+
+impl From<Symbol> for PropertyKey {
+    fn from(symbol: Symbol) -> Self {
+        PropertyKey::Symbol(symbol)
+    }
+}
+
+impl fmt::Display for PropertyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyKey::String(key) => f.write_str(key.as_str()),
+            PropertyKey::Text(key) => f.write_str(&String::from_utf16_lossy(key.code_units())),
+            PropertyKey::Symbol(symbol) => f.write_str(&symbol.descriptive_string()),
+        }
+    }
+}
+
+/// A Property Descriptor. When defining a property any field may be absent
+/// to keep, or default, that attribute; the descriptors objects store are
+/// complete data or accessor descriptors. An accessor without a getter or
+/// setter has `undefined` there.
+#[derive(Debug, Clone, Default)]
+pub struct PropertyDescriptor {
+    pub value: Option<JsValue>,
+    pub writable: Option<bool>,
+    pub get: Option<JsValue>,
+    pub set: Option<JsValue>,
+    pub enumerable: Option<bool>,
+    pub configurable: Option<bool>,
+}
+
+impl Trace for PropertyDescriptor {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.value.trace(tracer);
+        self.get.trace(tracer);
+        self.set.trace(tracer);
+    }
+}
+
+impl PropertyDescriptor {
+    pub fn data(value: JsValue, writable: bool, enumerable: bool, configurable: bool) -> Self {
+        PropertyDescriptor {
+            value: Some(value),
+            writable: Some(writable),
+            enumerable: Some(enumerable),
+            configurable: Some(configurable),
+            ..Default::default()
+        }
+    }
+
+    pub fn accessor(get: JsValue, set: JsValue, enumerable: bool, configurable: bool) -> Self {
+        PropertyDescriptor {
+            get: Some(get),
+            set: Some(set),
+            enumerable: Some(enumerable),
+            configurable: Some(configurable),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_accessor(&self) -> bool {
+        self.get.is_some() || self.set.is_some()
+    }
+
+    pub fn is_data(&self) -> bool {
+        self.value.is_some() || self.writable.is_some()
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.is_accessor() && !self.is_data()
+    }
+
+    pub fn is_enumerable(&self) -> bool {
+        self.enumerable == Some(true)
+    }
+
+    pub fn is_configurable(&self) -> bool {
+        self.configurable == Some(true)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.writable == Some(true)
+    }
+
+    /// CompletePropertyDescriptor: absent fields take their defaults,
+    /// `undefined` and `false`.
+    pub fn complete(self) -> Self {
+        let (enumerable, configurable) = (self.is_enumerable(), self.is_configurable());
+        let or_undefined = |value: Option<JsValue>| value.unwrap_or(JsValue::Undefined);
+        if self.is_accessor() {
+            PropertyDescriptor::accessor(or_undefined(self.get), or_undefined(self.set), enumerable, configurable)
+        } else {
+            let writable = self.is_writable();
+            PropertyDescriptor::data(or_undefined(self.value), writable, enumerable, configurable)
+        }
+    }
+
+    /// Whether the property `self` describes may be redefined by `change`:
+    /// the checks of ValidateAndApplyPropertyDescriptor. Anything goes for
+    /// configurable properties; otherwise only a writable data property's
+    /// value may change, and it may be made read-only.
+    pub fn allows(&self, change: &PropertyDescriptor) -> bool {
+        if self.is_configurable() {
+            return true;
+        }
+        if change.is_configurable() || change.enumerable.is_some_and(|enumerable| enumerable != self.is_enumerable()) {
+            return false;
+        }
+        if !change.is_generic() && change.is_accessor() != self.is_accessor() {
+            return false;
+        }
+        let unchanged = |new: &Option<JsValue>, old: &Option<JsValue>| {
+            new.as_ref().is_none_or(|new| new.same_value(old.as_ref().unwrap_or(&JsValue::Undefined)))
+        };
+        if self.is_accessor() {
+            unchanged(&change.get, &self.get) && unchanged(&change.set, &self.set)
+        } else {
+            self.is_writable() || (!change.is_writable() && unchanged(&change.value, &self.value))
+        }
+    }
+
+    /// Applies `change`, which [`PropertyDescriptor::allows`], to this
+    /// complete descriptor. Switching between data and accessor keeps only
+    /// the enumerable and configurable attributes.
+    pub fn apply(&mut self, change: PropertyDescriptor) {
+        if change.is_accessor() && self.is_data() || change.is_data() && self.is_accessor() {
+            let attributes = PropertyDescriptor {
+                enumerable: self.enumerable,
+                configurable: self.configurable,
+                ..Default::default()
+            };
+            *self = attributes;
+        }
+        let PropertyDescriptor { value, writable, get, set, enumerable, configurable } = change;
+        self.value = value.or(self.value.take());
+        self.writable = writable.or(self.writable);
+        self.get = get.or(self.get.take());
+        self.set = set.or(self.set.take());
+        self.enumerable = enumerable.or(self.enumerable);
+        self.configurable = configurable.or(self.configurable);
+        *self = std::mem::take(self).complete();
+    }
+
+    /// ToPropertyDescriptor, reading the fields `Object.defineProperty` takes
+    /// from `attributes`.
+    pub fn from_object(ctx: &mut dyn Context, attributes: &JsValue) -> Result<Self, JsError> {
+        let JsValue::Object(object) = attributes else {
+            return Err(JsError::type_error(format!("Property description must be an object: {}", attributes)));
+        };
+        let mut field = |name: &str| -> Result<Option<JsValue>, JsError> {
+            let key = PropertyKey::from(name);
+            if !object.has_property(&key) {
+                return Ok(None);
+            }
+            object.get(ctx, &key, attributes).map(Some)
+        };
+        let descriptor = PropertyDescriptor {
+            enumerable: field("enumerable")?.map(|value| value.to_boolean()),
+            configurable: field("configurable")?.map(|value| value.to_boolean()),
+            value: field("value")?,
+            writable: field("writable")?.map(|value| value.to_boolean()),
+            get: field("get")?,
+            set: field("set")?,
+        };
+
+        for (name, accessor) in [("getter", &descriptor.get), ("setter", &descriptor.set)] {
+            if let Some(function) = accessor {
+                if !function.is_callable() && !matches!(function, JsValue::Undefined) {
+                    return Err(JsError::type_error(format!("{} must be a function: {}", name, function)));
+                }
+            }
+        }
+        if descriptor.is_accessor() && descriptor.is_data() {
+            return Err(JsError::type_error(
+                "Invalid property descriptor. Cannot both specify accessors and a value or writable attribute",
+            ));
+        }
+        Ok(descriptor)
+    }
+
+    /// FromPropertyDescriptor: an object with a property per present field.
+    pub fn to_object(&self, prototype: &ObjectRef) -> ObjectRef {
+        let object = ObjectRef::ordinary(Some(prototype.clone()));
+        let fields = [
+            ("value", self.value.clone()),
+            ("writable", self.writable.map(JsValue::from)),
+            ("get", self.get.clone()),
+            ("set", self.set.clone()),
+            ("enumerable", self.enumerable.map(JsValue::from)),
+            ("configurable", self.configurable.map(JsValue::from)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                object.create_data_property(name, value);
+            }
+        }
+        object
+    }
+}
+
+const WRITABLE: u8 = 1;
+const ENUMERABLE: u8 = 2;
+const CONFIGURABLE: u8 = 4;
+
+/// A complete property as an object stores it: a NaN-boxed value, or a
+/// boxed getter and setter pair, beside attribute bits. Sixteen bytes,
+/// where a [`PropertyDescriptor`] of optional fields takes more than a
+/// hundred.
+#[derive(Debug, Clone)]
+pub enum PropertySlot {
+    Data { value: Value, attributes: u8 },
+    Accessor { pair: Box<(JsValue, JsValue)>, attributes: u8 },
+}
+
+impl Trace for PropertySlot {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            PropertySlot::Data { value, .. } => value.trace(tracer),
+            PropertySlot::Accessor { pair, .. } => {
+                pair.0.trace(tracer);
+                pair.1.trace(tracer);
+            }
+        }
+    }
+}
+
+impl PropertySlot {
+    /// The slot for a descriptor, whose absent fields take their defaults
+    /// as [`PropertyDescriptor::complete`] gives them.
+    pub fn new(descriptor: PropertyDescriptor) -> Self {
+        let PropertyDescriptor { value, writable, get, set, enumerable, configurable } = descriptor;
+        let mut attributes = 0;
+        if enumerable == Some(true) {
+            attributes |= ENUMERABLE;
+        }
+        if configurable == Some(true) {
+            attributes |= CONFIGURABLE;
+        }
+        if get.is_some() || set.is_some() {
+            let or_undefined = |value: Option<JsValue>| value.unwrap_or(JsValue::Undefined);
+            let pair = Box::new((or_undefined(get), or_undefined(set)));
+            return PropertySlot::Accessor { pair, attributes };
+        }
+        if writable == Some(true) {
+            attributes |= WRITABLE;
+        }
+        let value = value.map_or(Value::UNDEFINED, Value::from);
+        PropertySlot::Data { value, attributes }
+    }
+
+    /// The complete descriptor of the property.
+    pub fn descriptor(&self) -> PropertyDescriptor {
+        match self {
+            PropertySlot::Data { value, attributes } => PropertyDescriptor::data(
+                value.to_js(),
+                attributes & WRITABLE != 0,
+                attributes & ENUMERABLE != 0,
+                attributes & CONFIGURABLE != 0,
+            ),
+            PropertySlot::Accessor { pair, attributes } => PropertyDescriptor::accessor(
+                pair.0.clone(),
+                pair.1.clone(),
+                attributes & ENUMERABLE != 0,
+                attributes & CONFIGURABLE != 0,
+            ),
+        }
+    }
+
+    /// The value of a data property.
+    pub fn value(&self) -> Option<JsValue> {
+        match self {
+            PropertySlot::Data { value, .. } => Some(value.to_js()),
+            PropertySlot::Accessor { .. } => None,
+        }
+    }
+
+    pub fn is_data(&self) -> bool {
+        matches!(self, PropertySlot::Data { .. })
+    }
+
+    pub fn is_writable(&self) -> bool {
+        matches!(self, PropertySlot::Data { attributes, .. } if attributes & WRITABLE != 0)
+    }
+
+    /// Replaces the value of a data property, which the caller has checked
+    /// is writable.
+    pub fn set_value(&mut self, new: Value) {
+        if let PropertySlot::Data { value, .. } = self {
+            *value = new;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_index() {
+        assert_eq!(PropertyKey::from("0").array_index(), Some(0));
+        assert_eq!(PropertyKey::from("4294967294").array_index(), Some(4294967294));
+        for key in ["4294967295", "01", "-1", "+1", "1.0", "", "a"] {
+            assert_eq!(PropertyKey::from(key).array_index(), None, "{:?}", key);
+        }
+        assert_eq!(PropertyKey::from(Symbol::new(Some("0"))).array_index(), None);
+    }
+
+    #[test]
+    fn test_run_time_keys_are_not_interned() {
+        let key = PropertyKey::from(JsString::from("computed key text"));
+        assert!(matches!(key, PropertyKey::Text(_)));
+        assert!(Atom::lookup("computed key text").is_none());
+
+        // Atoms made later still find the properties of earlier text keys.
+        let atom = PropertyKey::from(Atom::new("computed key text"));
+        let mut keys = std::collections::HashSet::new();
+        keys.insert(key);
+        assert!(keys.contains(&atom));
+        assert!(matches!(PropertyKey::from("computed key text"), PropertyKey::String(_)));
+
+        let mut interpreter = crate::interpreter::Interpreter::new();
+        let source = "var o = {}; o['made at' + ' run time'] = 1; o['made at ' + 'run time'];";
+        let program = crate::parser::ast::ASTNode::parse_program(&crate::lexical::lexer::Lexer::new(source).tokenize());
+        assert_eq!(interpreter.run(program).unwrap().to_string(), "1");
+        assert!(Atom::lookup("made at run time").is_none());
+    }
+
+    #[test]
+    fn test_redefining_non_configurable_properties() {
+        let frozen = PropertyDescriptor::data(1.0.into(), false, true, false);
+        assert!(frozen.allows(&PropertyDescriptor::default()));
+        assert!(frozen.allows(&PropertyDescriptor::data(1.0.into(), false, true, false)));
+        assert!(!frozen.allows(&PropertyDescriptor::data(2.0.into(), false, true, false)));
+        assert!(!frozen.allows(&PropertyDescriptor {
+            writable: Some(true),
+            ..Default::default()
+        }));
+        assert!(!frozen.allows(&PropertyDescriptor {
+            enumerable: Some(false),
+            ..Default::default()
+        }));
+        assert!(!frozen.allows(&PropertyDescriptor::accessor(JsValue::Undefined, JsValue::Undefined, true, false)));
+
+        let writable = PropertyDescriptor::data(1.0.into(), true, false, false);
+        assert!(writable.allows(&PropertyDescriptor::data(2.0.into(), false, false, false)));
+
+        let zero = PropertyDescriptor::data(0.0.into(), false, false, false);
+        assert!(!zero.allows(&PropertyDescriptor {
+            value: Some((-0.0).into()),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_property_slots() {
+        assert_eq!(std::mem::size_of::<PropertySlot>(), 16);
+        let data = PropertySlot::new(PropertyDescriptor::data("text".into(), true, false, true));
+        assert!(data.is_data() && data.is_writable());
+        assert_eq!(data.value().unwrap().to_string(), "text");
+        let descriptor = data.descriptor();
+        assert_eq!((descriptor.writable, descriptor.enumerable, descriptor.configurable), (Some(true), Some(false), Some(true)));
+
+        let accessor = PropertySlot::new(PropertyDescriptor {
+            get: Some(JsValue::Null),
+            enumerable: Some(true),
+            ..Default::default()
+        });
+        assert!(!accessor.is_data() && !accessor.is_writable());
+        assert!(accessor.value().is_none());
+        let descriptor = accessor.descriptor();
+        assert!(matches!(descriptor.set, Some(JsValue::Undefined)));
+        assert_eq!((descriptor.enumerable, descriptor.configurable), (Some(true), Some(false)));
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut descriptor = PropertyDescriptor::data(1.0.into(), true, true, true);
+        descriptor.apply(PropertyDescriptor {
+            writable: Some(false),
+            ..Default::default()
+        });
+        assert!(descriptor.value.as_ref().unwrap().strict_equals(&1.0.into()));
+        assert!(!descriptor.is_writable() && descriptor.is_enumerable());
+
+        descriptor.apply(PropertyDescriptor {
+            get: Some(JsValue::Undefined),
+            ..Default::default()
+        });
+        assert!(descriptor.is_accessor() && !descriptor.is_data());
+        assert!(matches!(descriptor.set, Some(JsValue::Undefined)));
+        assert!(descriptor.is_enumerable() && descriptor.is_configurable());
+    }
+}
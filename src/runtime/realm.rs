@@ -0,0 +1,119 @@
+use crate::interpreter::Interpreter;
+use crate::runtime::error::{ErrorKind, JsError};
+use crate::runtime::object::{FunctionKind, JsObject, NativeFunction, ObjectKind, ObjectRef};
+use crate::runtime::property::PropertyDescriptor;
+use crate::runtime::value::JsValue;
+
+/// The intrinsic prototypes that objects made by the runtime inherit from.
+/// They start out linked into their prototype chains but without methods,
+/// which the interpreter's built-ins install.
+pub struct Realm {
+    pub object_prototype: ObjectRef,
+    pub function_prototype: ObjectRef,
+    pub boolean_prototype: ObjectRef,
+    pub number_prototype: ObjectRef,
+    pub string_prototype: ObjectRef,
+    pub symbol_prototype: ObjectRef,
+    pub bigint_prototype: ObjectRef,
+    pub error_prototype: ObjectRef,
+    pub type_error_prototype: ObjectRef,
+    pub reference_error_prototype: ObjectRef,
+    pub range_error_prototype: ObjectRef,
+    pub syntax_error_prototype: ObjectRef,
+    pub weak_map_prototype: ObjectRef,
+    pub weak_set_prototype: ObjectRef,
+    pub weak_ref_prototype: ObjectRef,
+    pub finalization_registry_prototype: ObjectRef,
+}
+
+impl Default for Realm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Realm {
+    pub fn new() -> Self {
+        let object_prototype = ObjectRef::ordinary(None);
+        let inheriting = |kind| ObjectRef::new(JsObject::new(Some(object_prototype.clone()), kind));
+        // Function.prototype is itself a function, and the Boolean, Number
+        // and String prototypes are wrappers, of false, 0 and "".
+        let function_prototype = inheriting(ObjectKind::Function(FunctionKind::Native(NativeFunction {
+            name: "",
+            call: return_undefined,
+        })));
+        // The native error prototypes are ordinary objects under Error.prototype.
+        let error_prototype = inheriting(ObjectKind::Ordinary);
+        let native_error = || ObjectRef::ordinary(Some(error_prototype.clone()));
+        Realm {
+            type_error_prototype: native_error(),
+            reference_error_prototype: native_error(),
+            range_error_prototype: native_error(),
+            syntax_error_prototype: native_error(),
+            error_prototype,
+            function_prototype,
+            boolean_prototype: inheriting(ObjectKind::Primitive(false.into())),
+            number_prototype: inheriting(ObjectKind::Primitive(0.0.into())),
+            string_prototype: inheriting(ObjectKind::Primitive("".into())),
+            symbol_prototype: inheriting(ObjectKind::Ordinary),
+            bigint_prototype: inheriting(ObjectKind::Ordinary),
+            weak_map_prototype: inheriting(ObjectKind::Ordinary),
+            weak_set_prototype: inheriting(ObjectKind::Ordinary),
+            weak_ref_prototype: inheriting(ObjectKind::Ordinary),
+            finalization_registry_prototype: inheriting(ObjectKind::Ordinary),
+            object_prototype,
+        }
+    }
+
+    /// A new empty object inheriting from `Object.prototype`.
+    pub fn ordinary_object(&self) -> ObjectRef {
+        ObjectRef::ordinary(Some(self.object_prototype.clone()))
+    }
+
+    pub fn native_error_prototype(&self, kind: ErrorKind) -> &ObjectRef {
+        match kind {
+            ErrorKind::Error => &self.error_prototype,
+            ErrorKind::TypeError => &self.type_error_prototype,
+            ErrorKind::ReferenceError => &self.reference_error_prototype,
+            ErrorKind::RangeError => &self.range_error_prototype,
+            ErrorKind::SyntaxError => &self.syntax_error_prototype,
+        }
+    }
+
+    /// A new error object of the given kind, with an own `message` unless
+    /// `message` is `None`.
+    pub fn error_object(&self, kind: ErrorKind, message: Option<&str>) -> ObjectRef {
+        let prototype = self.native_error_prototype(kind).clone();
+        let error = ObjectRef::new(JsObject::new(Some(prototype), ObjectKind::Error));
+        if let Some(message) = message {
+            let descriptor = PropertyDescriptor::data(message.into(), true, false, true);
+            error.define_own_property("message".into(), descriptor);
+        }
+        error
+    }
+
+    /// The value script code sees for `error`: native errors become error
+    /// objects of this realm, thrown values stay as they are.
+    pub fn thrown_value(&self, error: JsError) -> JsValue {
+        match error {
+            JsError::Native(error) => self.error_object(error.kind, Some(&error.message)).into(),
+            JsError::Thrown(value) => value,
+        }
+    }
+
+    /// The prototype of the wrapper object ToObject makes for `primitive`.
+    pub fn wrapper_prototype(&self, primitive: &JsValue) -> &ObjectRef {
+        match primitive {
+            JsValue::Boolean(_) => &self.boolean_prototype,
+            JsValue::Number(_) => &self.number_prototype,
+            JsValue::String(_) => &self.string_prototype,
+            JsValue::Symbol(_) => &self.symbol_prototype,
+            JsValue::BigInt(_) => &self.bigint_prototype,
+            other => panic!("{} has no wrapper object", other),
+        }
+    }
+}
+
+fn return_undefined(_: &mut Interpreter, _: &JsValue, _: &[JsValue]) -> Result<JsValue, JsError> {
+    Ok(JsValue::Undefined)
+}
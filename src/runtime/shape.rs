@@ -0,0 +1,370 @@
+use crate::runtime::property::{PropertySlot, PropertyKey};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+/// Deletions an object survives before its properties move to a
+/// dictionary. Deleting the property added last only steps back to the
+/// parent shape and is not counted.
+pub const MAX_DELETIONS: u32 = 8;
+
+/// Properties an object can have while it has a shape. Objects used as
+/// large maps would otherwise grow a new branch of the transition tree for
+/// every key.
+pub const MAX_SHAPED_PROPERTIES: usize = 128;
+
+/// A hidden class: the keys of an object's own properties and the slot
+/// each is stored in, in the order they were added. Objects that gained
+/// the same keys in the same order share a shape, found by following
+/// transitions from the empty shape, so comparing shapes tells whether two
+/// objects keep a key in the same slot.
+///
+/// Attributes live in the slots rather than the shape, so changing a
+/// property's value or attributes keeps the shape.
+///
+/// A shape stores only the key it adds to its parent. The slots of all its
+/// keys are in a table it shares with its ancestors and descendants along
+/// one line of transitions, so that a chain of shapes takes memory linear
+/// in its length: a child appends its key to its parent's table when the
+/// parent is the last shape that table has grown to, and copies the keys
+/// it needs only where the tree branches.
+pub struct Shape {
+    parent: Option<Rc<Shape>>,
+    /// The key this shape adds, in slot `len - 1`.
+    key: Option<PropertyKey>,
+    len: u32,
+    /// Slots of this shape's keys, and perhaps of later keys in slots from
+    /// `len` on, which belong to descendants.
+    slots: Rc<RefCell<HashMap<PropertyKey, u32>>>,
+    /// Shapes one key larger. Held weakly, so that branches no object uses
+    /// any more are freed; every shape keeps its parent alive.
+    transitions: RefCell<HashMap<PropertyKey, Weak<Shape>>>,
+}
+
+thread_local! {
+    static ROOT: Rc<Shape> = Rc::new(Shape {
+        parent: None,
+        key: None,
+        len: 0,
+        slots: Rc::default(),
+        transitions: RefCell::new(HashMap::new()),
+    });
+}
+
+impl Shape {
+    /// The shape of objects without own properties.
+    pub fn root() -> Rc<Shape> {
+        ROOT.with(Rc::clone)
+    }
+
+    /// The shape reached by adding `key`, which must not be in this one.
+    pub fn with_key(self: &Rc<Shape>, key: PropertyKey) -> Rc<Shape> {
+        if let Some(shape) = self.transitions.borrow().get(&key).and_then(Weak::upgrade) {
+            return shape;
+        }
+        let slots = if self.slots.borrow().len() == self.len() {
+            self.slots.clone()
+        } else {
+            let keys = self.keys().into_iter().enumerate();
+            Rc::new(RefCell::new(keys.map(|(slot, key)| (key, slot as u32)).collect()))
+        };
+        slots.borrow_mut().insert(key.clone(), self.len);
+        let shape = Rc::new(Shape {
+            parent: Some(self.clone()),
+            key: Some(key.clone()),
+            len: self.len + 1,
+            slots,
+            transitions: RefCell::new(HashMap::new()),
+        });
+        let mut transitions = self.transitions.borrow_mut();
+        transitions.retain(|_, child| child.strong_count() > 0);
+        transitions.insert(key, Rc::downgrade(&shape));
+        shape
+    }
+
+    pub fn slot(&self, key: &PropertyKey) -> Option<usize> {
+        let slot = *self.slots.borrow().get(key)?;
+        (slot < self.len).then_some(slot as usize)
+    }
+
+    /// Keys in slot order, which is the order they were added.
+    pub fn keys(&self) -> Vec<PropertyKey> {
+        let mut keys = Vec::with_capacity(self.len());
+        let mut shape = self;
+        while let (Some(key), Some(parent)) = (&shape.key, &shape.parent) {
+            keys.push(key.clone());
+            shape = parent;
+        }
+        keys.reverse();
+        keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// An object's own properties: values in slots laid out by a shared
+/// shape, or, for objects that have had many properties deleted or have
+/// very many, a dictionary of their own.
+pub enum PropertyMap {
+    Shaped {
+        shape: Rc<Shape>,
+        slots: Vec<PropertySlot>,
+        deletions: u32,
+    },
+    Dictionary {
+        entries: Vec<(PropertyKey, PropertySlot)>,
+        positions: HashMap<PropertyKey, usize>,
+    },
+}
+
+impl Default for PropertyMap {
+    fn default() -> Self {
+        PropertyMap::Shaped {
+            shape: Shape::root(),
+            slots: Vec::new(),
+            deletions: 0,
+        }
+    }
+}
+
+impl PropertyMap {
+    /// The shape, unless the properties are in dictionary mode.
+    pub fn shape(&self) -> Option<&Rc<Shape>> {
+        match self {
+            PropertyMap::Shaped { shape, .. } => Some(shape),
+            PropertyMap::Dictionary { .. } => None,
+        }
+    }
+
+    pub fn is_dictionary(&self) -> bool {
+        matches!(self, PropertyMap::Dictionary { .. })
+    }
+
+    pub fn position(&self, key: &PropertyKey) -> Option<usize> {
+        match self {
+            PropertyMap::Shaped { shape, .. } => shape.slot(key),
+            PropertyMap::Dictionary { positions, .. } => positions.get(key).copied(),
+        }
+    }
+
+    /// The property at a position returned by [`PropertyMap::position`].
+    pub fn at(&self, position: usize) -> &PropertySlot {
+        match self {
+            PropertyMap::Shaped { slots, .. } => &slots[position],
+            PropertyMap::Dictionary { entries, .. } => &entries[position].1,
+        }
+    }
+
+    pub fn at_mut(&mut self, position: usize) -> &mut PropertySlot {
+        match self {
+            PropertyMap::Shaped { slots, .. } => &mut slots[position],
+            PropertyMap::Dictionary { entries, .. } => &mut entries[position].1,
+        }
+    }
+
+    pub fn get(&self, key: &PropertyKey) -> Option<&PropertySlot> {
+        self.position(key).map(|position| self.at(position))
+    }
+
+    /// Adds a property that is not in the map yet.
+    pub fn insert(&mut self, key: PropertyKey, slot: PropertySlot) {
+        if let PropertyMap::Shaped { shape, slots, .. } = self {
+            if shape.len() < MAX_SHAPED_PROPERTIES {
+                *shape = shape.with_key(key);
+                slots.push(slot);
+                return;
+            }
+            self.make_dictionary();
+        }
+        let PropertyMap::Dictionary { entries, positions } = self else {
+            unreachable!("properties were just made a dictionary");
+        };
+        positions.insert(key.clone(), entries.len());
+        entries.push((key, slot));
+    }
+
+    /// Adds the property that takes a shaped map to `shape`, a transition
+    /// of its current one.
+    pub fn transition(&mut self, shape: Rc<Shape>, slot: PropertySlot) {
+        let PropertyMap::Shaped { shape: current, slots, .. } = self else {
+            panic!("Expected shaped properties");
+        };
+        debug_assert!(shape.parent.as_ref().is_some_and(|parent| Rc::ptr_eq(parent, current)));
+        *current = shape;
+        slots.push(slot);
+    }
+
+    pub fn remove(&mut self, key: &PropertyKey) {
+        let Some(position) = self.position(key) else {
+            return;
+        };
+        match self {
+            PropertyMap::Shaped { shape, slots, deletions } => {
+                slots.remove(position);
+                if position == slots.len() {
+                    *shape = shape.parent.clone().expect("a shape with keys has a parent");
+                    return;
+                }
+                *deletions += 1;
+                if *deletions < MAX_DELETIONS {
+                    let mut rebuilt = Shape::root();
+                    for remaining in shape.keys().into_iter().filter(|remaining| remaining != key) {
+                        rebuilt = rebuilt.with_key(remaining);
+                    }
+                    *shape = rebuilt;
+                    return;
+                }
+                // The slot is gone already, so the key is left out.
+                let keys = shape.keys().into_iter().filter(|remaining| remaining != key);
+                let entries: Vec<_> = keys.zip(slots.drain(..)).collect();
+                *self = PropertyMap::dictionary(entries);
+            }
+            PropertyMap::Dictionary { entries, positions } => {
+                entries.remove(position);
+                positions.remove(key);
+                for (index, (key, _)) in entries.iter().enumerate().skip(position) {
+                    positions.insert(key.clone(), index);
+                }
+            }
+        }
+    }
+
+    /// Moves the properties out of their shape into a dictionary.
+    pub fn make_dictionary(&mut self) {
+        if let PropertyMap::Shaped { shape, slots, .. } = self {
+            let entries = shape.keys().into_iter().zip(slots.drain(..)).collect();
+            *self = PropertyMap::dictionary(entries);
+        }
+    }
+
+    fn dictionary(entries: Vec<(PropertyKey, PropertySlot)>) -> Self {
+        let positions = entries.iter().enumerate().map(|(index, (key, _))| (key.clone(), index)).collect();
+        PropertyMap::Dictionary { entries, positions }
+    }
+
+    /// Properties in the order they were added.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (PropertyKey, &PropertySlot)> + '_> {
+        match self {
+            PropertyMap::Shaped { shape, slots, .. } => Box::new(shape.keys().into_iter().zip(slots)),
+            PropertyMap::Dictionary { entries, .. } => Box::new(entries.iter().map(|(key, slot)| (key.clone(), slot))),
+        }
+    }
+
+    pub fn values(&self) -> Box<dyn Iterator<Item = &PropertySlot> + '_> {
+        match self {
+            PropertyMap::Shaped { slots, .. } => Box::new(slots.iter()),
+            PropertyMap::Dictionary { entries, .. } => Box::new(entries.iter().map(|(_, slot)| slot)),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = PropertyMap::default();
+    }
+
+    /// Memory the map owns, not counting shapes, which objects share.
+    pub fn heap_bytes(&self) -> usize {
+        match self {
+            PropertyMap::Shaped { slots, .. } => slots.capacity() * std::mem::size_of::<PropertySlot>(),
+            PropertyMap::Dictionary { entries, positions } => {
+                entries.capacity() * std::mem::size_of::<(PropertyKey, PropertySlot)>()
+                    + positions.capacity() * std::mem::size_of::<(PropertyKey, usize)>()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::property::PropertyDescriptor;
+    use crate::runtime::value::JsValue;
+
+    fn data(value: f64) -> PropertySlot {
+        PropertySlot::new(PropertyDescriptor::data(value.into(), true, true, true))
+    }
+
+    fn keys(map: &PropertyMap) -> Vec<String> {
+        map.iter().map(|(key, _)| key.to_string()).collect()
+    }
+
+    #[test]
+    fn test_transitions_are_shared() {
+        let mut first = PropertyMap::default();
+        let mut second = PropertyMap::default();
+        for map in [&mut first, &mut second] {
+            map.insert("x".into(), data(1.0));
+            map.insert("y".into(), data(2.0));
+        }
+        assert!(Rc::ptr_eq(first.shape().unwrap(), second.shape().unwrap()));
+        assert_eq!(first.position(&"y".into()), Some(1));
+
+        // Same keys in another order make another shape.
+        let mut third = PropertyMap::default();
+        third.insert("y".into(), data(2.0));
+        third.insert("x".into(), data(1.0));
+        assert!(!Rc::ptr_eq(first.shape().unwrap(), third.shape().unwrap()));
+
+        // Deleting the last key steps back along the transition.
+        let parent = first.shape().unwrap().parent.clone().unwrap();
+        second.remove(&"y".into());
+        assert!(Rc::ptr_eq(second.shape().unwrap(), &parent));
+        assert_eq!(keys(&second), ["x"]);
+    }
+
+    #[test]
+    fn test_slot_tables_are_shared_along_a_chain() {
+        let mut shape = Shape::root();
+        let mut chain = Vec::new();
+        for index in 0..50 {
+            shape = shape.with_key(format!("k{}", index).into());
+            chain.push(shape.clone());
+        }
+        assert!(chain.iter().all(|link| Rc::ptr_eq(&link.slots, &shape.slots)));
+        assert_eq!(chain[9].slot(&"k9".into()), Some(9));
+        assert_eq!(chain[9].slot(&"k10".into()), None);
+        assert_eq!(chain[2].keys(), ["k0".into(), "k1".into(), "k2".into()]);
+
+        // A branch copies its keys once and is independent of the line.
+        let branch = chain[1].with_key("other".into());
+        assert!(!Rc::ptr_eq(&branch.slots, &shape.slots));
+        assert_eq!((branch.slot(&"other".into()), branch.slot(&"k2".into())), (Some(2), None));
+        assert_eq!(shape.slot(&"other".into()), None);
+    }
+
+    #[test]
+    fn test_dictionary_mode() {
+        let mut map = PropertyMap::default();
+        for round in 1..MAX_DELETIONS {
+            map.insert("a".into(), data(round as f64));
+            map.insert("b".into(), data(round as f64));
+            map.remove(&"a".into());
+            map.remove(&"b".into());
+            assert!(!map.is_dictionary());
+        }
+        map.insert("a".into(), data(1.0));
+        map.insert("b".into(), data(2.0));
+        map.insert("c".into(), data(3.0));
+        map.remove(&"a".into());
+        assert!(map.is_dictionary());
+        assert!(map.shape().is_none());
+        assert_eq!(keys(&map), ["b", "c"]);
+        assert!(matches!(map.get(&"c".into()).unwrap().value(), Some(JsValue::Number(value)) if value == 3.0));
+        map.remove(&"b".into());
+        map.insert("d".into(), data(4.0));
+        assert_eq!(keys(&map), ["c", "d"]);
+        assert_eq!(map.position(&"d".into()), Some(1));
+
+        let mut large = PropertyMap::default();
+        for index in 0..=MAX_SHAPED_PROPERTIES {
+            large.insert(format!("k{}", index).into(), data(index as f64));
+        }
+        assert!(large.is_dictionary());
+        assert_eq!(large.iter().count(), MAX_SHAPED_PROPERTIES + 1);
+    }
+}
@@ -0,0 +1,324 @@
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// Concatenations shorter than this are copied into a flat string at once;
+/// longer ones make a rope.
+pub const MIN_ROPE_LENGTH: usize = 16;
+
+/// A string value: a sequence of UTF-16 code units, which need not be valid
+/// UTF-16, so lone surrogates survive. Strings whose units all fit in a
+/// byte are stored as Latin-1, the rest as UTF-16, and both know their
+/// length, so `length` and indexing take constant time.
+///
+/// Concatenating long strings makes a rope that holds both halves, copied
+/// into a flat string the first time its units are read. A loop appending
+/// to a string so copies it once rather than on every iteration.
+#[derive(Clone)]
+pub struct JsString(Rc<Node>);
+
+struct Node {
+    len: usize,
+    /// The halves of a rope until it is flattened.
+    halves: RefCell<Option<(JsString, JsString)>>,
+    flat: OnceCell<Flat>,
+}
+
+enum Flat {
+    Latin1(Box<[u8]>),
+    Utf16(Box<[u16]>),
+}
+
+impl Flat {
+    /// The narrowest storage for `units`.
+    fn new(units: Vec<u16>) -> Flat {
+        match units.iter().all(|&unit| unit <= 0xFF) {
+            true => Flat::Latin1(units.into_iter().map(|unit| unit as u8).collect()),
+            false => Flat::Utf16(units.into_boxed_slice()),
+        }
+    }
+
+    fn units(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Flat::Latin1(bytes) => Box::new(bytes.iter().map(|&byte| byte as u16)),
+            Flat::Utf16(units) => Box::new(units.iter().copied()),
+        }
+    }
+}
+
+impl Drop for Node {
+    /// Frees the ropes only this one holds without recursing, as a long
+    /// chain of appends would otherwise overflow the stack.
+    fn drop(&mut self) {
+        let mut pending: Vec<JsString> = self.halves.get_mut().take().into_iter().flat_map(|(l, r)| [l, r]).collect();
+        while let Some(part) = pending.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(part.0) {
+                pending.extend(node.halves.get_mut().take().into_iter().flat_map(|(l, r)| [l, r]));
+            }
+        }
+    }
+}
+
+impl JsString {
+    fn flat(flat: Flat) -> JsString {
+        let len = match &flat {
+            Flat::Latin1(bytes) => bytes.len(),
+            Flat::Utf16(units) => units.len(),
+        };
+        JsString(Rc::new(Node {
+            len,
+            halves: RefCell::new(None),
+            flat: OnceCell::from(flat),
+        }))
+    }
+
+    /// Gives up the handle as a pointer, for compact value encodings.
+    pub fn into_raw(self) -> *const () {
+        Rc::into_raw(self.0) as *const ()
+    }
+
+    /// # Safety
+    /// `pointer` must come from [`JsString::into_raw`], and the handle it
+    /// gave up passes to the result.
+    pub unsafe fn from_raw(pointer: *const ()) -> Self {
+        JsString(Rc::from_raw(pointer as *const Node))
+    }
+
+    pub fn from_code_units(units: &[u16]) -> JsString {
+        JsString::flat(Flat::new(units.to_vec()))
+    }
+
+    /// The length in UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.0.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.len == 0
+    }
+
+    /// Whether this is a rope not yet flattened.
+    pub fn is_rope(&self) -> bool {
+        self.0.flat.get().is_none()
+    }
+
+    /// The contents, flattening a rope first.
+    fn contents(&self) -> &Flat {
+        self.0.flat.get_or_init(|| {
+            let (left, right) = self.0.halves.take().expect("a string is flat or a rope");
+            let mut units = Vec::with_capacity(self.0.len);
+            let mut pending = vec![right, left];
+            while let Some(part) = pending.pop() {
+                if let Some(flat) = part.0.flat.get() {
+                    units.extend(flat.units());
+                    continue;
+                }
+                let halves = part.0.halves.borrow();
+                let (left, right) = halves.as_ref().expect("a string is flat or a rope");
+                pending.push(right.clone());
+                pending.push(left.clone());
+            }
+            Flat::new(units)
+        })
+    }
+
+    /// The code unit at `index`, if it is below the length.
+    pub fn code_unit(&self, index: usize) -> Option<u16> {
+        match self.contents() {
+            Flat::Latin1(bytes) => bytes.get(index).map(|&byte| byte as u16),
+            Flat::Utf16(units) => units.get(index).copied(),
+        }
+    }
+
+    pub fn code_units(&self) -> impl Iterator<Item = u16> + '_ {
+        self.contents().units()
+    }
+
+    /// The code units from `start` up to `end`, both clamped to the length.
+    pub fn substring(&self, start: usize, end: usize) -> JsString {
+        let end = end.min(self.len());
+        let start = start.min(end);
+        match self.contents() {
+            Flat::Latin1(bytes) => JsString::flat(Flat::Latin1(bytes[start..end].into())),
+            Flat::Utf16(units) => JsString::from_code_units(&units[start..end]),
+        }
+    }
+
+    /// The string followed by `other`.
+    pub fn concat(&self, other: &JsString) -> JsString {
+        if other.is_empty() {
+            return self.clone();
+        }
+        if self.is_empty() {
+            return other.clone();
+        }
+        let len = self.len() + other.len();
+        if len < MIN_ROPE_LENGTH {
+            return JsString::flat(Flat::new(self.code_units().chain(other.code_units()).collect()));
+        }
+        JsString(Rc::new(Node {
+            len,
+            halves: RefCell::new(Some((self.clone(), other.clone()))),
+            flat: OnceCell::new(),
+        }))
+    }
+
+    /// The text as a Rust string, with lone surrogates replaced by U+FFFD.
+    pub fn to_std_string(&self) -> String {
+        self.chars().map(|char| char.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+    }
+
+    /// The code points, or the lone surrogates that are not any.
+    fn chars(&self) -> impl Iterator<Item = Result<char, u16>> + '_ {
+        char::decode_utf16(self.code_units()).map(|char| char.map_err(|error| error.unpaired_surrogate()))
+    }
+}
+
+impl Default for JsString {
+    fn default() -> Self {
+        JsString::flat(Flat::Latin1(Box::default()))
+    }
+}
+
+impl From<&str> for JsString {
+    fn from(text: &str) -> Self {
+        match text.chars().all(|char| char <= '\u{FF}') {
+            true => JsString::flat(Flat::Latin1(text.chars().map(|char| char as u8).collect())),
+            false => JsString::flat(Flat::Utf16(text.encode_utf16().collect())),
+        }
+    }
+}
+
+impl From<String> for JsString {
+    fn from(text: String) -> Self {
+        JsString::from(text.as_str())
+    }
+}
+
+impl From<Rc<str>> for JsString {
+    fn from(text: Rc<str>) -> Self {
+        JsString::from(&*text)
+    }
+}
+
+impl From<&JsString> for String {
+    fn from(text: &JsString) -> Self {
+        text.to_std_string()
+    }
+}
+
+impl PartialEq for JsString {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.len() == other.len() && self.code_units().eq(other.code_units())
+    }
+}
+
+impl Eq for JsString {}
+
+impl PartialEq<str> for JsString {
+    fn eq(&self, other: &str) -> bool {
+        self.code_units().eq(other.encode_utf16())
+    }
+}
+
+impl PartialEq<&str> for JsString {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl Hash for JsString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        self.code_units().for_each(|unit| unit.hash(state));
+    }
+}
+
+impl PartialOrd for JsString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JsString {
+    /// By code units, as the relational operators compare strings, rather
+    /// than by code points.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.code_units().cmp(other.code_units())
+    }
+}
+
+impl fmt::Display for JsString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chars().try_for_each(|char| f.write_char(char.unwrap_or(char::REPLACEMENT_CHARACTER)))
+    }
+}
+
+impl fmt::Debug for JsString {
+    /// Quoted like a Rust string, with lone surrogates as `\u{d800}`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for char in self.chars() {
+            match char {
+                Ok(char) => write!(f, "{}", char.escape_debug())?,
+                Err(unit) => write!(f, "\\u{{{:x}}}", unit)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_and_code_units() {
+        let latin1 = JsString::from("café");
+        assert!(matches!(latin1.contents(), Flat::Latin1(_)));
+        assert_eq!((latin1.len(), latin1.code_unit(3)), (4, Some(0xE9)));
+
+        let emoji = JsString::from("a😀");
+        assert!(matches!(emoji.contents(), Flat::Utf16(_)));
+        assert_eq!(emoji.len(), 3);
+        assert_eq!(emoji.code_unit(1), Some(0xD83D));
+        assert_eq!(emoji.code_unit(3), None);
+
+        // Halves of a surrogate pair are lone surrogates, which rejoin.
+        let lead = emoji.substring(1, 2);
+        assert_eq!(format!("{:?}", lead), "\"\\u{d83d}\"");
+        assert_eq!(lead.to_std_string(), "\u{FFFD}");
+        assert_eq!(lead.concat(&emoji.substring(2, 3)), JsString::from("😀"));
+        assert_eq!(JsString::from_code_units(&[0x61, 0xDC00]).to_string(), "a\u{FFFD}");
+
+        // Code unit order puts U+FF61 after the lead surrogate of U+1F600.
+        assert_eq!(JsString::from("\u{FF61}").cmp(&JsString::from("😀")), Ordering::Greater);
+        assert_eq!(JsString::from("abc"), "abc");
+    }
+
+    #[test]
+    fn test_ropes() {
+        let short = JsString::from("ab").concat(&JsString::from("cd"));
+        assert!(!short.is_rope());
+        assert_eq!(short, "abcd");
+
+        let mut text = JsString::default();
+        for _ in 0..100_000 {
+            text = text.concat(&JsString::from("xy"));
+        }
+        assert!(text.is_rope());
+        assert_eq!(text.len(), 200_000);
+        let copy = text.clone();
+        assert_eq!(text.code_unit(199_999), Some('y' as u16));
+        assert!(!copy.is_rope());
+
+        // Dropping a deep rope does not recurse.
+        let mut deep = JsString::from("0123456789abcdef");
+        for _ in 0..100_000 {
+            deep = deep.concat(&JsString::from("é"));
+        }
+        drop(deep);
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// A symbol value. Every symbol is unique: two symbols are equal only if
+/// they are the same symbol, whatever their descriptions.
+#[derive(Clone)]
+pub struct Symbol(Rc<Option<Rc<str>>>);
+
+thread_local! {
+    static TO_PRIMITIVE: Symbol = Symbol::new(Some("Symbol.toPrimitive"));
+}
+
+impl Symbol {
+    pub fn new(description: Option<&str>) -> Self {
+        Symbol(Rc::new(description.map(Rc::from)))
+    }
+
+    /// The well-known `Symbol.toPrimitive`, consulted by ToPrimitive.
+    pub fn to_primitive() -> Self {
+        TO_PRIMITIVE.with(Symbol::clone)
+    }
+
+    /// Gives up the handle as a pointer, for compact value encodings.
+    pub fn into_raw(self) -> *const () {
+        Rc::into_raw(self.0) as *const ()
+    }
+
+    /// # Safety
+    /// `pointer` must come from [`Symbol::into_raw`], and the handle it gave
+    /// up passes to the result.
+    pub unsafe fn from_raw(pointer: *const ()) -> Self {
+        Symbol(Rc::from_raw(pointer as *const Option<Rc<str>>))
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    /// SymbolDescriptiveString, e.g. `Symbol(foo)`.
+    pub fn descriptive_string(&self) -> String {
+        format!("Symbol({})", self.description().unwrap_or(""))
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).hash(state);
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.descriptive_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let a = Symbol::new(Some("a"));
+        assert_eq!(a, a.clone());
+        assert_ne!(a, Symbol::new(Some("a")));
+        assert_eq!(Symbol::to_primitive(), Symbol::to_primitive());
+        assert_eq!(a.descriptive_string(), "Symbol(a)");
+        assert_eq!(Symbol::new(None).descriptive_string(), "Symbol()");
+    }
+}
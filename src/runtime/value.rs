@@ -0,0 +1,366 @@
+use crate::parser::numeric::number_to_string;
+use crate::runtime::bigint::BigInt;
+use crate::runtime::gc::{Trace, Tracer};
+use crate::runtime::object::ObjectRef;
+use crate::runtime::string::JsString;
+use crate::runtime::symbol::Symbol;
+use std::fmt;
+use std::rc::Rc;
+
+/// A JavaScript value. Conversions that may run script code, such as
+/// ToPrimitive, live in [`crate::runtime::conversions`].
+#[derive(Debug, Clone)]
+pub enum JsValue {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(JsString),
+    Symbol(Symbol),
+    BigInt(Rc<BigInt>),
+    Object(ObjectRef),
+}
+
+impl JsValue {
+    /// The result of the `typeof` operator.
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            JsValue::Undefined => "undefined",
+            JsValue::Null => "object",
+            JsValue::Boolean(_) => "boolean",
+            JsValue::Number(_) => "number",
+            JsValue::String(_) => "string",
+            JsValue::Symbol(_) => "symbol",
+            JsValue::BigInt(_) => "bigint",
+            JsValue::Object(object) if object.is_callable() => "function",
+            JsValue::Object(_) => "object",
+        }
+    }
+
+    pub fn is_nullish(&self) -> bool {
+        matches!(self, JsValue::Undefined | JsValue::Null)
+    }
+
+    pub fn as_object(&self) -> Option<&ObjectRef> {
+        match self {
+            JsValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn is_callable(&self) -> bool {
+        self.as_object().is_some_and(ObjectRef::is_callable)
+    }
+
+    /// ToBoolean.
+    pub fn to_boolean(&self) -> bool {
+        match self {
+            JsValue::Undefined | JsValue::Null => false,
+            JsValue::Boolean(value) => *value,
+            JsValue::Number(value) => !(value.is_nan() || *value == 0.0),
+            JsValue::String(value) => !value.is_empty(),
+            JsValue::BigInt(value) => !value.is_zero(),
+            JsValue::Symbol(_) | JsValue::Object(_) => true,
+        }
+    }
+
+    /// IsStrictlyEqual, the `===` operator: no conversions, NaN is unequal
+    /// to itself and the zeros are equal.
+    pub fn strict_equals(&self, other: &JsValue) -> bool {
+        match (self, other) {
+            (JsValue::Number(a), JsValue::Number(b)) => a == b,
+            _ => self.same_value_non_number(other),
+        }
+    }
+
+    /// SameValue, used by `Object.is`: NaN equals itself and `+0` differs
+    /// from `-0`.
+    pub fn same_value(&self, other: &JsValue) -> bool {
+        match (self, other) {
+            (JsValue::Number(a), JsValue::Number(b)) => {
+                (a.is_nan() && b.is_nan()) || (a == b && a.is_sign_negative() == b.is_sign_negative())
+            }
+            _ => self.same_value_non_number(other),
+        }
+    }
+
+    /// SameValueZero, used by `includes` and Map keys: like SameValue but
+    /// the zeros are equal.
+    pub fn same_value_zero(&self, other: &JsValue) -> bool {
+        match (self, other) {
+            (JsValue::Number(a), JsValue::Number(b)) => (a.is_nan() && b.is_nan()) || a == b,
+            _ => self.same_value_non_number(other),
+        }
+    }
+
+    fn same_value_non_number(&self, other: &JsValue) -> bool {
+        match (self, other) {
+            (JsValue::Undefined, JsValue::Undefined) | (JsValue::Null, JsValue::Null) => true,
+            (JsValue::Boolean(a), JsValue::Boolean(b)) => a == b,
+            (JsValue::String(a), JsValue::String(b)) => a == b,
+            (JsValue::Symbol(a), JsValue::Symbol(b)) => a == b,
+            (JsValue::BigInt(a), JsValue::BigInt(b)) => a == b,
+            (JsValue::Object(a), JsValue::Object(b)) => ObjectRef::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Trace for JsValue {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let JsValue::Object(object) = self {
+            object.trace(tracer);
+        }
+    }
+}
+
+impl From<bool> for JsValue {
+    fn from(value: bool) -> Self {
+        JsValue::Boolean(value)
+    }
+}
+
+impl From<f64> for JsValue {
+    fn from(value: f64) -> Self {
+        JsValue::Number(value)
+    }
+}
+
+impl From<&str> for JsValue {
+    fn from(value: &str) -> Self {
+        JsValue::String(value.into())
+    }
+}
+
+impl From<String> for JsValue {
+    fn from(value: String) -> Self {
+        JsValue::String(value.into())
+    }
+}
+
+impl From<Rc<str>> for JsValue {
+    fn from(value: Rc<str>) -> Self {
+        JsValue::String(value.into())
+    }
+}
+
+impl From<JsString> for JsValue {
+    fn from(value: JsString) -> Self {
+        JsValue::String(value)
+    }
+}
+
+impl From<Symbol> for JsValue {
+    fn from(symbol: Symbol) -> Self {
+        JsValue::Symbol(symbol)
+    }
+}
+
+impl From<BigInt> for JsValue {
+    fn from(value: BigInt) -> Self {
+        JsValue::BigInt(Rc::new(value))
+    }
+}
+
+impl From<ObjectRef> for JsValue {
+    fn from(object: ObjectRef) -> Self {
+        JsValue::Object(object)
+    }
+}
+
+impl fmt::Display for JsValue {
+    /// ToString for primitives other than symbols, which show their
+    /// descriptive string; objects show what the built-in `toString` gives.
+    /// Never runs script code, so it suits error messages.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsValue::Undefined => f.write_str("undefined"),
+            JsValue::Null => f.write_str("null"),
+            JsValue::Boolean(value) => write!(f, "{}", value),
+            JsValue::Number(value) => f.write_str(&number_to_string(*value)),
+            JsValue::String(value) => write!(f, "{}", value),
+            JsValue::Symbol(symbol) => f.write_str(&symbol.descriptive_string()),
+            JsValue::BigInt(value) => write!(f, "{}", value),
+            JsValue::Object(object) => f.write_str(&object.builtin_to_string()),
+        }
+    }
+}
+
+/// The WhiteSpace and LineTerminator code points that StringToNumber and
+/// `String.prototype.trim` strip. Unlike [`char::is_whitespace`] this
+/// includes U+FEFF and excludes U+0085.
+pub fn is_js_whitespace(ch: char) -> bool {
+    matches!(
+        ch,
+        '\t' | '\n'
+            | '\u{0B}'
+            | '\u{0C}'
+            | '\r'
+            | ' '
+            | '\u{A0}'
+            | '\u{1680}'
+            | '\u{2000}'..='\u{200A}'
+            | '\u{2028}'
+            | '\u{2029}'
+            | '\u{202F}'
+            | '\u{205F}'
+            | '\u{3000}'
+            | '\u{FEFF}'
+    )
+}
+
+/// StringToNumber: surrounding whitespace is ignored, the empty string is
+/// 0, and anything that is not a StringNumericLiteral is NaN. Unlike source
+/// literals, there are no separators, BigInt suffixes or legacy octals.
+pub fn string_to_number(text: &str) -> f64 {
+    let text = text.trim_matches(is_js_whitespace);
+    if text.is_empty() {
+        return 0.0;
+    }
+
+    if let Some(prefix) = text.get(..2) {
+        if matches!(prefix, "0x" | "0X" | "0o" | "0O" | "0b" | "0B") {
+            // Converting through BigInt rounds long literals correctly.
+            return BigInt::parse_literal(text).map_or(f64::NAN, |value| value.to_f64());
+        }
+    }
+
+    let (sign, unsigned) = match text.as_bytes()[0] {
+        b'-' => (-1.0, &text[1..]),
+        b'+' => (1.0, &text[1..]),
+        _ => (1.0, text),
+    };
+    if unsigned == "Infinity" {
+        return sign * f64::INFINITY;
+    }
+    // Rust's parser also accepts `inf`, `NaN` and a second sign.
+    let mut bytes = unsigned.bytes();
+    let starts_like_decimal = bytes.next().is_some_and(|b| b.is_ascii_digit() || b == b'.');
+    let is_decimal = bytes.all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'));
+    match unsigned.parse::<f64>() {
+        Ok(value) if starts_like_decimal && is_decimal => sign * value,
+        _ => f64::NAN,
+    }
+}
+
+/// ToInt32: the number modulo 2^32, as a signed integer.
+pub fn to_int32(value: f64) -> i32 {
+    to_uint32(value) as i32
+}
+
+/// ToUint32: the number truncated and taken modulo 2^32.
+pub fn to_uint32(value: f64) -> u32 {
+    if !value.is_finite() {
+        return 0;
+    }
+    value.trunc().rem_euclid(4294967296.0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_to_number() {
+        let cases = [
+            ("  42  ", 42.0),
+            ("", 0.0),
+            (" \u{FEFF}\u{2028}\t", 0.0),
+            ("0x1f", 31.0),
+            ("0B11", 3.0),
+            ("0o17", 15.0),
+            ("-Infinity", f64::NEG_INFINITY),
+            ("+Infinity", f64::INFINITY),
+            (".5e1", 5.0),
+            ("5.", 5.0),
+            ("-0", -0.0),
+            ("1E+2", 100.0),
+            ("017", 17.0),
+            ("0x20000000000001", 9007199254740992.0),
+            ("0x20000000000003", 9007199254740996.0),
+        ];
+        for (text, expected) in cases {
+            let value = string_to_number(text);
+            assert!(JsValue::from(value).same_value(&expected.into()), "{:?} gave {}", text, value);
+        }
+
+        for text in ["inf", "infinity", "NaN", "-0x10", "+-1", "1_000", "1n", ".", "e5", "1e", "0x", "\u{85}1", "12px"] {
+            assert!(string_to_number(text).is_nan(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_to_boolean() {
+        let falsy: [JsValue; 8] = [
+            JsValue::Undefined,
+            JsValue::Null,
+            false.into(),
+            0.0.into(),
+            (-0.0).into(),
+            f64::NAN.into(),
+            "".into(),
+            BigInt::zero().into(),
+        ];
+        for value in falsy {
+            assert!(!value.to_boolean(), "{:?}", value);
+        }
+
+        let truthy: [JsValue; 6] = [
+            "0".into(),
+            " ".into(),
+            (-1.0).into(),
+            BigInt::from_i64(-1).into(),
+            Symbol::new(None).into(),
+            ObjectRef::default().into(),
+        ];
+        for value in truthy {
+            assert!(value.to_boolean(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_equality_relations() {
+        let nan = JsValue::from(f64::NAN);
+        let zero = JsValue::from(0.0);
+        let negative_zero = JsValue::from(-0.0);
+
+        assert!(!nan.strict_equals(&nan));
+        assert!(nan.same_value(&nan));
+        assert!(nan.same_value_zero(&nan));
+
+        assert!(zero.strict_equals(&negative_zero));
+        assert!(!zero.same_value(&negative_zero));
+        assert!(zero.same_value_zero(&negative_zero));
+
+        assert!(!JsValue::from("1").strict_equals(&1.0.into()));
+        assert!(JsValue::from(BigInt::from_i64(5)).strict_equals(&BigInt::from_i64(5).into()));
+        assert!(!JsValue::from(BigInt::from_i64(5)).strict_equals(&5.0.into()));
+
+        let symbol = Symbol::new(Some("s"));
+        assert!(JsValue::from(symbol.clone()).same_value(&symbol.into()));
+        assert!(!JsValue::from(Symbol::new(Some("s"))).same_value(&Symbol::new(Some("s")).into()));
+
+        let object = ObjectRef::default();
+        assert!(JsValue::from(object.clone()).strict_equals(&object.into()));
+        assert!(!JsValue::from(ObjectRef::default()).strict_equals(&ObjectRef::default().into()));
+        assert!(JsValue::Null.strict_equals(&JsValue::Null));
+        assert!(!JsValue::Null.strict_equals(&JsValue::Undefined));
+    }
+
+    #[test]
+    fn test_int32_conversions() {
+        assert_eq!(to_int32(4294967297.0), 1);
+        assert_eq!(to_int32(2147483648.0), -2147483648);
+        assert_eq!(to_int32(-1.5), -1);
+        assert_eq!(to_uint32(-1.0), 4294967295);
+        assert_eq!(to_uint32(f64::NAN), 0);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(JsValue::from(1e21).to_string(), "1e+21");
+        assert_eq!(JsValue::from(BigInt::from_i64(-7)).to_string(), "-7");
+        assert_eq!(JsValue::from(Symbol::new(Some("x"))).to_string(), "Symbol(x)");
+        assert_eq!(JsValue::from(ObjectRef::default()).to_string(), "[object Object]");
+    }
+}
@@ -0,0 +1,236 @@
+use crate::runtime::gc::{Trace, Tracer};
+use crate::runtime::object::{ObjectRef, WeakObjectRef};
+use crate::runtime::value::JsValue;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+
+/// The entries of a `WeakMap` or `WeakSet`. Keys are held weakly and values
+/// as ephemerons: a value stays alive through the table only while its key
+/// does, even when the value refers back to the key.
+///
+/// Entries are found by the address of their key, which the weak handle
+/// keeps from being reused, so a freed key is never found again. Its entry
+/// is dropped when the collector sweeps the table.
+#[derive(Clone, Default)]
+pub struct WeakTable {
+    entries: HashMap<usize, (WeakObjectRef, JsValue)>,
+}
+
+impl WeakTable {
+    pub fn get(&self, key: &ObjectRef) -> Option<JsValue> {
+        self.entries.get(&key.address()).map(|(_, value)| value.clone())
+    }
+
+    pub fn has(&self, key: &ObjectRef) -> bool {
+        self.entries.contains_key(&key.address())
+    }
+
+    pub fn set(&mut self, key: &ObjectRef, value: JsValue) {
+        self.entries.insert(key.address(), (key.downgrade(), value));
+    }
+
+    pub fn delete(&mut self, key: &ObjectRef) -> bool {
+        self.entries.remove(&key.address()).is_some()
+    }
+
+    /// Entries in the table, including those with freed keys that have not
+    /// been swept yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn trace(&self, tracer: &mut Tracer) {
+        for (key, value) in self.entries.values() {
+            key.trace_ephemeron(value, tracer);
+        }
+    }
+
+    pub fn sweep(&mut self) {
+        self.entries.retain(|_, (key, _)| key.is_alive());
+    }
+}
+
+/// A `FinalizationRegistry`'s cleanup callback and the targets registered
+/// with it. Held values and the callback are held strongly, targets and
+/// unregister tokens weakly. Sweeping moves the cells of freed targets to
+/// the cleanup job queue.
+#[derive(Clone)]
+pub struct FinalizationCells {
+    callback: ObjectRef,
+    cells: Vec<FinalizationCell>,
+}
+
+#[derive(Clone)]
+struct FinalizationCell {
+    target: WeakObjectRef,
+    held_value: JsValue,
+    token: Option<WeakObjectRef>,
+}
+
+impl FinalizationCells {
+    pub fn new(callback: ObjectRef) -> Self {
+        FinalizationCells {
+            callback,
+            cells: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, target: &ObjectRef, held_value: JsValue, token: Option<&ObjectRef>) {
+        self.cells.push(FinalizationCell {
+            target: target.downgrade(),
+            held_value,
+            token: token.map(ObjectRef::downgrade),
+        });
+    }
+
+    /// Removes the cells registered with `token`, returning whether there
+    /// were any.
+    pub fn unregister(&mut self, token: &ObjectRef) -> bool {
+        let count = self.cells.len();
+        self.cells.retain(|cell| !cell.token.as_ref().is_some_and(|cell_token| cell_token.points_to(token)));
+        self.cells.len() < count
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn trace(&self, tracer: &mut Tracer) {
+        self.callback.trace(tracer);
+        for cell in &self.cells {
+            cell.target.trace_weak(tracer);
+            cell.held_value.trace(tracer);
+        }
+    }
+
+    pub fn sweep(&mut self) {
+        let callback = &self.callback;
+        self.cells.retain(|cell| {
+            let alive = cell.target.is_alive();
+            if !alive {
+                CLEANUP_JOBS.with(|jobs| {
+                    jobs.borrow_mut().push_back(CleanupJob {
+                        callback: callback.clone(),
+                        held_value: cell.held_value.clone(),
+                    })
+                });
+            }
+            alive
+        });
+    }
+}
+
+/// A call of a registry's cleanup callback with the held value of a target
+/// that has been freed.
+pub struct CleanupJob {
+    pub callback: ObjectRef,
+    pub held_value: JsValue,
+}
+
+thread_local! {
+    static CLEANUP_JOBS: RefCell<VecDeque<CleanupJob>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Takes the oldest cleanup job queued on this thread, for the interpreter
+/// to run once the script that was running has finished.
+pub fn next_cleanup_job() -> Option<CleanupJob> {
+    CLEANUP_JOBS.with(|jobs| jobs.borrow_mut().pop_front())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{Engine, Interpreter};
+    use crate::runtime::gc;
+    use crate::runtime::object::ObjectKind;
+
+    fn table_len(interpreter: &mut Interpreter, name: &str) -> usize {
+        let value = interpreter.eval(name).unwrap();
+        let kind = &value.as_object().unwrap().borrow().kind;
+        match kind {
+            ObjectKind::WeakMap(table) | ObjectKind::WeakSet(table) => table.len(),
+            _ => panic!("{} is not a weak collection", name),
+        }
+    }
+
+    #[test]
+    fn test_entries_disappear_with_their_keys() {
+        let code = "let map = WeakMap(); let set = WeakSet(); let kept = {};
+            function add() { let key = {}; map.set(key, { key: key }); set.add(key); return map.has(key) && set.has(key); }
+            map.set(kept, 1).set(kept, 2); set.add(kept);
+            add();";
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            assert_eq!(interpreter.eval(code).unwrap().to_string(), "true");
+            assert_eq!(table_len(&mut interpreter, "map"), 2, "{:?}", engine);
+            assert_eq!(table_len(&mut interpreter, "set"), 2, "{:?}", engine);
+
+            // The entry's value refers back to its key, which counting alone
+            // cannot free.
+            assert!(gc::collect() >= 2, "{:?}", engine);
+            assert_eq!(table_len(&mut interpreter, "map"), 1, "{:?}", engine);
+            assert_eq!(table_len(&mut interpreter, "set"), 1, "{:?}", engine);
+            let result = interpreter.eval("map.get(kept) + ' ' + set.has(kept) + ' ' + map.delete(kept) + ' ' + map.has(kept);");
+            assert_eq!(result.unwrap().to_string(), "2 true true false");
+        }
+    }
+
+    #[test]
+    fn test_weak_refs_and_finalization() {
+        let code = "let log = ''; let token = {};
+            function cleanup(held) { log += held + ';'; }
+            let registry = FinalizationRegistry(cleanup);
+            function make(name, token) { let target = {}; target.self = target; registry.register(target, name, token); return WeakRef(target); }
+            let first = make('first', undefined); let second = make('second', token); make('third', undefined);
+            registry.unregister(token) + ' ' + registry.unregister(token) + ' ' + (first.deref().self === first.deref());";
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            assert_eq!(interpreter.eval(code).unwrap().to_string(), "true false true");
+            assert!(gc::collect() >= 3, "{:?}", engine);
+            // Cleanup jobs wait for the running script to finish.
+            assert_eq!(interpreter.eval("log;").unwrap().to_string(), "", "{:?}", engine);
+            assert_eq!(interpreter.eval("log;").unwrap().to_string(), "first;third;", "{:?}", engine);
+            let result = interpreter.eval("first.deref() === undefined && second.deref() === undefined;");
+            assert_eq!(result.unwrap().to_string(), "true");
+
+            // A host collecting between scripts can run them itself.
+            interpreter.eval("let last = {}; last.self = last; registry.register(last, 'last'); last = undefined;").unwrap();
+            gc::collect();
+            interpreter.run_cleanup_jobs().unwrap();
+            assert_eq!(interpreter.eval("log;").unwrap().to_string(), "first;third;last;");
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut interpreter = Interpreter::new();
+        let code = "function message(f) { try { f(); } catch (e) { return e.name + ': ' + e.message; } }
+            function mapKey() { WeakMap().set(1, 2); }
+            function setValue() { WeakSet().add('a'); }
+            function target() { WeakRef(null); }
+            function cleanup() { FinalizationRegistry({}); }
+            function holdings() { let o = {}; FinalizationRegistry(message).register(o, o); }
+            function receiver() { WeakMap.prototype.get.call(WeakSet(), {}); }
+            message(mapKey) + '\\n' + message(setValue) + '\\n' + message(target) + '\\n' + message(cleanup) + '\\n' +
+            message(holdings) + '\\n' + message(receiver) + '\\n' +
+            Object.prototype.toString.call(WeakRef({})) + ' ' + (WeakMap().constructor === WeakMap) + ' ' + WeakMap().get(1);";
+        let expected = "\
+TypeError: Invalid value used as weak map key: 1
+TypeError: Invalid value used in weak set: \"a\"
+TypeError: WeakRef: target must be an object
+TypeError: FinalizationRegistry: cleanup must be callable
+TypeError: FinalizationRegistry.prototype.register: target and holdings must not be same
+TypeError: Method WeakMap.prototype.get called on incompatible receiver {}
+[object WeakRef] true undefined";
+        assert_eq!(interpreter.eval(code).unwrap().to_string(), expected);
+    }
+}
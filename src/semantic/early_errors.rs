@@ -0,0 +1,566 @@
+use crate::lexical::span::Span;
+use crate::lexical::atom::Atom;
+use crate::parser::arena::{ArenaLiteral, AstArena, NodeId, NodeKind};
+use crate::parser::ast::VariableKind;
+use crate::parser::operators::UnaryOp;
+use crate::parser::visitor::{walk_children, Visitor};
+use crate::semantic::scope::{BindingKind, ScopeKind, ScopeTree};
+use crate::semantic::strict::StrictMode;
+use std::fmt;
+
+/// A static-semantics error the spec requires to be reported before any code
+/// runs, such as `let x; let x;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EarlyError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for EarlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for EarlyError {}
+
+/// Runs every early-error check over a parsed program and returns the
+/// errors in source order. Strict-mode-only rules apply wherever `strict`
+/// says the code is strict.
+pub fn check(arena: &AstArena, scopes: &ScopeTree, strict: &StrictMode) -> Vec<EarlyError> {
+    let mut checker = Checker {
+        arena,
+        strict,
+        loop_depth: 0,
+        function_depth: 0,
+        labels: Vec::new(),
+        errors: Vec::new(),
+    };
+    checker.check_declarations(scopes);
+    checker.visit_node(arena.root());
+
+    checker.errors.sort_by_key(|error| (error.span.start, error.span.end));
+    checker.errors
+}
+
+struct Checker<'a> {
+    arena: &'a AstArena,
+    strict: &'a StrictMode,
+    loop_depth: usize,
+    function_depth: usize,
+    /// Enclosing labels, innermost last, and whether each labels a loop.
+    labels: Vec<(Atom, bool)>,
+    errors: Vec<EarlyError>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, node: NodeId, message: impl Into<String>) {
+        self.errors.push(EarlyError {
+            message: message.into(),
+            span: self.arena.span(node),
+        });
+    }
+
+    /// How the identifier `node` was declared, read off its parent.
+    fn declaration_kind(&self, node: NodeId) -> BindingKind {
+        let parent = self.arena.parent(node).expect("declarations have a parent");
+        match *self.arena.kind(parent) {
+            NodeKind::VariableDeclarator { .. } => {
+                let declaration = self.arena.parent(parent).unwrap();
+                match *self.arena.kind(declaration) {
+                    NodeKind::VariableDeclaration { kind, .. } => kind.into(),
+                    _ => unreachable!("declarator outside a declaration"),
+                }
+            }
+            NodeKind::FunctionDeclaration { name, .. } if name == node => BindingKind::Function,
+            NodeKind::FunctionDeclaration { .. } => BindingKind::Parameter,
+            NodeKind::CatchClause { .. } => BindingKind::CatchParameter,
+            _ => unreachable!("identifier is not in a binding position"),
+        }
+    }
+
+    fn check_declarations(&mut self, scopes: &ScopeTree) {
+        for id in scopes.bindings() {
+            let binding = scopes.binding(id);
+            let scope = scopes.scope(binding.scope);
+            let name = binding.name.as_str().to_string();
+
+            let mut declarations: Vec<(NodeId, BindingKind)> = binding
+                .declarations
+                .iter()
+                .map(|&node| (node, self.declaration_kind(node)))
+                .collect();
+            declarations.sort_by_key(|(node, _)| self.arena.span(*node).start);
+
+            // In a block, function declarations behave like `let`.
+            let lexical = |kind: BindingKind| {
+                kind.is_lexical() || (kind == BindingKind::Function && scope.kind == ScopeKind::Block)
+            };
+            for i in 1..declarations.len() {
+                let (node, kind) = declarations[i];
+                let strict = self.strict.is_strict(node);
+                let conflicts = declarations[..i].iter().any(|&(_, earlier)| {
+                    let both_functions = kind == BindingKind::Function && earlier == BindingKind::Function;
+                    if both_functions && scope.kind == ScopeKind::Block {
+                        // Annex B tolerates duplicate block functions in sloppy code.
+                        strict
+                    } else {
+                        (lexical(kind) || lexical(earlier)) && !both_functions
+                    }
+                });
+                if conflicts {
+                    self.error(node, format!("Identifier '{}' has already been declared", name));
+                } else if strict && kind == BindingKind::Parameter {
+                    self.error(node, "Duplicate parameter name not allowed in this context");
+                }
+            }
+
+            // A `var` conflicts with lexical declarations in every scope it
+            // is hoisted through, not only the one it lands in.
+            if binding.kind == BindingKind::Var {
+                for &(node, kind) in &declarations {
+                    if kind != BindingKind::Var {
+                        continue;
+                    }
+                    let from = scopes.enclosing_scope(self.arena, node);
+                    let shadowed = scopes
+                        .ancestors(from)
+                        .take_while(|&scope| scope != binding.scope)
+                        .filter_map(|scope| scopes.own_binding(scope, binding.name))
+                        .any(|other| {
+                            let other = scopes.binding(other);
+                            other.kind.is_lexical() || other.kind == BindingKind::Function
+                        });
+                    if shadowed {
+                        self.error(node, format!("Identifier '{}' has already been declared", name));
+                    }
+                }
+            }
+
+            if matches!(name.as_str(), "eval" | "arguments") {
+                for &(node, _) in &declarations {
+                    if self.strict.is_strict(node) {
+                        self.error(node, "Unexpected eval or arguments in strict mode");
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_loop(&mut self, node: NodeId, body: NodeId) {
+        self.check_substatement(body, false);
+        self.loop_depth += 1;
+        walk_children(self, node);
+        self.loop_depth -= 1;
+    }
+
+    fn label_name(&self, identifier: NodeId) -> Atom {
+        match *self.arena.kind(identifier) {
+            NodeKind::Identifier(name) => name,
+            _ => unreachable!("labels and catch parameters are identifiers"),
+        }
+    }
+
+    /// Only identifiers and member expressions can be assigned to.
+    fn check_target(&mut self, target: NodeId, message: &str) {
+        match self.arena.kind(target) {
+            NodeKind::Identifier(name) => {
+                if self.strict.is_strict(target) && matches!(name.as_str(), "eval" | "arguments") {
+                    self.error(target, "Unexpected eval or arguments in strict mode");
+                }
+            }
+            NodeKind::MemberExpression { .. } => {}
+            _ => self.error(target, message),
+        }
+    }
+
+    /// The body of `if`, `while` and `for` is a single statement, which must
+    /// not be a declaration. Sloppy code may still declare a function directly
+    /// under `if`.
+    fn check_substatement(&mut self, body: NodeId, is_if: bool) {
+        // The function's own directives do not count, only the surrounding code.
+        let strict = self.strict.is_strict(self.arena.parent(body).unwrap());
+        match self.arena.kind(body) {
+            NodeKind::VariableDeclaration { kind, .. } if *kind != VariableKind::Var => {
+                self.error(body, "Lexical declaration cannot appear in a single-statement context");
+            }
+            NodeKind::FunctionDeclaration { .. } if strict => self.error(
+                body,
+                "In strict mode code, functions can only be declared at top level or inside a block.",
+            ),
+            NodeKind::FunctionDeclaration { .. } if !is_if => self.error(
+                body,
+                "In non-strict mode code, functions can only be declared at top level, inside a block, or as the body of an if statement.",
+            ),
+            _ => {}
+        }
+    }
+
+    /// `__proto__: value` sets the prototype; shorthand and computed forms
+    /// are ordinary properties.
+    fn is_proto_property(&self, property: NodeId) -> bool {
+        let NodeKind::Property {
+            key,
+            computed: false,
+            shorthand: false,
+            ..
+        } = *self.arena.kind(property)
+        else {
+            return false;
+        };
+        match *self.arena.kind(key) {
+            NodeKind::Identifier(name) => name.as_str() == "__proto__",
+            NodeKind::Literal(ArenaLiteral::String(string)) => {
+                self.arena.string(string).iter().copied().eq("__proto__".encode_utf16())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Checker<'a> {
+    fn arena(&self) -> &'a AstArena {
+        self.arena
+    }
+
+    fn visit_function_declaration(&mut self, node: NodeId) {
+        // `break` and `continue` cannot cross a function boundary.
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let labels = std::mem::take(&mut self.labels);
+        self.function_depth += 1;
+        walk_children(self, node);
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        self.labels = labels;
+    }
+
+    fn visit_if_statement(&mut self, node: NodeId) {
+        let NodeKind::IfStatement { body, else_body, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.check_substatement(body, true);
+        if let Some(else_body) = else_body {
+            self.check_substatement(else_body, true);
+        }
+        walk_children(self, node);
+    }
+
+    fn visit_while_statement(&mut self, node: NodeId) {
+        let NodeKind::WhileStatement { body, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.visit_loop(node, body);
+    }
+
+    fn visit_for_statement(&mut self, node: NodeId) {
+        let NodeKind::ForStatement { body, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.visit_loop(node, body);
+    }
+
+    fn visit_labeled_statement(&mut self, node: NodeId) {
+        let NodeKind::LabeledStatement { label, body } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        let name = self.label_name(label);
+        if self.labels.iter().any(|&(other, _)| other == name) {
+            let message = format!("Label '{}' has already been declared", name.as_str());
+            self.error(label, message);
+        }
+        self.check_substatement(body, true);
+
+        // `a: b: while (x) continue a;` is fine: both labels name the loop.
+        let mut target = body;
+        while let NodeKind::LabeledStatement { body, .. } = *self.arena.kind(target) {
+            target = body;
+        }
+        let is_loop = matches!(
+            self.arena.kind(target),
+            NodeKind::WhileStatement { .. } | NodeKind::ForStatement { .. }
+        );
+        self.labels.push((name, is_loop));
+        self.visit_node(body);
+        self.labels.pop();
+    }
+
+    fn visit_break_statement(&mut self, node: NodeId) {
+        let NodeKind::BreakStatement { label } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        match label {
+            Some(label) => {
+                let name = self.label_name(label);
+                if !self.labels.iter().any(|&(other, _)| other == name) {
+                    self.error(node, format!("Undefined label '{}'", name.as_str()));
+                }
+            }
+            None if self.loop_depth == 0 => self.error(node, "Illegal break statement"),
+            None => {}
+        }
+    }
+
+    fn visit_continue_statement(&mut self, node: NodeId) {
+        let NodeKind::ContinueStatement { label } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        match label {
+            Some(label) => {
+                let name = self.label_name(label);
+                match self.labels.iter().rev().find(|&&(other, _)| other == name) {
+                    None => self.error(node, format!("Undefined label '{}'", name.as_str())),
+                    Some(&(_, false)) => self.error(
+                        node,
+                        format!(
+                            "Illegal continue statement: '{}' does not denote an iteration statement",
+                            name.as_str()
+                        ),
+                    ),
+                    Some(_) => {}
+                }
+            }
+            None if self.loop_depth == 0 => {
+                self.error(node, "Illegal continue statement: no surrounding iteration statement")
+            }
+            None => {}
+        }
+    }
+
+    fn visit_catch_clause(&mut self, node: NodeId) {
+        let NodeKind::CatchClause { parameter, body } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        if let Some(parameter) = parameter {
+            // The body block may not redeclare the parameter lexically.
+            let name = self.label_name(parameter);
+            let NodeKind::Block { body: statements } = *self.arena.kind(body) else {
+                unreachable!("catch body is a block")
+            };
+            for &statement in self.arena.list(statements) {
+                let declared: Vec<NodeId> = match *self.arena.kind(statement) {
+                    NodeKind::VariableDeclaration { kind, declarations } if kind != VariableKind::Var => self
+                        .arena
+                        .list(declarations)
+                        .iter()
+                        .filter_map(|&declarator| match *self.arena.kind(declarator) {
+                            NodeKind::VariableDeclarator { identifier, .. } => Some(identifier),
+                            _ => None,
+                        })
+                        .collect(),
+                    NodeKind::FunctionDeclaration { name, .. } => vec![name],
+                    _ => vec![],
+                };
+                for identifier in declared {
+                    if self.arena.kind(identifier) == &NodeKind::Identifier(name) {
+                        let message = format!("Identifier '{}' has already been declared", name.as_str());
+                        self.error(identifier, message);
+                    }
+                }
+            }
+        }
+        walk_children(self, node);
+    }
+
+    fn visit_return_statement(&mut self, node: NodeId) {
+        if self.function_depth == 0 {
+            self.error(node, "Illegal return statement");
+        }
+        walk_children(self, node);
+    }
+
+    fn visit_assignment_expression(&mut self, node: NodeId) {
+        let NodeKind::AssignmentExpression { left, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.check_target(left, "Invalid left-hand side in assignment");
+        walk_children(self, node);
+    }
+
+    fn visit_update_expression(&mut self, node: NodeId) {
+        let NodeKind::UpdateExpression { prefix, argument, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        let message = if prefix {
+            "Invalid left-hand side expression in prefix operation"
+        } else {
+            "Invalid left-hand side expression in postfix operation"
+        };
+        self.check_target(argument, message);
+        walk_children(self, node);
+    }
+
+    fn visit_unary_expression(&mut self, node: NodeId) {
+        let NodeKind::UnaryExpression { operator, argument } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        if operator == UnaryOp::Delete
+            && self.strict.is_strict(node)
+            && matches!(self.arena.kind(argument), NodeKind::Identifier(_))
+        {
+            self.error(node, "Delete of an unqualified identifier in strict mode.");
+        }
+        walk_children(self, node);
+    }
+
+    fn visit_object_expression(&mut self, node: NodeId) {
+        let NodeKind::ObjectExpression { properties } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        let protos: Vec<NodeId> = self
+            .arena
+            .list(properties)
+            .iter()
+            .copied()
+            .filter(|&property| self.is_proto_property(property))
+            .collect();
+        for &duplicate in protos.iter().skip(1) {
+            self.error(duplicate, "Duplicate __proto__ fields are not allowed in object literals");
+        }
+        walk_children(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn errors_with(code: &str, module: bool) -> Vec<(String, String)> {
+        let arena = AstArena::parse(&Lexer::new(code).tokenize());
+        let scopes = ScopeTree::analyze(&arena);
+        check(&arena, &scopes, &StrictMode::analyze(&arena, module))
+            .into_iter()
+            .map(|error| (error.message, code[error.span.start..error.span.end].to_string()))
+            .collect()
+    }
+
+    fn errors(code: &str) -> Vec<(String, String)> {
+        errors_with(code, false)
+    }
+
+    fn messages(code: &str) -> Vec<String> {
+        errors(code).into_iter().map(|(message, _)| message).collect()
+    }
+
+    #[test]
+    fn test_valid_program() {
+        let code = "var a; var a; function f(a) { var a; function g() {} function g() {} } \
+                    for (let i = 0; i < 2; i++) { if (i) break; else continue; } o.x = 1; ({ __proto__: a, ['__proto__']: 2 });";
+        assert_eq!(errors(code), vec![]);
+    }
+
+    #[test]
+    fn test_duplicate_lexical_declarations() {
+        assert_eq!(
+            errors("let x; let x;"),
+            vec![("Identifier 'x' has already been declared".to_string(), "x".to_string())]
+        );
+        assert_eq!(errors("const a = 1; { let a; } function a() {}").len(), 1);
+        assert_eq!(errors("function f(a) { let a; }").len(), 1);
+        assert_eq!(errors("{ function g() {} let g; }").len(), 1);
+    }
+
+    #[test]
+    fn test_let_redeclaring_var() {
+        assert_eq!(errors("var x; let x;").len(), 1);
+        assert_eq!(errors("let x; { var x; }").len(), 1);
+        assert_eq!(errors("{ let x; { var x; } }").len(), 1);
+        assert_eq!(errors("for (let i;;) { var i; }").len(), 1);
+        assert_eq!(errors("{ let x; } var x;"), vec![]);
+        assert_eq!(errors("try {} catch (e) { let e; }").len(), 1);
+        assert_eq!(errors("try {} catch (e) { var e; { let e; } }"), vec![]);
+    }
+
+    #[test]
+    fn test_jump_statements() {
+        assert_eq!(
+            errors("break; while (a) { function f() { continue; } }"),
+            vec![
+                ("Illegal break statement".to_string(), "break;".to_string()),
+                (
+                    "Illegal continue statement: no surrounding iteration statement".to_string(),
+                    "continue;".to_string()
+                ),
+            ]
+        );
+        assert_eq!(messages("return 1;"), vec!["Illegal return statement"]);
+        assert_eq!(
+            messages("a: { break a; } b: { continue b; } c: d: for (;;) { continue c; break e; } f: function g() { break f; } h: h: x;"),
+            vec![
+                "Illegal continue statement: 'b' does not denote an iteration statement",
+                "Undefined label 'e'",
+                "Undefined label 'f'",
+                "Label 'h' has already been declared",
+            ]
+        );
+        assert_eq!(messages("a: while (x) { try { break a; } finally { continue a; } }"), Vec::<String>::new());
+        assert_eq!(messages("function f() { return 1; }"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_invalid_assignment_targets() {
+        assert_eq!(
+            errors("a + 1 = 2; f() += 1; ++1; (a.b)++; x = 1; this = 1;"),
+            vec![
+                ("Invalid left-hand side in assignment".to_string(), "a + 1".to_string()),
+                ("Invalid left-hand side in assignment".to_string(), "f()".to_string()),
+                (
+                    "Invalid left-hand side expression in prefix operation".to_string(),
+                    "1".to_string()
+                ),
+                ("Invalid left-hand side in assignment".to_string(), "this".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_rules() {
+        let code = "delete x; delete o.x; eval = 1; var arguments; function f(a, a) {} { function g() {} function g() {} }";
+        assert_eq!(messages(code), Vec::<String>::new());
+        assert_eq!(
+            errors_with(code, true),
+            vec![
+                ("Delete of an unqualified identifier in strict mode.".to_string(), "delete x".to_string()),
+                ("Unexpected eval or arguments in strict mode".to_string(), "eval".to_string()),
+                ("Unexpected eval or arguments in strict mode".to_string(), "arguments".to_string()),
+                ("Duplicate parameter name not allowed in this context".to_string(), "a".to_string()),
+                ("Identifier 'g' has already been declared".to_string(), "g".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_proto() {
+        assert_eq!(
+            errors("x = { __proto__: a, b, '__proto__': c };"),
+            vec![(
+                "Duplicate __proto__ fields are not allowed in object literals".to_string(),
+                "'__proto__': c".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_single_statement_context() {
+        assert_eq!(
+            messages("if (a) let x = 1; while (a) function f() {} if (a) function g() {}"),
+            vec![
+                "Lexical declaration cannot appear in a single-statement context",
+                "In non-strict mode code, functions can only be declared at top level, inside a block, or as the body of an if statement.",
+            ]
+        );
+        assert_eq!(errors_with("if (a) function g() {}", true).len(), 1);
+    }
+
+    #[test]
+    fn test_strictness_follows_directives() {
+        let code = "delete a; function f(x, x) { 'use strict'; delete b; } function g(y, y) { delete c; }";
+        assert_eq!(
+            errors(code),
+            vec![
+                ("Duplicate parameter name not allowed in this context".to_string(), "x".to_string()),
+                ("Delete of an unqualified identifier in strict mode.".to_string(), "delete b".to_string()),
+            ]
+        );
+    }
+}
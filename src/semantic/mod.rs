@@ -0,0 +1,3 @@
+pub mod early_errors;
+pub mod scope;
+pub mod strict;
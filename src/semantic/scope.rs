@@ -0,0 +1,698 @@
+use crate::lexical::atom::Atom;
+use crate::parser::arena::{AstArena, NodeId, NodeKind, SideTable};
+use crate::parser::ast::VariableKind;
+use crate::parser::visitor::{walk_children, Visitor};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(u32);
+
+impl ScopeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BindingId(u32);
+
+impl BindingId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Global,
+    /// Top level of a module; sits under the global scope so that its
+    /// declarations do not leak into it.
+    Module,
+    Function,
+    Block,
+    Catch,
+    Class,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Var,
+    Let,
+    Const,
+    Function,
+    Parameter,
+    CatchParameter,
+    Class,
+}
+
+impl BindingKind {
+    /// `let`, `const` and `class` bindings are block scoped and live in the
+    /// temporal dead zone until their declaration runs.
+    pub fn is_lexical(self) -> bool {
+        matches!(self, BindingKind::Let | BindingKind::Const | BindingKind::Class)
+    }
+}
+
+impl From<VariableKind> for BindingKind {
+    fn from(kind: VariableKind) -> Self {
+        match kind {
+            VariableKind::Var => BindingKind::Var,
+            VariableKind::Let => BindingKind::Let,
+            VariableKind::Const => BindingKind::Const,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    /// The node that introduced the scope: the program, a function or a block.
+    pub node: NodeId,
+    pub children: Vec<ScopeId>,
+    bindings: HashMap<Atom, BindingId>,
+}
+
+impl Scope {
+    pub fn bindings(&self) -> impl Iterator<Item = BindingId> + '_ {
+        self.bindings.values().copied()
+    }
+
+    /// Scopes that `var` declarations hoist to.
+    pub fn is_var_scope(&self) -> bool {
+        matches!(self.kind, ScopeKind::Global | ScopeKind::Module | ScopeKind::Function)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: Atom,
+    pub kind: BindingKind,
+    pub scope: ScopeId,
+    /// Every identifier that declares this binding, in source order. Only
+    /// `var` and function bindings can legally have more than one.
+    pub declarations: Vec<NodeId>,
+    pub references: Vec<NodeId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Binding(BindingId),
+    /// No enclosing declaration; looked up on the global object at runtime.
+    UnresolvedGlobal,
+}
+
+/// Result of scope analysis over an [`AstArena`]: the scope tree, every
+/// binding, and a resolution for each `Identifier` node.
+#[derive(Debug, Clone)]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    bindings: Vec<Binding>,
+    resolutions: SideTable<Resolution>,
+    node_scopes: SideTable<ScopeId>,
+    unresolved: Vec<NodeId>,
+}
+
+impl ScopeTree {
+    /// Analyses the arena's root as a classic script.
+    pub fn analyze(arena: &AstArena) -> Self {
+        Resolver::new(arena).run(false)
+    }
+
+    /// Analyses the arena's root as a module: top-level declarations go into
+    /// a module scope beneath the global one.
+    pub fn analyze_module(arena: &AstArena) -> Self {
+        Resolver::new(arena).run(true)
+    }
+
+    pub fn root(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.index()]
+    }
+
+    pub fn scopes(&self) -> impl Iterator<Item = ScopeId> {
+        (0..self.scopes.len() as u32).map(ScopeId)
+    }
+
+    pub fn binding(&self, id: BindingId) -> &Binding {
+        &self.bindings[id.index()]
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = BindingId> {
+        (0..self.bindings.len() as u32).map(BindingId)
+    }
+
+    /// How an `Identifier` node resolves, for both declarations and references.
+    pub fn resolution(&self, identifier: NodeId) -> Option<Resolution> {
+        self.resolutions.get(identifier).copied()
+    }
+
+    pub fn binding_of(&self, identifier: NodeId) -> Option<BindingId> {
+        match self.resolution(identifier)? {
+            Resolution::Binding(binding) => Some(binding),
+            Resolution::UnresolvedGlobal => None,
+        }
+    }
+
+    /// The scope introduced by `node`, if it introduces one.
+    pub fn scope_of(&self, node: NodeId) -> Option<ScopeId> {
+        self.node_scopes.get(node).copied()
+    }
+
+    /// Innermost scope enclosing `node`. A function's name belongs to the
+    /// scope around the function, not the function's own scope.
+    pub fn enclosing_scope(&self, arena: &AstArena, node: NodeId) -> ScopeId {
+        let start = match arena.parent(node) {
+            Some(parent) if matches!(arena.kind(parent), NodeKind::FunctionDeclaration { name, .. } if *name == node) => {
+                parent
+            }
+            _ => node,
+        };
+        arena
+            .ancestors(start)
+            .find_map(|ancestor| self.scope_of(ancestor))
+            .unwrap_or(self.root())
+    }
+
+    /// `scope` and its ancestors, innermost first.
+    pub fn ancestors(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(Some(scope), move |&current| self.scope(current).parent)
+    }
+
+    /// Binding declared directly in `scope`.
+    pub fn own_binding(&self, scope: ScopeId, name: Atom) -> Option<BindingId> {
+        self.scope(scope).bindings.get(&name).copied()
+    }
+
+    /// Resolves `name` from `scope` outwards.
+    pub fn lookup(&self, scope: ScopeId, name: Atom) -> Option<BindingId> {
+        self.ancestors(scope).find_map(|scope| self.own_binding(scope, name))
+    }
+
+    /// Identifier references with no declaration, in source order.
+    pub fn unresolved(&self) -> &[NodeId] {
+        &self.unresolved
+    }
+}
+
+struct Resolver<'a> {
+    arena: &'a AstArena,
+    tree: ScopeTree,
+    current: ScopeId,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(arena: &'a AstArena) -> Self {
+        Resolver {
+            arena,
+            tree: ScopeTree {
+                scopes: Vec::new(),
+                bindings: Vec::new(),
+                resolutions: SideTable::for_arena(arena),
+                node_scopes: SideTable::for_arena(arena),
+                unresolved: Vec::new(),
+            },
+            current: ScopeId(0),
+        }
+    }
+
+    fn run(mut self, module: bool) -> ScopeTree {
+        let root = self.arena.root();
+        let NodeKind::Program { body } = *self.arena.kind(root) else {
+            panic!("Scope analysis expects a Program root");
+        };
+
+        self.push_scope(ScopeKind::Global, root);
+        if module {
+            self.push_scope(ScopeKind::Module, root);
+        }
+
+        let statements = self.arena.list(body);
+        self.declare_hoisted(statements);
+        self.visit_statements(statements);
+        self.tree
+    }
+
+    fn push_scope(&mut self, kind: ScopeKind, node: NodeId) -> ScopeId {
+        let id = ScopeId(self.tree.scopes.len() as u32);
+        let parent = (!self.tree.scopes.is_empty()).then_some(self.current);
+        self.tree.scopes.push(Scope {
+            kind,
+            parent,
+            node,
+            children: Vec::new(),
+            bindings: HashMap::new(),
+        });
+        if let Some(parent) = parent {
+            self.tree.scopes[parent.index()].children.push(id);
+        }
+        // The program node maps to its innermost top-level scope.
+        self.tree.node_scopes.insert(node, id);
+        self.current = id;
+        id
+    }
+
+    fn pop_scope(&mut self) {
+        self.current = self.tree.scope(self.current).parent.expect("cannot pop the global scope");
+    }
+
+    fn var_scope(&self) -> ScopeId {
+        self.tree
+            .ancestors(self.current)
+            .find(|&scope| self.tree.scope(scope).is_var_scope())
+            .unwrap()
+    }
+
+    fn declare(&mut self, scope: ScopeId, identifier: NodeId, kind: BindingKind) -> BindingId {
+        let NodeKind::Identifier(name) = *self.arena.kind(identifier) else {
+            panic!("Expected identifier in binding position");
+        };
+
+        let binding = match self.tree.own_binding(scope, name) {
+            Some(existing) => existing,
+            None => {
+                let id = BindingId(self.tree.bindings.len() as u32);
+                self.tree.bindings.push(Binding {
+                    name,
+                    kind,
+                    scope,
+                    declarations: Vec::new(),
+                    references: Vec::new(),
+                });
+                self.tree.scopes[scope.index()].bindings.insert(name, id);
+                id
+            }
+        };
+
+        self.tree.bindings[binding.index()].declarations.push(identifier);
+        self.tree.resolutions.insert(identifier, Resolution::Binding(binding));
+        binding
+    }
+
+    /// Declares everything visible from the start of a statement list: the
+    /// list's own `let`/`const`/function declarations, plus, at function or
+    /// program level, every `var` in nested blocks.
+    fn declare_hoisted(&mut self, statements: &[NodeId]) {
+        if self.tree.scope(self.current).is_var_scope() {
+            let scope = self.current;
+            for &statement in statements {
+                self.hoist_vars(statement, scope);
+            }
+        }
+
+        for &statement in statements {
+            let mut statement = statement;
+            while let NodeKind::LabeledStatement { body, .. } = *self.arena.kind(statement) {
+                statement = body;
+            }
+            match *self.arena.kind(statement) {
+                NodeKind::VariableDeclaration { kind, declarations } if kind != VariableKind::Var => {
+                    for &declarator in self.arena.list(declarations) {
+                        if let NodeKind::VariableDeclarator { identifier, .. } = *self.arena.kind(declarator) {
+                            self.declare(self.current, identifier, kind.into());
+                        }
+                    }
+                }
+                // Function declarations are block scoped, as in strict code.
+                NodeKind::FunctionDeclaration { name, .. } => {
+                    self.declare(self.current, name, BindingKind::Function);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn hoist_vars(&mut self, node: NodeId, scope: ScopeId) {
+        match *self.arena.kind(node) {
+            NodeKind::VariableDeclaration {
+                kind: VariableKind::Var,
+                declarations,
+            } => {
+                for &declarator in self.arena.list(declarations) {
+                    if let NodeKind::VariableDeclarator { identifier, .. } = *self.arena.kind(declarator) {
+                        self.declare(scope, identifier, BindingKind::Var);
+                    }
+                }
+            }
+            NodeKind::Block { body } => {
+                for &statement in self.arena.list(body) {
+                    self.hoist_vars(statement, scope);
+                }
+            }
+            NodeKind::IfStatement { body, else_body, .. } => {
+                self.hoist_vars(body, scope);
+                if let Some(else_body) = else_body {
+                    self.hoist_vars(else_body, scope);
+                }
+            }
+            NodeKind::WhileStatement { body, .. } | NodeKind::LabeledStatement { body, .. } => self.hoist_vars(body, scope),
+            NodeKind::TryStatement {
+                block,
+                handler,
+                finalizer,
+            } => {
+                self.hoist_vars(block, scope);
+                if let Some(handler) = handler {
+                    self.hoist_vars(handler, scope);
+                }
+                if let Some(finalizer) = finalizer {
+                    self.hoist_vars(finalizer, scope);
+                }
+            }
+            NodeKind::CatchClause { body, .. } => self.hoist_vars(body, scope),
+            NodeKind::ForStatement { init, body, .. } => {
+                if let Some(init) = init {
+                    self.hoist_vars(init, scope);
+                }
+                self.hoist_vars(body, scope);
+            }
+            // Nested functions hoist into their own scope; other statements
+            // cannot contain declarations.
+            _ => {}
+        }
+    }
+
+    fn visit_statements(&mut self, statements: &[NodeId]) {
+        for &statement in statements {
+            self.visit_node(statement);
+        }
+    }
+
+    fn reference(&mut self, identifier: NodeId, name: Atom) {
+        let resolution = match self.tree.lookup(self.current, name) {
+            Some(binding) => {
+                self.tree.bindings[binding.index()].references.push(identifier);
+                Resolution::Binding(binding)
+            }
+            None => {
+                self.tree.unresolved.push(identifier);
+                Resolution::UnresolvedGlobal
+            }
+        };
+        self.tree.resolutions.insert(identifier, resolution);
+    }
+}
+
+impl<'a> Visitor<'a> for Resolver<'a> {
+    fn arena(&self) -> &'a AstArena {
+        self.arena
+    }
+
+    fn visit_block(&mut self, node: NodeId) {
+        let NodeKind::Block { body } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.push_scope(ScopeKind::Block, node);
+        let statements = self.arena.list(body);
+        self.declare_hoisted(statements);
+        self.visit_statements(statements);
+        self.pop_scope();
+    }
+
+    fn visit_function_declaration(&mut self, node: NodeId) {
+        let NodeKind::FunctionDeclaration { parameters, body, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        // The name was declared by the enclosing statement list.
+        self.push_scope(ScopeKind::Function, node);
+        for &parameter in self.arena.list(parameters) {
+            self.declare(self.current, parameter, BindingKind::Parameter);
+        }
+
+        // The body block shares the function scope.
+        let NodeKind::Block { body: statements } = *self.arena.kind(body) else {
+            panic!("Function body must be a block");
+        };
+        let statements = self.arena.list(statements);
+        self.declare_hoisted(statements);
+        self.visit_statements(statements);
+        self.pop_scope();
+    }
+
+    fn visit_variable_declarator(&mut self, node: NodeId) {
+        let NodeKind::VariableDeclarator { identifier, value } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        if self.tree.resolution(identifier).is_none() {
+            // Only reachable if `hoist_vars` misses a statement kind;
+            // declare in place so no declaration is left unresolved.
+            let scope = self.var_scope();
+            self.declare(scope, identifier, BindingKind::Var);
+        }
+        if let Some(value) = value {
+            self.visit_node(value);
+        }
+    }
+
+    fn visit_for_statement(&mut self, node: NodeId) {
+        let NodeKind::ForStatement { init, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        match init {
+            // `for (let ...)` bindings live in a scope around the loop.
+            Some(init)
+                if matches!(
+                    self.arena.kind(init),
+                    NodeKind::VariableDeclaration { kind, .. } if *kind != VariableKind::Var
+                ) =>
+            {
+                self.push_scope(ScopeKind::Block, node);
+                self.declare_hoisted(&[init]);
+                walk_children(self, node);
+                self.pop_scope();
+            }
+            _ => walk_children(self, node),
+        }
+    }
+
+    fn visit_catch_clause(&mut self, node: NodeId) {
+        let NodeKind::CatchClause { parameter, body } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        // The parameter gets its own scope around the body block.
+        self.push_scope(ScopeKind::Catch, node);
+        if let Some(parameter) = parameter {
+            self.declare(self.current, parameter, BindingKind::CatchParameter);
+        }
+        self.visit_node(body);
+        self.pop_scope();
+    }
+
+    // Labels live in their own namespace.
+    fn visit_labeled_statement(&mut self, node: NodeId) {
+        let NodeKind::LabeledStatement { body, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.visit_node(body);
+    }
+
+    fn visit_break_statement(&mut self, _node: NodeId) {}
+
+    fn visit_continue_statement(&mut self, _node: NodeId) {}
+
+    // Property names are not references.
+    fn visit_member_expression(&mut self, node: NodeId) {
+        let NodeKind::MemberExpression {
+            object,
+            property,
+            computed,
+        } = *self.arena.kind(node)
+        else {
+            unreachable!()
+        };
+        self.visit_node(object);
+        if computed {
+            self.visit_node(property);
+        }
+    }
+
+    fn visit_property(&mut self, node: NodeId) {
+        let NodeKind::Property {
+            key, value, computed, ..
+        } = *self.arena.kind(node)
+        else {
+            unreachable!()
+        };
+        if computed {
+            self.visit_node(key);
+        }
+        self.visit_node(value);
+    }
+
+    fn visit_identifier(&mut self, node: NodeId) {
+        let NodeKind::Identifier(name) = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.reference(node, name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn analyze(code: &str) -> (AstArena, ScopeTree) {
+        let arena = AstArena::parse(&Lexer::new(code).tokenize());
+        let scopes = ScopeTree::analyze(&arena);
+        (arena, scopes)
+    }
+
+    /// Identifier nodes named `name`, in source order.
+    fn identifiers(arena: &AstArena, name: &str) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = arena.ids().filter(|&id| arena.identifier_name(id) == Some(name)).collect();
+        ids.sort_by_key(|&id| arena.span(id).start);
+        ids
+    }
+
+    fn scope_kind_of(scopes: &ScopeTree, binding: BindingId) -> ScopeKind {
+        scopes.scope(scopes.binding(binding).scope).kind
+    }
+
+    #[test]
+    fn test_var_hoists_out_of_blocks() {
+        let (arena, scopes) = analyze("x; { if (a) { var x = 1; } } x;");
+        let xs = identifiers(&arena, "x");
+
+        let binding = scopes.binding_of(xs[0]).expect("x resolves before its declaration");
+        assert!(xs.iter().all(|&x| scopes.binding_of(x) == Some(binding)));
+        assert_eq!(scope_kind_of(&scopes, binding), ScopeKind::Global);
+        assert_eq!(scopes.binding(binding).kind, BindingKind::Var);
+        assert_eq!(scopes.binding(binding).references, vec![xs[0], xs[2]]);
+    }
+
+    #[test]
+    fn test_let_is_block_scoped() {
+        let (arena, scopes) = analyze("let x = 1; { x; let x = 2; x; } x;");
+        let xs = identifiers(&arena, "x");
+
+        let outer = scopes.binding_of(xs[0]).unwrap();
+        let inner = scopes.binding_of(xs[2]).unwrap();
+        assert_ne!(outer, inner);
+        // The reference before the inner `let` is in its temporal dead zone,
+        // but still resolves to it rather than the outer binding.
+        assert_eq!(scopes.binding_of(xs[1]), Some(inner));
+        assert_eq!(scopes.binding_of(xs[3]), Some(inner));
+        assert_eq!(scopes.binding_of(xs[4]), Some(outer));
+        assert_eq!(scope_kind_of(&scopes, inner), ScopeKind::Block);
+        assert!(scopes.binding(inner).kind.is_lexical());
+    }
+
+    #[test]
+    fn test_function_hoisting_and_parameters() {
+        let (arena, scopes) = analyze("f(1); function f(a) { var b = a; function g() { b = a + c; } }");
+
+        let f = identifiers(&arena, "f");
+        let binding = scopes.binding_of(f[0]).unwrap();
+        assert_eq!(scopes.binding(binding).kind, BindingKind::Function);
+        assert_eq!(scopes.binding(binding).declarations, vec![f[1]]);
+
+        let a = identifiers(&arena, "a");
+        let parameter = scopes.binding_of(a[0]).unwrap();
+        assert_eq!(scopes.binding(parameter).kind, BindingKind::Parameter);
+        assert!(a.iter().all(|&a| scopes.binding_of(a) == Some(parameter)));
+        assert_eq!(scope_kind_of(&scopes, parameter), ScopeKind::Function);
+
+        let b = identifiers(&arena, "b");
+        assert_eq!(scopes.binding_of(b[1]), scopes.binding_of(b[0]));
+
+        let c = identifiers(&arena, "c");
+        assert_eq!(scopes.resolution(c[0]), Some(Resolution::UnresolvedGlobal));
+        assert_eq!(scopes.unresolved(), &[c[0]]);
+    }
+
+    #[test]
+    fn test_catch_parameter_and_labels() {
+        let (arena, scopes) = analyze("e: try { throw e; } catch (e) { var v = e; break e; } v;");
+        let e = identifiers(&arena, "e");
+
+        let parameter = scopes.binding_of(e[2]).unwrap();
+        assert_eq!(scopes.binding(parameter).kind, BindingKind::CatchParameter);
+        assert_eq!(scope_kind_of(&scopes, parameter), ScopeKind::Catch);
+        assert_eq!(scopes.binding(parameter).references, vec![e[3]]);
+        // Labels are not references, and the thrown `e` is a global.
+        assert_eq!(scopes.resolution(e[0]), None);
+        assert_eq!(scopes.resolution(e[4]), None);
+        assert_eq!(scopes.unresolved(), &[e[1]]);
+
+        let v = identifiers(&arena, "v");
+        let var = scopes.binding_of(v[0]).unwrap();
+        assert_eq!(scope_kind_of(&scopes, var), ScopeKind::Global);
+        assert_eq!(scopes.binding_of(v[1]), Some(var));
+    }
+
+    #[test]
+    fn test_scope_tree_shape() {
+        let (arena, scopes) = analyze("function f(a) { { let b; } } { }");
+        let root = scopes.root();
+        assert_eq!(scopes.scope(root).kind, ScopeKind::Global);
+        assert_eq!(scopes.scope_of(arena.root()), Some(root));
+
+        let kinds: Vec<ScopeKind> = scopes.scopes().map(|id| scopes.scope(id).kind).collect();
+        assert_eq!(
+            kinds,
+            vec![ScopeKind::Global, ScopeKind::Function, ScopeKind::Block, ScopeKind::Block]
+        );
+        assert_eq!(scopes.scope(root).children.len(), 2);
+
+        let b = identifiers(&arena, "b")[0];
+        let block = scopes.enclosing_scope(&arena, b);
+        assert_eq!(scopes.scope(block).kind, ScopeKind::Block);
+        assert_eq!(scopes.ancestors(block).count(), 3);
+
+        let f = identifiers(&arena, "f")[0];
+        assert_eq!(scopes.enclosing_scope(&arena, f), root);
+
+        let name = Atom::lookup("a").unwrap();
+        assert!(scopes.lookup(block, name).is_some());
+        assert!(scopes.lookup(root, name).is_none());
+    }
+
+    #[test]
+    fn test_module_scope() {
+        let arena = AstArena::parse(&Lexer::new("var x; let y; z;").tokenize());
+        let scopes = ScopeTree::analyze_module(&arena);
+
+        let module = scopes.scope_of(arena.root()).unwrap();
+        assert_eq!(scopes.scope(module).kind, ScopeKind::Module);
+        assert_eq!(scopes.scope(module).parent, Some(scopes.root()));
+        assert_eq!(scopes.scope(scopes.root()).bindings().count(), 0);
+        assert_eq!(scopes.scope(module).bindings().count(), 2);
+        assert_eq!(scopes.unresolved().len(), 1);
+    }
+
+    #[test]
+    fn test_loops_and_property_names() {
+        let (arena, scopes) = analyze("for (let i = 0; i < n; i++) { var v = o.i; } for (var j; ; ) ({ i: j, v });");
+
+        let i = identifiers(&arena, "i");
+        let binding = scopes.binding_of(i[0]).unwrap();
+        assert_eq!(scope_kind_of(&scopes, binding), ScopeKind::Block);
+        assert_eq!(scopes.binding(binding).references, vec![i[1], i[2]]);
+        // `o.i` and the `i:` key are property names, not references.
+        assert_eq!(scopes.resolution(i[3]), None);
+        assert_eq!(scopes.resolution(i[4]), None);
+
+        // The shorthand `{ v }` has a key and a value node at the same span.
+        let v = identifiers(&arena, "v");
+        assert_eq!(scopes.resolution(v[1]), None);
+        assert_eq!(scopes.binding_of(v[2]), scopes.binding_of(v[0]));
+        assert_eq!(scope_kind_of(&scopes, scopes.binding_of(v[0]).unwrap()), ScopeKind::Global);
+
+        let j = identifiers(&arena, "j");
+        assert_eq!(scopes.binding_of(j[1]), scopes.binding_of(j[0]));
+    }
+
+    #[test]
+    fn test_redeclared_var_shares_binding() {
+        let (arena, scopes) = analyze("var a = 1; var a; a;");
+        let a = identifiers(&arena, "a");
+        let binding = scopes.binding_of(a[2]).unwrap();
+
+        assert_eq!(scopes.binding(binding).declarations, vec![a[0], a[1]]);
+        assert_eq!(scopes.bindings().count(), 1);
+    }
+}
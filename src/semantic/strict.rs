@@ -0,0 +1,206 @@
+use crate::parser::arena::{ArenaLiteral, AstArena, NodeId, NodeKind, NodeList, SideTable};
+use crate::parser::ast::{ASTNode, Literal};
+use crate::parser::visitor::{walk_children, walk_node, Visitor};
+
+/// Strictness of every node in an arena. Code is strict when it is inside a
+/// module, or inside a program or function whose directive prologue contains
+/// `"use strict"`; nested functions inherit it from their parents.
+///
+/// The parser enforces the syntactic strict-mode rules as it goes; this table
+/// lets later passes ask about any node after the fact. The compiler copies it
+/// onto each function template, so that the VM rejects implicit globals.
+#[derive(Debug, Clone)]
+pub struct StrictMode {
+    strict: SideTable<bool>,
+}
+
+impl StrictMode {
+    /// `module` makes the whole program strict, as module code always is.
+    pub fn analyze(arena: &AstArena, module: bool) -> Self {
+        let mut marker = Marker {
+            arena,
+            strict: SideTable::for_arena(arena),
+            current: module,
+        };
+        marker.visit_node(arena.root());
+        StrictMode { strict: marker.strict }
+    }
+
+    /// Whether `node` is strict mode code. A function's name and parameters
+    /// follow the strictness of its body.
+    pub fn is_strict(&self, node: NodeId) -> bool {
+        self.strict.get(node).copied().unwrap_or(false)
+    }
+}
+
+/// Marks every node with the strictness of the code around it; programs and
+/// functions switch it on for their subtree when their prologue asks.
+struct Marker<'a> {
+    arena: &'a AstArena,
+    strict: SideTable<bool>,
+    current: bool,
+}
+
+impl Marker<'_> {
+    fn enter(&mut self, node: NodeId, body: NodeList) {
+        let outer = self.current;
+        self.current = outer || has_use_strict(self.arena, self.arena.list(body));
+        self.strict.insert(node, self.current);
+        walk_children(self, node);
+        self.current = outer;
+    }
+}
+
+impl<'a> Visitor<'a> for Marker<'a> {
+    fn arena(&self) -> &'a AstArena {
+        self.arena
+    }
+
+    fn visit_node(&mut self, node: NodeId) {
+        self.strict.insert(node, self.current);
+        walk_node(self, node);
+    }
+
+    fn visit_program(&mut self, node: NodeId) {
+        let NodeKind::Program { body } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        self.enter(node, body);
+    }
+
+    fn visit_function_declaration(&mut self, node: NodeId) {
+        let NodeKind::FunctionDeclaration { body, .. } = *self.arena.kind(node) else {
+            unreachable!()
+        };
+        match *self.arena.kind(body) {
+            NodeKind::Block { body } => self.enter(node, body),
+            _ => walk_children(self, node),
+        }
+    }
+}
+
+/// The directive prologue of a program or function body: the leading
+/// statements that consist of nothing but a string literal.
+pub fn directives(arena: &AstArena, statements: &[NodeId]) -> Vec<NodeId> {
+    statements
+        .iter()
+        .copied()
+        .take_while(|&statement| directive_literal(arena, statement).is_some())
+        .collect()
+}
+
+/// Whether the prologue of `statements` contains a `"use strict"` directive.
+pub fn has_use_strict(arena: &AstArena, statements: &[NodeId]) -> bool {
+    directives(arena, statements).into_iter().any(|statement| {
+        let literal = directive_literal(arena, statement).unwrap();
+        let NodeKind::Literal(ArenaLiteral::String(value)) = *arena.kind(literal) else {
+            unreachable!()
+        };
+        // Escapes or line continuations would make the literal longer than
+        // the 12 characters of `"use strict"`, and disqualify it.
+        arena.string(value).iter().copied().eq("use strict".encode_utf16()) && arena.span(literal).len() == 12
+    })
+}
+
+/// [`has_use_strict`] for statements of the boxed tree.
+pub fn body_has_use_strict(statements: &[ASTNode]) -> bool {
+    statements
+        .iter()
+        .map_while(|statement| match statement {
+            ASTNode::ExpressionStatement { expression, span } => match expression.as_ref() {
+                ASTNode::Literal {
+                    value: Literal::String(value),
+                    span: literal_span,
+                } if literal_span.start == span.start => Some(value.iter().copied().eq("use strict".encode_utf16()) && literal_span.len() == 12),
+                _ => None,
+            },
+            _ => None,
+        })
+        .any(|is_use_strict| is_use_strict)
+}
+
+/// The string literal making up a directive statement, if it is one. A
+/// parenthesised string is an ordinary expression, not a directive.
+fn directive_literal(arena: &AstArena, statement: NodeId) -> Option<NodeId> {
+    match *arena.kind(statement) {
+        NodeKind::ExpressionStatement { expression } => {
+            let is_string = matches!(arena.kind(expression), NodeKind::Literal(ArenaLiteral::String(_)));
+            let unparenthesised = arena.span(expression).start == arena.span(statement).start;
+            (is_string && unparenthesised).then_some(expression)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn arena(code: &str) -> AstArena {
+        AstArena::parse(&Lexer::new(code).tokenize())
+    }
+
+    fn strictness_of(arena: &AstArena, mode: &StrictMode, name: &str) -> bool {
+        let node = arena.ids().find(|&id| arena.identifier_name(id) == Some(name)).unwrap();
+        mode.is_strict(node)
+    }
+
+    #[test]
+    fn test_function_directive() {
+        let arena = arena("a; function f(b) { 'use strict'; c; function g() { d; } } e;");
+        let mode = StrictMode::analyze(&arena, false);
+
+        assert!(!mode.is_strict(arena.root()));
+        assert!(!strictness_of(&arena, &mode, "a"));
+        assert!(strictness_of(&arena, &mode, "b"));
+        assert!(strictness_of(&arena, &mode, "c"));
+        assert!(strictness_of(&arena, &mode, "d"));
+        assert!(!strictness_of(&arena, &mode, "e"));
+    }
+
+    #[test]
+    fn test_program_directive_after_other_directives() {
+        let arena = arena("'a'; \"use strict\"; x;");
+        let mode = StrictMode::analyze(&arena, false);
+
+        assert!(mode.is_strict(arena.root()));
+        assert!(strictness_of(&arena, &mode, "x"));
+        let NodeKind::Program { body } = *arena.kind(arena.root()) else { unreachable!() };
+        assert_eq!(directives(&arena, arena.list(body)).len(), 2);
+    }
+
+    #[test]
+    fn test_not_directives() {
+        for code in [
+            "x; 'use strict';",
+            "('use strict'); x;",
+            "'use\\x20strict'; x;",
+            "'use strict' + 1; x;",
+            "'use \\\nstrict'; x;",
+        ] {
+            let arena = arena(code);
+            assert!(!StrictMode::analyze(&arena, false).is_strict(arena.root()), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_boxed_tree_directives() {
+        let parse = |code: &str| match ASTNode::parse_program(&Lexer::new(code).tokenize()) {
+            ASTNode::Program { body, .. } => body,
+            _ => unreachable!(),
+        };
+
+        assert!(body_has_use_strict(&parse("'a'; 'use strict'; x;")));
+        assert!(!body_has_use_strict(&parse("x; 'use strict';")));
+        assert!(!body_has_use_strict(&parse("('use strict');")));
+    }
+
+    #[test]
+    fn test_module_is_strict() {
+        let arena = arena("function f() { x; }");
+        let mode = StrictMode::analyze(&arena, true);
+
+        assert!(strictness_of(&arena, &mode, "x"));
+    }
+}